@@ -0,0 +1,388 @@
+use crate::common::{Camera, CameraBuilder, Color, Lerp, Point3};
+use crate::hittable::{Hittable, HittableList, Transform};
+use crate::renderer::Renderer;
+use crate::util::png::{self, BitDepth};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A Track holds the keyframes of an animated value, each of which is a `(time, value)` pair.
+/// The value in between two keyframes is linearly interpolated, and the value before the first
+/// (or after the last) keyframe is held at the value of that keyframe
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T: Lerp> {
+    keyframes: Vec<(f64, T)>,
+}
+
+impl<T: Lerp> Track<T> {
+    /// Returns a new track from its `keyframes`, which may be given in any order
+    ///
+    /// # Panics
+    /// if `keyframes` is empty
+    pub fn new(mut keyframes: Vec<(f64, T)>) -> Self {
+        assert!(!keyframes.is_empty(), "a track needs at least one keyframe");
+        keyframes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { keyframes }
+    }
+
+    /// Returns a track that holds `value` at all times
+    pub fn constant(value: T) -> Self {
+        Self {
+            keyframes: vec![(0.0, value)],
+        }
+    }
+
+    /// Returns the keyframes of this track, ordered by time
+    pub fn keyframes(&self) -> &[(f64, T)] {
+        &self.keyframes
+    }
+
+    /// Returns the value of this track at `time`
+    pub fn value_at(&self, time: f64) -> T {
+        // the index of the first keyframe after `time`
+        let next = self.keyframes.partition_point(|(t, _)| *t <= time);
+        match next {
+            0 => self.keyframes[0].1,
+            n if n == self.keyframes.len() => self.keyframes[n - 1].1,
+            n => {
+                let (t0, v0) = self.keyframes[n - 1];
+                let (t1, v1) = self.keyframes[n];
+                v0.lerp(&v1, (time - t0) / (t1 - t0))
+            }
+        }
+    }
+}
+
+/// The animated settings of a camera. Every frame starts from the `base` camera settings, and
+/// then replaces the settings that have a track with the value of the track at the frame's time.
+/// An orbit places the camera with `CameraBuilder::on_orbit`, before the `look_from` and
+/// `look_at` tracks, so that those tracks replace the position or target of the orbit
+#[derive(Debug, Clone)]
+pub struct CameraAnimation {
+    base: CameraBuilder,
+    orbit: Option<Orbit>,
+    look_from: Option<Track<Point3>>,
+    look_at: Option<Track<Point3>>,
+    vertical_field_of_view: Option<Track<f64>>,
+    focus_distance: Option<Track<f64>>,
+    aperture: Option<Track<f64>>,
+}
+
+/// The keyframed orbit of a camera around a `target` point. The angles are interpolated, rather
+/// than the position of the camera, so that the camera stays on the sphere of the orbit
+#[derive(Debug, Clone)]
+struct Orbit {
+    target: Point3,
+    radius: Track<f64>,
+    azimuth: Track<f64>,
+    elevation: Track<f64>,
+}
+
+impl CameraAnimation {
+    /// Returns a camera animation that does not change any of the `base` settings
+    pub fn new(base: CameraBuilder) -> Self {
+        Self {
+            base,
+            orbit: None,
+            look_from: None,
+            look_at: None,
+            vertical_field_of_view: None,
+            focus_distance: None,
+            aperture: None,
+        }
+    }
+
+    /// Returns a camera that orbits the `target` point once, at the given `radius` and
+    /// `elevation` (in degrees), over the animation's time range of `[0..1]`, like a turntable.
+    /// Every other camera setting is taken from `base`
+    pub fn orbit(base: CameraBuilder, target: Point3, radius: f64, elevation: f64) -> Self {
+        CameraAnimation::new(base).with_orbit(
+            target,
+            Track::constant(radius),
+            Track::new(vec![(0.0, 0.0), (1.0, 360.0)]),
+            Track::constant(elevation),
+        )
+    }
+
+    /// Animates the camera along an orbit of the `target` point, see `CameraBuilder::on_orbit`.
+    /// The `azimuth` and `elevation` are **given in degrees**
+    pub fn with_orbit(
+        mut self,
+        target: Point3,
+        radius: Track<f64>,
+        azimuth: Track<f64>,
+        elevation: Track<f64>,
+    ) -> Self {
+        self.orbit = Some(Orbit {
+            target,
+            radius,
+            azimuth,
+            elevation,
+        });
+        self
+    }
+
+    /// Animates the position of the camera
+    pub fn with_look_from(mut self, look_from: Track<Point3>) -> Self {
+        self.look_from = Some(look_from);
+        self
+    }
+
+    /// Animates the point the camera looks at
+    pub fn with_look_at(mut self, look_at: Track<Point3>) -> Self {
+        self.look_at = Some(look_at);
+        self
+    }
+
+    /// Animates the vertical field of view of the camera, **given in degrees**
+    pub fn with_vertical_field_of_view(mut self, vfov_degrees: Track<f64>) -> Self {
+        self.vertical_field_of_view = Some(vfov_degrees);
+        self
+    }
+
+    /// Animates the focus distance of the camera, i.e. to pull focus between two objects
+    pub fn with_focus_distance(mut self, focus_distance: Track<f64>) -> Self {
+        self.focus_distance = Some(focus_distance);
+        self
+    }
+
+    /// Animates the aperture of the camera
+    pub fn with_aperture(mut self, aperture: Track<f64>) -> Self {
+        self.aperture = Some(aperture);
+        self
+    }
+
+    /// Returns the camera at `time`
+    pub fn camera_at(&self, time: f64) -> Camera {
+        let mut builder = self.base;
+        if let Some(orbit) = &self.orbit {
+            builder.on_orbit(
+                orbit.target,
+                orbit.radius.value_at(time),
+                orbit.azimuth.value_at(time),
+                orbit.elevation.value_at(time),
+            );
+        }
+        if let Some(track) = &self.look_from {
+            builder.look_from(track.value_at(time));
+        }
+        if let Some(track) = &self.look_at {
+            builder.look_at(track.value_at(time));
+        }
+        if let Some(track) = &self.vertical_field_of_view {
+            builder.vertical_field_of_view(track.value_at(time));
+        }
+        if let Some(track) = &self.focus_distance {
+            builder.focus_distance(track.value_at(time));
+        }
+        if let Some(track) = &self.aperture {
+            builder.aperture(track.value_at(time));
+        }
+        builder.build()
+    }
+}
+
+/// An Animation is a sequence of `frames` evenly spaced over the time range `[0..1]`, showing
+/// a `world` through an animated `camera`. Objects that move are added with `add_animated`,
+/// along with a track of their `Transform` over time, while the objects of `world` stay still.
+/// A `looping` animation, such as a turntable, leaves out the frame at time `1`, as it would
+/// repeat the first frame
+#[derive(Debug, Clone)]
+pub struct Animation {
+    camera: CameraAnimation,
+    world: HittableList,
+    animated: Vec<(Arc<dyn Hittable>, Track<Transform>)>,
+    frames: u32,
+    looping: bool,
+}
+
+impl Animation {
+    /// Returns a new animation of `frames` frames, of the still `world` seen by the `camera`
+    ///
+    /// # Panics
+    /// if `frames` is zero
+    pub fn new(camera: CameraAnimation, world: HittableList, frames: u32) -> Self {
+        assert!(frames > 0, "an animation needs at least one frame");
+        Self {
+            camera,
+            world,
+            animated: vec![],
+            frames,
+            looping: false,
+        }
+    }
+
+    /// Makes this animation loop, so that its last frame leads back into its first
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Adds a `hittable` that is moved by the keyframed `transform` during the animation
+    pub fn add_animated(&mut self, hittable: Arc<dyn Hittable>, transform: Track<Transform>) {
+        self.animated.push((hittable, transform));
+    }
+
+    /// Returns the number of frames in this animation
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Returns the time of `frame`, where the first frame is at time `0` and the last frame is
+    /// at time `1`, or one frame before time `1` if this animation loops
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        match (self.frames, self.looping) {
+            (frames, true) => frame as f64 / frames as f64,
+            (1, false) => 0.0,
+            (frames, false) => frame as f64 / (frames - 1) as f64,
+        }
+    }
+
+    /// Returns the camera of `frame`
+    pub fn camera_at(&self, frame: u32) -> Camera {
+        self.camera.camera_at(self.frame_time(frame))
+    }
+
+    /// Returns the world of `frame`, holding the still objects and the animated objects
+    /// placed by their transform at the frame's time
+    pub fn world_at(&self, frame: u32) -> HittableList {
+        let time = self.frame_time(frame);
+        let mut world = self.world.clone();
+        for (hittable, transform) in &self.animated {
+            world.add(transform.value_at(time).apply(Arc::clone(hittable)));
+        }
+        world
+    }
+
+    /// Renders every frame of this animation with `renderer`, and writes each one to a PNG file,
+    /// named `frame_0001.png`, `frame_0002.png`, etc., in the `dir` directory, using `bit_depth`
+    /// bits per color channel. A still world is rendered by `Renderer::render_animation`, and a
+    /// world with animated objects by `Renderer::render_animated_world`, which refits a single
+    /// BVH to each frame. The display colors of each frame are then passed to `on_frame`, i.e.
+    /// to also collect the frames into an animated GIF with `util::gif`.
+    ///
+    /// # Returns
+    /// the paths of the written frames, in order, or the first error that occurred
+    pub fn render_frames(
+        &self,
+        renderer: &Renderer,
+        dir: impl AsRef<Path>,
+        bit_depth: BitDepth,
+        mut on_frame: impl FnMut(&[Color]),
+    ) -> image::ImageResult<Vec<PathBuf>> {
+        let cameras: Vec<Camera> = (0..self.frames)
+            .map(|frame| self.camera_at(frame))
+            .collect();
+        let (width, height) = (cameras[0].image_width, cameras[0].image_height);
+        let images = if self.animated.is_empty() {
+            renderer
+                .clone()
+                .render_animation(cameras, self.world.clone())
+        } else {
+            let worlds = (0..self.frames).map(|frame| self.world_at(frame)).collect();
+            renderer.clone().render_animated_world(cameras, worlds)
+        };
+
+        std::fs::create_dir_all(&dir)?;
+        images
+            .iter()
+            .enumerate()
+            .map(|(frame, image)| {
+                let path = dir.as_ref().join(format!("frame_{:04}.png", frame + 1));
+                png::write_file(&path, width, height, image, bit_depth)?;
+                on_frame(image);
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::{Animation, CameraAnimation, Track};
+    use crate::common::Color;
+    use crate::common::{CameraBuilder, Point3, Vec3};
+    use crate::hittable::{build_solid_sphere, Hittable, HittableList, Transform};
+    use crate::renderer::{BackgroundColor, Renderer};
+    use crate::util::png::BitDepth;
+    use std::sync::Arc;
+
+    fn test_camera() -> CameraBuilder {
+        CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .aspect_ratio(1.0)
+            .image_width(8)
+            .focus_distance(1.0)
+            .vertical_field_of_view(90.0)
+            .open_close_time(0.0, 1.0)
+    }
+
+    #[test]
+    fn tracks_interpolate_between_keyframes_and_hold_their_ends() {
+        let track = Track::new(vec![(1.0, 10.0), (0.0, 0.0), (2.0, 30.0)]);
+
+        assert_eq!(track.value_at(-1.0), 0.0);
+        assert_eq!(track.value_at(0.5), 5.0);
+        assert_eq!(track.value_at(1.0), 10.0);
+        assert_eq!(track.value_at(1.5), 20.0);
+        assert_eq!(track.value_at(3.0), 30.0);
+    }
+
+    #[test]
+    fn orbiting_camera_keeps_its_distance_to_the_target() {
+        let target = Point3::new(1.0, 0.0, -2.0);
+        let animation = CameraAnimation::orbit(test_camera(), target, 5.0, 20.0);
+
+        for step in 0..=20 {
+            let camera = animation.camera_at(step as f64 / 20.0);
+            assert!(((camera.look_from - target).length() - 5.0).abs() < 1e-9);
+        }
+        let start = animation.camera_at(0.0).look_from;
+        let halfway = animation.camera_at(0.5).look_from;
+        // half way around the orbit, the camera is on the opposite side of the target
+        assert!((start.x() - target.x()) * (halfway.x() - target.x()) < 0.0);
+    }
+
+    #[test]
+    fn frames_are_rendered_to_numbered_files() {
+        let sphere: Arc<dyn Hittable> = Arc::new(build_solid_sphere(
+            Point3::default(),
+            0.5,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let mut animation =
+            Animation::new(CameraAnimation::new(test_camera()), HittableList::new(), 3);
+        animation.add_animated(
+            sphere,
+            Track::new(vec![
+                (0.0, Transform::translation(Vec3::new(-1.0, 0.0, -2.0))),
+                (1.0, Transform::translation(Vec3::new(1.0, 0.0, -2.0))),
+            ]),
+        );
+        let dir =
+            std::env::temp_dir().join(format!("raytracer_animation_frames_{}", std::process::id()));
+        let renderer = Renderer::new(2, 1, BackgroundColor::Solid(Color::default()), 1);
+
+        let mut rendered = 0;
+        let paths = animation.render_frames(&renderer, &dir, BitDepth::Eight, |_| rendered += 1);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let names: Vec<String> = paths
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            ["frame_0001.png", "frame_0002.png", "frame_0003.png"]
+        );
+        assert_eq!(rendered, 3);
+        // the middle frame places the sphere half way along its path
+        let middle = animation.world_at(1);
+        let bbox = middle.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.min().x() + bbox.max().x()).abs() < 1e-9);
+        // a looping animation stops one frame short of the end
+        assert_eq!(animation.looping().frame_time(2), 2.0 / 3.0);
+    }
+}
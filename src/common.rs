@@ -1,6 +1,8 @@
 pub mod vec3;
 pub use vec3::*;
 
+pub mod color;
+
 pub mod ray;
 pub use ray::*;
 
@@ -10,6 +12,21 @@ pub use camera::*;
 pub mod camera_builder;
 pub use camera_builder::*;
 
+pub mod onb;
+pub use onb::*;
+
+pub mod matrix;
+pub use matrix::*;
+
+pub mod lerp;
+pub use lerp::*;
+
+pub mod sampler;
+pub use sampler::*;
+
+pub mod rng;
+pub use rng::*;
+
 /// alias for a 3D point with x,y,z coordinates
 pub type Point3 = Vec3;
 
@@ -1,5 +1,122 @@
-use crate::common::{Point3, Ray, Vec3};
+use crate::common::{Point3, Ray, RngContext, Vec3};
 use rand::Rng;
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// The projection a `Camera` uses to map the pixels of its image to the directions of its rays
+/// `Perspective` - the default, a pinhole (or thin lens) camera, whose field of view is set by
+/// the camera's vertical field of view
+/// `Fisheye` - an equidistant fisheye lens, where the angle between a ray and the view direction
+/// grows linearly with its distance from the image center. `field_of_view` is the angle, in
+/// degrees, spanned by the width of the image, and can be up to 360
+/// `Equirectangular` - a full 360 x 180 degree latitude-longitude panorama, as used by VR
+/// panoramas and environment maps. Images should have an aspect ratio of 2:1, and the camera
+/// should look horizontally so that the poles of the panorama are straight up and down
+///
+/// Only perspective cameras have a lens, the other projections ignore the aperture
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LensModel {
+    #[default]
+    Perspective,
+    Fisheye {
+        field_of_view: f64,
+    },
+    Equirectangular,
+}
+
+/// The shape of a `Camera`'s aperture, which is the shape that out of focus highlights (bokeh)
+/// take on
+/// `Disk` - the default, a round aperture
+/// `Polygon` - the regular polygon formed by the `blades` of an iris diaphragm, such as the
+/// hexagonal bokeh of a six bladed lens. `rotation` turns the polygon, in degrees. A polygon
+/// needs at least three blades, fewer blades fall back to a disk
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ApertureShape {
+    #[default]
+    Disk,
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+}
+
+impl ApertureShape {
+    /// Maps the point `(u, v)` of the unit square onto this aperture shape, inscribed in the
+    /// unit disk. Uniformly distributed points of the square map to uniformly distributed
+    /// points of the shape. Returns the point as a vector in the xy plane
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        match *self {
+            ApertureShape::Polygon { blades, rotation } if blades >= 3 => {
+                // pick one of the polygon's equally sized triangles, and reuse the rest of `u`
+                // to pick a point within that triangle
+                let scaled = u * blades as f64;
+                let blade = scaled.floor().min(blades as f64 - 1.0);
+                let u = scaled - blade;
+                let angle = |k: f64| rotation.to_radians() + 2.0 * PI * k / blades as f64;
+                let (a0, a1) = (angle(blade), angle(blade + 1.0));
+                let a = u.sqrt();
+                Vec3::new(
+                    a * ((1.0 - v) * a0.cos() + v * a1.cos()),
+                    a * ((1.0 - v) * a0.sin() + v * a1.sin()),
+                    0.0,
+                )
+            }
+            _ => {
+                // an area preserving polar mapping
+                let r = u.sqrt();
+                let theta = 2.0 * PI * v;
+                Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+            }
+        }
+    }
+}
+
+/// How a `Camera`'s shutter exposes the interval between its open and close times, which
+/// decides the times its rays are sent at, and so the look of the motion blur of moving objects
+/// `None` - the default, there is no motion blur. Every ray is sent at the open time
+/// `Uniform` - rays are sent at uniformly distributed times between the open and close times
+/// `Triangle` - the shutter opens and closes gradually, so more rays are sent in the middle of
+/// the interval, which softens the ends of motion blur streaks
+/// `Eased(curve)` - a custom shutter, `curve` maps a uniformly distributed number in `[0..1)`
+/// to the fraction of the interval a ray is sent at. Eased shutters can not be serialized
+///
+/// Cameras whose open and close times are equal send every ray at the open time
+#[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShutterModel {
+    #[default]
+    None,
+    Uniform,
+    Triangle,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Eased(fn(f64) -> f64),
+}
+
+/// Parses a lens model from `perspective`, `equirectangular` or `fisheye:<field_of_view>`
+impl FromStr for LensModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            ("perspective", None) => Ok(LensModel::Perspective),
+            ("equirectangular", None) => Ok(LensModel::Equirectangular),
+            ("fisheye", Some(arg)) => arg
+                .parse::<f64>()
+                .map(|field_of_view| LensModel::Fisheye { field_of_view })
+                .map_err(|e| format!("invalid fisheye field of view '{}': {}", arg, e)),
+            _ => Err(format!(
+                "unknown lens model '{}', expected perspective, equirectangular or fisheye:<field_of_view>",
+                s
+            )),
+        }
+    }
+}
 
 /// A positionable `Camera` with a configurable vertical field of view, aperture, focus distance,
 /// and shutter open/close time.
@@ -8,6 +125,7 @@ use rand::Rng;
 /// function
 #[allow(dead_code)]
 #[derive(Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Camera {
     pub image_width: u32,
     pub image_height: u32,
@@ -20,21 +138,387 @@ pub struct Camera {
     pub v: Vec3,
     pub open_time: f64,
     pub close_time: f64,
+    pub lens_model: LensModel,
+    pub aperture_shape: ApertureShape,
+    pub shutter: ShutterModel,
 }
 
 impl Camera {
     /// returns a `Ray` that originates from this camera's origin, with its direction pointing
     /// towards the given `s, t` offsets
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
-        let direction = self.lower_left_corner + (s * self.horizontal) + (t * self.vertical)
-            - self.look_from
-            - offset;
+        let rd = match self.aperture_shape {
+            ApertureShape::Disk => self.lens_radius * Vec3::random_in_unit_disk(),
+            shape => {
+                let mut rng = RngContext::current();
+                self.lens_radius * shape.sample(rng.gen(), rng.gen())
+            }
+        };
+        self.get_ray_from_lens_offset(s, t, rd)
+    }
+
+    /// returns a `Ray` pointing towards the given `s, t` offsets, like `get_ray`, except that
+    /// the point on the lens the ray originates from is chosen by the caller. `lens_u, lens_v`
+    /// are in the range `[0..1)` and are mapped onto the lens' aperture shape. This allows the
+    /// lens sample to be drawn from the same (low-discrepancy) sampler as the pixel sample
+    pub fn get_ray_with_lens(&self, s: f64, t: f64, lens_u: f64, lens_v: f64) -> Ray {
+        let rd = self.lens_radius * self.aperture_shape.sample(lens_u, lens_v);
+        self.get_ray_from_lens_offset(s, t, rd)
+    }
+
+    /// returns a `Ray` pointing towards the given `s, t` offsets, that originates from the
+    /// point `rd` on the lens' aperture
+    fn get_ray_from_lens_offset(&self, s: f64, t: f64, rd: Vec3) -> Ray {
+        let (offset, direction) = match self.lens_model {
+            LensModel::Perspective => {
+                let offset = self.u * rd.x() + self.v * rd.y();
+                let direction =
+                    self.lower_left_corner + (s * self.horizontal) + (t * self.vertical)
+                        - self.look_from
+                        - offset;
+                (offset, direction)
+            }
+            _ => (Vec3::default(), self.projected_direction(s, t)),
+        };
+
+        Ray::new(self.look_from + offset, direction, self.shutter_time())
+            .with_spread(self.pixel_spread())
+    }
+
+    /// returns a random time, within the interval the camera's shutter is open, that is
+    /// distributed according to the camera's `ShutterModel`
+    pub fn shutter_time(&self) -> f64 {
+        if self.close_time <= self.open_time {
+            return self.open_time;
+        }
+        let fraction = match self.shutter {
+            ShutterModel::None => return self.open_time,
+            ShutterModel::Uniform => {
+                return RngContext::current().gen_range(self.open_time..self.close_time)
+            }
+            ShutterModel::Triangle => {
+                let u: f64 = RngContext::current().gen();
+                if u < 0.5 {
+                    (u / 2.0).sqrt()
+                } else {
+                    1.0 - ((1.0 - u) / 2.0).sqrt()
+                }
+            }
+            ShutterModel::Eased(curve) => curve(RngContext::current().gen()).clamp(0.0, 1.0),
+        };
+        self.open_time + fraction * (self.close_time - self.open_time)
+    }
+
+    /// returns the direction of the ray through the `s, t` offsets of the image, for the
+    /// fisheye and equirectangular lens models
+    fn projected_direction(&self, s: f64, t: f64) -> Vec3 {
+        // w points from the view direction back towards the camera
+        let w = self.u.cross(self.v);
+        let (x, y, z) = match self.lens_model {
+            LensModel::Fisheye { field_of_view } => {
+                // offsets from the image center, where the left and right edges are at -1 and 1
+                let px = 2.0 * s - 1.0;
+                let py = (2.0 * t - 1.0) / self.aspect_ratio();
+                let theta = px.hypot(py) * field_of_view.to_radians() / 2.0;
+                let phi = py.atan2(px);
+                (
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                )
+            }
+            _ => {
+                let longitude = (s - 0.5) * 2.0 * PI;
+                let latitude = (t - 0.5) * PI;
+                (
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.cos(),
+                )
+            }
+        };
+        x * self.u + y * self.v - z * w
+    }
+
+    /// returns the width of this camera's image divided by its height
+    fn aspect_ratio(&self) -> f64 {
+        self.image_width as f64 / self.image_height.max(1) as f64
+    }
+
+    /// returns the angle, in radians, subtended by a single pixel of this camera's image
+    pub fn pixel_spread(&self) -> f64 {
+        let field_of_view = match self.lens_model {
+            LensModel::Perspective => {
+                let w = self.u.cross(self.v);
+                let focus_dist = (self.look_from - self.lower_left_corner).dot(&w);
+                return self.horizontal.length() / (self.image_width as f64 * focus_dist);
+            }
+            LensModel::Fisheye { field_of_view } => field_of_view.to_radians(),
+            LensModel::Equirectangular => 2.0 * PI,
+        };
+        field_of_view / self.image_width as f64
+    }
+
+    /// returns the screen-space pixel coordinates `(x, y)` that `world_point` projects to, or
+    /// `None` if the point is behind the camera. This is the inverse of the projection made by
+    /// `get_ray` (ignoring the lens' defocus blur). `x` increases from the left edge of the
+    /// image and `y` increases from the top edge of the image, as in the saved image files.
+    /// Points outside of the camera's field of view have coordinates outside of the image.
+    /// Fisheye and equirectangular cameras see all around them, so they only return `None` for
+    /// the camera's own position
+    pub fn project(&self, world_point: Point3) -> Option<(f64, f64)> {
+        // w points from the focus plane back towards the camera
+        let w = self.u.cross(self.v);
+        if self.lens_model != LensModel::Perspective {
+            let direction = (world_point - self.look_from).unit_vector();
+            let (x, y, z) = (
+                direction.dot(&self.u),
+                direction.dot(&self.v),
+                -direction.dot(&w),
+            );
+            if x.is_nan() {
+                return None;
+            }
+            let (s, t) = match self.lens_model {
+                LensModel::Fisheye { field_of_view } => {
+                    let r = z.clamp(-1.0, 1.0).acos() / (field_of_view.to_radians() / 2.0);
+                    let phi = y.atan2(x);
+                    (
+                        0.5 + r * phi.cos() / 2.0,
+                        0.5 + r * phi.sin() * self.aspect_ratio() / 2.0,
+                    )
+                }
+                _ => (
+                    0.5 + x.atan2(z) / (2.0 * PI),
+                    0.5 + y.clamp(-1.0, 1.0).asin() / PI,
+                ),
+            };
+            return Some((
+                s * (self.image_width - 1) as f64,
+                (1.0 - t) * (self.image_height - 1) as f64,
+            ));
+        }
+        let to_corner = self.lower_left_corner - self.look_from;
+        let focus_dist = -to_corner.dot(&w);
+        let direction = world_point - self.look_from;
+        let depth = -direction.dot(&w);
+        if depth <= 0.0 {
+            return None;
+        }
+
+        // where the ray from the camera to the point crosses the focus plane
+        let on_plane = direction * (focus_dist / depth) - to_corner;
+        let s = on_plane.dot(&self.horizontal) / self.horizontal.length_squared();
+        let t = on_plane.dot(&self.vertical) / self.vertical.length_squared();
+
+        let x = s * (self.image_width - 1) as f64;
+        let y = (1.0 - t) * (self.image_height - 1) as f64;
+        Some((x, y))
+    }
+
+    /// returns a copy of this camera that renders an image of `image_width` x `image_height`
+    /// pixels. The field of view, position and orientation of the camera are unchanged
+    pub fn with_resolution(&self, image_width: u32, image_height: u32) -> Camera {
+        Camera {
+            image_width,
+            image_height,
+            ..*self
+        }
+    }
+
+    /// returns a copy of this camera moved by `offset`, while keeping the same orientation,
+    /// field of view and focus plane orientation
+    pub fn translated(&self, offset: Vec3) -> Camera {
+        Camera {
+            look_from: self.look_from + offset,
+            lower_left_corner: self.lower_left_corner + offset,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{
+        ApertureShape, Camera, CameraBuilder, LensModel, Point3, ShutterModel, Vec3,
+    };
+    use std::f64::consts::PI;
+
+    fn camera() -> Camera {
+        CameraBuilder::new()
+            .look_from(Point3::new(1.0, 2.0, 3.0))
+            .look_at(Point3::new(-4.0, 0.0, -2.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(201)
+            .aspect_ratio(2.0)
+            .vertical_field_of_view(40.0)
+            .aperture(0.1)
+            .focus_distance(5.0)
+            .open_close_time(0.0, 1.0)
+            .build()
+    }
+
+    #[test]
+    fn projecting_look_at_lands_on_the_image_center() {
+        let (x, y) = camera().project(Point3::new(-4.0, 0.0, -2.0)).unwrap();
+
+        assert!((x - 100.0).abs() < 1e-9);
+        assert!((y - 49.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn projecting_a_point_behind_the_camera_returns_none() {
+        assert!(camera().project(Point3::new(6.0, 4.0, 8.0)).is_none());
+    }
+
+    #[test]
+    fn projecting_a_point_on_the_focus_plane_inverts_get_ray() {
+        let camera = camera();
+        // the point that get_ray(0.25, 0.75) aims at, on the focus plane
+        let point = camera.lower_left_corner + 0.25 * camera.horizontal + 0.75 * camera.vertical;
+
+        let (x, y) = camera.project(point).unwrap();
+
+        assert!((x - 0.25 * 200.0).abs() < 1e-9);
+        assert!((y - 0.25 * 99.0).abs() < 1e-9);
+    }
+
+    /// builds a camera at the origin, looking down the negative z axis, with the `lens_model`
+    fn camera_with_lens(lens_model: LensModel) -> Camera {
+        CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(200)
+            .aspect_ratio(2.0)
+            .vertical_field_of_view(40.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .lens_model(lens_model)
+            .build()
+    }
+
+    fn assert_direction(camera: &Camera, s: f64, t: f64, expected: Vec3) {
+        let direction = camera.get_ray(s, t).direction();
+        assert!(
+            (direction - expected).length() < 1e-9,
+            "expected {:?} at ({}, {}), got {:?}",
+            expected,
+            s,
+            t,
+            direction
+        );
+    }
+
+    #[test]
+    fn equirectangular_cameras_see_all_around_them() {
+        let camera = camera_with_lens(LensModel::Equirectangular);
+
+        assert_direction(&camera, 0.5, 0.5, Vec3::new(0.0, 0.0, -1.0));
+        assert_direction(&camera, 0.75, 0.5, Vec3::new(1.0, 0.0, 0.0));
+        assert_direction(&camera, 0.0, 0.5, Vec3::new(0.0, 0.0, 1.0));
+        assert_direction(&camera, 0.5, 1.0, Vec3::new(0.0, 1.0, 0.0));
+
+        // a point behind the camera projects back onto the image
+        let ray = camera.get_ray(0.1, 0.3);
+        let (x, y) = camera.project(ray.at(10.0)).unwrap();
+        assert!((x - 0.1 * 199.0).abs() < 1e-6);
+        assert!((y - 0.7 * 99.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fisheye_angles_grow_linearly_from_the_image_center() {
+        let camera = camera_with_lens(LensModel::Fisheye {
+            field_of_view: 180.0,
+        });
+
+        assert_direction(&camera, 0.5, 0.5, Vec3::new(0.0, 0.0, -1.0));
+        assert_direction(&camera, 1.0, 0.5, Vec3::new(1.0, 0.0, 0.0));
+        // the image is half as tall as it is wide, so its top edge sees 45 degrees up
+        assert_direction(&camera, 0.5, 1.0, Vec3::new(0.0, 1.0, -1.0).unit_vector());
+
+        let ray = camera.get_ray(0.3, 0.6);
+        let (x, y) = camera.project(ray.at(10.0)).unwrap();
+        assert!((x - 0.3 * 199.0).abs() < 1e-6);
+        assert!((y - 0.4 * 99.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lens_models_are_parsed_from_strings() {
+        assert_eq!("perspective".parse(), Ok(LensModel::Perspective));
+        assert_eq!("Equirectangular".parse(), Ok(LensModel::Equirectangular));
+        assert_eq!(
+            "fisheye:180".parse(),
+            Ok(LensModel::Fisheye {
+                field_of_view: 180.0
+            })
+        );
+        assert!("fisheye".parse::<LensModel>().is_err());
+    }
+
+    #[test]
+    fn hexagonal_apertures_sample_points_within_the_hexagon() {
+        let hexagon = ApertureShape::Polygon {
+            blades: 6,
+            rotation: 0.0,
+        };
+        let apothem = (PI / 6.0).cos();
+        let points: Vec<Vec3> = (0..50)
+            .flat_map(|i| (0..50).map(move |j| (i as f64 / 50.0, j as f64 / 50.0)))
+            .map(|(u, v)| hexagon.sample(u, v))
+            .collect();
+
+        for p in points.iter() {
+            // the distance to every edge, along the edge's normal, is within the apothem
+            for k in 0..6 {
+                let normal_angle = PI / 6.0 + k as f64 * PI / 3.0;
+                let distance = p.x() * normal_angle.cos() + p.y() * normal_angle.sin();
+                assert!(distance <= apothem + 1e-9, "{:?} is outside the hexagon", p);
+            }
+        }
+        // the corners of the hexagon, beyond the inscribed circle, are reached
+        assert!(points.iter().any(|p| p.length() > 0.95));
+        let mean = points.iter().fold(Vec3::default(), |sum, p| sum + *p) / points.len() as f64;
+        assert!(mean.length() < 0.02);
+    }
+
+    #[test]
+    fn static_cameras_do_not_need_a_shutter_interval() {
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(20)
+            .aspect_ratio(2.0)
+            .vertical_field_of_view(40.0)
+            .focus_distance(1.0)
+            .build();
+        assert_eq!(camera.get_ray(0.5, 0.5).time(), 0.0);
+
+        // a zero length interval sends every ray at the open time, whatever the shutter
+        let instant = CameraBuilder::new()
+            .open_close_time(2.0, 2.0)
+            .shutter(ShutterModel::Triangle)
+            .build();
+        assert_eq!(instant.shutter_time(), 2.0);
+    }
 
-        // generate a random amount of time the camera shutter was open
-        let shutter_open: f64 = rand::thread_rng().gen_range(self.open_time..self.close_time);
+    #[test]
+    fn shutter_models_shape_the_distribution_of_ray_times() {
+        let mean_time = |shutter: ShutterModel| {
+            let camera = CameraBuilder::new()
+                .open_close_time(1.0, 3.0)
+                .shutter(shutter)
+                .build();
+            let times: Vec<f64> = (0..4000).map(|_| camera.shutter_time()).collect();
+            assert!(times.iter().all(|t| (1.0..=3.0).contains(t)));
+            times.iter().sum::<f64>() / times.len() as f64
+        };
 
-        Ray::new(self.look_from + offset, direction, shutter_open)
+        assert_eq!(mean_time(ShutterModel::None), 1.0);
+        assert!((mean_time(ShutterModel::Uniform) - 2.0).abs() < 0.05);
+        assert!((mean_time(ShutterModel::Triangle) - 2.0).abs() < 0.05);
+        // u^2 eases in, so a third of the way through the interval is the average time
+        assert!((mean_time(ShutterModel::Eased(|u| u * u)) - (1.0 + 2.0 / 3.0)).abs() < 0.05);
     }
 }
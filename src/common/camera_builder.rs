@@ -1,9 +1,10 @@
 use crate::common;
-use crate::common::{Camera, Point3, Vec3};
+use crate::common::{ApertureShape, Camera, LensModel, Point3, ShutterModel, Vec3};
 
 /// A builder struct for constructing a `Camera`.
 /// Supply all the fields and the call the `build()` function to return a new Camera
 #[derive(Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CameraBuilder {
     look_from: Point3,
     look_at: Point3,
@@ -15,6 +16,10 @@ pub struct CameraBuilder {
     focus_dist: f64,
     open_time: f64,
     close_time: f64,
+    lens_model: LensModel,
+    aperture_shape: ApertureShape,
+    // the shutter model set by `shutter`, if any, which is resolved when the camera is built
+    shutter: Option<ShutterModel>,
 }
 
 impl CameraBuilder {
@@ -37,6 +42,24 @@ impl CameraBuilder {
         *self
     }
 
+    /// Positions the camera on a sphere of the given `radius` centered at `target` and points
+    /// the camera at `target`.
+    /// `azimuth` is the angle around the y-axis, measured from the positive x-axis towards the
+    /// positive z-axis, and `elevation` is the angle above the xz plane. Both angles are
+    /// **given in degrees**. Sweeping `azimuth` over a series of frames produces a turntable
+    pub fn on_orbit(&mut self, target: Point3, radius: f64, azimuth: f64, elevation: f64) -> Self {
+        let azimuth = common::degrees_to_radians(azimuth);
+        let elevation = common::degrees_to_radians(elevation);
+        let offset = Vec3::new(
+            radius * elevation.cos() * azimuth.cos(),
+            radius * elevation.sin(),
+            radius * elevation.cos() * azimuth.sin(),
+        );
+        self.look_from = target + offset;
+        self.look_at = target;
+        *self
+    }
+
     /// Sets the camera's *up vector*, which is similar to it's rotation
     /// about its origin
     pub fn up_direction(&mut self, up_direction: Vec3) -> Self {
@@ -69,6 +92,13 @@ impl CameraBuilder {
         *self
     }
 
+    /// Sets the shape of the camera's aperture, which shapes the bokeh of out of focus
+    /// highlights. Defaults to `ApertureShape::Disk`
+    pub fn aperture_shape(&mut self, aperture_shape: ApertureShape) -> Self {
+        self.aperture_shape = aperture_shape;
+        *self
+    }
+
     /// Sets the distance from the camera to the virtual focus plane. This can be used
     /// to achieve a depth of field effect.
     /// This is not the same as *focal length*. Anything at the focus plane will be in
@@ -80,13 +110,28 @@ impl CameraBuilder {
 
     /// Sets the camera lenses open and close time in order render a motion blur effect.
     /// This setting will only affect primitives that can *move*, such as `MoveableSphere`,
-    /// and only if the primitive moves between the `open_time` and `closed_time`
+    /// and only if the primitive moves between the `open_time` and `closed_time`.
+    /// A camera that is not given a shutter model gets a `Uniform` shutter
     pub fn open_close_time(&mut self, open_time: f64, close_time: f64) -> Self {
         self.open_time = open_time;
         self.close_time = close_time;
         *self
     }
 
+    /// Sets how the shutter exposes the interval between the open and close times. Defaults to
+    /// `ShutterModel::Uniform` if the camera has an open and close time, and otherwise to
+    /// `ShutterModel::None`, which disables motion blur
+    pub fn shutter(&mut self, shutter: ShutterModel) -> Self {
+        self.shutter = Some(shutter);
+        *self
+    }
+
+    /// Sets the projection of the camera's lens, defaults to `LensModel::Perspective`
+    pub fn lens_model(&mut self, lens_model: LensModel) -> Self {
+        self.lens_model = lens_model;
+        *self
+    }
+
     /// builds and returns a new `Camera` struct
     pub fn build(&mut self) -> Camera {
         let w = (self.look_from - self.look_at).unit_vector();
@@ -113,6 +158,13 @@ impl CameraBuilder {
             vertical,
             u,
             v,
+            lens_model: self.lens_model,
+            aperture_shape: self.aperture_shape,
+            shutter: self.shutter.unwrap_or(if self.close_time > self.open_time {
+                ShutterModel::Uniform
+            } else {
+                ShutterModel::None
+            }),
         }
     }
 
@@ -129,3 +181,105 @@ impl CameraBuilder {
         (vp_width, vp_height)
     }
 }
+
+/// Recovers the settings of a built `Camera`, so that a camera returned by a scene can be
+/// modified, i.e. moved onto an orbit. The camera looks at the center of its focus plane
+impl From<&Camera> for CameraBuilder {
+    fn from(camera: &Camera) -> Self {
+        // w points from the view direction back towards the camera
+        let w = camera.u.cross(camera.v);
+        let viewport_center =
+            camera.lower_left_corner + camera.horizontal / 2.0 + camera.vertical / 2.0;
+        let focus_dist = (camera.look_from - viewport_center).dot(&w);
+        let vfov = 2.0 * (camera.vertical.length() / 2.0 / focus_dist).atan();
+
+        Self {
+            look_from: camera.look_from,
+            look_at: viewport_center,
+            vup: camera.v,
+            vfov: vfov.to_degrees(),
+            aspect_ratio: camera.horizontal.length() / camera.vertical.length(),
+            image_width: camera.image_width,
+            aperture: camera.lens_radius * 2.0,
+            focus_dist,
+            open_time: camera.open_time,
+            close_time: camera.close_time,
+            lens_model: camera.lens_model,
+            aperture_shape: camera.aperture_shape,
+            shutter: Some(camera.shutter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Camera, CameraBuilder, Point3, ShutterModel, Vec3};
+
+    #[test]
+    fn orbit_at_zero_azimuth_and_elevation_sits_on_the_x_axis() {
+        let target = Point3::new(1.0, 2.0, 3.0);
+        let builder = CameraBuilder::new().on_orbit(target, 5.0, 0.0, 0.0);
+
+        assert_eq!(builder.look_from, target + Point3::new(5.0, 0.0, 0.0));
+        assert_eq!(builder.look_at, target);
+    }
+
+    #[test]
+    fn orbit_keeps_the_camera_at_the_orbit_radius() {
+        let target = Point3::new(1.0, 2.0, 3.0);
+        let builder = CameraBuilder::new().on_orbit(target, 5.0, 123.0, 40.0);
+
+        assert!(((builder.look_from - target).length() - 5.0).abs() < 1e-9);
+        assert_eq!(builder.look_at, target);
+    }
+
+    #[test]
+    fn a_camera_is_rebuilt_from_its_own_settings() {
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(13.0, 2.0, 3.0))
+            .look_at(Point3::new(0.0, 0.0, 0.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .vertical_field_of_view(20.0)
+            .aspect_ratio(1.6)
+            .image_width(320)
+            .aperture(0.1)
+            .focus_distance(10.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+
+        let rebuilt: Camera = CameraBuilder::from(&camera).build();
+
+        assert_eq!(rebuilt.image_width, camera.image_width);
+        assert_eq!(rebuilt.image_height, camera.image_height);
+        assert!((rebuilt.lens_radius - camera.lens_radius).abs() < 1e-12);
+        for (a, b) in [
+            (rebuilt.look_from, camera.look_from),
+            (rebuilt.lower_left_corner, camera.lower_left_corner),
+            (rebuilt.horizontal, camera.horizontal),
+            (rebuilt.vertical, camera.vertical),
+        ] {
+            assert!((a - b).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn an_explicit_shutter_model_is_kept_whatever_the_order_of_the_settings() {
+        let without_shutter = CameraBuilder::new().open_close_time(0.0, 1.0).build();
+        assert!(matches!(without_shutter.shutter, ShutterModel::Uniform));
+
+        let still = CameraBuilder::new().build();
+        assert!(matches!(still.shutter, ShutterModel::None));
+
+        let shutter_first = CameraBuilder::new()
+            .shutter(ShutterModel::None)
+            .open_close_time(0.0, 1.0)
+            .build();
+        assert!(matches!(shutter_first.shutter, ShutterModel::None));
+
+        let shutter_last = CameraBuilder::new()
+            .open_close_time(0.0, 1.0)
+            .shutter(ShutterModel::Triangle)
+            .build();
+        assert!(matches!(shutter_last.shutter, ShutterModel::Triangle));
+    }
+}
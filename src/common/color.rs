@@ -0,0 +1,107 @@
+use crate::common::Color;
+
+/// Conversions between RGB `Color`s and the HSV (hue, saturation, value) and HSL (hue,
+/// saturation, lightness) color models, for generating colors procedurally, i.e. evenly spaced
+/// hues. Hues are in degrees, in the range `[0..360)`, and every other component is in the
+/// range `[0..1]`, as are the RGB channels
+impl Color {
+    /// Returns the RGB color of the given `hue`, `saturation` and `value`
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Color {
+        let chroma = value * saturation;
+        Color::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Returns the `(hue, saturation, value)` of this RGB color
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (max, min) = self.channel_bounds();
+        let chroma = max - min;
+        let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+        (self.hue(max, chroma), saturation, max)
+    }
+
+    /// Returns the RGB color of the given `hue`, `saturation` and `lightness`
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Color {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// Returns the `(hue, saturation, lightness)` of this RGB color
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (max, min) = self.channel_bounds();
+        let chroma = max - min;
+        let lightness = (max + min) / 2.0;
+        let saturation = if lightness > 0.0 && lightness < 1.0 {
+            chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        (self.hue(max, chroma), saturation, lightness)
+    }
+
+    /// Returns the RGB color with the given `hue` and `chroma`, with `min` added to every channel
+    fn from_hue_chroma(hue: f64, chroma: f64, min: f64) -> Color {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Color::new(r + min, g + min, b + min)
+    }
+
+    /// Returns the largest and smallest channels of this color
+    fn channel_bounds(&self) -> (f64, f64) {
+        let max = self.x().max(self.y()).max(self.z());
+        let min = self.x().min(self.y()).min(self.z());
+        (max, min)
+    }
+
+    /// Returns the hue of this color, in degrees, given its largest channel `max` and its
+    /// `chroma`. Grays have no hue, and return `0`
+    fn hue(&self, max: f64, chroma: f64) -> f64 {
+        if chroma == 0.0 {
+            return 0.0;
+        }
+        let (r, g, b) = (self.x(), self.y(), self.z());
+        let sector = if max == r {
+            ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / chroma + 2.0
+        } else {
+            (r - g) / chroma + 4.0
+        };
+        60.0 * sector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+
+    #[test]
+    fn hsv_hues_of_red_and_green() {
+        assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(Color::from_hsl(240.0, 1.0, 0.5), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn round_trips_through_hsv_and_hsl_preserve_the_color() {
+        let colors = [
+            Color::new(0.2, 0.4, 0.9),
+            Color::new(0.9, 0.1, 0.5),
+            Color::new(0.5, 0.5, 0.5),
+            Color::new(0.3, 0.8, 0.1),
+        ];
+        for color in colors {
+            let (h, s, v) = color.to_hsv();
+            assert!((Color::from_hsv(h, s, v) - color).length() < 1e-12);
+            let (h, s, l) = color.to_hsl();
+            assert!((Color::from_hsl(h, s, l) - color).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use crate::common::Vec3;
+
+/// A value that can be linearly interpolated, i.e. between two keyframes of an animation
+pub trait Lerp: Copy {
+    /// Returns the value `t` of the way from `self` to `other`, where `t` is in `[0..1]`
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
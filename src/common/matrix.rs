@@ -0,0 +1,279 @@
+use crate::common::{degrees_to_radians, Point3, Vec3};
+use std::ops::Mul;
+
+/// A 4x4 matrix of an affine transformation, in row-major order. Points are treated as column
+/// vectors with an implicit `w` of 1, and vectors (directions) with a `w` of 0, so that vectors
+/// are not translated. Matrices are composed with `*`, where `a * b` applies `b` first
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    /// Returns a matrix from its four `rows`
+    pub fn from_rows(rows: [[f64; 4]; 4]) -> Self {
+        Self { m: rows }
+    }
+
+    /// Returns the identity matrix, which leaves every point unchanged
+    pub fn identity() -> Self {
+        Self::from_rows([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns a matrix that moves points by `offset`
+    pub fn translation(offset: Vec3) -> Self {
+        let mut matrix = Self::identity();
+        for i in 0..3 {
+            matrix.m[i][3] = offset[i];
+        }
+        matrix
+    }
+
+    /// Returns a matrix that scales each axis by the matching component of `factors`
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut matrix = Self::identity();
+        for i in 0..3 {
+            matrix.m[i][i] = factors[i];
+        }
+        matrix
+    }
+
+    /// Returns a matrix that rotates by `degrees` about the x-axis
+    pub fn rotation_x(degrees: f64) -> Self {
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        Self::from_rows([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns a matrix that rotates by `degrees` about the y-axis, in the same direction as
+    /// `RotateY`
+    pub fn rotation_y(degrees: f64) -> Self {
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        Self::from_rows([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns a matrix that rotates by `degrees` about the z-axis
+    pub fn rotation_z(degrees: f64) -> Self {
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        Self::from_rows([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns a matrix that rotates by `degrees` about `axis`, a line through the origin,
+    /// following the right hand rule. The axis does not need to be a unit vector
+    ///
+    /// # Panics
+    /// if `axis` is the zero vector
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        assert!(
+            axis.length_squared() > 0.0,
+            "can't rotate about a zero length axis"
+        );
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        let t = 1.0 - cos;
+        Self::from_rows([
+            [
+                t * a.x() * a.x() + cos,
+                t * a.x() * a.y() - sin * a.z(),
+                t * a.x() * a.z() + sin * a.y(),
+                0.0,
+            ],
+            [
+                t * a.x() * a.y() + sin * a.z(),
+                t * a.y() * a.y() + cos,
+                t * a.y() * a.z() - sin * a.x(),
+                0.0,
+            ],
+            [
+                t * a.x() * a.z() - sin * a.y(),
+                t * a.y() * a.z() + sin * a.x(),
+                t * a.z() * a.z() + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the rows of this matrix
+    pub fn rows(&self) -> [[f64; 4]; 4] {
+        self.m
+    }
+
+    /// Returns the transpose of this matrix
+    pub fn transpose(&self) -> Self {
+        let mut transpose = *self;
+        for (i, row) in transpose.m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        transpose
+    }
+
+    /// Returns the inverse of this matrix, or `None` if the matrix is singular (i.e. it scales
+    /// an axis by zero). The inverse is found by Gauss-Jordan elimination with partial pivoting
+    pub fn inverse(&self) -> Option<Self> {
+        let mut m = self.m;
+        let mut inverse = Self::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+                .unwrap_or(col);
+            if m[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            m.swap(col, pivot);
+            inverse.swap(col, pivot);
+
+            let scale = 1.0 / m[col][col];
+            for j in 0..4 {
+                m[col][j] *= scale;
+                inverse[col][j] *= scale;
+            }
+            for row in 0..4 {
+                let factor = m[row][col];
+                if row != col && factor != 0.0 {
+                    for j in 0..4 {
+                        m[row][j] -= factor * m[col][j];
+                        inverse[row][j] -= factor * inverse[col][j];
+                    }
+                }
+            }
+        }
+        Some(Self::from_rows(inverse))
+    }
+
+    /// Returns the determinant of the upper-left 3x3 (linear) part of this matrix, the factor by
+    /// which it scales volumes. A negative determinant means the matrix mirrors space
+    pub fn linear_determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Returns the point `p` transformed by this matrix
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Returns the vector `v` transformed by this matrix, which ignores its translation
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let row = |i: usize| self.m[i][0] * v.x() + self.m[i][1] * v.y() + self.m[i][2] * v.z();
+        Vec3::new(row(0), row(1), row(2))
+    }
+}
+
+impl Default for Mat4 {
+    /// Returns the identity matrix
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// Returns the matrix that applies `rhs` first, and then `self`
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        let mut product = [[0.0; 4]; 4];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4::from_rows(product)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Mat4, Point3, Vec3};
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotations_follow_the_right_hand_rule() {
+        let p = Point3::new(0.0, 1.0, 0.0);
+        assert_near(
+            Mat4::rotation_x(90.0).transform_point(&p),
+            Point3::new(0.0, 0.0, 1.0),
+        );
+        assert_near(
+            Mat4::rotation_z(90.0).transform_point(&p),
+            Point3::new(-1.0, 0.0, 0.0),
+        );
+        assert_near(
+            Mat4::rotation_y(90.0).transform_point(&Point3::new(0.0, 0.0, 1.0)),
+            Point3::new(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn rotations_about_the_axes_match_the_axis_rotations() {
+        let axes = [
+            (Vec3::new(2.0, 0.0, 0.0), Mat4::rotation_x(35.0)),
+            (Vec3::new(0.0, 1.0, 0.0), Mat4::rotation_y(35.0)),
+            (Vec3::new(0.0, 0.0, 0.5), Mat4::rotation_z(35.0)),
+        ];
+        let p = Point3::new(1.0, 2.0, 3.0);
+        for (axis, expected) in axes {
+            assert_near(
+                Mat4::rotation(axis, 35.0).transform_point(&p),
+                expected.transform_point(&p),
+            );
+        }
+    }
+
+    #[test]
+    fn a_matrix_times_its_inverse_is_the_identity() {
+        let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation_x(30.0)
+            * Mat4::rotation_z(-45.0)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, 3.0));
+        let product = (m * m.inverse().unwrap()).rows();
+        let identity = Mat4::identity().rows();
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((product[i][j] - identity[i][j]).abs() < 1e-9);
+            }
+        }
+        assert!((m.linear_determinant() - 3.0).abs() < 1e-9);
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn vectors_are_not_translated() {
+        let m =
+            Mat4::translation(Vec3::new(5.0, 5.0, 5.0)) * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+        assert_near(
+            m.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(2.0, 0.0, 0.0),
+        );
+        assert_near(
+            m.transform_point(&Point3::new(1.0, 0.0, 0.0)),
+            Point3::new(7.0, 5.0, 5.0),
+        );
+    }
+}
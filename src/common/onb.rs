@@ -0,0 +1,47 @@
+use crate::common::Vec3;
+
+/// An orthonormal basis of three perpendicular unit vectors, `u`, `v` and `w`. It transforms
+/// directions that were generated relative to the z-axis, such as the samples of a `CosinePdf`,
+/// into directions relative to `w`, such as the normal of a surface
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Returns an orthonormal basis whose `w` axis points along `n`, which need not be a unit
+    /// vector
+    pub fn build_from_w(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        let (u, v) = w.build_orthonormal_basis();
+        Self { u, v, w }
+    }
+
+    /// Returns the vector with coordinates `(a, b, c)` in this basis
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        a * self.u + b * self.v + c * self.w
+    }
+
+    /// Transforms the vector `a`, whose coordinates are in this basis, into world space
+    pub fn local_vec(&self, a: &Vec3) -> Vec3 {
+        self.local(a.x(), a.y(), a.z())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Onb, Vec3};
+
+    #[test]
+    fn the_z_axis_maps_to_w() {
+        let onb = Onb::build_from_w(&Vec3::new(0.0, 3.0, 4.0));
+
+        let w = onb.local_vec(&Vec3::new(0.0, 0.0, 1.0));
+        assert!((w - Vec3::new(0.0, 0.6, 0.8)).length() < 1e-12);
+        assert!(onb.u.dot(&onb.v).abs() < 1e-12);
+        assert!(onb.u.dot(&onb.w).abs() < 1e-12);
+        assert!((onb.u.length() - 1.0).abs() < 1e-12 && (onb.v.length() - 1.0).abs() < 1e-12);
+    }
+}
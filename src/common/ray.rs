@@ -1,18 +1,33 @@
 use crate::common::{Point3, Vec3};
 
 /// a three dimensional Ray consisting of an origin point, a direction `dir` ['Vec3'] and
-/// a moment in `time` that the ray existed
+/// a moment in `time` that the ray existed.
+/// `spread` is the angle (in radians) that the ray's footprint widens by, per unit of distance
+/// travelled. It is used to estimate how much of a surface a ray covers when it hits.
 #[derive(Debug, Default, PartialEq, Copy, Clone)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
     time: f64,
+    spread: f64,
 }
 
 impl Ray {
-    /// construct a new Ray with the given `origin`, `direction`, and `time`
+    /// construct a new Ray with the given `origin`, `direction`, and `time`. The ray has
+    /// no spread
     pub fn new(orig: Point3, dir: Vec3, time: f64) -> Self {
-        Self { orig, dir, time }
+        Self {
+            orig,
+            dir,
+            time,
+            spread: 0.0,
+        }
+    }
+
+    /// returns this Ray with its spread angle set to `spread` radians
+    pub fn with_spread(mut self, spread: f64) -> Self {
+        self.spread = spread;
+        self
     }
 
     /// returns a copy of this Ray's origin field
@@ -30,6 +45,11 @@ impl Ray {
         self.time
     }
 
+    /// returns the spread angle of this Ray, in radians
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
     /// returns the point, on this Ray, **at** the "ray parameter" `t`
     /// **P(t) = A + tb**
     pub fn at(&self, t: f64) -> Point3 {
@@ -0,0 +1,123 @@
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+thread_local! {
+    /// the random number generator of each thread, seeded from the operating system by default
+    static THREAD_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// The single source of randomness used by the ray tracer. Every random number, from camera
+/// shutter times and material scattering to the sampling of lights, is drawn from
+/// `RngContext::current()`, so that reseeding it makes a render reproducible.
+///
+/// Each thread has its own generator, so an `RngContext` is a cheap handle, like
+/// `rand::rngs::ThreadRng`, that can not be sent to other threads. The renderer reseeds the
+/// generator of a worker thread for each tile, when a seed is set with `Renderer::with_seed`,
+/// so that the output does not depend on how tiles are scheduled. The generator the worker had
+/// before the tile is restored afterwards, so unseeded renders on the same threads stay random
+#[derive(Debug, Default)]
+pub struct RngContext {
+    // prevents an RngContext from being sent to, or shared with, another thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl RngContext {
+    /// Returns a handle to the current thread's random number generator
+    pub fn current() -> Self {
+        RngContext::default()
+    }
+
+    /// Reseeds the current thread's random number generator with `seed`
+    pub fn seed(seed: u64) {
+        THREAD_RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    }
+
+    /// Reseeds the current thread's random number generator from the operating system
+    pub fn seed_from_entropy() {
+        THREAD_RNG.with(|rng| *rng.borrow_mut() = StdRng::from_entropy());
+    }
+
+    /// Reseeds the current thread's random number generator with `seed` until the returned
+    /// guard is dropped, which puts back the generator it replaced
+    pub fn seed_scoped(seed: u64) -> SeedGuard {
+        let previous = THREAD_RNG
+            .with(|rng| std::mem::replace(&mut *rng.borrow_mut(), StdRng::seed_from_u64(seed)));
+        SeedGuard {
+            previous: Some(previous),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Restores the random number generator of the current thread, that was replaced by
+/// `RngContext::seed_scoped`, when dropped
+#[derive(Debug)]
+#[must_use = "the seed is only used until the guard is dropped"]
+pub struct SeedGuard {
+    previous: Option<StdRng>,
+    // the guard must be dropped on the thread whose generator it replaced
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for SeedGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            THREAD_RNG.with(|rng| *rng.borrow_mut() = previous);
+        }
+    }
+}
+
+impl RngCore for RngContext {
+    fn next_u32(&mut self) -> u32 {
+        THREAD_RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        THREAD_RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        THREAD_RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        THREAD_RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::RngContext;
+    use rand::Rng;
+
+    #[test]
+    fn reseeding_repeats_the_same_numbers() {
+        RngContext::seed(7);
+        let first: Vec<f64> = (0..8).map(|_| RngContext::current().gen()).collect();
+        RngContext::seed(7);
+        let second: Vec<f64> = (0..8).map(|_| RngContext::current().gen()).collect();
+        RngContext::seed_from_entropy();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn a_scoped_seed_restores_the_previous_generator() {
+        RngContext::seed(7);
+        let expected: Vec<f64> = (0..8).map(|_| RngContext::current().gen()).collect();
+        RngContext::seed(7);
+        let seeded = {
+            let _guard = RngContext::seed_scoped(11);
+            RngContext::current().gen::<f64>()
+        };
+        let after: Vec<f64> = (0..8).map(|_| RngContext::current().gen()).collect();
+        RngContext::seed_from_entropy();
+
+        // the seeded number came from the scoped generator, and the thread's own generator
+        // carries on where it left off
+        assert!(!expected.contains(&seeded));
+        assert_eq!(after, expected);
+    }
+}
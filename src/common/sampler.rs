@@ -0,0 +1,218 @@
+use crate::common::RngContext;
+use rand::Rng;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// the prime bases used for each of the four dimensions of a `Halton4` sample
+const HALTON_BASES: [u32; 4] = [2, 3, 5, 7];
+
+/// Returns the `index`th element of the Halton (radical inverse) sequence in the given prime
+/// `base`. The elements are in the range `[0..1)` and evenly fill it as the index increases
+pub fn halton(mut index: u32, base: u32) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inv_base;
+    }
+    result
+}
+
+/// A source of the four dimensional samples taken within a single pixel: the first two
+/// dimensions are used for the pixel (anti-aliasing) offset, and the last two for the lens
+/// (defocus blur) offset. A renderer creates one sampler for each tile, and reseeds it before
+/// the first sample of every pixel
+pub trait Sampler: Send + Sync + Debug {
+    /// Returns the `index`th sample of this sampler, each dimension is in the range `[0..1)`
+    fn sample(&self, index: u32) -> [f64; 4];
+
+    /// Prepares this sampler for the samples of another pixel, so that neighboring pixels do
+    /// not share the same sample pattern. The base implementation of this trait does nothing
+    fn reseed(&mut self) {}
+}
+
+/// The kind of `Sampler` a renderer creates for each pixel
+/// `Random` - independent random numbers, taken from the thread's random number generator
+/// `Halton` - the default, the stratified, low-discrepancy, `Halton4` sequence, which converges
+///  faster than random numbers for the same number of samples
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerKind {
+    Random,
+    #[default]
+    Halton,
+}
+
+impl SamplerKind {
+    /// Returns a new sampler of this kind
+    pub fn sampler(&self) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Random => Box::new(RandomSampler),
+            SamplerKind::Halton => Box::new(Halton4::new()),
+        }
+    }
+}
+
+/// Parses a sampler kind from `random` or `halton`
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "random" => Ok(SamplerKind::Random),
+            "halton" => Ok(SamplerKind::Halton),
+            _ => Err(format!(
+                "unknown sampler '{}', expected random or halton",
+                s
+            )),
+        }
+    }
+}
+
+/// A sampler that ignores the sample index and returns independent random numbers, i.e. plain
+/// Monte Carlo sampling
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RandomSampler;
+
+impl Sampler for RandomSampler {
+    fn sample(&self, _index: u32) -> [f64; 4] {
+        let mut rng = RngContext::current();
+        [rng.gen(), rng.gen(), rng.gen(), rng.gen()]
+    }
+}
+
+/// A four dimensional, low-discrepancy, sampler built from the Halton sequence. It generates
+/// the samples of a single pixel: the first two dimensions are used for the pixel (anti-aliasing)
+/// offset, and the last two for the lens (defocus blur) offset, so that both are evenly
+/// distributed with respect to each other.
+///
+/// Every sampler applies its own random offset (a Cranley-Patterson rotation) to the
+/// sequence, and draws a new one when it is reseeded, so that neighboring pixels do not share
+/// the same sample pattern
+#[derive(Debug, Copy, Clone)]
+pub struct Halton4 {
+    offset: [f64; 4],
+}
+
+impl Halton4 {
+    /// Returns a new sampler with a random offset
+    pub fn new() -> Self {
+        let mut rng = RngContext::current();
+        Self {
+            offset: [rng.gen(), rng.gen(), rng.gen(), rng.gen()],
+        }
+    }
+}
+
+impl Sampler for Halton4 {
+    fn sample(&self, index: u32) -> [f64; 4] {
+        let mut sample = [0.0; 4];
+        for (dim, value) in sample.iter_mut().enumerate() {
+            *value = (halton(index, HALTON_BASES[dim]) + self.offset[dim]).fract();
+        }
+        sample
+    }
+
+    /// Draws a new random offset for the sequence
+    fn reseed(&mut self) {
+        *self = Halton4::new();
+    }
+}
+
+impl Default for Halton4 {
+    fn default() -> Self {
+        Halton4::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{halton, CameraBuilder, Halton4, Point3, Sampler, SamplerKind, Vec3};
+
+    #[test]
+    fn halton_base_2_is_the_van_der_corput_sequence() {
+        let first: Vec<f64> = (0..5).map(|i| halton(i, 2)).collect();
+        assert_eq!(first, vec![0.0, 0.5, 0.25, 0.75, 0.125]);
+    }
+
+    #[test]
+    fn only_the_random_sampler_ignores_the_sample_index() {
+        let random = SamplerKind::Random.sampler();
+        let halton = "Halton".parse::<SamplerKind>().unwrap().sampler();
+
+        assert_ne!(random.sample(3), random.sample(3));
+        assert_eq!(halton.sample(3), halton.sample(3));
+        assert!(random.sample(0).iter().all(|x| (0.0..1.0).contains(x)));
+        assert!("sobol".parse::<SamplerKind>().is_err());
+    }
+
+    #[test]
+    fn reseeding_a_halton_sampler_changes_its_pattern() {
+        let mut sampler = SamplerKind::Halton.sampler();
+        let first = sampler.sample(3);
+        sampler.reseed();
+
+        assert_ne!(sampler.sample(3), first);
+        assert_eq!(sampler.sample(3), sampler.sample(3));
+    }
+
+    #[test]
+    fn four_dimensional_sampling_has_lower_defocus_variance_than_independent_sampling() {
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(100)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(40.0)
+            .aperture(1.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        // estimates the average x coordinate, on a plane far behind the focus plane, that the
+        // rays of a (very large) pixel hit. The estimate depends on both the pixel and lens offsets
+        let estimate = |samples: &[[f64; 4]]| -> f64 {
+            let total: f64 = samples
+                .iter()
+                .map(|[px, py, lx, ly]| {
+                    let ray = camera.get_ray_with_lens(0.4 + 0.2 * px, 0.4 + 0.2 * py, *lx, *ly);
+                    let t = -10.0 / ray.direction().z();
+                    ray.at(t).x()
+                })
+                .sum();
+            total / samples.len() as f64
+        };
+        let variance = |estimates: &[f64]| -> f64 {
+            let mean = estimates.iter().sum::<f64>() / estimates.len() as f64;
+            estimates.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / estimates.len() as f64
+        };
+
+        let (trials, samples) = (200, 64);
+        let halton_estimates: Vec<f64> = (0..trials)
+            .map(|_| {
+                let sampler = Halton4::new();
+                let samples: Vec<[f64; 4]> = (0..samples).map(|i| sampler.sample(i)).collect();
+                estimate(&samples)
+            })
+            .collect();
+        let random_estimates: Vec<f64> = (0..trials)
+            .map(|_| {
+                let samples: Vec<[f64; 4]> = (0..samples)
+                    .map(|_| {
+                        [
+                            rand::random(),
+                            rand::random(),
+                            rand::random(),
+                            rand::random(),
+                        ]
+                    })
+                    .collect();
+                estimate(&samples)
+            })
+            .collect();
+
+        assert!(variance(&halton_estimates) < variance(&random_estimates));
+    }
+}
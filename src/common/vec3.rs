@@ -1,11 +1,17 @@
-use crate::common::clamp;
+use crate::common::{clamp, RngContext};
 use core::f64::consts::PI;
 use rand::Rng;
 use std::fmt::{Display, Formatter, Result};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
-/// a 3 dimensional vector containing `x`,`y` and `z` coordinates
+/// a 3 dimensional vector containing `x`,`y` and `z` coordinates. With the `serde` feature, a
+/// Vec3 is serialized as an array of its three coordinates
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f64; 3]", into = "[f64; 3]")
+)]
 pub struct Vec3 {
     x: f64,
     y: f64,
@@ -63,7 +69,7 @@ impl Vec3 {
 
     /// returns a `Vec3` with it's `x,y,z` fields set to a random f64 in the range `0..1`
     pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = RngContext::current();
         Self {
             x: rng.gen(),
             y: rng.gen(),
@@ -73,7 +79,7 @@ impl Vec3 {
 
     /// returns a `Vec3` with it's `x,y,z` fields set to a random f64 in the range `min..max`
     pub fn random_range(min: f64, max: f64) -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = RngContext::current();
         Self {
             x: rng.gen_range(min..max),
             y: rng.gen_range(min..max),
@@ -97,7 +103,7 @@ impl Vec3 {
     /// [Lambertian Diffuse](https://en.wikipedia.org/wiki/Lambert%27s_cosine_law) to generate
     /// a vector that is more uniformly distributed
     pub fn random_unit_vector() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = RngContext::current();
         let a = rng.gen_range(0.0..(2.0 * PI));
         let z = rng.gen_range(-1.0..1.0);
         let r = f64::sqrt(1.0 - z * z);
@@ -123,10 +129,27 @@ impl Vec3 {
         }
     }
 
+    /// returns a random unit vector in the hemisphere around the z-axis, where the probability
+    /// of each direction is proportional to the cosine of its angle to the z-axis, as used for
+    /// importance sampling a Lambertian surface
+    pub fn random_cosine_direction() -> Self {
+        let mut rng = RngContext::current();
+        let r1: f64 = rng.gen();
+        let r2: f64 = rng.gen();
+        let phi = 2.0 * PI * r1;
+        let r = r2.sqrt();
+
+        Self {
+            x: phi.cos() * r,
+            y: phi.sin() * r,
+            z: (1.0 - r2).sqrt(),
+        }
+    }
+
     /// generates a random vector within an "unit disk". Essentially a unit vector with a
     /// a random x,y value and z=0.0
     pub fn random_in_unit_disk() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = RngContext::current();
         loop {
             let p = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
             if p.length_squared() < 1.0 {
@@ -135,6 +158,42 @@ impl Vec3 {
         }
     }
 
+    /// returns two unit vectors `(b1, b2)` that, together with this vector, form an orthonormal
+    /// basis, i.e. for transforming directions sampled around the z-axis into the hemisphere
+    /// of a surface normal. This vector **must** be a unit vector.
+    /// Uses the branchless method of
+    /// [Duff et al.](https://graphics.pixar.com/library/OrthonormalB/paper.pdf)
+    pub fn build_orthonormal_basis(&self) -> (Vec3, Vec3) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        let b1 = Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x);
+        let b2 = Vec3::new(b, sign + self.y * self.y * a, -self.y);
+        (b1, b2)
+    }
+
+    /// returns the relative luminance of this vector, treating it as an RGB `Color` with
+    /// sRGB (Rec.709) primaries
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    /// returns the projection of this vector onto the vector `onto`, the component of this
+    /// vector that is parallel to `onto`. Projecting onto a zero vector returns a zero vector
+    pub fn project_onto(&self, onto: Vec3) -> Self {
+        let length_squared = onto.length_squared();
+        if length_squared == 0.0 {
+            return Vec3::default();
+        }
+        onto * (self.dot(&onto) / length_squared)
+    }
+
+    /// returns the rejection of this vector from the vector `from`, the component of this
+    /// vector that is perpendicular to `from`
+    pub fn reject_from(&self, from: Vec3) -> Self {
+        *self - self.project_onto(from)
+    }
+
     /// clamps each `x,y,z` field of this `Vec3` to be between `min` and `max`
     pub fn clamped(&mut self, min: f64, max: f64) {
         self.x = clamp(self.x, min, max);
@@ -166,9 +225,9 @@ impl Neg for Vec3 {
     /// ```
     fn neg(self) -> Self::Output {
         Self {
-            x: self.x * -1.0,
-            y: self.y * -1.0,
-            z: self.z * -1.0,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
         }
     }
 }
@@ -372,6 +431,18 @@ impl IndexMut<usize> for Vec3 {
     }
 }
 
+impl From<[f64; 3]> for Vec3 {
+    fn from(e: [f64; 3]) -> Self {
+        Vec3::new(e[0], e[1], e[2])
+    }
+}
+
+impl From<Vec3> for [f64; 3] {
+    fn from(v: Vec3) -> Self {
+        v.as_array()
+    }
+}
+
 /// displays each coordinate of this Vec3 with a precision of 3 decimal places
 impl Display for Vec3 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
@@ -424,6 +495,32 @@ mod tests {
         assert_eq!(cp.z, -3.0);
     }
 
+    #[test]
+    fn orthonormal_basis_is_orthogonal_and_unit_length() {
+        let normals = [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 2.0, -3.0).unit_vector(),
+            Vec3::new(-0.3, 0.1, 0.9).unit_vector(),
+        ];
+        for n in normals.iter() {
+            let (b1, b2) = n.build_orthonormal_basis();
+            assert!((b1.length() - 1.0).abs() < 1e-12);
+            assert!((b2.length() - 1.0).abs() < 1e-12);
+            assert!(b1.dot(&b2).abs() < 1e-12);
+            assert!(b1.dot(n).abs() < 1e-12);
+            assert!(b2.dot(n).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn luminance_of_white_is_one() {
+        assert!((Vec3::new(1.0, 1.0, 1.0).luminance() - 1.0).abs() < 1e-12);
+        assert!(Vec3::new(0.0, 1.0, 0.0).luminance() > Vec3::new(1.0, 0.0, 0.0).luminance());
+    }
+
     #[test]
     fn add_two_vec3s() {
         let v1 = Vec3::new(1.0, 2.0, 3.0);
@@ -535,4 +632,22 @@ mod tests {
         let varr = v.as_array();
         assert_eq!(v.as_array(), varr);
     }
+
+    #[test]
+    fn vec3_project_onto_x_axis() {
+        let v = Vec3::new(1.0, 1.0, 0.0);
+        assert_eq!(
+            v.project_onto(Vec3::new(2.0, 0.0, 0.0)),
+            Vec3::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn vec3_reject_from_x_axis() {
+        let v = Vec3::new(1.0, 1.0, 0.0);
+        assert_eq!(
+            v.reject_from(Vec3::new(2.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0)
+        );
+    }
 }
@@ -13,6 +13,18 @@ pub use flip_face::*;
 pub mod bvh_node;
 pub use bvh_node::*;
 
+pub mod flat_bvh;
+pub use flat_bvh::*;
+
+pub mod uniform_grid;
+pub use uniform_grid::*;
+
+pub mod kd_tree;
+pub use kd_tree::*;
+
+pub mod refit_bvh;
+pub use refit_bvh::*;
+
 pub mod hit_record;
 pub use hit_record::*;
 
@@ -25,7 +37,38 @@ pub use translate::*;
 pub mod rotate;
 pub use rotate::*;
 
-use crate::common::Ray;
+pub mod transform;
+pub use transform::*;
+
+pub mod affine_transform;
+pub use affine_transform::*;
+
+pub mod animated_transform;
+pub use animated_transform::*;
+
+pub mod instance;
+pub use instance::*;
+
+pub mod mesh;
+pub use mesh::*;
+
+pub mod triangle_mesh;
+pub use triangle_mesh::*;
+
+pub mod visibility;
+pub use visibility::*;
+
+pub mod portal;
+pub use portal::*;
+
+pub mod object_id;
+pub use object_id::*;
+
+use crate::common::{Point3, Ray, Vec3};
+use rand::RngCore;
+
+/// the default `tessellation` used when tessellating curved hittables, see `Hittable::tessellate`
+pub const DEFAULT_TESSELLATION: u32 = 16;
 
 /// A trait for primitives in a scene that can be *hit* by a Ray
 pub trait Hittable: Send + Sync + std::fmt::Debug {
@@ -34,6 +77,42 @@ pub trait Hittable: Send + Sync + std::fmt::Debug {
     /// hit must be between `t_min and t_max`. If the Ray did not hit then `None` is returned
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
 
+    /// returns `true` if the given `[Ray]` `r` hits *anything* between `t_min` and `t_max`.
+    /// Unlike `hit`, the closest hit does not need to be found, so this is a cheaper test for
+    /// occlusion (i.e. shadow rays). The default implementation calls `hit`
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit(r, t_min, t_max).is_some()
+    }
+
     /// Computes and returns the axis-aligned bounding box `Aabb` of this hittable
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb>;
+
+    /// Returns a triangle `Mesh` approximating the surface of this hittable, or `None` if this
+    /// hittable can not be tessellated. The default implementation returns `None`.
+    /// `tessellation` is the number of latitude bands used to subdivide curved surfaces, such
+    /// as spheres, which also use twice as many longitude bands. Flat surfaces ignore it
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        None
+    }
+
+    /// Returns the probability density, with respect to solid angle, of sampling `direction`
+    /// from `origin` when this hittable is sampled as a light using `random`.
+    /// The default implementation returns 0, meaning this hittable can not be light sampled
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Returns a random direction from `origin` towards this hittable, used for sampling
+    /// this hittable as a light. The default implementation returns the x-axis
+    fn random(&self, _origin: &Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Returns a random point that is uniformly distributed over the surface area of this
+    /// hittable, along with the outward facing (unit) normal of the surface at that point, i.e.
+    /// for scattering instances of other objects over this hittable.
+    /// The default implementation returns `None`, meaning this hittable can not be sampled
+    fn sample_surface(&self, _rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        None
+    }
 }
@@ -1,4 +1,12 @@
 use crate::common::{Point3, Ray};
+#[cfg(feature = "bvh-heatmap")]
+use std::cell::Cell;
+
+#[cfg(feature = "bvh-heatmap")]
+thread_local! {
+    /// the number of `Aabb::hit` tests performed by each thread
+    static HIT_TESTS: Cell<u64> = const { Cell::new(0) };
+}
 
 /// Axis Aligned Bounding Box that surrounds a `Hittable`
 #[derive(Debug, Clone, Copy)]
@@ -36,6 +44,8 @@ impl Aabb {
     /// `tmin,tmax` are the positions on the Ray that "intersected" the bounding box.
     /// This hit function was developed by Andrew Kensler at Pixar
     pub fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
+        #[cfg(feature = "bvh-heatmap")]
+        HIT_TESTS.with(|tests| tests.set(tests.get() + 1));
         let mut tmin = tmin;
         let mut tmax = tmax;
 
@@ -58,6 +68,17 @@ impl Aabb {
         Some((tmin, tmax))
     }
 
+    /// Returns the number of `hit` tests the current thread has performed so far. The
+    /// difference between two calls is the number of tests performed in between, i.e. while
+    /// a ray traversed a BVH. The tests are only counted with the `bvh-heatmap` feature, which
+    /// keeps the counting off the hot path of regular renders, otherwise this is always 0
+    pub fn hit_tests() -> u64 {
+        #[cfg(feature = "bvh-heatmap")]
+        return HIT_TESTS.with(|tests| tests.get());
+        #[cfg(not(feature = "bvh-heatmap"))]
+        0
+    }
+
     /// Returns an axis-aligned bounding box, that surrounds `box0` **and** `box1`
     pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Self {
         let small: Point3 = Point3::new(
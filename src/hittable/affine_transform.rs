@@ -0,0 +1,239 @@
+use crate::common::{Mat4, Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
+use std::sync::Arc;
+
+/// An AffineTransform places a `Hittable` in the world with an arbitrary affine `Mat4`, so that
+/// a hittable can be rotated about any axis, scaled (also unevenly, or mirrored), sheared and
+/// translated by a single wrapper, instead of a chain of `RotateY` and `Translate` wrappers.
+///
+/// Rays are moved into the hittable's own (object) space by the inverse of the matrix. The
+/// direction of the ray is not normalized, so the `t` of a hit is the same in both spaces.
+/// Normals are moved back into world space with the normal matrix, the transpose of the
+/// inverse, which keeps them perpendicular to the surface under uneven scaling
+#[derive(Debug)]
+pub struct AffineTransform {
+    ptr: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+    bbox: Option<Aabb>,
+}
+
+impl AffineTransform {
+    /// Returns a new `AffineTransform` hittable that places the hittable `p` in the world
+    /// with `matrix`
+    ///
+    /// # Panics
+    /// if the matrix is singular, i.e. it scales an axis by zero
+    pub fn from(p: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        let inverse = matrix
+            .inverse()
+            .expect("can't transform a Hittable with a singular matrix");
+
+        // the bounding box surrounds the eight transformed corners of the hittable's box
+        let bbox = p.bounding_box(0.0, 1.0).map(|bbox| {
+            let (min, max) = (bbox.min(), bbox.max());
+            (0..8)
+                .map(|corner| {
+                    matrix.transform_point(&Point3::new(
+                        if corner & 1 == 0 { min.x() } else { max.x() },
+                        if corner & 2 == 0 { min.y() } else { max.y() },
+                        if corner & 4 == 0 { min.z() } else { max.z() },
+                    ))
+                })
+                .fold(None, |acc: Option<Aabb>, p| {
+                    let corner = Aabb::new(p, p);
+                    Some(acc.map_or(corner, |acc| Aabb::surrounding_box(&acc, &corner)))
+                })
+                .expect("a box has eight corners")
+        });
+
+        Self {
+            ptr: p,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox,
+        }
+    }
+
+    /// Returns the matrix that places the hittable in the world
+    pub fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+
+    /// Returns the ray `r` moved into object space
+    fn object_ray(&self, r: &Ray) -> Ray {
+        object_ray(&self.inverse, r)
+    }
+}
+
+/// Returns the ray `r` moved into object space by the `inverse` of a transform's matrix.
+/// The footprint of a ray is `spread * t * |direction|` wide, and `t` is the same in both
+/// spaces, so the footprint already shrinks and grows with the length of the object space
+/// direction. The matrix resizes the footprint by its average scale instead, so the spread is
+/// corrected by the ratio of the two, which is 1 for rigid and uniformly scaled transforms
+pub(crate) fn object_ray(inverse: &Mat4, r: &Ray) -> Ray {
+    let direction = inverse.transform_vector(&r.direction());
+    let stretch = direction.length() / r.direction().length();
+    let spread = match stretch > 0.0 {
+        true => r.spread() * inverse.linear_determinant().abs().cbrt() / stretch,
+        false => r.spread(),
+    };
+    Ray::new(inverse.transform_point(&r.origin()), direction, r.time()).with_spread(spread)
+}
+
+impl Hittable for AffineTransform {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.ptr
+            .hit(&self.object_ray(r), t_min, t_max)
+            .map(|mut rec| {
+                // the normal matrix keeps the sign of the normal's dot product with the ray
+                // direction, so the normal still faces against the ray and front_face is kept
+                rec.p = self.matrix.transform_point(&rec.p);
+                rec.normal = self
+                    .normal_matrix
+                    .transform_vector(&rec.normal)
+                    .unit_vector();
+                rec.velocity = self.matrix.transform_vector(&rec.velocity);
+                rec
+            })
+    }
+
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.ptr.hit_any(&self.object_ray(r), t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        self.bbox
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation).map(|mut mesh| {
+            mesh.transform_vertices(|v| self.matrix.transform_point(&v));
+            mesh
+        })
+    }
+
+    /// Returns the pdf of the hittable, seen from `origin` and `direction` moved into object
+    /// space. Scaling changes the solid angle the hittable covers, so the object space pdf is
+    /// multiplied by the Jacobian of the mapping of world directions to object directions
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let object_direction = self.inverse.transform_vector(&direction.unit_vector());
+        let length = object_direction.length();
+        let jacobian = self.inverse.linear_determinant().abs() / (length * length * length);
+        self.ptr.pdf_value(
+            &self.inverse.transform_point(origin),
+            &(object_direction / length),
+        ) * jacobian
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.matrix
+            .transform_vector(&self.ptr.random(&self.inverse.transform_point(origin)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Mat4, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_sphere, AffineTransform, BoxInst, Hittable, RotateY, Translate,
+    };
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn hits_match_the_rotate_y_and_translate_wrappers() {
+        let geometry: Arc<dyn Hittable> = Arc::new(BoxInst::from(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 1.0),
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let offset = Vec3::new(1.0, 2.0, -5.0);
+        let transformed = AffineTransform::from(
+            Arc::clone(&geometry),
+            Mat4::translation(offset) * Mat4::rotation_y(30.0),
+        );
+        let wrapped = Translate::from(Arc::new(RotateY::from(geometry, 30.0)), offset);
+
+        for x in 0..20 {
+            for y in 0..20 {
+                let ray = Ray::new(
+                    Point3::new(x as f64 * 0.2 - 1.0, y as f64 * 0.2, 5.0),
+                    Vec3::new(0.1, 0.05, -1.0),
+                    0.0,
+                );
+                let expected = wrapped.hit(&ray, 0.001, f64::INFINITY);
+                let hit = transformed.hit(&ray, 0.001, f64::INFINITY);
+                assert_eq!(hit.is_some(), expected.is_some());
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert!((hit.t - expected.t).abs() < 1e-9);
+                    assert!((hit.p - expected.p).length() < 1e-9);
+                    assert!((hit.normal - expected.normal).length() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_sphere_scaled_along_x_has_perpendicular_normals() {
+        let sphere = Arc::new(build_solid_sphere(
+            Point3::default(),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let ellipsoid = AffineTransform::from(sphere, Mat4::scaling(Vec3::new(2.0, 1.0, 1.0)));
+
+        let bbox = ellipsoid.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.max() - Point3::new(2.0, 1.0, 1.0)).length() < 1e-9);
+
+        // hit the ellipsoid x^2/4 + y^2 + z^2 = 1 at (1.2, 0, 0.8), where its normal is along
+        // the gradient (x/2, 2y, 2z)
+        let p = Point3::new(1.2, 0.0, 0.8);
+        let ray = Ray::new(p + Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = ellipsoid.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - p).length() < 1e-9);
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        let gradient = Vec3::new(p.x() / 2.0, 2.0 * p.y(), 2.0 * p.z()).unit_vector();
+        assert!((rec.normal - gradient).length() < 1e-9);
+    }
+
+    #[test]
+    fn the_pdf_of_a_scaled_sphere_matches_a_larger_sphere() {
+        let small = Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let scaled = AffineTransform::from(small, Mat4::scaling(Vec3::new(2.0, 2.0, 2.0)));
+        let large = build_solid_sphere(Point3::new(0.0, 0.0, -4.0), 1.0, Color::new(0.5, 0.5, 0.5));
+
+        let origin = Point3::new(0.0, 0.0, 1.0);
+        let direction = Vec3::new(0.05, 0.02, -1.0);
+        let expected = large.pdf_value(&origin, &direction);
+        assert!(expected > 0.0);
+        assert!((scaled.pdf_value(&origin, &direction) - expected).abs() < 1e-9 * expected);
+    }
+
+    #[test]
+    fn a_scaled_sphere_has_the_texture_footprint_of_a_larger_sphere() {
+        let small = Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let scaled = AffineTransform::from(small, Mat4::scaling(Vec3::new(2.0, 2.0, 2.0)));
+        let large = build_solid_sphere(Point3::new(0.0, 0.0, -4.0), 1.0, Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+            .with_spread(0.001);
+
+        let expected = large.hit(&ray, 0.001, f64::INFINITY).unwrap().uv_footprint;
+        let footprint = scaled.hit(&ray, 0.001, f64::INFINITY).unwrap().uv_footprint;
+        assert!(expected > 0.0);
+        assert!((footprint - expected).abs() < 1e-9 * expected);
+    }
+}
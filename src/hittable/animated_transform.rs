@@ -0,0 +1,516 @@
+use crate::common::{degrees_to_radians, Lerp, Mat4, Point3, Ray, Vec3};
+use crate::hittable::{
+    affine_transform, Aabb, AffineTransform, HitRecord, Hittable, Mesh, Transform,
+};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A Pose places a hittable at one end of an `AnimatedTransform`: the hittable is first scaled
+/// by `scale`, then rotated by `degrees` about the axis of the animated transform, and then
+/// translated by `offset`. The default pose leaves a hittable unchanged
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pose {
+    pub scale: Vec3,
+    pub degrees: f64,
+    pub offset: Vec3,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self {
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            degrees: 0.0,
+            offset: Vec3::default(),
+        }
+    }
+}
+
+/// A `Transform` is a pose about the y-axis, that does not scale
+impl From<Transform> for Pose {
+    fn from(transform: Transform) -> Self {
+        Self {
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            degrees: transform.rotate_y,
+            offset: transform.offset,
+        }
+    }
+}
+
+/// Poses are interpolated one component at a time, like `Transform`s
+impl Lerp for Pose {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Self {
+            scale: self.scale.lerp(&other.scale, t),
+            degrees: self.degrees.lerp(&other.degrees, t),
+            offset: self.offset.lerp(&other.offset, t),
+        }
+    }
+}
+
+impl Pose {
+    /// Returns the matrix of this pose, rotating about `axis`, a line through the origin
+    pub fn matrix(&self, axis: Vec3) -> Mat4 {
+        Mat4::translation(self.offset)
+            * Mat4::rotation(axis, self.degrees)
+            * Mat4::scaling(self.scale)
+    }
+}
+
+/// An AnimatedTransform moves any `Hittable` from `pose0` at `time0` to `pose1` at `time1`,
+/// so that boxes, meshes and whole groups of hittables can be motion blurred, like a
+/// `MovingSphere`. The scale, the rotation about the (fixed) `axis` and the offset of the pose
+/// are each interpolated linearly in time, and, like a `MovingSphere`, the motion continues on
+/// outside of the time interval.
+///
+/// Each ray is tested against the hittable placed at the time of the ray, and the velocity of
+/// a hit includes the growth of the scale and the spin of the rotation as well as the offset's
+/// motion. Lights are sampled, and tessellated, in their pose at `time0`
+#[derive(Debug)]
+pub struct AnimatedTransform {
+    ptr: Arc<dyn Hittable>,
+    axis: Vec3,
+    pose0: Pose,
+    pose1: Pose,
+    time0: f64,
+    time1: f64,
+    // the hittable placed in `pose0`, which samples and tessellates the hittable
+    rest: AffineTransform,
+}
+
+impl AnimatedTransform {
+    /// Returns a new `AnimatedTransform` hittable that moves `p` from `transform0` at `time0`
+    /// to `transform1` at `time1`, rotating it about the y-axis
+    pub fn new(
+        p: Arc<dyn Hittable>,
+        transform0: Transform,
+        transform1: Transform,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        AnimatedTransform::about_axis(
+            p,
+            Vec3::new(0.0, 1.0, 0.0),
+            Pose::from(transform0),
+            Pose::from(transform1),
+            time0,
+            time1,
+        )
+    }
+
+    /// Returns a new `AnimatedTransform` hittable that moves `p` from `pose0` at `time0` to
+    /// `pose1` at `time1`, rotating it about `axis`, a line through the origin of `p`
+    ///
+    /// # Panics
+    /// if `axis` is the zero vector, or if `pose0` scales an axis by zero
+    pub fn about_axis(
+        p: Arc<dyn Hittable>,
+        axis: Vec3,
+        pose0: Pose,
+        pose1: Pose,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        let axis = axis.unit_vector();
+        Self {
+            rest: AffineTransform::from(Arc::clone(&p), pose0.matrix(axis)),
+            ptr: p,
+            axis,
+            pose0,
+            pose1,
+            time0,
+            time1,
+        }
+    }
+
+    /// Returns the pose that places the hittable in the world at `time`
+    pub fn pose_at(&self, time: f64) -> Pose {
+        self.pose0.lerp(&self.pose1, self.fraction(time))
+    }
+
+    /// Returns the matrix that places the hittable in the world at `time`
+    pub fn matrix_at(&self, time: f64) -> Mat4 {
+        self.pose_at(time).matrix(self.axis)
+    }
+
+    /// Returns how far `time` is from `time0` to `time1`. A motion over an empty time interval
+    /// stays at `pose0`
+    fn fraction(&self, time: f64) -> f64 {
+        match self.time1 - self.time0 {
+            0.0 => 0.0,
+            duration => (time - self.time0) / duration,
+        }
+    }
+
+    /// Returns the change of the pose per unit of time, as the growth of the scale, the
+    /// angular velocity, in radians, about the axis, and the velocity of the offset
+    fn velocity(&self) -> (Vec3, f64, Vec3) {
+        match self.time1 - self.time0 {
+            0.0 => (Vec3::default(), 0.0, Vec3::default()),
+            duration => (
+                (self.pose1.scale - self.pose0.scale) / duration,
+                degrees_to_radians(self.pose1.degrees - self.pose0.degrees) / duration,
+                (self.pose1.offset - self.pose0.offset) / duration,
+            ),
+        }
+    }
+}
+
+/// Returns the `(min, max)` of `radius * cos(theta - phase)`, over `theta` in `[a, b]`
+fn cosine_range(radius: f64, phase: f64, a: f64, b: f64) -> (f64, f64) {
+    // returns true if an angle of `phase + offset + 2πk` lies within [a, b]
+    let passes = |offset: f64| {
+        let angle = phase + offset;
+        angle + ((a - angle) / (2.0 * PI)).ceil() * 2.0 * PI <= b
+    };
+    let (fa, fb) = (radius * (a - phase).cos(), radius * (b - phase).cos());
+    (
+        if passes(PI) { -radius } else { fa.min(fb) },
+        if passes(0.0) { radius } else { fa.max(fb) },
+    )
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let pose = self.pose_at(r.time());
+        let matrix = pose.matrix(self.axis);
+        // a pose that scales an axis by zero flattens the hittable, which can't be hit
+        let inverse = matrix.inverse()?;
+        let object_ray = affine_transform::object_ray(&inverse, r);
+
+        self.ptr.hit(&object_ray, t_min, t_max).map(|mut rec| {
+            // the normal matrix keeps the normal facing against the ray, so front_face is kept
+            let object_p = rec.p;
+            rec.p = matrix.transform_point(&rec.p);
+            rec.normal = inverse
+                .transpose()
+                .transform_vector(&rec.normal)
+                .unit_vector();
+
+            // the hit point is carried along by the offset, spun about the axis through the
+            // offset, and moved away from the axis as the scale grows
+            let (growth, spin, offset_velocity) = self.velocity();
+            let arm = rec.p - pose.offset;
+            let rotation = Mat4::rotation(self.axis, pose.degrees);
+            rec.velocity = matrix.transform_vector(&rec.velocity)
+                + offset_velocity
+                + spin * self.axis.cross(arm)
+                + rotation.transform_vector(&(growth * object_p));
+            rec
+        })
+    }
+
+    /// Returns a box around the hittable over all of its poses between `t0` and `t1`. The
+    /// hittable's own box is scaled by both end poses, each corner of the box of those scaled
+    /// boxes sweeps an arc about the axis as it rotates, and the box of those arcs is then
+    /// grown by the distance the offset moves
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        let bbox = self.ptr.bounding_box(t0, t1)?;
+        let (start, end) = (self.pose_at(t0), self.pose_at(t1));
+        let (a, b) = {
+            let (a, b) = (
+                degrees_to_radians(start.degrees),
+                degrees_to_radians(end.degrees),
+            );
+            (a.min(b), a.max(b))
+        };
+
+        let (mut min, mut max) = (
+            Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        );
+        // the scale is interpolated linearly, so the scaled box always lies within the box of
+        // the box scaled by each end pose
+        let (lo, hi) = (bbox.min(), bbox.max());
+        let scaled = [
+            lo * start.scale,
+            hi * start.scale,
+            lo * end.scale,
+            hi * end.scale,
+        ];
+        let (lo, hi) = scaled.iter().fold(
+            (
+                Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+                Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(lo, hi), p| {
+                (
+                    Point3::new(lo.x().min(p.x()), lo.y().min(p.y()), lo.z().min(p.z())),
+                    Point3::new(hi.x().max(p.x()), hi.y().max(p.y()), hi.z().max(p.z())),
+                )
+            },
+        );
+        for corner in 0..8 {
+            let p = Point3::new(
+                [lo.x(), hi.x()][corner & 1],
+                [lo.y(), hi.y()][(corner >> 1) & 1],
+                [lo.z(), hi.z()][corner >> 2],
+            );
+            // the corner rotates about its center c on the axis, as
+            // c + w cos(θ) + (axis × w) sin(θ), so each coordinate is c + R cos(θ - phase)
+            let center = self.axis * self.axis.dot(&p);
+            let (w, v) = (p - center, self.axis.cross(p - center));
+            let (mut corner_min, mut corner_max) = (center, center);
+            for i in 0..3 {
+                let (low, high) = cosine_range(w[i].hypot(v[i]), v[i].atan2(w[i]), a, b);
+                corner_min[i] += low;
+                corner_max[i] += high;
+            }
+            min = Point3::new(
+                min.x().min(corner_min.x()),
+                min.y().min(corner_min.y()),
+                min.z().min(corner_min.z()),
+            );
+            max = Point3::new(
+                max.x().max(corner_max.x()),
+                max.y().max(corner_max.y()),
+                max.z().max(corner_max.z()),
+            );
+        }
+
+        let offset_min = Vec3::new(
+            start.offset.x().min(end.offset.x()),
+            start.offset.y().min(end.offset.y()),
+            start.offset.z().min(end.offset.z()),
+        );
+        let offset_max = Vec3::new(
+            start.offset.x().max(end.offset.x()),
+            start.offset.y().max(end.offset.y()),
+            start.offset.z().max(end.offset.z()),
+        );
+        Some(Aabb::new(min + offset_min, max + offset_max))
+    }
+
+    /// Returns the tessellation of the hittable placed in `pose0`
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.rest.tessellate(tessellation)
+    }
+
+    /// Returns the pdf of the hittable placed in `pose0`, see `AffineTransform::pdf_value`
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.rest.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.rest.random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_sphere, AffineTransform, AnimatedTransform, BoxInst, Hittable, MovingSphere,
+        Pose, Transform,
+    };
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn a_translated_sphere_moves_like_a_moving_sphere() {
+        let material = Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let (center0, center1) = (Point3::new(0.0, 0.0, -3.0), Point3::new(2.0, 1.0, -3.0));
+        let moving = MovingSphere::new(center0, center1, 0.0, 1.0, 0.5, material);
+        let animated = AnimatedTransform::new(
+            Arc::new(build_solid_sphere(
+                Point3::default(),
+                0.5,
+                Color::new(0.5, 0.5, 0.5),
+            )),
+            Transform::translation(center0),
+            Transform::translation(center1),
+            0.0,
+            1.0,
+        );
+
+        for step in 0..=10 {
+            let time = step as f64 / 10.0;
+            let target = center0 + (center1 - center0) * time + Vec3::new(0.1, 0.2, 0.0);
+            let ray = Ray::new(Point3::default(), target, time);
+            let expected = moving.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            let rec = animated.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert!((rec.t - expected.t).abs() < 1e-9);
+            assert!((rec.p - expected.p).length() < 1e-9);
+            assert!((rec.normal - expected.normal).length() < 1e-9);
+            assert!((rec.velocity - expected.velocity).length() < 1e-9);
+        }
+        let expected_box = moving.bounding_box(0.0, 1.0).unwrap();
+        let bbox = animated.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.min() - expected_box.min()).length() < 1e-9);
+        assert!((bbox.max() - expected_box.max()).length() < 1e-9);
+    }
+
+    #[test]
+    fn the_bounding_box_holds_a_spinning_box_at_all_times() {
+        let box_inst: Arc<dyn Hittable> = Arc::new(BoxInst::from(
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 0.5),
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let transform0 = Transform::translation(Vec3::new(0.0, 0.0, -5.0));
+        let transform1 = Transform {
+            rotate_y: 135.0,
+            offset: Vec3::new(1.0, 0.0, -5.0),
+        };
+        let animated =
+            AnimatedTransform::new(Arc::clone(&box_inst), transform0, transform1, 0.0, 1.0);
+        let bbox = animated.bounding_box(0.0, 1.0).unwrap();
+
+        for step in 0..=50 {
+            let time = step as f64 / 50.0;
+            let placed = AffineTransform::from(Arc::clone(&box_inst), animated.matrix_at(time))
+                .bounding_box(0.0, 1.0)
+                .unwrap();
+            for axis in 0..3 {
+                assert!(placed.min()[axis] >= bbox.min()[axis] - 1e-9);
+                assert!(placed.max()[axis] <= bbox.max()[axis] + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn a_spinning_box_is_hit_where_it_is_at_the_ray_time() {
+        let box_inst: Arc<dyn Hittable> = Arc::new(BoxInst::from(
+            Point3::new(1.0, 0.0, -0.5),
+            Point3::new(2.0, 1.0, 0.5),
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let animated = AnimatedTransform::new(
+            box_inst,
+            Transform::default(),
+            Transform::rotation_y(180.0),
+            0.0,
+            1.0,
+        );
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        // the box starts on the positive x-axis, and ends on the negative x-axis
+        let right = Ray::new(Point3::new(1.5, 5.0, 0.0), down, 0.0);
+        let left = Ray::new(Point3::new(-1.5, 5.0, 0.0), down, 1.0);
+        assert!(animated.hit(&right, 0.001, f64::INFINITY).is_some());
+        assert!(animated.hit(&left, 0.001, f64::INFINITY).is_some());
+        assert!(animated
+            .hit(
+                &Ray::new(Point3::new(1.5, 5.0, 0.0), down, 1.0),
+                0.001,
+                f64::INFINITY
+            )
+            .is_none());
+
+        // half way, the box lies on the negative z-axis, and the hit point, 1.5 from the axis,
+        // spins towards -x at π radians per unit of time
+        let rec = animated
+            .hit(
+                &Ray::new(Point3::new(0.0, 5.0, -1.5), down, 0.5),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert!((rec.p - Point3::new(0.0, 1.0, -1.5)).length() < 1e-9);
+        let spin = std::f64::consts::PI;
+        assert!((rec.velocity - Vec3::new(-spin * 1.5, 0.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn the_bounding_box_holds_a_box_growing_and_tumbling_about_any_axis() {
+        let box_inst: Arc<dyn Hittable> = Arc::new(BoxInst::from(
+            Point3::new(0.5, -0.5, 0.0),
+            Point3::new(2.0, 1.0, 0.5),
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let pose0 = Pose {
+            offset: Vec3::new(0.0, 0.0, -5.0),
+            ..Pose::default()
+        };
+        let pose1 = Pose {
+            scale: Vec3::new(2.0, 0.5, 1.5),
+            degrees: 200.0,
+            offset: Vec3::new(1.0, 2.0, -5.0),
+        };
+        let animated = AnimatedTransform::about_axis(
+            Arc::clone(&box_inst),
+            Vec3::new(1.0, 0.5, 0.25),
+            pose0,
+            pose1,
+            0.0,
+            1.0,
+        );
+        let bbox = animated.bounding_box(0.0, 1.0).unwrap();
+
+        for step in 0..=50 {
+            let time = step as f64 / 50.0;
+            let placed = AffineTransform::from(Arc::clone(&box_inst), animated.matrix_at(time))
+                .bounding_box(0.0, 1.0)
+                .unwrap();
+            for axis in 0..3 {
+                assert!(placed.min()[axis] >= bbox.min()[axis] - 1e-9);
+                assert!(placed.max()[axis] <= bbox.max()[axis] + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn a_growing_sphere_is_hit_with_the_velocity_of_its_surface() {
+        let animated = AnimatedTransform::about_axis(
+            Arc::new(build_solid_sphere(
+                Point3::default(),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )),
+            Vec3::new(0.0, 0.0, 1.0),
+            Pose::default(),
+            Pose {
+                scale: Vec3::new(3.0, 3.0, 3.0),
+                ..Pose::default()
+            },
+            0.0,
+            1.0,
+        );
+
+        // half way, the sphere has a radius of 2, and its surface moves outwards at 2 units
+        // per unit of time
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.5);
+        let rec = animated.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point3::new(2.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((rec.velocity - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn an_animated_light_is_sampled_in_its_first_pose() {
+        let light = || {
+            Arc::new(build_solid_sphere(
+                Point3::default(),
+                0.5,
+                Color::new(0.5, 0.5, 0.5),
+            ))
+        };
+        let start = Transform::translation(Vec3::new(0.0, 0.0, -4.0));
+        let animated = AnimatedTransform::new(
+            light(),
+            start,
+            Transform::translation(Vec3::new(3.0, 0.0, -4.0)),
+            0.0,
+            1.0,
+        );
+        let rest = AffineTransform::from(light(), start.matrix());
+
+        let origin = Point3::default();
+        let direction = Vec3::new(0.05, 0.02, -1.0);
+        let expected = rest.pdf_value(&origin, &direction);
+        assert!(expected > 0.0);
+        assert_eq!(animated.pdf_value(&origin, &direction), expected);
+        for _ in 0..100 {
+            let direction = animated.random(&origin);
+            assert!(rest.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
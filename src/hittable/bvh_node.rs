@@ -1,11 +1,110 @@
-use crate::common::Ray;
-use crate::hittable::{Aabb, HitRecord, Hittable, HittableList};
-use rand::{thread_rng, Rng};
+use crate::common::{Ray, RngContext};
+use crate::hittable::{Aabb, HitRecord, Hittable, HittableList, Mesh};
+use rand::Rng;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Formatter;
 use std::sync::Arc;
 
+/// the number of hittables, at or below which `BvhNode::build_auto` keeps a BVH with single
+/// hittable leaves
+pub(crate) const AUTO_FLAT_LEAF_THRESHOLD: usize = 16;
+
+/// the leaf size chosen by `BvhNode::build_auto` for lists with more hittables than the
+/// `AUTO_FLAT_LEAF_THRESHOLD`
+pub(crate) const AUTO_LEAF_SIZE: usize = 4;
+
+/// A child of a `BvhNode`. Hittables are stored along with their index in the list the BVH
+/// was built from, so that ties between hits within the surface epsilon are resolved in favor
+/// of the hittable that was added to the list first, whatever order the BVH sorted them into
+enum BvhChild {
+    /// an interior node
+    Node(Box<BvhNode>),
+    /// a single hittable
+    Object(usize, Arc<dyn Hittable>),
+    /// the hittables of a leaf that is tested linearly, see `BvhNode::from_with_leaf_size`
+    List(Arc<[(usize, Arc<dyn Hittable>)]>),
+}
+
+impl BvhChild {
+    /// Returns the closest hit of this child, along with the index of the hittable that was hit
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        surface_epsilon: f64,
+    ) -> Option<(HitRecord, usize)> {
+        match self {
+            BvhChild::Node(node) => node.hit_indexed(ray, t_min, t_max),
+            BvhChild::Object(index, object) => {
+                object.hit(ray, t_min, t_max).map(|rec| (rec, *index))
+            }
+            BvhChild::List(objects) => objects.iter().fold(
+                None,
+                |closest: Option<(HitRecord, usize)>, (index, object)| {
+                    let limit = closest
+                        .as_ref()
+                        .map_or(t_max, |(rec, _)| rec.t + surface_epsilon);
+                    match object.hit(ray, t_min, limit) {
+                        Some(rec) => BvhNode::closer(closest, (rec, *index), surface_epsilon),
+                        None => closest,
+                    }
+                },
+            ),
+        }
+    }
+
+    fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        match self {
+            BvhChild::Node(node) => node.hit_any(ray, t_min, t_max),
+            BvhChild::Object(_, object) => object.hit_any(ray, t_min, t_max),
+            BvhChild::List(objects) => objects
+                .iter()
+                .any(|(_, object)| object.hit_any(ray, t_min, t_max)),
+        }
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        match self {
+            BvhChild::Node(node) => node.bounding_box(t0, t1),
+            BvhChild::Object(_, object) => object.bounding_box(t0, t1),
+            BvhChild::List(objects) => objects
+                .iter()
+                .map(|(_, object)| object.bounding_box(t0, t1))
+                .reduce(|a, b| Some(Aabb::surrounding_box(&a?, &b?)))
+                .flatten(),
+        }
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        match self {
+            BvhChild::Node(node) => node.tessellate(tessellation),
+            BvhChild::Object(_, object) => object.tessellate(tessellation),
+            BvhChild::List(objects) => objects
+                .iter()
+                .filter_map(|(_, object)| object.tessellate(tessellation))
+                .reduce(|mut mesh, other| {
+                    mesh.append(other);
+                    mesh
+                }),
+        }
+    }
+}
+
+impl std::fmt::Debug for BvhChild {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BvhChild::Node(node) => node.fmt(f),
+            BvhChild::Object(_, object) => object.fmt(f),
+            BvhChild::List(objects) => f
+                .debug_list()
+                .entries(objects.iter().map(|(_, object)| object))
+                .finish(),
+        }
+    }
+}
+
 /// A Bounded Volume Hierarchy (BVH)
 /// A BVH is `Hittable` but it’s really a container. It's a binary "tree like" structure that can
 /// respond to the question, “does this ray hit you?”.
@@ -14,22 +113,61 @@ use std::sync::Arc;
 /// such that their bounding boxes are contained within their parent bounding box.
 /// The "leaves" of the BVH contain a single primitive, such as a sphere or cube etc...
 pub struct BvhNode {
-    left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+    left: BvhChild,
+    // `None` for a leaf that holds a single hittable, or a flat list of hittables, in `left`
+    right: Option<BvhChild>,
     // a bounding box that surrounds the BVH Node and it's children
     bbox: Aabb,
+    // the surface epsilon of the list this BVH was built from, see `HittableList`
+    surface_epsilon: f64,
 }
 
 impl BvhNode {
     /// Constructs a BVH from the `list` of Hittables. The returned BVH will be the "root" node
-    /// of the BVH
-    pub fn from(list: &mut HittableList, time0: f64, time1: f64) -> BvhNode {
-        BvhNode::split_volumes(list.objects(), time0, time1)
+    /// of the BVH. Like the list, the BVH resolves hits that are within the list's surface
+    /// epsilon of each other in favor of the object that was added to the list first.
+    /// The BVH sorts its own copy of the list's (reference counted) objects, so `list` is
+    /// only borrowed, and its order is left unchanged
+    pub fn from(list: &HittableList, time0: f64, time1: f64) -> BvhNode {
+        BvhNode::from_with_leaf_size(list, time0, time1, 1)
+    }
+
+    /// Constructs a BVH from the `list` of Hittables, like `from`, but stops subdividing the
+    /// list once a node holds `leaf_size` hittables or fewer. The hittables of those leaves
+    /// are kept in a flat list that is tested linearly, which saves the overhead of visiting
+    /// the many nodes near the bottom of the tree. A `leaf_size` of 1 builds the same tree
+    /// as `from`
+    pub fn from_with_leaf_size(
+        list: &HittableList,
+        time0: f64,
+        time1: f64,
+        leaf_size: usize,
+    ) -> BvhNode {
+        let surface_epsilon = list.surface_epsilon();
+        let mut objects: Vec<(usize, Arc<dyn Hittable>)> =
+            list.as_slice().iter().cloned().enumerate().collect();
+        BvhNode::split_volumes(&mut objects, time0, time1, surface_epsilon, leaf_size)
+    }
+
+    /// Constructs a BVH from the `list` of Hittables, choosing its leaf size from the number
+    /// of hittables in the list. Small lists get single hittable leaves, while larger lists
+    /// keep a few hittables in each leaf, see `from_with_leaf_size`
+    pub fn build_auto(list: &HittableList, time0: f64, time1: f64) -> BvhNode {
+        let leaf_size = match list.len() {
+            count if count <= AUTO_FLAT_LEAF_THRESHOLD => 1,
+            _ => AUTO_LEAF_SIZE,
+        };
+        BvhNode::from_with_leaf_size(list, time0, time1, leaf_size)
     }
 
     /// Constructs a single `BvhNode`
-    fn new(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>, bbox: Aabb) -> Self {
-        Self { left, right, bbox }
+    fn new(left: BvhChild, right: Option<BvhChild>, bbox: Aabb, surface_epsilon: f64) -> Self {
+        Self {
+            left,
+            right,
+            bbox,
+            surface_epsilon,
+        }
     }
 
     /// Constructs a BVH from a list of Hittables.
@@ -42,46 +180,86 @@ impl BvhNode {
     /// 1. randomly choose an axis
     /// 2. sort the (hittable) primitives
     /// 3. put half in each subtree
-    fn split_volumes(objects: &mut [Arc<dyn Hittable>], time0: f64, time1: f64) -> BvhNode {
+    ///
+    /// Each object is paired with its index in the list, which decides ties between hits
+    /// regardless of the axis the objects were sorted along.
+    /// Lists of more than two, but at most `leaf_size`, objects are not split, they are stored
+    /// as a flat list in the left child of a leaf node, which has no right child
+    fn split_volumes(
+        objects: &mut [(usize, Arc<dyn Hittable>)],
+        time0: f64,
+        time1: f64,
+        surface_epsilon: f64,
+        leaf_size: usize,
+    ) -> BvhNode {
         // randomly choose an x,y, or z axis for sorting the list of hittable objects
-        let axis: usize = thread_rng().gen_range(0..3);
+        let axis: usize = RngContext::current().gen_range(0..3);
 
+        let object = |(index, object): &(usize, Arc<dyn Hittable>)| {
+            BvhChild::Object(*index, Arc::clone(object))
+        };
         let mut node: BvhNode = if objects.len() == 1 {
-            // if there's only one element, put a reference to it in each subtree and end recursion
-            BvhNode::new(
-                Arc::clone(&objects[0]),
-                Arc::clone(&objects[0]),
-                Aabb::default(),
-            )
+            // if there's only one element, put it in the left subtree and end recursion
+            BvhNode::new(object(&objects[0]), None, Aabb::default(), surface_epsilon)
         } else if objects.len() == 2 {
             // if objects only has two elements, put one in each subtree and end recursion
-            if BvhNode::box_compare(&*objects[0], &*objects[1], axis) == Ordering::Less {
+            if BvhNode::box_compare(&*objects[0].1, &*objects[1].1, axis) != Ordering::Greater {
                 BvhNode::new(
-                    Arc::clone(&objects[0]),
-                    Arc::clone(&objects[1]),
+                    object(&objects[0]),
+                    Some(object(&objects[1])),
                     Aabb::default(),
+                    surface_epsilon,
                 )
             } else {
                 BvhNode::new(
-                    Arc::clone(&objects[1]),
-                    Arc::clone(&objects[0]),
+                    object(&objects[1]),
+                    Some(object(&objects[0])),
                     Aabb::default(),
+                    surface_epsilon,
                 )
             }
+        } else if objects.len() <= leaf_size {
+            // keep the objects of a small list together in a single leaf
+            BvhNode::new(
+                BvhChild::List(objects.into()),
+                None,
+                Aabb::default(),
+                surface_epsilon,
+            )
         } else {
             // recursively partition the remaining hittables into BVH Nodes, using their
             // bounding box axis' to sort then into left and right children
-            objects.sort_unstable_by(|a, b| BvhNode::box_compare(&**a, &**b, axis));
+            objects.sort_by(|a, b| BvhNode::box_compare(&*a.1, &*b.1, axis));
             let mid = objects.len() / 2;
-            let left = BvhNode::split_volumes(objects[0..mid].as_mut(), time0, time1);
-            let right = BvhNode::split_volumes(objects[mid..].as_mut(), time0, time1);
+            let left = BvhNode::split_volumes(
+                objects[0..mid].as_mut(),
+                time0,
+                time1,
+                surface_epsilon,
+                leaf_size,
+            );
+            let right = BvhNode::split_volumes(
+                objects[mid..].as_mut(),
+                time0,
+                time1,
+                surface_epsilon,
+                leaf_size,
+            );
 
-            BvhNode::new(Arc::new(left), Arc::new(right), Aabb::default())
+            BvhNode::new(
+                BvhChild::Node(Box::new(left)),
+                Some(BvhChild::Node(Box::new(right))),
+                Aabb::default(),
+                surface_epsilon,
+            )
         };
 
         // construct a bounding box encompassing this node's left and right children
         let box_left = node.left.bounding_box(time0, time1);
-        let box_right = node.right.bounding_box(time0, time1);
+        let box_right = match &node.right {
+            Some(right) => right.bounding_box(time0, time1),
+            None => box_left,
+        };
         if box_left.is_none() || box_right.is_none() {
             panic!("a hittable did not have a bounding box during BVH construction");
         }
@@ -96,7 +274,7 @@ impl BvhNode {
     /// 0 = x-axis,
     /// 1 = y-axis,
     /// 2 = z-axis
-    fn box_compare<T: Hittable + ?Sized>(a: &T, b: &T, axis: usize) -> Ordering {
+    pub(crate) fn box_compare<T: Hittable + ?Sized>(a: &T, b: &T, axis: usize) -> Ordering {
         let box_a = a
             .bounding_box(0.0, 0.0)
             .expect("Hittable 'a' doesn't have a bounding box");
@@ -115,6 +293,42 @@ impl BvhNode {
                 )
             })
     }
+
+    /// Returns the closer of the `closest` hit so far and a new `hit`, which was found within
+    /// the surface epsilon beyond the closest hit. Hits within the surface epsilon of each
+    /// other are resolved in favor of the hittable with the lower index, like `KdTree`
+    fn closer(
+        closest: Option<(HitRecord, usize)>,
+        hit: (HitRecord, usize),
+        surface_epsilon: f64,
+    ) -> Option<(HitRecord, usize)> {
+        match closest {
+            Some((rec, index)) if hit.0.t >= rec.t - surface_epsilon && hit.1 > index => {
+                Some((rec, index))
+            }
+            _ => Some(hit),
+        }
+    }
+
+    /// Returns the closest hit of this node, along with the index of the hittable that was hit
+    fn hit_indexed(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(HitRecord, usize)> {
+        // first check if the hittable's bounding box was hit
+        self.bbox.hit(ray, t_min, t_max)?;
+
+        // check if the left and right children are hit. The right child may replace the left
+        // hit if it is closer, or within the surface epsilon of it and added to the list first
+        let hit_left = self.left.hit(ray, t_min, t_max, self.surface_epsilon);
+        let Some(right) = &self.right else {
+            return hit_left;
+        };
+        let limit = hit_left
+            .as_ref()
+            .map_or(t_max, |(rec, _)| rec.t + self.surface_epsilon);
+        match right.hit(ray, t_min, limit, self.surface_epsilon) {
+            Some(hit_right) => BvhNode::closer(hit_left, hit_right, self.surface_epsilon),
+            None => hit_left,
+        }
+    }
 }
 
 impl Hittable for BvhNode {
@@ -122,25 +336,17 @@ impl Hittable for BvhNode {
     /// to determine which child was hit (if any).
     /// Returns a `HitRecord` for the deepest node that was hit
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        // first check if the hittable's bounding box was hit
-        self.bbox.hit(ray, t_min, t_max)?;
-
-        // check if the left and right children are hit. The hittable being checked could be
-        // a BvhNode, or some other Hittable, like a primitive (sphere etc...)
-        let hit_left = self.left.hit(ray, t_min, t_max);
-        let hit_right = if let Some(hit_rec) = &hit_left {
-            self.right.hit(ray, t_min, hit_rec.t)
-        } else {
-            self.right.hit(ray, t_min, t_max)
-        };
+        self.hit_indexed(ray, t_min, t_max).map(|(rec, _)| rec)
+    }
 
-        if hit_right.is_some() {
-            hit_right
-        } else if hit_left.is_some() {
-            hit_left
-        } else {
-            None
-        }
+    /// Returns `true` as soon as any child is hit, without searching for the closest hit
+    fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.bbox.hit(ray, t_min, t_max).is_some()
+            && (self.left.hit_any(ray, t_min, t_max)
+                || self
+                    .right
+                    .as_ref()
+                    .is_some_and(|right| right.hit_any(ray, t_min, t_max)))
     }
 
     /// Returns `Some(Aabb)` which is the axis-aligned bounding box that encompasses **all** of
@@ -148,6 +354,22 @@ impl Hittable for BvhNode {
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
         Some(self.bbox)
     }
+
+    /// Returns the combined tessellation of this node's children
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        let left = self.left.tessellate(tessellation);
+        let right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.tessellate(tessellation));
+        match (left, right) {
+            (Some(mut left), Some(right)) => {
+                left.append(right);
+                Some(left)
+            }
+            (left, right) => left.or(right),
+        }
+    }
 }
 
 impl std::fmt::Debug for BvhNode {
@@ -156,19 +378,41 @@ impl std::fmt::Debug for BvhNode {
             .field("left", &self.left)
             .field("right", &self.right)
             .field("bbox", &self.bbox)
+            .field("surface_epsilon", &self.surface_epsilon)
             .finish()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::common::Point3;
-    use crate::hittable::{BvhNode, Sphere};
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_sphere, Aabb, BvhNode, HitRecord, Hittable, HittableList, Sphere, XZRect,
+    };
     use crate::material::{Lambertian, Material};
     use crate::texture::{SolidColor, Texture};
     use std::cmp::Ordering;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
     use std::sync::Arc;
 
+    /// a hittable that counts how many times it was tested for a hit
+    #[derive(Debug)]
+    struct CountedHits {
+        inner: Sphere,
+        hits: AtomicUsize,
+    }
+
+    impl Hittable for CountedHits {
+        fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+            self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+            self.inner.hit(r, t_min, t_max)
+        }
+
+        fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+            self.inner.bounding_box(t0, t1)
+        }
+    }
+
     #[test]
     fn box_compare_sphere1_x_axis_lt_sphere2() {
         let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
@@ -235,7 +479,144 @@ mod tests {
     //     hit_list.add(Arc::new(sphere2));
     //     hit_list.add(Arc::new(sphere3));
     //     hit_list.add(Arc::new(sphere4));
-    //     let root = BvhNode::from(&mut hit_list, 0.0, 1.0);
+    //     let root = BvhNode::from(&hit_list, 0.0, 1.0);
     //     //dbg!(root);
     // }
+
+    #[test]
+    fn building_a_bvh_leaves_the_list_order_unchanged() {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let mut list = HittableList::new();
+        for x in [3.0, 1.0, 4.0, 0.0, 2.0] {
+            list.add(Arc::new(build_solid_sphere(
+                Point3::new(x, x, x),
+                0.5,
+                gray,
+            )));
+        }
+        let before = list.objects().clone();
+
+        let _bvh = BvhNode::from(&list, 0.0, 1.0);
+
+        assert!(before
+            .iter()
+            .zip(list.objects().iter())
+            .all(|(a, b)| Arc::ptr_eq(a, b)));
+    }
+
+    #[test]
+    fn leaf_sizes_of_one_and_four_find_the_same_hits() {
+        let mut world = HittableList::new();
+        for i in 0..6 {
+            for j in 0..6 {
+                world.add(Arc::new(build_solid_sphere(
+                    Point3::new(i as f64 * 2.5, j as f64 * 2.5, -(i + j) as f64),
+                    1.0,
+                    Color::new(0.5, 0.5, 0.5),
+                )));
+            }
+        }
+        let single = BvhNode::from_with_leaf_size(&world, 0.0, 1.0, 1);
+        let flat = BvhNode::from_with_leaf_size(&world, 0.0, 1.0, 4);
+        let auto = BvhNode::build_auto(&world, 0.0, 1.0);
+
+        for x in 0..40 {
+            for y in 0..40 {
+                let ray = Ray::new(
+                    Point3::new(x as f64 * 0.4 - 1.0, y as f64 * 0.4 - 1.0, 20.0),
+                    Vec3::new(0.05, -0.02, -1.0),
+                    0.0,
+                );
+                let single_hit = single.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                assert_eq!(
+                    flat.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                    single_hit
+                );
+                assert_eq!(
+                    auto.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                    single_hit
+                );
+            }
+        }
+    }
+
+    /// a list of two coplanar rectangles, the first has the smaller min on the z-axis and the
+    /// second has the smaller min on the x-axis, and a sphere away from them
+    fn coplanar_rects() -> HittableList {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let mut list = HittableList::new();
+        list.add(Arc::new(XZRect::from(
+            0.0,
+            10.0,
+            0.0,
+            10.0,
+            0.0,
+            Arc::clone(&gray),
+        )));
+        list.add(Arc::new(XZRect::from(
+            -5.0,
+            5.0,
+            2.0,
+            8.0,
+            0.0,
+            Arc::clone(&gray),
+        )));
+        list.add(Arc::new(Sphere::new(
+            Point3::new(20.0, 0.0, 5.0),
+            1.0,
+            gray,
+        )));
+        list
+    }
+
+    #[test]
+    fn coplanar_hittables_resolve_to_the_first_one_along_any_split_axis() {
+        let list = coplanar_rects();
+        let ray = Ray::new(Point3::new(2.0, 5.0, 5.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        // the first rectangle is hit at u = 0.2, the second at u = 0.7
+        let expected = list.hit(&ray, 0.001, f64::INFINITY).unwrap().u;
+        assert!((expected - 0.2).abs() < 1e-9);
+
+        // every build picks random split axes, some of which sort the second rectangle first
+        for _ in 0..20 {
+            for leaf_size in [1, 4] {
+                let bvh = BvhNode::from_with_leaf_size(&list, 0.0, 1.0, leaf_size);
+                assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY).unwrap().u, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn the_hittables_of_a_leaf_are_tested_once_per_ray() {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let counted: Vec<Arc<CountedHits>> = (0..4)
+            .map(|i| {
+                Arc::new(CountedHits {
+                    inner: Sphere::new(Point3::new(i as f64, 0.0, 0.0), 0.4, Arc::clone(&gray)),
+                    hits: AtomicUsize::new(0),
+                })
+            })
+            .collect();
+
+        for leaf_size in [1, 4] {
+            let mut list = HittableList::new();
+            for hittable in &counted {
+                hittable.hits.store(0, AtomicOrdering::Relaxed);
+                list.add(Arc::clone(hittable) as Arc<dyn Hittable>);
+            }
+            let bvh = BvhNode::from_with_leaf_size(&list, 0.0, 1.0, leaf_size);
+
+            // a ray along a corner of the spheres' boxes passes through all of the boxes,
+            // but misses the spheres, so every leaf is tested
+            let ray = Ray::new(Point3::new(-5.0, 0.39, 0.39), Vec3::new(1.0, 0.0, 0.0), 0.0);
+            assert!(bvh.hit(&ray, 0.001, f64::INFINITY).is_none());
+            for hittable in &counted {
+                assert_eq!(hittable.hits.load(AtomicOrdering::Relaxed), 1);
+            }
+        }
+    }
 }
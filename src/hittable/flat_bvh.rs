@@ -0,0 +1,552 @@
+use crate::common::Ray;
+use crate::hittable::{
+    Aabb, BvhNode, HitRecord, Hittable, HittableList, Mesh, AUTO_FLAT_LEAF_THRESHOLD,
+    AUTO_LEAF_SIZE,
+};
+use std::mem::size_of;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// the maximum depth of a `FlatBvh`, and so the size of its traversal stack. The median split
+/// of the builder halves the hittables at every level, so the depth only grows with the
+/// logarithm of the number of hittables
+const MAX_DEPTH: usize = 64;
+
+/// the number of hittables, at or above which the two halves of a node are built in parallel.
+/// Smaller nodes are built serially, as they are not worth the cost of merging their nodes
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+
+/// The statistics of building a `FlatBvh`
+/// `object_count` is the number of hittables in the BVH
+/// `node_count` is the number of (interior and leaf) nodes, and `leaf_count` the number of leaves
+/// `depth` is the number of nodes on the longest path from the root to a leaf
+/// `elapsed` is how long the BVH took to build
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct BvhBuildStats {
+    pub object_count: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub depth: usize,
+    pub elapsed: Duration,
+}
+
+/// A node of a `FlatBvh`
+#[derive(Debug, Copy, Clone)]
+enum FlatNode {
+    /// an interior node, its left child directly follows it in the node array, and its right
+    /// child is at index `right`
+    Interior { bbox: Aabb, right: u32 },
+    /// a leaf node holding the `count` hittables starting at index `first` of the hittable array
+    Leaf { bbox: Aabb, first: u32, count: u32 },
+}
+
+impl FlatNode {
+    fn bbox(&self) -> &Aabb {
+        match self {
+            FlatNode::Interior { bbox, .. } | FlatNode::Leaf { bbox, .. } => bbox,
+        }
+    }
+}
+
+/// A Bounded Volume Hierarchy (BVH) stored as one contiguous array of nodes, instead of a tree
+/// of reference counted `BvhNode`s. The nodes are laid out in depth first order, so the left
+/// child of a node directly follows it, and only the index of the right child is stored.
+/// The hittables of the leaves are kept, in leaf order, in a second array.
+///
+/// The tree is split at the median, like `BvhNode`, but along the longest axis of each node,
+/// and resolves hits within the list's surface epsilon the same way, but it is traversed with a loop and a small fixed size stack
+/// instead of recursive calls through `Arc<dyn Hittable>` pointers. A leaf holds any number of
+/// hittables, so a single hittable is not duplicated into both children of a leaf.
+///
+/// Large lists are built in parallel, on the current rayon thread pool. The split axes do not
+/// depend on the order the halves are built in, so the tree is the same for every build
+#[derive(Debug)]
+pub struct FlatBvh {
+    nodes: Vec<FlatNode>,
+    objects: Vec<Arc<dyn Hittable>>,
+    // the index of each hittable in the list this BVH was built from, which decides ties
+    // between hits within the surface epsilon
+    object_indices: Vec<u32>,
+    // the surface epsilon of the list this BVH was built from, see `HittableList`
+    surface_epsilon: f64,
+    build_stats: BvhBuildStats,
+}
+
+impl FlatBvh {
+    /// Constructs a BVH from the `list` of Hittables, with a single hittable in each leaf.
+    /// The list itself is left unchanged
+    pub fn from(list: &mut HittableList, time0: f64, time1: f64) -> FlatBvh {
+        FlatBvh::from_with_leaf_size(list, time0, time1, 1)
+    }
+
+    /// Constructs a BVH from the `list` of Hittables, that stops subdividing the list once a
+    /// node holds `leaf_size` hittables or fewer, see `BvhNode::from_with_leaf_size`
+    pub fn from_with_leaf_size(
+        list: &mut HittableList,
+        time0: f64,
+        time1: f64,
+        leaf_size: usize,
+    ) -> FlatBvh {
+        let start = Instant::now();
+        let mut objects: Vec<(u32, Arc<dyn Hittable>)> = list
+            .objects()
+            .iter()
+            .enumerate()
+            .map(|(index, object)| (index as u32, Arc::clone(object)))
+            .collect();
+        let mut nodes = Vec::with_capacity(2 * objects.len());
+        if !objects.is_empty() {
+            FlatBvh::split_volumes(
+                &mut nodes,
+                &mut objects,
+                0,
+                time0,
+                time1,
+                leaf_size.max(1),
+                1,
+            );
+        }
+        let (object_indices, objects) = objects.into_iter().unzip();
+        let mut bvh = FlatBvh {
+            nodes,
+            objects,
+            object_indices,
+            surface_epsilon: list.surface_epsilon(),
+            build_stats: BvhBuildStats::default(),
+        };
+        bvh.build_stats = BvhBuildStats {
+            object_count: bvh.objects.len(),
+            node_count: bvh.nodes.len(),
+            leaf_count: bvh
+                .nodes
+                .iter()
+                .filter(|node| matches!(node, FlatNode::Leaf { .. }))
+                .count(),
+            depth: bvh.depth(),
+            elapsed: start.elapsed(),
+        };
+        bvh
+    }
+
+    /// Constructs a BVH from the `list` of Hittables, choosing its leaf size from the number
+    /// of hittables in the list, like `BvhNode::build_auto`
+    pub fn build_auto(list: &mut HittableList, time0: f64, time1: f64) -> FlatBvh {
+        let leaf_size = match list.objects().len() {
+            count if count <= AUTO_FLAT_LEAF_THRESHOLD => 1,
+            _ => AUTO_LEAF_SIZE,
+        };
+        FlatBvh::from_with_leaf_size(list, time0, time1, leaf_size)
+    }
+
+    /// Returns the estimated number of bytes allocated by a BVH over `object_count` hittables:
+    /// its nodes, of which there are at most `2 * object_count - 1`, plus the reference to, and
+    /// list index of, every hittable
+    pub fn estimated_size(object_count: usize) -> usize {
+        let node_bytes = (2 * object_count).saturating_sub(1) * size_of::<FlatNode>();
+        let object_bytes = object_count * (size_of::<Arc<dyn Hittable>>() + size_of::<u32>());
+        node_bytes + object_bytes
+    }
+
+    /// Returns the number of (interior and leaf) nodes in this BVH
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the statistics of building this BVH
+    pub fn build_stats(&self) -> BvhBuildStats {
+        self.build_stats
+    }
+
+    /// Returns the number of nodes on the longest path from the root to a leaf
+    fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack = vec![(0, 1)];
+        while let Some((index, node_depth)) = stack.pop() {
+            match self.nodes.get(index) {
+                Some(FlatNode::Interior { right, .. }) => {
+                    stack.push((index + 1, node_depth + 1));
+                    stack.push((*right as usize, node_depth + 1));
+                }
+                Some(FlatNode::Leaf { .. }) => depth = depth.max(node_depth),
+                None => {}
+            }
+        }
+        depth
+    }
+
+    /// Appends the nodes of a BVH over `objects`, which start at index `first` of the
+    /// hittable array, to `nodes`, sorting `objects` into leaf order along the way.
+    /// `depth` is the depth of the new node, the root has a depth of 1
+    fn split_volumes(
+        nodes: &mut Vec<FlatNode>,
+        objects: &mut [(u32, Arc<dyn Hittable>)],
+        first: usize,
+        time0: f64,
+        time1: f64,
+        leaf_size: usize,
+        depth: usize,
+    ) {
+        let bbox = objects
+            .iter()
+            .map(|(_, object)| {
+                object
+                    .bounding_box(time0, time1)
+                    .expect("a hittable did not have a bounding box during BVH construction")
+            })
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b))
+            .expect("a BVH node holds at least one hittable");
+
+        let index = nodes.len();
+        if objects.len() <= leaf_size || depth == MAX_DEPTH {
+            nodes.push(FlatNode::Leaf {
+                bbox,
+                first: first as u32,
+                count: objects.len() as u32,
+            });
+            return;
+        }
+
+        // sort the objects along the longest axis of their box. Unlike the random axis of
+        // BvhNode, this keeps the tree the same whichever thread builds each half
+        let extent = bbox.max() - bbox.min();
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+        objects.sort_by(|a, b| BvhNode::box_compare(&*a.1, &*b.1, axis));
+
+        // reserve this node's slot, so that its left child directly follows it
+        nodes.push(FlatNode::Leaf {
+            bbox: Aabb::default(),
+            first: 0,
+            count: 0,
+        });
+        let objects_len = objects.len();
+        let mid = objects_len / 2;
+        let (left, right) = objects.split_at_mut(mid);
+        let right_index = if objects_len >= PARALLEL_BUILD_THRESHOLD {
+            // build each half into its own node array, then append them after this node
+            let build = |objects: &mut [(u32, Arc<dyn Hittable>)], first: usize| {
+                let mut nodes = Vec::with_capacity(2 * objects.len());
+                FlatBvh::split_volumes(
+                    &mut nodes,
+                    objects,
+                    first,
+                    time0,
+                    time1,
+                    leaf_size,
+                    depth + 1,
+                );
+                nodes
+            };
+            let (left_nodes, right_nodes) =
+                rayon::join(|| build(left, first), || build(right, first + mid));
+            FlatBvh::append_subtree(nodes, left_nodes);
+            let right_index = nodes.len();
+            FlatBvh::append_subtree(nodes, right_nodes);
+            right_index
+        } else {
+            FlatBvh::split_volumes(nodes, left, first, time0, time1, leaf_size, depth + 1);
+            let right_index = nodes.len();
+            FlatBvh::split_volumes(
+                nodes,
+                right,
+                first + mid,
+                time0,
+                time1,
+                leaf_size,
+                depth + 1,
+            );
+            right_index
+        };
+        nodes[index] = FlatNode::Interior {
+            bbox,
+            right: right_index as u32,
+        };
+    }
+
+    /// Appends the nodes of a `subtree`, that was built into a node array of its own, to
+    /// `nodes`, offsetting the indices of its right children by their new position
+    fn append_subtree(nodes: &mut Vec<FlatNode>, subtree: Vec<FlatNode>) {
+        let offset = nodes.len() as u32;
+        nodes.extend(subtree.into_iter().map(|node| match node {
+            FlatNode::Interior { bbox, right } => FlatNode::Interior {
+                bbox,
+                right: right + offset,
+            },
+            leaf => leaf,
+        }));
+    }
+
+    /// Returns the hittables of the leaf node `first..first + count`
+    fn leaf_objects(&self, first: u32, count: u32) -> &[Arc<dyn Hittable>] {
+        &self.objects[first as usize..(first + count) as usize]
+    }
+
+    /// Returns the list indices of the hittables of the leaf node `first..first + count`
+    fn leaf_indices(&self, first: u32, count: u32) -> &[u32] {
+        &self.object_indices[first as usize..(first + count) as usize]
+    }
+}
+
+impl Hittable for FlatBvh {
+    /// Visits the nodes whose bounding boxes are hit by the `ray`, left child first, and
+    /// returns the closest hit of their hittables. Like `BvhNode`, hits within the surface
+    /// epsilon of each other are resolved in favor of the hittable added to the list first
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        // hits up to the surface epsilon beyond the closest hit may still replace it
+        let mut limit = t_max;
+        // the closest hit so far, and the index of its hittable in the list
+        let mut closest: Option<(HitRecord, u32)> = None;
+
+        let mut stack = [0u32; MAX_DEPTH + 1];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index as usize];
+            if node.bbox().hit(ray, t_min, limit).is_none() {
+                continue;
+            }
+            match *node {
+                FlatNode::Interior { right, .. } => {
+                    // the left child is pushed last, so it is visited first
+                    stack[stack_len] = right;
+                    stack[stack_len + 1] = index + 1;
+                    stack_len += 2;
+                }
+                FlatNode::Leaf { first, count, .. } => {
+                    let leaf = self
+                        .leaf_objects(first, count)
+                        .iter()
+                        .zip(self.leaf_indices(first, count));
+                    for (object, &index) in leaf {
+                        if let Some(hit_record) = object.hit(ray, t_min, limit) {
+                            let replace = match &closest {
+                                Some((rec, closest_index)) => {
+                                    hit_record.t < rec.t - self.surface_epsilon
+                                        || index < *closest_index
+                                }
+                                None => true,
+                            };
+                            if replace {
+                                limit = hit_record.t + self.surface_epsilon;
+                                closest = Some((hit_record, index));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        closest.map(|(rec, _)| rec)
+    }
+
+    /// Returns `true` as soon as any hittable is hit, without searching for the closest hit
+    fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let mut stack = [0u32; MAX_DEPTH + 1];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index as usize];
+            if node.bbox().hit(ray, t_min, t_max).is_none() {
+                continue;
+            }
+            match *node {
+                FlatNode::Interior { right, .. } => {
+                    stack[stack_len] = right;
+                    stack[stack_len + 1] = index + 1;
+                    stack_len += 2;
+                }
+                FlatNode::Leaf { first, count, .. } => {
+                    if self
+                        .leaf_objects(first, count)
+                        .iter()
+                        .any(|object| object.hit_any(ray, t_min, t_max))
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the bounding box of the root node, or `None` if this BVH is empty
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        self.nodes.first().map(|root| *root.bbox())
+    }
+
+    /// Returns the combined tessellation of every hittable in this BVH
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.objects
+            .iter()
+            .filter_map(|object| object.tessellate(tessellation))
+            .reduce(|mut mesh, other| {
+                mesh.append(other);
+                mesh
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_sphere, BvhNode, FlatBvh, Hittable, HittableList, Sphere, XZRect,
+    };
+    use crate::material::{Lambertian, Material};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    fn build_grid() -> HittableList {
+        let mut world = HittableList::new();
+        for i in 0..6 {
+            for j in 0..6 {
+                world.add(Arc::new(build_solid_sphere(
+                    Point3::new(i as f64 * 2.5, j as f64 * 2.5, -(i + j) as f64),
+                    1.0,
+                    Color::new(0.5, 0.5, 0.5),
+                )));
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_a_bvh_node() {
+        let mut world = build_grid();
+        let tree = BvhNode::from(&world, 0.0, 1.0);
+        let single = FlatBvh::from(&mut world, 0.0, 1.0);
+        let flat = FlatBvh::from_with_leaf_size(&mut world, 0.0, 1.0, 4);
+
+        // a leaf per sphere, and one interior node fewer than leaves
+        assert_eq!(single.node_count(), 2 * 36 - 1);
+        assert!(flat.node_count() < single.node_count());
+        for x in 0..40 {
+            for y in 0..40 {
+                let ray = Ray::new(
+                    Point3::new(x as f64 * 0.4 - 1.0, y as f64 * 0.4 - 1.0, 20.0),
+                    Vec3::new(0.05, -0.02, -1.0),
+                    0.0,
+                );
+                let tree_hit = tree.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                assert_eq!(
+                    single.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                    tree_hit
+                );
+                assert_eq!(
+                    flat.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                    tree_hit
+                );
+                assert_eq!(flat.hit_any(&ray, 0.001, f64::INFINITY), tree_hit.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn a_single_hittable_is_one_leaf_and_an_empty_list_has_no_nodes() {
+        let mut list = HittableList::new();
+        assert_eq!(FlatBvh::from(&mut list, 0.0, 1.0).node_count(), 0);
+        assert!(FlatBvh::from(&mut list, 0.0, 1.0)
+            .bounding_box(0.0, 1.0)
+            .is_none());
+
+        list.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        let bvh = FlatBvh::from(&mut list, 0.0, 1.0);
+        assert_eq!(bvh.node_count(), 1);
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY).unwrap().t, 1.5);
+    }
+
+    #[test]
+    fn large_lists_are_built_in_parallel_with_the_same_hits() {
+        let mut world = HittableList::new();
+        for i in 0..80 {
+            for j in 0..80 {
+                world.add(Arc::new(build_solid_sphere(
+                    Point3::new(i as f64, j as f64, -((i * j) % 7) as f64),
+                    0.4,
+                    Color::new(0.5, 0.5, 0.5),
+                )));
+            }
+        }
+        let tree = BvhNode::from(&world, 0.0, 1.0);
+        let flat = FlatBvh::from(&mut world, 0.0, 1.0);
+
+        let stats = flat.build_stats();
+        assert_eq!(stats.object_count, 6400);
+        assert_eq!(stats.leaf_count, 6400);
+        assert_eq!(stats.node_count, 2 * 6400 - 1);
+        assert_eq!(stats.depth, 14);
+        // the split axes do not depend on which thread builds each half
+        let again = FlatBvh::from(&mut world, 0.0, 1.0);
+        assert_eq!(again.object_indices, flat.object_indices);
+        for x in 0..100 {
+            let ray = Ray::new(
+                Point3::new(x as f64 * 0.8 - 0.3, x as f64 * 0.7, 20.0),
+                Vec3::new(0.01, 0.02, -1.0),
+                0.0,
+            );
+            assert_eq!(
+                flat.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                tree.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p)
+            );
+        }
+    }
+
+    /// a list of two coplanar rectangles, the first has the smaller min on the z-axis and the
+    /// second has the smaller min on the x-axis, and a sphere away from them
+    fn coplanar_rects() -> HittableList {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let mut list = HittableList::new();
+        list.add(Arc::new(XZRect::from(
+            0.0,
+            10.0,
+            0.0,
+            10.0,
+            0.0,
+            Arc::clone(&gray),
+        )));
+        list.add(Arc::new(XZRect::from(
+            -5.0,
+            5.0,
+            2.0,
+            8.0,
+            0.0,
+            Arc::clone(&gray),
+        )));
+        list.add(Arc::new(Sphere::new(
+            Point3::new(20.0, 0.0, 5.0),
+            1.0,
+            gray,
+        )));
+        list
+    }
+
+    #[test]
+    fn coplanar_hittables_resolve_to_the_first_one_along_any_split_axis() {
+        let mut list = coplanar_rects();
+        let ray = Ray::new(Point3::new(2.0, 5.0, 5.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let expected = list.hit(&ray, 0.001, f64::INFINITY).unwrap().u;
+
+        for _ in 0..20 {
+            for leaf_size in [1, 4] {
+                let bvh = FlatBvh::from_with_leaf_size(&mut list, 0.0, 1.0, leaf_size);
+                assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY).unwrap().u, expected);
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use crate::common::Ray;
-use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
 use std::sync::Arc;
 
 /// FlipFace is a "wrapper" struct that wraps another hittable and "flips" its front face
@@ -28,4 +28,19 @@ impl Hittable for FlipFace {
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
         self.ptr.bounding_box(t0, t1)
     }
+
+    /// Returns the tessellated hittable with the winding of its faces reversed
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation).map(|mut mesh| {
+            mesh.flip_winding();
+            mesh
+        })
+    }
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.ptr.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.ptr.random(origin)
+    }
 }
@@ -1,4 +1,4 @@
-use crate::common::{Point3, Ray, Vec3};
+use crate::common::{Color, Point3, Ray, Vec3};
 use crate::material::Material;
 use std::sync::Arc;
 
@@ -22,9 +22,31 @@ pub struct HitRecord {
     // texture v coordinate
     pub v: f64,
 
+    // estimated width, in texture (u,v) units, of the ray's footprint at the point that was hit.
+    // Zero if the footprint is unknown, which selects the finest texture detail
+    pub uv_footprint: f64,
+
+    // velocity, in world units per unit of time, of the point that was hit. Zero for
+    // hittables that do not move
+    pub velocity: Vec3,
+
     // true if ray hit a front face of a hittable (ray hit from outside the hittable),
     // false if a ray hit a backward face of a 'hittable' (ray hit from the inside of a hittable)
     pub front_face: bool,
+
+    // true if the hittable that was hit is visible to primary (camera) rays
+    pub primary_visible: bool,
+
+    // true if the hittable that was hit is visible to secondary (scattered and shadow) rays
+    pub secondary_visible: bool,
+
+    // the id of the object that was hit, as assigned by an `ObjectId` wrapper. Zero if the
+    // object has not been given an id
+    pub object_id: u32,
+
+    // the color of the surface at the point that was hit, interpolated from the colors of the
+    // vertices of a `Triangle`. `None` if the hittable has no vertex colors
+    pub vertex_color: Option<Color>,
 }
 
 impl HitRecord {
@@ -45,7 +67,13 @@ impl HitRecord {
             t,
             u,
             v,
+            uv_footprint: 0.0,
+            velocity: Vec3::default(),
             front_face,
+            primary_visible: true,
+            secondary_visible: true,
+            object_id: 0,
+            vertex_color: None,
         }
     }
 
@@ -80,6 +108,16 @@ impl HitRecord {
         ray.direction().dot(outward_normal) < 0.0
     }
 
+    /// returns true if the hittable that was hit is visible to a ray. `primary` is true if the
+    /// ray is a primary (camera) ray, and false for a secondary ray
+    pub fn is_visible_to(&self, primary: bool) -> bool {
+        if primary {
+            self.primary_visible
+        } else {
+            self.secondary_visible
+        }
+    }
+
     /// compute and set the `front_face` and `normal` fields of this HitRecord given
     /// a Ray and outward_normal
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
@@ -1,24 +1,50 @@
 use std::sync::Arc;
 
-use crate::common::Ray;
+use crate::common::{Point3, Ray, RngContext, Vec3};
+use rand::Rng;
 
 use super::{HitRecord, Hittable};
-use crate::hittable::Aabb;
+use crate::hittable::{Aabb, Mesh, Transform};
 use std::fmt::Formatter;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// the default `surface_epsilon` of a `HittableList`
+pub const DEFAULT_SURFACE_EPSILON: f64 = 1e-7;
 
 /// a list of all Hittable objects in the ray tracer's "world" (a.k.a scene)
-#[derive(Default)]
+///
+/// `surface_epsilon` is the distance (in units of `t`) by which a hit must be closer than the
+/// closest hit found so far, in order to replace it. Coplanar surfaces, such as a box side lying
+/// on a Cornell box wall, are hit at (nearly) the same `t`, and without an epsilon floating point
+/// noise decides which surface is seen, causing speckles. With it, the object added to the list
+/// first wins
+#[derive(Clone)]
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
+    surface_epsilon: f64,
 }
 
 impl HittableList {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            surface_epsilon: DEFAULT_SURFACE_EPSILON,
         }
     }
 
+    /// Sets the surface epsilon of this list, `0.0` disables it
+    pub fn set_surface_epsilon(&mut self, surface_epsilon: f64) {
+        self.surface_epsilon = surface_epsilon;
+    }
+
+    /// Returns the surface epsilon of this list
+    pub fn surface_epsilon(&self) -> f64 {
+        self.surface_epsilon
+    }
+
     /// clear the list of all objects
     pub fn clear(&mut self) {
         self.objects.clear();
@@ -29,9 +55,55 @@ impl HittableList {
         self.objects.push(object);
     }
 
+    /// Returns the number of objects in this list
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns `true` if this list has no objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Returns the objects of this list, in the order they were added
+    pub fn as_slice(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
     pub fn objects(&mut self) -> &mut Vec<Arc<dyn Hittable>> {
         &mut self.objects
     }
+
+    /// Places every object of the `other` world into this list, transformed by `transform`, i.e.
+    /// to compose a large scene from reusable pieces. The other world is added as a single
+    /// (transformed) hittable, so all of its objects move together
+    pub fn merge_transformed(&mut self, other: HittableList, transform: Transform) {
+        if other.objects.is_empty() {
+            return;
+        }
+        self.add(transform.apply(Arc::new(other)));
+    }
+
+    /// Writes the tessellated surfaces of every object in this list to a Wavefront OBJ file
+    /// located at `file_path`, so that the scene can be inspected in an external 3D viewer.
+    /// Spheres, rectangles and boxes (including rotated, translated and flipped ones) are
+    /// exported, objects that can not be tessellated (such as volumes) are skipped.
+    /// `tessellation` controls the smoothness, and size, of the exported spheres, see
+    /// `Hittable::tessellate`. `DEFAULT_TESSELLATION` is a reasonable choice
+    pub fn export_obj(&self, file_path: impl AsRef<Path>, tessellation: u32) -> io::Result<()> {
+        let mesh = self.tessellate(tessellation).unwrap_or_default();
+        let mut writer = BufWriter::new(File::create(file_path)?);
+
+        writeln!(writer, "# exported by raytracer")?;
+        writeln!(
+            writer,
+            "# {} vertices, {} faces",
+            mesh.vertices.len(),
+            mesh.faces.len()
+        )?;
+        mesh.write_obj(&mut writer)?;
+        writer.flush()
+    }
 }
 
 impl Hittable for HittableList {
@@ -45,7 +117,8 @@ impl Hittable for HittableList {
 
         for object in self.objects.iter() {
             if let Some(hit_record) = object.hit(r, t_min, closest_so_far) {
-                closest_so_far = hit_record.t;
+                // later objects must be closer by more than the epsilon to replace this hit
+                closest_so_far = hit_record.t - self.surface_epsilon;
                 hit_anything = Some(hit_record);
             }
         }
@@ -53,6 +126,13 @@ impl Hittable for HittableList {
         hit_anything
     }
 
+    /// Returns `true` as soon as any object in the list is hit by the ray
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.objects
+            .iter()
+            .any(|object| object.hit_any(r, t_min, t_max))
+    }
+
     /// Returns a bounding box for the entire list of objects
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
         if self.objects.is_empty() {
@@ -89,20 +169,61 @@ impl Hittable for HittableList {
         // }
         Some(output_box)
     }
+
+    /// Returns the combined tessellation of every object in this list that can be tessellated
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.objects
+            .iter()
+            .filter_map(|hittable| hittable.tessellate(tessellation))
+            .reduce(|mut acc, mesh| {
+                acc.append(mesh);
+                acc
+            })
+    }
+
+    /// Returns the average pdf of every object in this list, since `random` chooses each
+    /// object with equal probability
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|hittable| weight * hittable.pdf_value(origin, direction))
+            .sum()
+    }
+
+    /// Returns a random direction towards a randomly chosen object of this list
+    fn random(&self, origin: &Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let idx = RngContext::current().gen_range(0..self.objects.len());
+        self.objects[idx].random(origin)
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        HittableList::new()
+    }
 }
 
 impl std::fmt::Debug for HittableList {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HittableList")
             .field("objects", &self.objects)
+            .field("surface_epsilon", &self.surface_epsilon)
             .finish()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::common::Point3;
-    use crate::hittable::{Hittable, HittableList, Sphere};
+    use crate::common::Ray;
+    use crate::common::{Point3, Vec3};
+    use crate::hittable::{Hittable, HittableList, Sphere, Transform, DEFAULT_TESSELLATION};
     use crate::material::{Lambertian, Material};
     use crate::texture::{SolidColor, Texture};
     use std::sync::Arc;
@@ -122,4 +243,89 @@ mod tests {
         assert_eq!(surrounding_bb.unwrap().min(), Point3::new(0.0, 0.0, 0.0));
         assert_eq!(surrounding_bb.unwrap().max(), Point3::new(3.0, 3.0, 3.0));
     }
+
+    #[test]
+    fn export_a_single_sphere_to_obj() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let mut hit_list = HittableList::new();
+        hit_list.add(Arc::new(Sphere::new(
+            Point3::new(1.0, 2.0, 3.0),
+            2.0,
+            lamb_mat,
+        )));
+        let path = std::env::temp_dir().join("raytracer_export_a_single_sphere.obj");
+
+        hit_list.export_obj(&path, DEFAULT_TESSELLATION).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let vertices: Vec<Point3> = contents
+            .lines()
+            .filter(|line| line.starts_with("v "))
+            .map(|line| {
+                let c: Vec<f64> = line[2..]
+                    .split_whitespace()
+                    .map(|n| n.parse().unwrap())
+                    .collect();
+                Point3::new(c[0], c[1], c[2])
+            })
+            .collect();
+        let face_count = contents
+            .lines()
+            .filter(|line| line.starts_with("f "))
+            .count();
+        assert!(vertices.len() > 100);
+        // a closed triangle mesh of genus 0 has F = 2V - 4 faces
+        assert_eq!(face_count, 2 * vertices.len() - 4);
+
+        let min = vertices
+            .iter()
+            .fold(Point3::new(f64::MAX, f64::MAX, f64::MAX), |acc, v| {
+                Point3::new(acc.x().min(v.x()), acc.y().min(v.y()), acc.z().min(v.z()))
+            });
+        let max = vertices
+            .iter()
+            .fold(Point3::new(f64::MIN, f64::MIN, f64::MIN), |acc, v| {
+                Point3::new(acc.x().max(v.x()), acc.y().max(v.y()), acc.z().max(v.z()))
+            });
+        assert!((min - Point3::new(-1.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((max - Point3::new(3.0, 4.0, 5.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn higher_tessellation_exports_more_vertices_with_the_same_bounds() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let mut hit_list = HittableList::new();
+        hit_list.add(Arc::new(Sphere::new(center, 2.0, lamb_mat)));
+
+        let coarse = hit_list.tessellate(4).unwrap();
+        let fine = hit_list.tessellate(32).unwrap();
+
+        assert!(fine.vertices.len() > coarse.vertices.len());
+        for mesh in [coarse, fine] {
+            let bbox = mesh.bounding_box().unwrap();
+            assert!((bbox.min() - (center - Vec3::new(2.0, 2.0, 2.0))).length() < 1e-9);
+            assert!((bbox.max() - (center + Vec3::new(2.0, 2.0, 2.0))).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn merged_world_is_placed_at_the_transform_offset() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let mut piece = HittableList::new();
+        piece.add(Arc::new(Sphere::new(Point3::default(), 1.0, lamb_mat)));
+        let mut world = HittableList::new();
+
+        world.merge_transformed(piece, Transform::translation(Vec3::new(10.0, 0.0, 0.0)));
+
+        let towards_offset = Ray::new(Point3::new(10.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let towards_origin = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = world.hit(&towards_offset, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point3::new(10.0, 0.0, -1.0)).length() < 1e-9);
+        assert!(world.hit(&towards_origin, 0.001, f64::INFINITY).is_none());
+    }
 }
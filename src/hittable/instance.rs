@@ -0,0 +1,223 @@
+use crate::common::{degrees_to_radians, Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh, Transform};
+use std::sync::Arc;
+
+/// An Instance places a copy of shared `geometry` in the world with its own `Transform`,
+/// without duplicating the geometry. Rays are moved into the geometry's own (object) space,
+/// so a single BVH, i.e. the one of a `TriangleMesh`, can be shared by any number of instances.
+///
+/// Adding the instances to a `FlatBvh` builds a two-level BVH: the top level sorts the
+/// instances by their (transformed) bounding boxes, and each instance then descends into the
+/// BVH of its geometry. Unlike the `AffineTransform` of `Transform::apply`, an instance only
+/// rotates about the y-axis and translates, so it avoids the cost of a general matrix
+#[derive(Debug)]
+pub struct Instance {
+    geometry: Arc<dyn Hittable>,
+    transform: Transform,
+    sin_theta: f64,
+    cos_theta: f64,
+    bbox: Aabb,
+}
+
+impl Instance {
+    /// Returns an instance of `geometry` placed in the world by `transform`
+    ///
+    /// # Panics
+    /// if the geometry does not have a bounding box
+    pub fn new(geometry: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let geometry_box = geometry
+            .bounding_box(0.0, 1.0)
+            .expect("can't instance a Hittable that doesn't have a bounding box");
+        let theta = degrees_to_radians(transform.rotate_y);
+        let mut instance = Self {
+            geometry,
+            transform,
+            sin_theta: theta.sin(),
+            cos_theta: theta.cos(),
+            bbox: Aabb::default(),
+        };
+
+        // the bounding box of the instance surrounds the eight transformed corners of the
+        // geometry's bounding box
+        let (min, max) = (geometry_box.min(), geometry_box.max());
+        for corner in 0..8 {
+            let p = instance.to_world(&Point3::new(
+                if corner & 1 == 0 { min.x() } else { max.x() },
+                if corner & 2 == 0 { min.y() } else { max.y() },
+                if corner & 4 == 0 { min.z() } else { max.z() },
+            ));
+            instance.bbox = Aabb::surrounding_box(&instance.bbox, &Aabb::new(p, p));
+        }
+        instance
+    }
+
+    /// Returns the geometry shared by this instance
+    pub fn geometry(&self) -> &Arc<dyn Hittable> {
+        &self.geometry
+    }
+
+    /// Returns the transform that places this instance in the world
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Rotates the vector `v` from object space into world space
+    fn rotate_to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    /// Rotates the vector `v` from world space into object space
+    fn rotate_to_object(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() - self.sin_theta * v.z(),
+            v.y(),
+            self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    /// Moves the point `p` from object space into world space
+    fn to_world(&self, p: &Point3) -> Point3 {
+        self.rotate_to_world(p) + self.transform.offset
+    }
+
+    /// Moves the point `p` from world space into object space
+    fn to_object(&self, p: &Point3) -> Point3 {
+        self.rotate_to_object(&(*p - self.transform.offset))
+    }
+
+    /// Returns the ray `r` moved into object space. The direction is only rotated, so the `t`
+    /// of a hit, and the spread of the ray, are the same in both spaces
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.to_object(&r.origin()),
+            self.rotate_to_object(&r.direction()),
+            r.time(),
+        )
+        .with_spread(r.spread())
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.geometry
+            .hit(&self.object_ray(r), t_min, t_max)
+            .map(|mut rec| {
+                // a rotation keeps the normal facing against the ray, so front_face is kept
+                rec.p = self.to_world(&rec.p);
+                rec.normal = self.rotate_to_world(&rec.normal);
+                rec.velocity = self.rotate_to_world(&rec.velocity);
+                rec
+            })
+    }
+
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.geometry.hit_any(&self.object_ray(r), t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.geometry.tessellate(tessellation).map(|mut mesh| {
+            mesh.transform_vertices(|v| self.to_world(&v));
+            mesh
+        })
+    }
+
+    /// Returns the pdf of the geometry, seen from `origin` and `direction` moved into object
+    /// space
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.geometry
+            .pdf_value(&self.to_object(origin), &self.rotate_to_object(direction))
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.rotate_to_world(&self.geometry.random(&self.to_object(origin)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_sphere, BoxInst, FlatBvh, Hittable, HittableList, Instance, Transform,
+    };
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn hits_match_the_rotate_and_translate_wrappers() {
+        let geometry: Arc<dyn Hittable> = Arc::new(BoxInst::from(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 1.0),
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let transform = Transform {
+            rotate_y: 30.0,
+            offset: Vec3::new(1.0, 2.0, -5.0),
+        };
+        let instance = Instance::new(Arc::clone(&geometry), transform);
+        let wrapped = transform.apply(geometry);
+
+        let (instance_box, wrapped_box) = (
+            instance.bounding_box(0.0, 1.0).unwrap(),
+            wrapped.bounding_box(0.0, 1.0).unwrap(),
+        );
+        assert!((instance_box.min() - wrapped_box.min()).length() < 1e-9);
+        assert!((instance_box.max() - wrapped_box.max()).length() < 1e-9);
+        for x in 0..20 {
+            for y in 0..20 {
+                let ray = Ray::new(
+                    Point3::new(x as f64 * 0.2 - 1.0, y as f64 * 0.2, 5.0),
+                    Vec3::new(0.1, 0.05, -1.0),
+                    0.0,
+                );
+                let expected = wrapped.hit(&ray, 0.001, f64::INFINITY);
+                let hit = instance.hit(&ray, 0.001, f64::INFINITY);
+                assert_eq!(hit.is_some(), expected.is_some());
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert!((hit.p - expected.p).length() < 1e-9);
+                    assert!((hit.normal - expected.normal).length() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ten_thousand_instances_share_one_geometry() {
+        let geometry: Arc<dyn Hittable> = Arc::new(build_solid_sphere(
+            Point3::default(),
+            0.4,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let mut forest = HittableList::new();
+        for i in 0..100 {
+            for j in 0..100 {
+                let offset = Vec3::new(i as f64, 0.0, -(j as f64));
+                forest.add(Arc::new(Instance::new(
+                    Arc::clone(&geometry),
+                    Transform::translation(offset),
+                )));
+            }
+        }
+        let bvh = FlatBvh::from(&mut forest, 0.0, 1.0);
+        assert_eq!(Arc::strong_count(&geometry), 10_001);
+
+        // a ray straight down onto the instance at (42, 0, -17)
+        let ray = Ray::new(
+            Point3::new(42.0, 5.0, -17.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            0.0,
+        );
+        let rec = bvh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point3::new(42.0, 0.4, -17.0)).length() < 1e-9);
+    }
+}
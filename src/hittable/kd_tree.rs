@@ -0,0 +1,464 @@
+use crate::common::Ray;
+use crate::hittable::{Aabb, HitRecord, Hittable, HittableList, Mesh};
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// the estimated cost of intersecting a hittable, relative to a traversal step of the tree
+const INTERSECT_COST: f64 = 80.0;
+
+/// the estimated cost of a traversal step of the tree
+const TRAVERSAL_COST: f64 = 1.0;
+
+/// the fraction of the cost saved by a split that leaves one of its children empty
+const EMPTY_BONUS: f64 = 0.5;
+
+/// the number of hittables, at or below which a node is never split
+const MAX_LEAF_SIZE: usize = 1;
+
+/// the number of splits, along a path from the root, that may cost more than a leaf before
+/// the path is ended with a leaf anyway. Bad splits sometimes lead to good splits below them
+const MAX_BAD_REFINES: u32 = 3;
+
+/// A node of a `KdTree`
+#[derive(Debug, Copy, Clone)]
+enum KdNode {
+    /// an interior node, split by the plane at `split` along `axis`. The child below the plane
+    /// directly follows it in the node array, the child above the plane is at index `above`
+    Interior { axis: usize, split: f64, above: u32 },
+    /// a leaf node holding the `count` hittables starting at index `first` of the index array
+    Leaf { first: u32, count: u32 },
+}
+
+/// An edge of a hittable's bounding box along one axis, used to sweep the candidate split
+/// planes of a node
+#[derive(Debug, Copy, Clone)]
+struct BoundEdge {
+    t: f64,
+    start: bool,
+    object: u32,
+}
+
+/// A kd-tree, an acceleration structure that recursively splits space, rather than the list
+/// of hittables like a BVH, with axis aligned planes. Each split plane is chosen with the
+/// surface area heuristic (SAH), which estimates the cost of tracing rays through each
+/// candidate plane from the bounding boxes of the hittables. A hittable that straddles a
+/// plane is listed on both sides of it.
+///
+/// A ray visits the nodes it passes nearest first, and stops at the first node with a hit,
+/// so scenes of tightly packed, axis aligned, hittables, such as the walls of a room or rows
+/// of boxes, often traverse faster than with a BVH, whose boxes may overlap. Like a
+/// `HittableList`, hits within the list's surface epsilon of each other are resolved in favor
+/// of the hittable that was added to the list first
+#[derive(Debug)]
+pub struct KdTree {
+    objects: Vec<Arc<dyn Hittable>>,
+    nodes: Vec<KdNode>,
+    // the indices, into `objects`, of the hittables of each leaf
+    object_indices: Vec<u32>,
+    bbox: Option<Aabb>,
+    surface_epsilon: f64,
+}
+
+impl KdTree {
+    /// Constructs a kd-tree over the `list` of Hittables
+    ///
+    /// # Panics
+    /// if a hittable in the list does not have a bounding box
+    pub fn from(list: &HittableList, time0: f64, time1: f64) -> KdTree {
+        let objects = list.as_slice().to_vec();
+        let boxes: Vec<Aabb> = objects
+            .iter()
+            .map(|object| {
+                object
+                    .bounding_box(time0, time1)
+                    .expect("a hittable did not have a bounding box during kd-tree construction")
+            })
+            .collect();
+        let mut tree = KdTree {
+            objects,
+            nodes: vec![],
+            object_indices: vec![],
+            bbox: list.bounding_box(time0, time1),
+            surface_epsilon: list.surface_epsilon(),
+        };
+        if let Some(bbox) = tree.bbox {
+            let max_depth = (8.0 + 1.3 * (boxes.len() as f64).log2()).round() as u32;
+            let indices: Vec<u32> = (0..boxes.len() as u32).collect();
+            tree.build(&boxes, &bbox, indices, max_depth, 0);
+        }
+        tree
+    }
+
+    /// Returns the estimated number of bytes allocated by a kd-tree over `object_count`
+    /// hittables: the reference to every hittable, plus its index in each leaf it is listed
+    /// in, and the nodes of the tree. Hittables that straddle a split plane are listed on both
+    /// sides of it, so every hittable is assumed to be listed in two leaves, which need about
+    /// as many interior nodes again
+    pub fn estimated_size(object_count: usize) -> usize {
+        let listed_count = 2 * object_count;
+        let node_bytes = (2 * listed_count).max(1) * size_of::<KdNode>();
+        let object_bytes =
+            object_count * size_of::<Arc<dyn Hittable>>() + listed_count * size_of::<u32>();
+        node_bytes + object_bytes
+    }
+
+    /// Returns the number of (interior and leaf) nodes in this kd-tree
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Appends the nodes of a kd-tree over the hittables `indices`, whose bounding boxes are
+    /// `boxes`, within the bounds `node_box`, to the nodes of this tree
+    fn build(
+        &mut self,
+        boxes: &[Aabb],
+        node_box: &Aabb,
+        indices: Vec<u32>,
+        depth: u32,
+        bad_refines: u32,
+    ) {
+        let leaf_cost = INTERSECT_COST * indices.len() as f64;
+        let split = match indices.len() <= MAX_LEAF_SIZE || depth == 0 {
+            true => None,
+            false => KdTree::best_split(boxes, node_box, &indices),
+        };
+        // splits that cost more than a leaf are only accepted a few times, and only for nodes
+        // holding many hittables
+        let split = split.filter(|(_, _, cost, _)| {
+            *cost < leaf_cost || (bad_refines < MAX_BAD_REFINES && indices.len() > 16)
+        });
+        let (axis, split, cost, edges) = match split {
+            Some(split) => split,
+            None => {
+                self.nodes.push(KdNode::Leaf {
+                    first: self.object_indices.len() as u32,
+                    count: indices.len() as u32,
+                });
+                self.object_indices.extend(indices);
+                return;
+            }
+        };
+        let bad_refines = bad_refines + (cost >= leaf_cost) as u32;
+
+        // objects starting before the plane are below it, objects ending after it are above
+        let offset = edges
+            .iter()
+            .position(|edge| edge.t == split)
+            .expect("the split plane is one of the edges");
+        let below: Vec<u32> = edges[..offset]
+            .iter()
+            .filter(|edge| edge.start)
+            .map(|edge| edge.object)
+            .collect();
+        let above: Vec<u32> = edges[offset + 1..]
+            .iter()
+            .filter(|edge| !edge.start)
+            .map(|edge| edge.object)
+            .collect();
+        let (mut below_max, mut above_min) = (node_box.max(), node_box.min());
+        below_max[axis] = split;
+        above_min[axis] = split;
+
+        let index = self.nodes.len();
+        self.nodes.push(KdNode::Leaf { first: 0, count: 0 });
+        self.build(
+            boxes,
+            &Aabb::new(node_box.min(), below_max),
+            below,
+            depth - 1,
+            bad_refines,
+        );
+        let above_index = self.nodes.len() as u32;
+        self.build(
+            boxes,
+            &Aabb::new(above_min, node_box.max()),
+            above,
+            depth - 1,
+            bad_refines,
+        );
+        self.nodes[index] = KdNode::Interior {
+            axis,
+            split,
+            above: above_index,
+        };
+    }
+
+    /// Sweeps the bounding box edges of the hittables `indices` along each axis, and returns
+    /// the split plane with the lowest estimated cost, as its `(axis, position, cost, edges)`,
+    /// where `edges` are the sorted edges of the split's axis. Returns `None` if no plane lies
+    /// within the node's bounds
+    fn best_split(
+        boxes: &[Aabb],
+        node_box: &Aabb,
+        indices: &[u32],
+    ) -> Option<(usize, f64, f64, Vec<BoundEdge>)> {
+        let extent = node_box.max() - node_box.min();
+        let surface_area = |d: [f64; 3]| 2.0 * (d[0] * d[1] + d[0] * d[2] + d[1] * d[2]);
+        let inv_area = 1.0 / surface_area([extent.x(), extent.y(), extent.z()]);
+        let count = indices.len();
+
+        let mut best: Option<(usize, f64, f64, Vec<BoundEdge>)> = None;
+        for axis in 0..3 {
+            let mut edges: Vec<BoundEdge> = indices
+                .iter()
+                .flat_map(|&object| {
+                    let bbox = &boxes[object as usize];
+                    [
+                        BoundEdge {
+                            t: bbox.min()[axis],
+                            start: true,
+                            object,
+                        },
+                        BoundEdge {
+                            t: bbox.max()[axis],
+                            start: false,
+                            object,
+                        },
+                    ]
+                })
+                .collect();
+            // at equal positions, starting edges come first
+            edges.sort_by(|a, b| {
+                a.t.partial_cmp(&b.t)
+                    .unwrap_or(Ordering::Equal)
+                    .then(b.start.cmp(&a.start))
+            });
+
+            let (mut below, mut above) = (0, count);
+            let mut axis_best: Option<(f64, f64)> = None;
+            for (i, edge) in edges.iter().enumerate() {
+                if !edge.start {
+                    above -= 1;
+                }
+                let t = edge.t;
+                // the first edge at a position is the one a partition by `t` starts from
+                let first_at_t = i == 0 || edges[i - 1].t != t;
+                if first_at_t && t > node_box.min()[axis] && t < node_box.max()[axis] {
+                    let mut below_extent = [extent.x(), extent.y(), extent.z()];
+                    let mut above_extent = below_extent;
+                    below_extent[axis] = t - node_box.min()[axis];
+                    above_extent[axis] = node_box.max()[axis] - t;
+                    let p_below = surface_area(below_extent) * inv_area;
+                    let p_above = surface_area(above_extent) * inv_area;
+                    let bonus = match below == 0 || above == 0 {
+                        true => EMPTY_BONUS,
+                        false => 0.0,
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECT_COST
+                            * (1.0 - bonus)
+                            * (p_below * below as f64 + p_above * above as f64);
+                    if axis_best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                        axis_best = Some((t, cost));
+                    }
+                }
+                if edge.start {
+                    below += 1;
+                }
+            }
+            if let Some((t, cost)) = axis_best {
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, _, best_cost, _)| cost < *best_cost)
+                {
+                    best = Some((axis, t, cost, edges));
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the hittable indices of the leaf `first..first + count`
+    fn leaf_indices(&self, first: u32, count: u32) -> &[u32] {
+        &self.object_indices[first as usize..(first + count) as usize]
+    }
+
+    /// Walks the leaves passed by `ray` between `t_min` and `t_max`, nearest first.
+    /// `visit_leaf` is called with the hittable indices of each leaf, and the `t` at which the
+    /// ray leaves it, and stops the walk by returning `true`
+    fn walk_leaves<F: FnMut(&[u32], f64) -> bool>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut visit_leaf: F,
+    ) {
+        let (mut node_min, mut node_max) = match self.bbox.and_then(|b| b.hit(ray, t_min, t_max)) {
+            Some(range) => range,
+            None => return,
+        };
+        let (origin, direction) = (ray.origin(), ray.direction());
+        // the far children still to visit, and the range of t the ray spends inside of them
+        let mut stack: Vec<(usize, f64, f64)> = Vec::with_capacity(32);
+        let mut index = 0;
+        loop {
+            match self.nodes[index] {
+                KdNode::Interior { axis, split, above } => {
+                    let t_plane = match direction[axis] {
+                        0.0 => f64::INFINITY,
+                        d => (split - origin[axis]) / d,
+                    };
+                    let below_first =
+                        origin[axis] < split || (origin[axis] == split && direction[axis] <= 0.0);
+                    let (near, far) = match below_first {
+                        true => (index + 1, above as usize),
+                        false => (above as usize, index + 1),
+                    };
+                    if t_plane > node_max || t_plane <= 0.0 {
+                        index = near;
+                    } else if t_plane < node_min {
+                        index = far;
+                    } else {
+                        stack.push((far, t_plane, node_max));
+                        index = near;
+                        node_max = t_plane;
+                    }
+                }
+                KdNode::Leaf { first, count } => {
+                    if visit_leaf(self.leaf_indices(first, count), node_max) {
+                        return;
+                    }
+                    match stack.pop() {
+                        Some((next, next_min, next_max)) => {
+                            index = next;
+                            node_min = next_min;
+                            node_max = next_max;
+                        }
+                        None => return,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Hittable for KdTree {
+    /// Tests the hittables of the leaves passed by the `ray`, nearest leaf first, and returns
+    /// the closest hit as soon as it lies within the leaves visited so far
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // the closest hit so far, and the index of its hittable in the list
+        let mut closest: Option<(HitRecord, u32)> = None;
+        self.walk_leaves(ray, t_min, t_max, |indices, t_leaf_exit| {
+            for &index in indices {
+                // hits within the surface epsilon of the closest hit may replace it, if their
+                // hittable was added to the list earlier
+                let limit = closest
+                    .as_ref()
+                    .map_or(t_max, |(rec, _)| rec.t + self.surface_epsilon);
+                if let Some(hit_record) = self.objects[index as usize].hit(ray, t_min, limit) {
+                    let replace = match &closest {
+                        Some((rec, closest_index)) => {
+                            hit_record.t < rec.t - self.surface_epsilon || index < *closest_index
+                        }
+                        None => true,
+                    };
+                    if replace {
+                        closest = Some((hit_record, index));
+                    }
+                }
+            }
+            // a hittable in a farther leaf can not be closer than a hit within this leaf
+            closest
+                .as_ref()
+                .is_some_and(|(rec, _)| rec.t <= t_leaf_exit)
+        });
+        closest.map(|(rec, _)| rec)
+    }
+
+    /// Returns `true` as soon as a hittable of any leaf passed by the ray is hit
+    fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut hit = false;
+        self.walk_leaves(ray, t_min, t_max, |indices, _| {
+            hit = indices
+                .iter()
+                .any(|&index| self.objects[index as usize].hit_any(ray, t_min, t_max));
+            hit
+        });
+        hit
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        self.bbox
+    }
+
+    /// Returns the combined tessellation of every hittable in this kd-tree
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.objects
+            .iter()
+            .filter_map(|object| object.tessellate(tessellation))
+            .reduce(|mut mesh, other| {
+                mesh.append(other);
+                mesh
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{build_solid_sphere, BoxInst, BvhNode, Hittable, HittableList, KdTree};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn finds_the_same_hits_as_a_bvh_node() {
+        let material = Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))));
+        let mut world = HittableList::new();
+        // rows of touching boxes, and spheres scattered among them
+        for i in 0..8 {
+            for j in 0..8 {
+                let min = Point3::new(i as f64, 0.0, -(j as f64));
+                world.add(Arc::new(BoxInst::from(
+                    min,
+                    min + Vec3::new(1.0, 0.5 + ((i + j) % 3) as f64 * 0.5, 1.0),
+                    material.clone(),
+                )));
+            }
+        }
+        for i in 0..10 {
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(i as f64 * 0.8, 2.5, -(i as f64) * 0.6),
+                0.4,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+        }
+        let tree = BvhNode::from(&world, 0.0, 1.0);
+        let kd_tree = KdTree::from(&world, 0.0, 1.0);
+        assert!(kd_tree.node_count() > 1);
+
+        for x in 0..40 {
+            for y in 0..40 {
+                for direction in [Vec3::new(0.1, -0.3, -1.0), Vec3::new(0.0, -1.0, 0.0)] {
+                    let ray = Ray::new(
+                        Point3::new(x as f64 * 0.25 - 0.99, 5.0, y as f64 * 0.25 - 7.99),
+                        direction,
+                        0.0,
+                    );
+                    let tree_hit = tree.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                    let kd_hit = kd_tree.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                    assert_eq!(kd_hit.is_some(), tree_hit.is_some());
+                    if let (Some(kd_hit), Some(tree_hit)) = (kd_hit, tree_hit) {
+                        assert!((kd_hit - tree_hit).length() < 1e-9);
+                    }
+                    assert_eq!(
+                        kd_tree.hit_any(&ray, 0.001, f64::INFINITY),
+                        tree_hit.is_some()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn an_empty_kd_tree_is_never_hit() {
+        let list = HittableList::new();
+        let kd_tree = KdTree::from(&list, 0.0, 1.0);
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(kd_tree.hit(&ray, 0.001, f64::INFINITY).is_none());
+        assert_eq!(kd_tree.node_count(), 0);
+    }
+}
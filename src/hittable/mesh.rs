@@ -0,0 +1,282 @@
+use crate::common::{Point3, Vec3};
+use crate::hittable::Aabb;
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::PI;
+use std::io;
+use std::io::Write;
+
+/// An indexed triangle mesh consisting of a list of vertex positions and a list of triangular
+/// faces. Each face holds three indices into `vertices`, wound **counter-clockwise** when
+/// viewed from the front (outward facing) side of the face.
+///
+/// A `Mesh` is the tessellated form of a `Hittable` and is used for exchanging geometry with
+/// external programs
+#[derive(Debug, Default, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Mesh::default()
+    }
+
+    /// Returns a quad made of two triangles from four corner points `a,b,c,d`, which must be
+    /// given in counter-clockwise order when viewed from the front of the quad
+    pub fn quad(a: Point3, b: Point3, c: Point3, d: Point3) -> Self {
+        Self {
+            vertices: vec![a, b, c, d],
+            faces: vec![[0, 1, 2], [0, 2, 3]],
+        }
+    }
+
+    /// Returns a latitude/longitude tessellation of a sphere with its poles on the y-axis.
+    /// `stacks` is the number of latitude bands, and `slices` is the number of longitude bands
+    pub fn uv_sphere(center: Point3, radius: f64, stacks: usize, slices: usize) -> Self {
+        let stacks = stacks.max(2);
+        let slices = slices.max(3);
+        let point = |theta: f64, phi: f64| -> Point3 {
+            center
+                + radius
+                    * Vec3::new(
+                        theta.sin() * phi.cos(),
+                        theta.cos(),
+                        theta.sin() * phi.sin(),
+                    )
+        };
+
+        let mut mesh = Mesh::new();
+        // north pole, the inner rings of vertices, and then the south pole
+        mesh.vertices.push(center + Vec3::new(0.0, radius, 0.0));
+        for i in 1..stacks {
+            let theta = PI * i as f64 / stacks as f64;
+            for j in 0..slices {
+                let phi = 2.0 * PI * j as f64 / slices as f64;
+                mesh.vertices.push(point(theta, phi));
+            }
+        }
+        mesh.vertices.push(center - Vec3::new(0.0, radius, 0.0));
+
+        let north = 0;
+        let south = mesh.vertices.len() - 1;
+        // index of the vertex at ring `i` (0-based, excluding the poles) and slice `j`
+        let ring = |i: usize, j: usize| -> usize { 1 + i * slices + (j % slices) };
+
+        for j in 0..slices {
+            mesh.faces.push([north, ring(0, j + 1), ring(0, j)]);
+        }
+        for i in 0..(stacks - 2) {
+            for j in 0..slices {
+                let (a, b) = (ring(i, j), ring(i, j + 1));
+                let (c, d) = (ring(i + 1, j), ring(i + 1, j + 1));
+                mesh.faces.push([a, d, c]);
+                mesh.faces.push([a, b, d]);
+            }
+        }
+        for j in 0..slices {
+            mesh.faces
+                .push([south, ring(stacks - 2, j), ring(stacks - 2, j + 1)]);
+        }
+
+        mesh
+    }
+
+    /// Appends the vertices and faces of `other` to this mesh
+    pub fn append(&mut self, other: Mesh) {
+        let offset = self.vertices.len();
+        self.vertices.extend(other.vertices);
+        self.faces.extend(
+            other
+                .faces
+                .into_iter()
+                .map(|[a, b, c]| [a + offset, b + offset, c + offset]),
+        );
+    }
+
+    /// Reverses the winding order of every face, which flips the side that faces outward
+    pub fn flip_winding(&mut self) {
+        for face in self.faces.iter_mut() {
+            face.swap(1, 2);
+        }
+    }
+
+    /// Makes the winding of this mesh's faces consistent, i.e. after importing a mesh whose
+    /// faces are inconsistently wound, so that every face is outward facing. Returns the number
+    /// of faces that were flipped.
+    ///
+    /// The winding is flood-filled across the edges shared by neighboring faces, so that
+    /// neighbors traverse their shared edge in opposite directions. Then every connected part
+    /// of the mesh that encloses a negative (signed) volume is inside-out, and is flipped.
+    /// Open, flat, parts of a mesh keep the winding of their first face
+    pub fn unify_winding(&mut self) -> usize {
+        // the faces sharing each (undirected) edge
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (f, [a, b, c]) in self.faces.iter().enumerate() {
+            for (v0, v1) in [(*a, *b), (*b, *c), (*c, *a)] {
+                edge_faces
+                    .entry((v0.min(v1), v0.max(v1)))
+                    .or_default()
+                    .push(f);
+            }
+        }
+        // returns true if `face` traverses the directed edge from `v0` to `v1`
+        let has_directed_edge = |face: &[usize; 3], v0: usize, v1: usize| -> bool {
+            (0..3).any(|i| face[i] == v0 && face[(i + 1) % 3] == v1)
+        };
+
+        let mut flipped = vec![false; self.faces.len()];
+        let mut visited = vec![false; self.faces.len()];
+        for start in 0..self.faces.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut component = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(f) = queue.pop_front() {
+                let [a, b, c] = self.faces[f];
+                for (v0, v1) in [(a, b), (b, c), (c, a)] {
+                    for &neighbor in edge_faces[&(v0.min(v1), v0.max(v1))].iter() {
+                        if visited[neighbor] {
+                            continue;
+                        }
+                        // a consistently wound neighbor traverses the edge from v1 to v0
+                        if has_directed_edge(&self.faces[neighbor], v0, v1) {
+                            self.faces[neighbor].swap(1, 2);
+                            flipped[neighbor] = !flipped[neighbor];
+                        }
+                        visited[neighbor] = true;
+                        component.push(neighbor);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+
+            if self.signed_volume(&component) < 0.0 {
+                for f in component {
+                    self.faces[f].swap(1, 2);
+                    flipped[f] = !flipped[f];
+                }
+            }
+        }
+        flipped.into_iter().filter(|f| *f).count()
+    }
+
+    /// Returns the signed volume enclosed by the given `faces` of this mesh, which is positive
+    /// if the faces are wound outward. The volume is measured relative to the centroid of the
+    /// faces, to keep it accurate for meshes that are far from the origin
+    fn signed_volume(&self, faces: &[usize]) -> f64 {
+        let centroid = faces
+            .iter()
+            .flat_map(|f| self.faces[*f])
+            .fold(Point3::default(), |acc, v| acc + self.vertices[v])
+            / (3 * faces.len()) as f64;
+        faces
+            .iter()
+            .map(|f| {
+                let [a, b, c] = self.faces[*f].map(|v| self.vertices[v] - centroid);
+                a.dot(&b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    /// Applies the function `f` to every vertex of this mesh
+    pub fn transform_vertices<F: Fn(Point3) -> Point3>(&mut self, f: F) {
+        for vertex in self.vertices.iter_mut() {
+            *vertex = f(*vertex);
+        }
+    }
+
+    /// Returns the axis-aligned bounding box of this mesh's vertices, or `None` if the mesh
+    /// has no vertices
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        if self.vertices.is_empty() {
+            return None;
+        }
+        let bbox = self.vertices.iter().fold(Aabb::default(), |acc, v| {
+            Aabb::surrounding_box(&acc, &Aabb::new(*v, *v))
+        });
+        Some(bbox)
+    }
+
+    /// Writes this mesh to `writer` in the Wavefront OBJ format
+    pub fn write_obj<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for v in self.vertices.iter() {
+            writeln!(writer, "v {} {} {}", v.x(), v.y(), v.z())?;
+        }
+        // OBJ vertex indices start at 1
+        for [a, b, c] in self.faces.iter() {
+            writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Point3;
+    use crate::hittable::Mesh;
+
+    #[test]
+    fn uv_sphere_faces_point_outward() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let mesh = Mesh::uv_sphere(center, 2.0, 8, 16);
+
+        for [a, b, c] in mesh.faces.iter() {
+            let (a, b, c) = (mesh.vertices[*a], mesh.vertices[*b], mesh.vertices[*c]);
+            let normal = (b - a).cross(c - a);
+            let centroid = (a + b + c) / 3.0;
+            assert!(normal.dot(&(centroid - center)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn append_offsets_face_indices() {
+        let mut mesh = Mesh::quad(
+            Point3::new(0., 0., 0.),
+            Point3::new(1., 0., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(0., 1., 0.),
+        );
+        mesh.append(mesh.clone());
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.faces[2], [4, 5, 6]);
+        assert_eq!(mesh.faces[3], [4, 6, 7]);
+    }
+
+    #[test]
+    fn unify_winding_orients_a_mis_wound_triangle_pair_consistently() {
+        let mut mesh = Mesh::quad(
+            Point3::new(0., 0., 0.),
+            Point3::new(1., 0., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(0., 1., 0.),
+        );
+        // wind the second triangle of the quad the other way
+        mesh.faces[1].swap(1, 2);
+
+        assert_eq!(mesh.unify_winding(), 1);
+        let normal = |[a, b, c]: [usize; 3]| {
+            (mesh.vertices[b] - mesh.vertices[a]).cross(mesh.vertices[c] - mesh.vertices[a])
+        };
+        assert!(normal(mesh.faces[0]).z() > 0.0);
+        assert!(normal(mesh.faces[1]).z() > 0.0);
+    }
+
+    #[test]
+    fn unify_winding_turns_an_inside_out_sphere_outward() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let mut mesh = Mesh::uv_sphere(center, 2.0, 8, 16);
+        mesh.flip_winding();
+        mesh.faces[5].swap(1, 2);
+
+        assert_eq!(mesh.unify_winding(), mesh.faces.len() - 1);
+        for [a, b, c] in mesh.faces.iter() {
+            let (a, b, c) = (mesh.vertices[*a], mesh.vertices[*b], mesh.vertices[*c]);
+            let centroid = (a + b + c) / 3.0;
+            assert!((b - a).cross(c - a).dot(&(centroid - center)) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
+use std::sync::Arc;
+
+/// ObjectId is a "wrapper" struct that tags every hit of the hittable it wraps with an `id`,
+/// which is written to the object ID buffer of `Renderer::with_aovs`, for compositing masks.
+/// When ids are nested, the innermost one is kept, so a part of a larger, tagged, object
+/// can be given an id of its own
+#[derive(Debug)]
+pub struct ObjectId {
+    ptr: Arc<dyn Hittable>,
+    id: u32,
+}
+
+impl ObjectId {
+    pub fn from(other: Arc<dyn Hittable>, id: u32) -> Self {
+        Self { ptr: other, id }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Hittable for ObjectId {
+    /// hits the wrapped hittable and sets the object id of the `HitRecord`, unless a nested
+    /// `ObjectId` has already set it
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.ptr.hit(r, t_min, t_max).map(|mut hit_rec| {
+            if hit_rec.object_id == 0 {
+                hit_rec.object_id = self.id;
+            }
+            hit_rec
+        })
+    }
+
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.ptr.hit_any(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        self.ptr.bounding_box(t0, t1)
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.ptr.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.ptr.random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, ObjectId, Sphere};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn innermost_id_is_kept() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        ));
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(sphere.hit(&ray, 0.001, f64::INFINITY).unwrap().object_id, 0);
+
+        let inner: Arc<dyn Hittable> = Arc::new(ObjectId::from(sphere, 7));
+        let outer = ObjectId::from(inner.clone(), 3);
+        assert_eq!(inner.hit(&ray, 0.001, f64::INFINITY).unwrap().object_id, 7);
+        assert_eq!(outer.hit(&ray, 0.001, f64::INFINITY).unwrap().object_id, 7);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::common::{Point3, Ray, RngContext, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable};
+use rand::Rng;
+
+/// A Portal is an invisible parallelogram shaped opening, such as a window, that light enters a
+/// scene through. The portal has a `corner` and two edge vectors, `edge_u` and `edge_v`, that
+/// span the opening.
+///
+/// Rays pass straight through a portal, but passing it to `Renderer::with_portals` focuses the
+/// light samples of interior surfaces onto the opening, instead of wasting them on the walls
+/// surrounding it. This is the "portal light" optimization used for interior scenes
+/// lit by the sky
+#[derive(Debug, Copy, Clone)]
+pub struct Portal {
+    corner: Point3,
+    edge_u: Vec3,
+    edge_v: Vec3,
+    // unit normal of the portal's plane
+    normal: Vec3,
+    area: f64,
+}
+
+impl Portal {
+    pub fn new(corner: Point3, edge_u: Vec3, edge_v: Vec3) -> Self {
+        let n = edge_u.cross(edge_v);
+        Self {
+            corner,
+            edge_u,
+            edge_v,
+            normal: n.unit_vector(),
+            area: n.length(),
+        }
+    }
+
+    /// Returns the area of the portal's opening
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Returns `Some(t)`, the position along the ray `r` where it passes through the portal's
+    /// opening, if the ray passes through it between `t_min` and `t_max`, else `None`
+    pub fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        let denom = self.normal.dot(&r.direction());
+        // the ray is parallel to the portal's plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = self.normal.dot(&(self.corner - r.origin())) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // express the point in the plane using the (non-orthogonal) edge vectors
+        let planar = r.at(t) - self.corner;
+        let n = self.edge_u.cross(self.edge_v);
+        let w = n / n.dot(&n);
+        let alpha = w.dot(&planar.cross(self.edge_v));
+        let beta = w.dot(&self.edge_u.cross(planar));
+        if (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Hittable for Portal {
+    /// a portal is an opening, so rays are never stopped by it
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        let corners = [
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ];
+        // pad the box so that it has a non-zero width in each dimension
+        let padding = Vec3::new(0.0001, 0.0001, 0.0001);
+        Some(corners.iter().fold(Aabb::default(), |acc, c| {
+            Aabb::surrounding_box(&acc, &Aabb::new(*c - padding, *c + padding))
+        }))
+    }
+
+    /// Returns the pdf, with respect to solid angle, of sampling `direction` from `origin` by
+    /// choosing a uniformly distributed point of the portal's opening
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *direction, 0.0);
+        match self.intersect(&ray, 0.001, f64::INFINITY) {
+            Some(t) => {
+                let distance_squared = t * t * direction.length_squared();
+                let cosine = (direction.dot(&self.normal) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Returns a direction from `origin` to a random point of the portal's opening
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let mut rng = RngContext::current();
+        let point = self.corner + rng.gen::<f64>() * self.edge_u + rng.gen::<f64>() * self.edge_v;
+        point - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, Portal};
+
+    #[test]
+    fn sampled_directions_pass_through_the_portal_opening() {
+        // a 2x1 window in the ceiling of a room, seen from a point on the floor
+        let portal = Portal::new(
+            Point3::new(-1.0, 3.0, -0.5),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let origin = Point3::new(2.0, 0.0, 1.0);
+
+        for _ in 0..1000 {
+            let direction = portal.random(&origin);
+            let ray = Ray::new(origin, direction, 0.0);
+            let t = portal.intersect(&ray, 0.001, f64::INFINITY).unwrap();
+            let p = ray.at(t);
+
+            assert!((p.y() - 3.0).abs() < 1e-9);
+            assert!((-1.0..=1.0).contains(&p.x()));
+            assert!((-0.5..=0.5).contains(&p.z()));
+            assert!(portal.pdf_value(&origin, &direction) > 0.0);
+        }
+        // directions that miss the opening are never sampled
+        assert_eq!(portal.pdf_value(&origin, &Vec3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn rays_pass_through_a_portal() {
+        let portal = Portal::new(
+            Point3::new(-1.0, 3.0, -0.5),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0), 0.0);
+
+        assert!(portal.intersect(&ray, 0.001, f64::INFINITY).is_some());
+        assert!(portal.hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+}
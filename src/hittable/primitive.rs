@@ -7,8 +7,14 @@ pub use moving_sphere::*;
 pub mod aa_rect;
 pub use aa_rect::*;
 
+pub mod annulus;
+pub use annulus::*;
+
 pub mod box_instance;
 pub use box_instance::*;
 
+pub mod triangle;
+pub use triangle::*;
+
 pub mod builder;
 pub use builder::*;
@@ -1,6 +1,7 @@
-use crate::common::{Point3, Ray, Vec3};
-use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::common::{Point3, Ray, RngContext, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
 use crate::material::Material;
+use rand::{Rng, RngCore};
 use std::sync::Arc;
 
 /// a 2D, Axis-Aligned, `Hittable` rectangle, that's aligned on the **xy plane**
@@ -33,7 +34,9 @@ impl Hittable for XYRect {
     /// `t0,t1` are the time intervals of the ray
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let t = (self.k - r.origin().z()) / r.direction().z();
-        if t < t_min || t > t_max {
+        // a hit exactly at t_max is rejected, so that a coplanar surface that was hit first
+        // keeps priority
+        if t < t_min || t >= t_max {
             return None;
         }
 
@@ -65,6 +68,38 @@ impl Hittable for XYRect {
         );
         Some(bbox)
     }
+
+    /// Returns this rectangle as two triangles facing the positive z-axis
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        Some(Mesh::quad(
+            Point3::new(self.x0, self.y0, self.k),
+            Point3::new(self.x1, self.y0, self.k),
+            Point3::new(self.x1, self.y1, self.k),
+            Point3::new(self.x0, self.y1, self.k),
+        ))
+    }
+
+    /// Returns a uniformly distributed point on this rectangle, and its (front facing) normal
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        let p = Point3::new(
+            self.x0 + (self.x1 - self.x0) * rng.gen::<f64>(),
+            self.y0 + (self.y1 - self.y0) * rng.gen::<f64>(),
+            self.k,
+        );
+        Some((p, Vec3::new(0.0, 0.0, 1.0)))
+    }
+
+    /// Returns the pdf, with respect to solid angle, of sampling `direction` from `origin` by
+    /// choosing a uniformly distributed point of this rectangle
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    /// Returns a direction from `origin` to a random point of this rectangle
+    fn random(&self, origin: &Point3) -> Vec3 {
+        rect_random(self, origin)
+    }
 }
 
 /// a 2D, `Hittable` rectangle, that's aligned on the **xz plane**
@@ -97,7 +132,9 @@ impl Hittable for XZRect {
     /// `t0,t1` are the time intervals of the ray
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let t = (self.k - r.origin().y()) / r.direction().y();
-        if t < t_min || t > t_max {
+        // a hit exactly at t_max is rejected, so that a coplanar surface that was hit first
+        // keeps priority
+        if t < t_min || t >= t_max {
             return None;
         }
 
@@ -129,6 +166,38 @@ impl Hittable for XZRect {
         );
         Some(bbox)
     }
+
+    /// Returns this rectangle as two triangles facing the positive y-axis
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        Some(Mesh::quad(
+            Point3::new(self.x0, self.k, self.z0),
+            Point3::new(self.x0, self.k, self.z1),
+            Point3::new(self.x1, self.k, self.z1),
+            Point3::new(self.x1, self.k, self.z0),
+        ))
+    }
+
+    /// Returns a uniformly distributed point on this rectangle, and its (front facing) normal
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        let p = Point3::new(
+            self.x0 + (self.x1 - self.x0) * rng.gen::<f64>(),
+            self.k,
+            self.z0 + (self.z1 - self.z0) * rng.gen::<f64>(),
+        );
+        Some((p, Vec3::new(0.0, 1.0, 0.0)))
+    }
+
+    /// Returns the pdf, with respect to solid angle, of sampling `direction` from `origin` by
+    /// choosing a uniformly distributed point of this rectangle
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    /// Returns a direction from `origin` to a random point of this rectangle
+    fn random(&self, origin: &Point3) -> Vec3 {
+        rect_random(self, origin)
+    }
 }
 
 /// a 2D, `Hittable` rectangle, that's aligned on the **yz plane**
@@ -161,7 +230,9 @@ impl Hittable for YZRect {
     /// `t0,t1` are the time intervals of the ray
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let t = (self.k - r.origin().x()) / r.direction().x();
-        if t < t_min || t > t_max {
+        // a hit exactly at t_max is rejected, so that a coplanar surface that was hit first
+        // keeps priority
+        if t < t_min || t >= t_max {
             return None;
         }
 
@@ -193,4 +264,57 @@ impl Hittable for YZRect {
         );
         Some(bbox)
     }
+
+    /// Returns this rectangle as two triangles facing the positive x-axis
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        Some(Mesh::quad(
+            Point3::new(self.k, self.y0, self.z0),
+            Point3::new(self.k, self.y1, self.z0),
+            Point3::new(self.k, self.y1, self.z1),
+            Point3::new(self.k, self.y0, self.z1),
+        ))
+    }
+
+    /// Returns a uniformly distributed point on this rectangle, and its (front facing) normal
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        let p = Point3::new(
+            self.k,
+            self.y0 + (self.y1 - self.y0) * rng.gen::<f64>(),
+            self.z0 + (self.z1 - self.z0) * rng.gen::<f64>(),
+        );
+        Some((p, Vec3::new(1.0, 0.0, 0.0)))
+    }
+
+    /// Returns the pdf, with respect to solid angle, of sampling `direction` from `origin` by
+    /// choosing a uniformly distributed point of this rectangle
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    /// Returns a direction from `origin` to a random point of this rectangle
+    fn random(&self, origin: &Point3) -> Vec3 {
+        rect_random(self, origin)
+    }
+}
+
+/// Returns the pdf, with respect to solid angle, of sampling `direction` from `origin` by
+/// choosing a uniformly distributed point of the rectangle `rect`, whose area is `area`
+fn rect_pdf_value(rect: &dyn Hittable, area: f64, origin: &Point3, direction: &Vec3) -> f64 {
+    match rect.hit(&Ray::new(*origin, *direction, 0.0), 0.001, f64::INFINITY) {
+        Some(rec) => {
+            let distance_squared = rec.t * rec.t * direction.length_squared();
+            let cosine = (direction.dot(&rec.normal) / direction.length()).abs();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
+}
+
+/// Returns a direction from `origin` to a uniformly distributed point of the rectangle `rect`
+fn rect_random(rect: &dyn Hittable, origin: &Point3) -> Vec3 {
+    let (point, _) = rect
+        .sample_surface(&mut RngContext::current())
+        .expect("rectangles can always be sampled");
+    point - *origin
 }
@@ -0,0 +1,174 @@
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
+use crate::material::Material;
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// a flat ring (annulus) `Hittable`, centered at `center` and facing the direction of
+/// `normal`, that covers the points of its plane between `inner_radius` and `outer_radius`
+/// from its center. With an inner radius of zero it is a disk.
+///
+/// Used with a `DiffuseLight` material, it makes a ring light. The `u` texture coordinate is
+/// the angle around the ring, in turns, and `v` runs from the inner edge (0) to the outer
+/// edge (1) of the ring
+#[derive(Debug)]
+pub struct Annulus {
+    center: Point3,
+    normal: Vec3,
+    inner_radius: f64,
+    outer_radius: f64,
+    mp: Arc<dyn Material>,
+    // two unit vectors that, along with the normal, form a right-handed basis of the plane
+    u_axis: Vec3,
+    v_axis: Vec3,
+}
+
+impl Annulus {
+    /// Returns a new ring from its `center`, `normal` direction and inner and outer radii
+    ///
+    /// # Panics
+    /// if `inner_radius` is negative, or is not less than `outer_radius`, or if `normal` is
+    /// a zero vector
+    pub fn new(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        mp: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            inner_radius >= 0.0 && inner_radius < outer_radius,
+            "inner_radius must be in the range [0..outer_radius)"
+        );
+        assert!(normal.length() > 0.0, "normal must not be a zero vector");
+        let normal = normal.unit_vector();
+        let (u_axis, v_axis) = normal.build_orthonormal_basis();
+        Self {
+            center,
+            normal,
+            inner_radius,
+            outer_radius,
+            mp,
+            u_axis,
+            v_axis,
+        }
+    }
+
+    /// Returns the point of this ring at `radius` from its center and `angle` radians around it
+    fn point(&self, radius: f64, angle: f64) -> Point3 {
+        self.center + radius * (angle.cos() * self.u_axis + angle.sin() * self.v_axis)
+    }
+}
+
+impl Hittable for Annulus {
+    /// Returns `Some(HitRecord)` if the given Ray `r` intersects this ring, else `None`
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.normal.dot(&r.direction());
+        // rays parallel to the ring's plane never hit it
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let t = self.normal.dot(&(self.center - r.origin())) / denom;
+        // a hit exactly at t_max is rejected, the same as the axis-aligned rectangles
+        if t < t_min || t >= t_max {
+            return None;
+        }
+
+        let p = r.at(t);
+        let offset = p - self.center;
+        let radius = offset.length();
+        if radius < self.inner_radius || radius > self.outer_radius {
+            return None;
+        }
+
+        let mut angle = offset.dot(&self.v_axis).atan2(offset.dot(&self.u_axis));
+        if angle < 0.0 {
+            angle += 2.0 * PI;
+        }
+        Some(HitRecord::with_face_normal(
+            r,
+            p,
+            &self.normal,
+            Arc::clone(&self.mp),
+            t,
+            angle / (2.0 * PI),
+            (radius - self.inner_radius) / (self.outer_radius - self.inner_radius),
+        ))
+    }
+
+    /// Returns a bounding box that tightly encloses the outer edge of this ring
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        // the extent of a circle along each axis shrinks as the axis aligns with its normal.
+        // Pad each dimension a small amount, as a ring that faces an axis is flat along it
+        let extent = |n: f64| self.outer_radius * (1.0 - n * n).max(0.0).sqrt() + 0.001;
+        let extent = Vec3::new(
+            extent(self.normal.x()),
+            extent(self.normal.y()),
+            extent(self.normal.z()),
+        );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    /// Returns this ring as a band of `4 * tessellation` quads facing its normal
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        let segments = 4 * tessellation.max(1) as usize;
+        let mut mesh = Mesh::new();
+        for i in 0..segments {
+            let angle = 2.0 * PI * i as f64 / segments as f64;
+            mesh.vertices.push(self.point(self.inner_radius, angle));
+            mesh.vertices.push(self.point(self.outer_radius, angle));
+        }
+        for i in 0..segments {
+            let (inner, outer) = (2 * i, 2 * i + 1);
+            let (next_inner, next_outer) =
+                ((2 * i + 2) % (2 * segments), (2 * i + 3) % (2 * segments));
+            mesh.faces.push([inner, outer, next_outer]);
+            mesh.faces.push([inner, next_outer, next_inner]);
+        }
+        Some(mesh)
+    }
+
+    /// Returns a uniformly distributed point on the surface of this ring
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        let (inner_sq, outer_sq) = (self.inner_radius.powi(2), self.outer_radius.powi(2));
+        let radius = (inner_sq + (outer_sq - inner_sq) * rng.gen::<f64>()).sqrt();
+        let angle = 2.0 * PI * rng.gen::<f64>();
+        Some((self.point(radius, angle), self.normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{Annulus, Hittable};
+    use crate::material::{DiffuseLight, Material};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn ray_through_the_hole_misses_and_ray_at_the_ring_hits() {
+        let light: Arc<dyn Material> = Arc::new(DiffuseLight::from(Arc::new(
+            SolidColor::from_rgb(4.0, 4.0, 4.0),
+        )));
+        let ring = Annulus::new(
+            Point3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            1.0,
+            2.0,
+            light,
+        );
+
+        let through_hole = Ray::new(Point3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        assert!(ring.hit(&through_hole, 0.001, f64::INFINITY).is_none());
+
+        let at_ring = Ray::new(Point3::new(0.0, 0.0, -1.5), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let rec = ring.hit(&at_ring, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.0, 2.0, -1.5));
+        assert!(rec.front_face);
+        assert!((rec.v - 0.5).abs() < 1e-12);
+
+        let outside = Ray::new(Point3::new(2.5, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        assert!(ring.hit(&outside, 0.001, f64::INFINITY).is_none());
+    }
+}
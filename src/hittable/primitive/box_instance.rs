@@ -1,5 +1,7 @@
 use crate::common::{Point3, Ray};
-use crate::hittable::{Aabb, FlipFace, HitRecord, Hittable, HittableList, XYRect, XZRect, YZRect};
+use crate::hittable::{
+    Aabb, FlipFace, HitRecord, Hittable, HittableList, Mesh, XYRect, XZRect, YZRect,
+};
 use crate::material::Material;
 use std::sync::Arc;
 
@@ -90,4 +92,8 @@ impl Hittable for BoxInst {
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
         Some(Aabb::new(self.box_min, self.box_max))
     }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.sides.tessellate(tessellation)
+    }
 }
@@ -100,6 +100,14 @@ pub fn build_xy_diff_light(
     XYRect::from(x0, x1, y0, y1, k, Arc::new(diff_light))
 }
 
+/// Returns a sphere with a diffuse light material with the specified Color, such as a "sun".
+/// Emissive spheres can be importance sampled as lights, see `Renderer::with_lights`
+pub fn build_sphere_diff_light(light_color: Color, center: Point3, rad: f64) -> Sphere {
+    let light_color = SolidColor::from(light_color);
+    let diff_light = DiffuseLight::from(Arc::new(light_color));
+    Sphere::new(center, rad, Arc::new(diff_light))
+}
+
 /// Returns a lambertian material with a solid color texture specified by the  `r,g,b` values
 pub fn build_solid_lambertian(r: f64, g: f64, b: f64) -> impl Material {
     let solid_color = SolidColor::from_rgb(r, g, b);
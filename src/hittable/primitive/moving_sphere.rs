@@ -8,7 +8,8 @@ use std::sync::Arc;
 /// a sphere that has its center move linearly from `center0` at `time0` to `center1` at `time1`.
 /// After that time interval, it continues on, so the times do not need to match up with the
 /// camera's aperture open and close. This type of Sphere is capable of "motion blur" if
-/// rendered by a camera that has a open shutter.
+/// rendered by a camera that has a open shutter. Other hittables can be moved, and spun, with an
+/// `AnimatedTransform`
 pub struct MovingSphere {
     center0: Point3,
     center1: Point3,
@@ -43,6 +44,11 @@ impl MovingSphere {
             + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
     }
 
+    /// returns the distance this moving sphere's center travels per unit of time
+    pub fn velocity(&self) -> Vec3 {
+        (self.center1 - self.center0) / (self.time1 - self.time0)
+    }
+
     /// Returns the radius of this Sphere
     pub fn radius(&self) -> f64 {
         self.radius
@@ -56,7 +62,7 @@ impl Hittable for MovingSphere {
             let hit_point = r.at(t);
             let outward_normal = (hit_point - self.center(r.time())) / self.radius;
             let (u, v) = texture::get_sphere_uv(&outward_normal);
-            HitRecord::with_face_normal(
+            let mut rec = HitRecord::with_face_normal(
                 r,
                 hit_point,
                 &outward_normal,
@@ -64,7 +70,9 @@ impl Hittable for MovingSphere {
                 t,
                 u,
                 v,
-            )
+            );
+            rec.velocity = self.velocity();
+            rec
         };
 
         // this sphere center at the the Ray's time
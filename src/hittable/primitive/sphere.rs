@@ -1,15 +1,24 @@
-use crate::common::{Point3, Ray, Vec3};
-use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::common::{Point3, Ray, RngContext, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
 use crate::material::Material;
 use crate::texture;
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
 use std::fmt::Formatter;
 use std::sync::Arc;
 
-/// a 3D sphere "primitive" with a `center` and `radius`
+/// a 3D sphere "primitive" with a `center` and `radius`.
+///
+/// A sphere can also be clamped to a *partial* sphere, such as a dome, a bowl or a wedge, by
+/// limiting the polar angle (`theta`, measured from the sphere's +y pole) and the azimuthal
+/// angle (`phi`, measured around the y-axis from +x towards +z) of the points that can be hit
 pub struct Sphere {
     center: Point3,
     radius: f64,
     mat_ptr: Arc<dyn Material>,
+    theta_min: f64,
+    theta_max: f64,
+    phi_max: f64,
 }
 
 impl Sphere {
@@ -18,16 +27,56 @@ impl Sphere {
             center,
             radius,
             mat_ptr,
+            theta_min: 0.0,
+            theta_max: PI,
+            phi_max: 2.0 * PI,
         }
     }
 
     /// convenience constructor to create a Sphere from x,y,z coordinates and a radius
     pub fn from_coords(cx: f64, cy: f64, cz: f64, radius: f64, mat_ptr: Arc<dyn Material>) -> Self {
-        Self {
-            center: Point3::new(cx, cy, cz),
-            radius,
-            mat_ptr,
+        Sphere::new(Point3::new(cx, cy, cz), radius, mat_ptr)
+    }
+
+    /// Clamps this sphere to a partial sphere that only contains the points with a polar angle
+    /// in `[theta_min..theta_max]` and an azimuthal angle in `[0..phi_max]`, in radians.
+    /// i.e. a `theta_max` of `PI/2` leaves the upper hemisphere (a dome), and a `theta_min` of
+    /// `PI/2` leaves the lower hemisphere (a bowl)
+    ///
+    /// # Panics
+    /// if `theta_min >= theta_max`, or if `phi_max` is not in the range `(0..2PI]`
+    pub fn with_angles(mut self, theta_min: f64, theta_max: f64, phi_max: f64) -> Self {
+        assert!(
+            theta_min < theta_max,
+            "theta_min must be less than theta_max"
+        );
+        assert!(
+            phi_max > 0.0 && phi_max <= 2.0 * PI,
+            "phi_max must be in the range (0..2PI]"
+        );
+        self.theta_min = theta_min.max(0.0);
+        self.theta_max = theta_max.min(PI);
+        self.phi_max = phi_max;
+        self
+    }
+
+    /// Returns true if this sphere has been clamped to a partial sphere
+    pub fn is_partial(&self) -> bool {
+        self.theta_min > 0.0 || self.theta_max < PI || self.phi_max < 2.0 * PI
+    }
+
+    /// Returns true if the point on this sphere with the (unit length) `outward_normal` lies
+    /// within the sphere's polar and azimuthal angle ranges
+    fn contains(&self, outward_normal: &Vec3) -> bool {
+        if !self.is_partial() {
+            return true;
+        }
+        let theta = outward_normal.y().clamp(-1.0, 1.0).acos();
+        let mut phi = outward_normal.z().atan2(outward_normal.x());
+        if phi < 0.0 {
+            phi += 2.0 * PI;
         }
+        theta >= self.theta_min && theta <= self.theta_max && phi <= self.phi_max
     }
 
     pub fn center(&self) -> Point3 {
@@ -37,6 +86,28 @@ impl Sphere {
     pub fn radius(&self) -> f64 {
         self.radius
     }
+
+    /// Returns the cosine of the half-angle of the cone, with its apex at `origin`, that
+    /// exactly encloses this sphere
+    fn cos_theta_max(&self, origin: &Point3) -> f64 {
+        let distance_squared = (self.center - *origin).length_squared();
+        (1.0 - self.radius * self.radius / distance_squared)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+/// Returns a random direction, in a local basis whose z-axis points towards the center of a
+/// sphere, that is uniformly distributed within the cone with cosine half-angle `cos_theta_max`
+fn random_in_cone(cos_theta_max: f64) -> Vec3 {
+    let mut rng = RngContext::current();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+    let z = 1.0 + r2 * (cos_theta_max - 1.0);
+    let phi = 2.0 * PI * r1;
+    let sin_theta = (1.0 - z * z).sqrt();
+
+    Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
 }
 
 impl Hittable for Sphere {
@@ -46,7 +117,7 @@ impl Hittable for Sphere {
             let hit_point = r.at(t);
             let outward_normal = (hit_point - self.center) / self.radius;
             let (u, v) = texture::get_sphere_uv(&outward_normal);
-            HitRecord::with_face_normal(
+            let mut rec = HitRecord::with_face_normal(
                 r,
                 hit_point,
                 &outward_normal,
@@ -54,7 +125,11 @@ impl Hittable for Sphere {
                 t,
                 u,
                 v,
-            )
+            );
+            // the texture's u coordinate wraps once around the sphere's circumference
+            let footprint = r.spread() * t * r.direction().length();
+            rec.uv_footprint = footprint / (2.0 * PI * self.radius);
+            rec
         };
 
         let oc = r.origin() - self.center;
@@ -66,12 +141,17 @@ impl Hittable for Sphere {
         // if the Ray hit some point on this Sphere
         if discriminant > 0.0 {
             let root = f64::sqrt(discriminant);
+            // a partial sphere rejects the roots outside of its angle ranges, so a ray can
+            // pass through its opening and hit the inside of the far side
+            let in_range = |t: f64| -> bool {
+                t < t_max && t > t_min && self.contains(&((r.at(t) - self.center) / self.radius))
+            };
             let t_temp = (-half_b - root) / a;
-            if t_temp < t_max && t_temp > t_min {
+            if in_range(t_temp) {
                 return Some(build_hit_record(t_temp));
             }
             let t_temp = (-half_b + root) / a;
-            if t_temp < t_max && t_temp > t_min {
+            if in_range(t_temp) {
                 return Some(build_hit_record(t_temp));
             }
         }
@@ -86,6 +166,65 @@ impl Hittable for Sphere {
             self.center() + Vec3::new(self.radius(), self.radius(), self.radius()),
         ))
     }
+
+    /// returns a latitude/longitude tessellation of this sphere, partial spheres are not
+    /// tessellated
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        if self.is_partial() {
+            return None;
+        }
+        Some(Mesh::uv_sphere(
+            self.center,
+            self.radius,
+            tessellation as usize,
+            2 * tessellation as usize,
+        ))
+    }
+
+    /// Returns the reciprocal of the solid angle subtended by this sphere when seen from
+    /// `origin`, if `direction` points at this sphere, else 0.
+    /// Because the solid angle shrinks with the square of the distance to the sphere, so does
+    /// the light gathered from an emissive sphere. Partial spheres are not light sampled, as
+    /// their openings would leave part of the sampled cone empty, so their pdf is 0
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.is_partial()
+            || self
+                .hit(&Ray::new(*origin, *direction, 0.0), 0.001, f64::INFINITY)
+                .is_none()
+        {
+            return 0.0;
+        }
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_theta_max(origin));
+        1.0 / solid_angle
+    }
+
+    /// Returns a random direction from `origin` within the cone that encloses this sphere.
+    /// Partial spheres are not light sampled, see `pdf_value`, so they return the x-axis, like
+    /// the other hittables that can not be light sampled
+    fn random(&self, origin: &Point3) -> Vec3 {
+        if self.is_partial() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let w = (self.center - *origin).unit_vector();
+        // build an orthonormal basis (u,v,w) around the direction to the sphere's center
+        let (u, v) = w.build_orthonormal_basis();
+
+        let local = random_in_cone(self.cos_theta_max(origin));
+        local.x() * u + local.y() * v + local.z() * w
+    }
+
+    /// Returns a uniformly distributed point on the surface of this sphere, partial spheres
+    /// are not sampled
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        if self.is_partial() {
+            return None;
+        }
+        let z: f64 = 1.0 - 2.0 * rng.gen::<f64>();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z * z).sqrt();
+        let normal = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+        Some((self.center + self.radius * normal, normal))
+    }
 }
 
 impl std::fmt::Debug for Sphere {
@@ -93,6 +232,9 @@ impl std::fmt::Debug for Sphere {
         f.debug_struct("Sphere")
             .field("center", &self.center)
             .field("radius", &self.radius)
+            .field("theta_min", &self.theta_min)
+            .field("theta_max", &self.theta_max)
+            .field("phi_max", &self.phi_max)
             .field("material", &self.mat_ptr)
             .finish()
     }
@@ -100,7 +242,7 @@ impl std::fmt::Debug for Sphere {
 
 #[cfg(test)]
 mod tests {
-    use crate::common::Point3;
+    use crate::common::{Point3, Ray, Vec3};
     use crate::hittable::{Hittable, Sphere};
     use crate::material::{Lambertian, Material};
     use crate::texture::{SolidColor, Texture};
@@ -127,4 +269,80 @@ mod tests {
         let sphere = Sphere::new(Point3::new(1.0, 1.0, 1.0), 1.0, lamb_mat);
         println!("{:#?}", sphere);
     }
+
+    #[test]
+    fn random_directions_point_at_the_sphere() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let sphere = Sphere::new(Point3::new(0.0, 5.0, 0.0), 1.0, lamb_mat);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        for _ in 0..100 {
+            let direction = sphere.random(&origin);
+            assert!(sphere.pdf_value(&origin, &direction) > 0.0);
+        }
+        assert_eq!(sphere.pdf_value(&origin, &Vec3::new(0.0, -1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn sampled_surface_points_lie_on_the_sphere_with_outward_normals() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let center = Point3::new(1.0, -2.0, 3.0);
+        let sphere = Sphere::new(center, 1.0, lamb_mat);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let (p, normal) = sphere.sample_surface(&mut rng).unwrap();
+            assert!(((p - center).length() - 1.0).abs() < 1e-9);
+            assert!((normal.length() - 1.0).abs() < 1e-9);
+            assert!(normal.dot(&(p - center)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn hemisphere_is_hit_on_its_top_half_but_not_its_bottom() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let dome = Sphere::new(Point3::default(), 1.0, lamb_mat).with_angles(
+            0.0,
+            std::f64::consts::FRAC_PI_2,
+            2.0 * std::f64::consts::PI,
+        );
+
+        let from_above = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = dome.hit(&from_above, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.0, 1.0, 0.0));
+
+        let below_equator = Ray::new(Point3::new(5.0, -0.5, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        assert!(dome.hit(&below_equator, 0.001, f64::INFINITY).is_none());
+
+        // a ray from below passes through the opening, and hits the inside of the dome
+        let from_below = Ray::new(Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let rec = dome.hit(&from_below, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.0, 1.0, 0.0));
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn partial_spheres_are_not_light_sampled() {
+        let tex: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5));
+        let lamb_mat: Arc<dyn Material> = Arc::new(Lambertian::new(tex));
+        let sphere = Sphere::new(Point3::default(), 1.0, Arc::clone(&lamb_mat));
+        let dome = Sphere::new(Point3::default(), 1.0, lamb_mat).with_angles(
+            0.0,
+            std::f64::consts::FRAC_PI_2,
+            2.0 * std::f64::consts::PI,
+        );
+        let origin = Point3::new(0.0, 5.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        // the dome is hit from above, but it has no pdf, unlike the whole sphere
+        assert!(dome
+            .hit(&Ray::new(origin, down, 0.0), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(sphere.pdf_value(&origin, &down) > 0.0);
+        assert_eq!(dome.pdf_value(&origin, &down), 0.0);
+        assert!(dome.sample_surface(&mut rand::thread_rng()).is_none());
+    }
 }
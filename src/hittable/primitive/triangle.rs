@@ -0,0 +1,274 @@
+use crate::common::{Color, Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
+use crate::material::Material;
+use rand::{Rng, RngCore};
+use std::sync::Arc;
+
+/// a single `Hittable` triangle with vertices `a`, `b` and `c`. The front face of the triangle
+/// is the side from which its vertices appear in counter-clockwise order, the same as the
+/// faces of a `Mesh`.
+///
+/// A triangle can optionally be given a normal and a texture coordinate for each of its
+/// vertices, i.e. from a model file, which are interpolated across the triangle using the
+/// barycentric coordinates of each hit. Without per-vertex normals the triangle is flat
+/// shaded, and without per-vertex UVs the `u` and `v` texture coordinates of a hit are its
+/// barycentric coordinates, the weights of vertices `b` and `c` respectively.
+///
+/// A triangle can also be given a color for each of its vertices, i.e. for a vertex painted
+/// mesh, which is interpolated in the same way into the `vertex_color` of each hit. Use the
+/// `VertexColor` material to shade a triangle with its vertex colors
+#[derive(Debug)]
+pub struct Triangle {
+    a: Point3,
+    b: Point3,
+    c: Point3,
+    mp: Arc<dyn Material>,
+    // the unit length normals of vertices a, b and c
+    normals: Option<[Vec3; 3]>,
+    // the (u,v) texture coordinates of vertices a, b and c
+    uvs: Option<[(f64, f64); 3]>,
+    // the colors of vertices a, b and c
+    colors: Option<[Color; 3]>,
+}
+
+impl Triangle {
+    /// Returns a new, flat shaded, triangle from its three vertices and material
+    pub fn new(a: Point3, b: Point3, c: Point3, mp: Arc<dyn Material>) -> Self {
+        Self {
+            a,
+            b,
+            c,
+            mp,
+            normals: None,
+            uvs: None,
+            colors: None,
+        }
+    }
+
+    /// Sets the normals of vertices `a`, `b` and `c`, which are interpolated to give a smooth
+    /// shading normal across the triangle. The normals should point to the front face of the
+    /// triangle, and need not be unit length
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals.map(|n| n.unit_vector()));
+        self
+    }
+
+    /// Sets the `(u,v)` texture coordinates of vertices `a`, `b` and `c`
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Sets the colors of vertices `a`, `b` and `c`
+    pub fn with_colors(mut self, colors: [Color; 3]) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    /// Returns `Some(HitRecord)` if the given Ray `r` intersects this triangle, using the
+    /// Möller–Trumbore algorithm, else `None`
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+        let p_vec = r.direction().cross(edge2);
+        let det = edge1.dot(&p_vec);
+        // rays parallel to the triangle's plane, and degenerate triangles, are never hit
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let t_vec = r.origin() - self.a;
+        let u = t_vec.dot(&p_vec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q_vec = t_vec.cross(edge1);
+        let v = r.direction().dot(&q_vec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(&q_vec) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // the barycentric weights of vertices a, b and c
+        let weights = [1.0 - u - v, u, v];
+        let (u, v) = match self.uvs {
+            Some(uvs) => (0..3).fold((0.0, 0.0), |(tex_u, tex_v), i| {
+                (tex_u + weights[i] * uvs[i].0, tex_v + weights[i] * uvs[i].1)
+            }),
+            None => (u, v),
+        };
+
+        // the side of the triangle that was hit comes from its geometric normal, while the
+        // shading normal may be interpolated from the vertex normals
+        let outward_normal = edge1.cross(edge2).unit_vector();
+        let front_face = r.direction().dot(&outward_normal) < 0.0;
+        let normal = match self.normals {
+            Some(normals) => {
+                (weights[0] * normals[0] + weights[1] * normals[1] + weights[2] * normals[2])
+                    .unit_vector()
+            }
+            None => outward_normal,
+        };
+        let mut rec = HitRecord::new(
+            r.at(t),
+            if front_face { normal } else { -normal },
+            Arc::clone(&self.mp),
+            t,
+            u,
+            v,
+            front_face,
+        );
+        rec.vertex_color = self
+            .colors
+            .map(|colors| weights[0] * colors[0] + weights[1] * colors[1] + weights[2] * colors[2]);
+        Some(rec)
+    }
+
+    /// Returns the bounding box of the three vertices. Each dimension is padded a small
+    /// amount, as a triangle that faces an axis is flat along it
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        let pad = Vec3::new(0.001, 0.001, 0.001);
+        let min = Point3::new(
+            self.a.x().min(self.b.x()).min(self.c.x()),
+            self.a.y().min(self.b.y()).min(self.c.y()),
+            self.a.z().min(self.b.z()).min(self.c.z()),
+        );
+        let max = Point3::new(
+            self.a.x().max(self.b.x()).max(self.c.x()),
+            self.a.y().max(self.b.y()).max(self.c.y()),
+            self.a.z().max(self.b.z()).max(self.c.z()),
+        );
+        Some(Aabb::new(min - pad, max + pad))
+    }
+
+    /// Returns this triangle as a single face mesh
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        Some(Mesh {
+            vertices: vec![self.a, self.b, self.c],
+            faces: vec![[0, 1, 2]],
+        })
+    }
+
+    /// Returns a uniformly distributed point on this triangle, and its (front facing) normal
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point3, Vec3)> {
+        // fold points that land outside of the triangle back into it
+        let (mut u, mut v) = (rng.gen::<f64>(), rng.gen::<f64>());
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        let p = self.a + u * (self.b - self.a) + v * (self.c - self.a);
+        let normal = (self.b - self.a).cross(self.c - self.a).unit_vector();
+        Some((p, normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, Triangle};
+    use crate::material::{Lambertian, Material, VertexColor};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn hits_inside_the_triangle_with_barycentric_uvs() {
+        let mat = Arc::new(Lambertian::new(Arc::new(SolidColor::from(Color::new(
+            0.5, 0.5, 0.5,
+        )))));
+        let triangle = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            mat,
+        );
+
+        let ray = Ray::new(Point3::new(0.25, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = triangle.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.25, 0.5, 0.0));
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        assert!(rec.front_face);
+
+        let outside = Ray::new(Point3::new(0.75, 0.75, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(triangle.hit(&outside, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn vertex_uvs_and_normals_are_interpolated_from_barycentric_coordinates() {
+        let mat = Arc::new(Lambertian::new(Arc::new(SolidColor::from(Color::new(
+            0.5, 0.5, 0.5,
+        )))));
+        let triangle = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+            mat,
+        )
+        .with_uvs([(0.5, 0.5), (1.0, 0.5), (0.5, 1.0)])
+        .with_normals([
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ]);
+
+        // hit the middle of the edge from a to b, halfway between their uvs and normals
+        let ray = Ray::new(Point3::new(1.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = triangle.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        let expected =
+            (Vec3::new(0.0, 0.0, 1.0) + Vec3::new(1.0, 0.0, 1.0).unit_vector()).unit_vector();
+        assert!((rec.normal - expected).length() < 1e-12);
+        assert!(rec.front_face);
+
+        // the shading normal is flipped along with the face when the back of the triangle is hit
+        let from_behind = Ray::new(Point3::new(1.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = triangle.hit(&from_behind, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert!((rec.normal + expected).length() < 1e-12);
+    }
+
+    #[test]
+    fn vertex_colors_are_interpolated_and_shaded_by_the_vertex_color_material() {
+        let mat = Arc::new(VertexColor::new(Arc::new(SolidColor::from(Color::new(
+            1.0, 1.0, 0.0,
+        )))));
+        let triangle = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 0.0),
+            Point3::new(0.0, 3.0, 0.0),
+            mat.clone(),
+        )
+        .with_colors([
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ]);
+
+        // the centroid is an equal mix of red, green and blue
+        let ray = Ray::new(Point3::new(1.0, 1.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = triangle.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let gray = Color::new(1.0, 1.0, 1.0) / 3.0;
+        assert!((rec.vertex_color.unwrap() - gray).length() < 1e-12);
+        let scatter = mat.scatter(&ray, &rec).unwrap();
+        assert!((scatter.attenuation - gray).length() < 1e-12);
+
+        // a triangle without vertex colors falls back to the material's texture
+        let plain = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 0.0),
+            Point3::new(0.0, 3.0, 0.0),
+            mat.clone(),
+        );
+        let rec = plain.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(rec.vertex_color.is_none());
+        let scatter = mat.scatter(&ray, &rec).unwrap();
+        assert_eq!(scatter.attenuation, Color::new(1.0, 1.0, 0.0));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::common::{Ray, RngContext};
+use crate::hittable::{Aabb, HitRecord, Hittable, HittableList};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+/// a child of a `RefitBvh` node, either another node or one of the BVH's objects, both given
+/// by their index
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Child {
+    Node(usize),
+    Object(usize),
+}
+
+/// a node of a `RefitBvh`. Leaves with a single object store it in both children
+#[derive(Debug, Copy, Clone)]
+struct RefitNode {
+    left: Child,
+    right: Child,
+    bbox: Aabb,
+}
+
+/// A Bounded Volume Hierarchy that can be *refit* to objects that have moved, for rendering
+/// animations of moving geometry.
+///
+/// A `RefitBvh` is built once, like a `BvhNode`, but stores its tree topology separately from
+/// its objects. Each frame of an animation then swaps in that frame's objects and only updates
+/// the bounding boxes of the nodes, which is far cheaper than rebuilding the tree. The tree
+/// stays valid for any motion, but is best suited to small per-frame motion, as the node
+/// boxes grow looser the further the objects move from where the tree was built
+#[derive(Clone)]
+pub struct RefitBvh {
+    objects: Vec<Arc<dyn Hittable>>,
+    // the nodes of the tree, the root is the first node, and every node is stored before
+    // its child nodes
+    nodes: Vec<RefitNode>,
+    // the surface epsilon of the list this BVH was built from, see `HittableList`
+    surface_epsilon: f64,
+}
+
+impl RefitBvh {
+    /// Constructs a BVH from the `list` of Hittables, using their bounding boxes from `time0`
+    /// to `time1`. The order of `list` is left unchanged
+    ///
+    /// # Panics
+    /// if the list is empty, or if a hittable does not have a bounding box
+    pub fn from(list: &mut HittableList, time0: f64, time1: f64) -> RefitBvh {
+        assert!(
+            !list.objects().is_empty(),
+            "cannot build a BVH from an empty list"
+        );
+        let mut bvh = RefitBvh {
+            objects: list.objects().clone(),
+            nodes: Vec::new(),
+            surface_epsilon: list.surface_epsilon(),
+        };
+        let mut indices: Vec<usize> = (0..bvh.objects.len()).collect();
+        bvh.split_volumes(&mut indices, time0, time1);
+        bvh.update_boxes(time0, time1);
+        bvh
+    }
+
+    /// Replaces the objects of this BVH with the objects of `list` and refits the bounding
+    /// boxes of its nodes to them, keeping the tree topology. The objects of `list` must be
+    /// given in the same order as the list this BVH was built from, so that each object takes
+    /// the place of the object it replaces, i.e. the same scene at another animation frame
+    ///
+    /// # Panics
+    /// if `list` does not have the same number of objects that this BVH was built from, or if
+    /// a hittable does not have a bounding box
+    pub fn refit(&mut self, list: &mut HittableList, time0: f64, time1: f64) {
+        assert_eq!(
+            list.objects().len(),
+            self.objects.len(),
+            "a BVH can only be refit to the same number of objects"
+        );
+        self.objects = list.objects().clone();
+        self.surface_epsilon = list.surface_epsilon();
+        self.update_boxes(time0, time1);
+    }
+
+    /// Builds the nodes for the objects at `indices`, sorting them along a random axis and
+    /// putting half in each subtree, in the same way as `BvhNode`. Returns the index of the
+    /// new node. The node boxes are computed afterwards, by `update_boxes`
+    fn split_volumes(&mut self, indices: &mut [usize], time0: f64, time1: f64) -> usize {
+        let axis: usize = RngContext::current().gen_range(0..3);
+        let node = self.nodes.len();
+        self.nodes.push(RefitNode {
+            left: Child::Object(indices[0]),
+            right: Child::Object(indices[0]),
+            bbox: Aabb::default(),
+        });
+
+        if indices.len() == 2 {
+            self.nodes[node].right = Child::Object(indices[1]);
+        } else if indices.len() > 2 {
+            let objects = &self.objects;
+            indices.sort_by(|a, b| {
+                RefitBvh::box_compare(&*objects[*a], &*objects[*b], axis, time0, time1)
+            });
+            let mid = indices.len() / 2;
+            let left = self.split_volumes(&mut indices[..mid], time0, time1);
+            let right = self.split_volumes(&mut indices[mid..], time0, time1);
+            self.nodes[node].left = Child::Node(left);
+            self.nodes[node].right = Child::Node(right);
+        }
+        node
+    }
+
+    /// Recomputes the bounding box of every node, from the bottom of the tree up
+    fn update_boxes(&mut self, time0: f64, time1: f64) {
+        // child nodes are stored after their parents, so visiting the nodes in reverse order
+        // updates the children before their parents
+        for node in (0..self.nodes.len()).rev() {
+            let box_left = self.child_box(self.nodes[node].left, time0, time1);
+            let box_right = self.child_box(self.nodes[node].right, time0, time1);
+            self.nodes[node].bbox = Aabb::surrounding_box(&box_left, &box_right);
+        }
+    }
+
+    /// Returns the bounding box of `child`, child nodes must already have been updated
+    fn child_box(&self, child: Child, time0: f64, time1: f64) -> Aabb {
+        match child {
+            Child::Node(node) => self.nodes[node].bbox,
+            Child::Object(object) => self.objects[object]
+                .bounding_box(time0, time1)
+                .expect("a hittable did not have a bounding box during BVH construction"),
+        }
+    }
+
+    /// Compares the minimum of the bounding boxes of two hittables along the given `axis`
+    fn box_compare<T: Hittable + ?Sized>(
+        a: &T,
+        b: &T,
+        axis: usize,
+        time0: f64,
+        time1: f64,
+    ) -> Ordering {
+        let box_a = a
+            .bounding_box(time0, time1)
+            .expect("Hittable 'a' doesn't have a bounding box");
+        let box_b = b
+            .bounding_box(time0, time1)
+            .expect("Hittable 'b' doesn't have a bounding box");
+        box_a.min()[axis]
+            .partial_cmp(&box_b.min()[axis])
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Returns the closest hit of `ray` with `child`
+    fn hit_child(&self, child: Child, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        match child {
+            Child::Node(node) => self.hit_node(node, ray, t_min, t_max),
+            Child::Object(object) => self.objects[object].hit(ray, t_min, t_max),
+        }
+    }
+
+    /// Returns the closest hit of `ray` with the subtree at `node`. Like `BvhNode`, the right
+    /// child must be closer by more than the surface epsilon to replace the left child's hit
+    fn hit_node(&self, node: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let RefitNode { left, right, bbox } = self.nodes[node];
+        bbox.hit(ray, t_min, t_max)?;
+
+        let hit_left = self.hit_child(left, ray, t_min, t_max);
+        let hit_right = match &hit_left {
+            Some(rec) => self.hit_child(right, ray, t_min, rec.t - self.surface_epsilon),
+            None => self.hit_child(right, ray, t_min, t_max),
+        };
+        hit_right.or(hit_left)
+    }
+}
+
+impl Hittable for RefitBvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_node(0, ray, t_min, t_max)
+    }
+
+    /// Returns the bounding box of the root node, as of the last build or refit
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        Some(self.nodes[0].bbox)
+    }
+}
+
+impl std::fmt::Debug for RefitBvh {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefitBvh")
+            .field("objects", &self.objects)
+            .field("nodes", &self.nodes)
+            .field("surface_epsilon", &self.surface_epsilon)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{build_solid_sphere, BvhNode, Hittable, HittableList, RefitBvh};
+    use std::sync::Arc;
+
+    /// returns a row of spheres along the x-axis, where the middle sphere is raised by `lift`
+    fn spheres(lift: f64) -> HittableList {
+        let mut world = HittableList::new();
+        for i in 0..7 {
+            let y = if i == 3 { lift } else { 0.0 };
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(i as f64 * 3.0, y, 0.0),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+        }
+        world
+    }
+
+    #[test]
+    fn refit_bvh_finds_a_moved_sphere_like_a_rebuilt_bvh() {
+        let mut bvh = RefitBvh::from(&mut spheres(0.0), 0.0, 1.0);
+        let mut moved = spheres(5.0);
+        bvh.refit(&mut moved, 0.0, 1.0);
+        let rebuilt = BvhNode::from(&moved, 0.0, 1.0);
+
+        let at_new_position = Ray::new(Point3::new(9.0, 5.0, 10.0), Vec3::new(0., 0., -1.), 0.0);
+        let rec = bvh.hit(&at_new_position, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(9.0, 5.0, 1.0));
+        let at_old_position = Ray::new(Point3::new(9.0, 0.0, 10.0), Vec3::new(0., 0., -1.), 0.0);
+        assert!(bvh.hit(&at_old_position, 0.001, f64::INFINITY).is_none());
+
+        for x in 0..50 {
+            for y in -2..8 {
+                let ray = Ray::new(
+                    Point3::new(x as f64 * 0.4 - 1.0, y as f64 * 0.9, 10.0),
+                    Vec3::new(0.0, 0.0, -1.0),
+                    0.0,
+                );
+                let refit_hit = bvh.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                let rebuilt_hit = rebuilt.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                assert_eq!(refit_hit, rebuilt_hit);
+            }
+        }
+    }
+}
@@ -1,7 +1,77 @@
-use crate::common::{degrees_to_radians, Point3, Ray, Vec3};
-use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::common::{degrees_to_radians, Mat4, Point3, Ray, Vec3};
+use crate::hittable::{Aabb, AffineTransform, HitRecord, Hittable, Mesh};
 use std::sync::Arc;
 
+/// Rotates a `Hittable` by an `angle`, in degrees, about any `axis` through any `pivot` point,
+/// following the right hand rule. Unlike `RotateY`, the axis does not need to be one of the
+/// world axes, nor pass through the origin, i.e. a door can be swung about its hinge
+#[derive(Debug)]
+pub struct Rotate {
+    transform: AffineTransform,
+    axis: Vec3,
+    angle: f64,
+    pivot: Point3,
+}
+
+impl Rotate {
+    /// Returns a new `Rotate` hittable that rotates `p` by `angle` degrees about the line
+    /// through `pivot` along `axis`
+    ///
+    /// # Panics
+    /// if `axis` is the zero vector
+    pub fn around_axis(p: Arc<dyn Hittable>, axis: Vec3, angle: f64, pivot: Point3) -> Self {
+        let matrix =
+            Mat4::translation(pivot) * Mat4::rotation(axis, angle) * Mat4::translation(-pivot);
+        Self {
+            transform: AffineTransform::from(p, matrix),
+            axis,
+            angle,
+            pivot,
+        }
+    }
+
+    /// Returns the axis of this rotation
+    pub fn axis(&self) -> Vec3 {
+        self.axis
+    }
+
+    /// Returns the angle, in degrees, of this rotation
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Returns the point the axis of this rotation passes through
+    pub fn pivot(&self) -> Point3 {
+        self.pivot
+    }
+}
+
+impl Hittable for Rotate {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.transform.hit(r, t_min, t_max)
+    }
+
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.transform.hit_any(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        self.transform.bounding_box(t0, t1)
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.transform.tessellate(tessellation)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.transform.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.transform.random(origin)
+    }
+}
+
 #[derive(Debug)]
 pub struct RotateY {
     ptr: Arc<dyn Hittable>,
@@ -62,18 +132,22 @@ impl Hittable for RotateY {
         direction[0] = self.cos_theta * r.direction()[0] - self.sin_theta * r.direction()[2];
         direction[2] = self.sin_theta * r.direction()[0] + self.cos_theta * r.direction()[2];
 
-        let rotated_r = Ray::new(origin, direction, r.time());
+        let rotated_r = Ray::new(origin, direction, r.time()).with_spread(r.spread());
 
         if let Some(mut rec) = self.ptr.hit(&rotated_r, t_min, t_max) {
             let mut p = rec.p;
             let mut normal = rec.normal;
+            let mut velocity = rec.velocity;
 
             p[0] = self.cos_theta * rec.p[0] + self.sin_theta * rec.p[2];
             p[2] = -self.sin_theta * rec.p[0] + self.cos_theta * rec.p[2];
             normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
             normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
+            velocity[0] = self.cos_theta * rec.velocity[0] + self.sin_theta * rec.velocity[2];
+            velocity[2] = -self.sin_theta * rec.velocity[0] + self.cos_theta * rec.velocity[2];
 
             rec.p = p;
+            rec.velocity = velocity;
             rec.set_face_normal(&rotated_r, &normal);
 
             Some(rec)
@@ -85,12 +159,25 @@ impl Hittable for RotateY {
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
         self.bbox
     }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation).map(|mut mesh| {
+            mesh.transform_vertices(|v| {
+                Vec3::new(
+                    self.cos_theta * v.x() + self.sin_theta * v.z(),
+                    v.y(),
+                    -self.sin_theta * v.x() + self.cos_theta * v.z(),
+                )
+            });
+            mesh
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::common::{Color, Point3};
-    use crate::hittable::{BoxInst, RotateY};
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{BoxInst, Hittable, Rotate, RotateY};
     use crate::material::Metal;
     use std::sync::Arc;
 
@@ -104,4 +191,28 @@ mod tests {
         let roty = RotateY::from(Arc::new(box_inst), 90.0);
         dbg!(roty);
     }
+
+    #[test]
+    fn rotate_about_an_axis_through_a_pivot() {
+        let box_inst = BoxInst::from(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1., 1., 1.),
+            Arc::new(Metal::new(Color::new(0., 0., 0.), 0.5)),
+        );
+        // a quarter turn about the z-axis through (1, 0, 0) swings the box below the x-axis
+        let rotated = Rotate::around_axis(
+            Arc::new(box_inst),
+            Vec3::new(0.0, 0.0, 1.0),
+            90.0,
+            Point3::new(1.0, 0.0, 0.0),
+        );
+        let bbox = rotated.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.min() - Point3::new(0.0, -1.0, 0.0)).length() < 1e-9);
+        assert!((bbox.max() - Point3::new(1.0, 0.0, 1.0)).length() < 1e-9);
+
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = rotated.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point3::new(0.5, 0.0, 0.5)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+    }
 }
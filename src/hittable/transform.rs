@@ -0,0 +1,55 @@
+use crate::common::{Lerp, Mat4, Vec3};
+use crate::hittable::{AffineTransform, Hittable};
+use std::sync::Arc;
+
+/// A Transform describes how to place a `Hittable` in the world: it is first rotated by
+/// `rotate_y` degrees about the y-axis, and then translated by `offset`.
+/// The default transform leaves a hittable unchanged
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub rotate_y: f64,
+    pub offset: Vec3,
+}
+
+impl Transform {
+    /// Returns a transform that only translates by `offset`
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            rotate_y: 0.0,
+            offset,
+        }
+    }
+
+    /// Returns a transform that only rotates by `degrees` about the y-axis
+    pub fn rotation_y(degrees: f64) -> Self {
+        Self {
+            rotate_y: degrees,
+            offset: Vec3::default(),
+        }
+    }
+
+    /// Returns the matrix of this transform, which rotates before it translates
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::translation(self.offset) * Mat4::rotation_y(self.rotate_y)
+    }
+
+    /// Wraps `hittable` in an `AffineTransform` that applies this transform. The default
+    /// transform returns the hittable unchanged
+    pub fn apply(&self, hittable: Arc<dyn Hittable>) -> Arc<dyn Hittable> {
+        if *self == Transform::default() {
+            return hittable;
+        }
+        Arc::new(AffineTransform::from(hittable, self.matrix()))
+    }
+}
+
+/// Transforms are interpolated one component at a time, so a keyframed `rotate_y` of 0 to 360
+/// degrees spins a hittable once around the y-axis
+impl Lerp for Transform {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Transform {
+            rotate_y: self.rotate_y.lerp(&other.rotate_y, t),
+            offset: self.offset.lerp(&other.offset, t),
+        }
+    }
+}
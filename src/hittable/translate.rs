@@ -1,5 +1,5 @@
-use crate::common::{Ray, Vec3};
-use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
 use std::sync::Arc;
 
 /// Holds the details of a `Hittable`s translation.
@@ -26,7 +26,8 @@ impl Translate {
 
 impl Hittable for Translate {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let moved_r = Ray::new(r.origin() - self.offset, r.direction(), r.time());
+        let moved_r =
+            Ray::new(r.origin() - self.offset, r.direction(), r.time()).with_spread(r.spread());
 
         match self.ptr.hit(&moved_r, t_min, t_max) {
             Some(mut rec) => {
@@ -43,4 +44,21 @@ impl Hittable for Translate {
             .bounding_box(t0, t1)
             .map(|bbox| Aabb::new(bbox.min() + self.offset, bbox.max() + self.offset))
     }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation).map(|mut mesh| {
+            mesh.transform_vertices(|v| v + self.offset);
+            mesh
+        })
+    }
+
+    /// Returns the pdf of the translated hittable, seen from `origin` moved by the opposite of
+    /// the offset
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.ptr.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.ptr.random(&(*origin - self.offset))
+    }
 }
@@ -0,0 +1,272 @@
+use crate::common::{Ray, Vec3};
+use crate::hittable::{Aabb, FlatBvh, HitRecord, Hittable, HittableList, Mesh, Triangle};
+use crate::material::Material;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A `Hittable` mesh of `Triangle`s that share a single material, such as a model loaded from
+/// a Wavefront OBJ file.
+///
+/// The triangles are stored in their own BVH, built once when the mesh is constructed, so
+/// that models with many thousands of triangles can be added to a scene as a single hittable
+/// and still render at a reasonable speed
+#[derive(Debug)]
+pub struct TriangleMesh {
+    bvh: FlatBvh,
+    mesh: Mesh,
+}
+
+impl TriangleMesh {
+    /// Returns a new triangle mesh, with the material `mp`, from the faces of `mesh`
+    ///
+    /// # Panics
+    /// if `mesh` has no faces, or a face refers to a vertex that does not exist
+    pub fn from_mesh(mesh: Mesh, mp: Arc<dyn Material>) -> Self {
+        let triangles = mesh
+            .faces
+            .iter()
+            .map(|[a, b, c]| {
+                Triangle::new(
+                    mesh.vertices[*a],
+                    mesh.vertices[*b],
+                    mesh.vertices[*c],
+                    Arc::clone(&mp),
+                )
+            })
+            .collect();
+        TriangleMesh::from_triangles(triangles, mesh)
+    }
+
+    /// Reads a Wavefront OBJ model from `reader` and returns it as a triangle mesh with the
+    /// material `mp`. The vertex positions (`v`), texture coordinates (`vt`), normals (`vn`)
+    /// and faces (`f`) of the model are used, and every other statement is ignored. Faces with
+    /// more than three vertices are split into a fan of triangles, which are smooth shaded if
+    /// all of their vertices have normals, and textured if they all have texture coordinates.
+    /// An error is returned if the model is malformed or has no faces
+    pub fn from_obj<R: BufRead>(reader: R, mp: Arc<dyn Material>) -> io::Result<Self> {
+        let model = read_obj(reader)?;
+        if model.mesh.faces.is_empty() {
+            return Err(invalid_data("the OBJ model has no faces".to_string()));
+        }
+        let triangles = model
+            .mesh
+            .faces
+            .iter()
+            .zip(model.face_attributes.iter())
+            .map(|([a, b, c], attributes)| {
+                let vertices = &model.mesh.vertices;
+                let mut triangle =
+                    Triangle::new(vertices[*a], vertices[*b], vertices[*c], Arc::clone(&mp));
+                if let Some(uvs) = attributes.uvs {
+                    triangle = triangle.with_uvs(uvs.map(|i| model.uvs[i]));
+                }
+                if let Some(normals) = attributes.normals {
+                    triangle = triangle.with_normals(normals.map(|i| model.normals[i]));
+                }
+                triangle
+            })
+            .collect();
+        Ok(TriangleMesh::from_triangles(triangles, model.mesh))
+    }
+
+    /// Reads the Wavefront OBJ file at `path` and returns it as a triangle mesh with the
+    /// material `mp`, see `from_obj`
+    pub fn from_obj_file(path: impl AsRef<Path>, mp: Arc<dyn Material>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        TriangleMesh::from_obj(BufReader::new(file), mp)
+    }
+
+    /// Builds the BVH of `triangles`, which are the faces of `mesh`
+    pub(crate) fn from_triangles(triangles: Vec<Triangle>, mesh: Mesh) -> Self {
+        let mut list = HittableList::new();
+        for triangle in triangles {
+            list.add(Arc::new(triangle));
+        }
+        let bvh = FlatBvh::build_auto(&mut list, 0.0, 1.0);
+        Self { bvh, mesh }
+    }
+
+    /// Returns the number of triangles in this mesh
+    pub fn triangle_count(&self) -> usize {
+        self.mesh.faces.len()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        self.bvh.bounding_box(t0, t1)
+    }
+
+    /// Returns the triangles of this mesh
+    fn tessellate(&self, _tessellation: u32) -> Option<Mesh> {
+        Some(self.mesh.clone())
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// the indices of the texture coordinates and normals of the vertices of an OBJ face
+#[derive(Debug, Default, Copy, Clone)]
+struct FaceAttributes {
+    uvs: Option<[usize; 3]>,
+    normals: Option<[usize; 3]>,
+}
+
+/// the geometry read from an OBJ model, where `face_attributes` holds the attributes of each
+/// face of `mesh`
+#[derive(Debug, Default)]
+struct ObjModel {
+    mesh: Mesh,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    face_attributes: Vec<FaceAttributes>,
+}
+
+/// Reads the vertex positions, texture coordinates, normals and faces of the OBJ model in
+/// `reader`
+fn read_obj<R: BufRead>(reader: R) -> io::Result<ObjModel> {
+    let mut model = ObjModel::default();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let error = |msg: &str| invalid_data(format!("line {}: {}", line_number + 1, msg));
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        match keyword {
+            Some("v") | Some("vn") => {
+                // extra values, such as vertex colors, are ignored
+                let coords =
+                    numbers(tokens.take(3), 3).ok_or_else(|| error("expected three numbers"))?;
+                let v = Vec3::new(coords[0], coords[1], coords[2]);
+                if keyword == Some("v") {
+                    model.mesh.vertices.push(v);
+                } else {
+                    model.normals.push(v);
+                }
+            }
+            Some("vt") => {
+                // the optional third (w) texture coordinate is ignored
+                let coords = numbers(tokens.take(2), 2)
+                    .ok_or_else(|| error("expected two texture coordinates"))?;
+                model.uvs.push((coords[0], coords[1]));
+            }
+            Some("f") => {
+                let corners = tokens
+                    .map(|token| face_corner(token, &model))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("invalid face"))?;
+                if corners.len() < 3 {
+                    return Err(error("a face needs at least three vertices"));
+                }
+                for i in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                    model.mesh.faces.push([a.0, b.0, c.0]);
+                    model.face_attributes.push(FaceAttributes {
+                        uvs: a.1.zip(b.1).zip(c.1).map(|((a, b), c)| [a, b, c]),
+                        normals: a.2.zip(b.2).zip(c.2).map(|((a, b), c)| [a, b, c]),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(model)
+}
+
+/// Parses exactly `count` numbers from `tokens`
+fn numbers<'a>(tokens: impl Iterator<Item = &'a str>, count: usize) -> Option<Vec<f64>> {
+    let numbers = tokens
+        .map(|token| token.parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    (numbers.len() == count).then_some(numbers)
+}
+
+/// Returns the zero based vertex, texture coordinate and normal indices of a face corner
+/// `token`, i.e. `3`, `3/1`, `3//2` or `3/1/2`, given the attributes read so far
+fn face_corner(token: &str, model: &ObjModel) -> Option<(usize, Option<usize>, Option<usize>)> {
+    let mut indices = token.split('/');
+    let vertex = obj_index(indices.next()?, model.mesh.vertices.len())?;
+    let uv = match indices.next() {
+        Some(index) if !index.is_empty() => Some(obj_index(index, model.uvs.len())?),
+        _ => None,
+    };
+    let normal = match indices.next() {
+        Some(index) if !index.is_empty() => Some(obj_index(index, model.normals.len())?),
+        _ => None,
+    };
+    Some((vertex, uv, normal))
+}
+
+/// Returns the zero based index of an OBJ `index`, given the number of elements read so far.
+/// OBJ indices start at 1, and negative indices count back from the last element read
+fn obj_index(index: &str, count: usize) -> Option<usize> {
+    let index = match index.parse::<i64>().ok()? {
+        i if i > 0 => i as usize - 1,
+        i if i < 0 => count.checked_sub(i.unsigned_abs() as usize)?,
+        _ => return None,
+    };
+    (index < count).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, TriangleMesh};
+    use crate::material::{Lambertian, Material};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::from(Color::new(
+            0.5, 0.5, 0.5,
+        )))))
+    }
+
+    #[test]
+    fn obj_quads_and_negative_indices_are_triangulated() {
+        let obj = "# a unit square and a triangle above it\n\
+                   v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                   vt 0 0\n\
+                   f 1/1 2/1 3/1 4/1\n\
+                   v 0 0 1\nv 1 0 1\nv 0 1 1\n\
+                   f -3 -2 -1\n";
+        let mesh = TriangleMesh::from_obj(obj.as_bytes(), gray()).unwrap();
+        assert_eq!(mesh.triangle_count(), 3);
+
+        let ray = Ray::new(Point3::new(0.9, 0.9, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.9, 0.9, 0.0));
+        let ray = Ray::new(Point3::new(0.2, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.2, 0.2, 1.0));
+    }
+
+    #[test]
+    fn obj_texture_coordinates_and_normals_are_applied_to_the_triangles() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 0 1\n\
+                   vn 0 0 1\nvn 1 0 1\n\
+                   f 1/1/1 2/2/2 3/3/1\n";
+        let mesh = TriangleMesh::from_obj(obj.as_bytes(), gray()).unwrap();
+
+        let ray = Ray::new(Point3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.u - 0.5).abs() < 1e-12 && rec.v.abs() < 1e-12);
+        assert!(rec.normal.x() > 0.0);
+    }
+
+    #[test]
+    fn malformed_obj_models_are_errors() {
+        assert!(TriangleMesh::from_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n".as_bytes(), gray()).is_err());
+        assert!(TriangleMesh::from_obj("v 0 0\n".as_bytes(), gray()).is_err());
+        assert!(TriangleMesh::from_obj("v 0 0 0\n".as_bytes(), gray()).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, HittableList, Mesh};
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// the number of grid cells created per hittable by `UniformGrid::from`
+const CELLS_PER_OBJECT: f64 = 2.0;
+
+/// the largest number of cells along each axis of a `UniformGrid` built by `UniformGrid::from`
+const MAX_RESOLUTION: usize = 64;
+
+/// the padding added to each side of a grid that is flat along an axis, so its cells have a size
+const FLAT_PADDING: f64 = 0.0001;
+
+/// A Uniform Grid, an acceleration structure that splits the bounding box of a scene into
+/// equally sized cells, and stores a list of the hittables overlapping each cell. A ray walks
+/// through the cells it passes, nearest first, and only tests the hittables of those cells,
+/// so it stops at the first cell with a hit.
+///
+/// A grid is quick to build, and works well for evenly distributed hittables, such as the
+/// ground boxes of the final scene, but poorly for scenes mixing large and small hittables,
+/// where a BVH adapts better. A hittable spanning several cells is listed in each of them.
+/// Like a `HittableList`, hits within the list's surface epsilon of each other are resolved in
+/// favor of the hittable that was added to the list first
+#[derive(Debug)]
+pub struct UniformGrid {
+    objects: Vec<Arc<dyn Hittable>>,
+    // the bounding box of the grid, which is padded along axes where it would be flat
+    bbox: Aabb,
+    // the number of cells along the x, y and z axes
+    resolution: [usize; 3],
+    // the size of a cell along the x, y and z axes
+    cell_size: Vec3,
+    // the hittables of cell `i` are `object_indices[cell_starts[i]..cell_starts[i + 1]]`
+    cell_starts: Vec<u32>,
+    object_indices: Vec<u32>,
+    surface_epsilon: f64,
+}
+
+impl UniformGrid {
+    /// Constructs a grid over the `list` of Hittables, with about `CELLS_PER_OBJECT` cells per
+    /// hittable, spread over the axes in proportion to the extent of the list's bounding box
+    pub fn from(list: &HittableList, time0: f64, time1: f64) -> UniformGrid {
+        let resolution = match UniformGrid::bounds(list, time0, time1) {
+            Some(bbox) => {
+                let extent = bbox.max() - bbox.min();
+                let volume = extent.x() * extent.y() * extent.z();
+                let cells = CELLS_PER_OBJECT * list.len() as f64;
+                let cells_per_unit = (cells / volume).cbrt();
+                let axis_resolution = |a: usize| {
+                    ((extent[a] * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION)
+                };
+                [axis_resolution(0), axis_resolution(1), axis_resolution(2)]
+            }
+            None => [1, 1, 1],
+        };
+        UniformGrid::from_with_resolution(list, time0, time1, resolution)
+    }
+
+    /// Constructs a grid over the `list` of Hittables with `resolution` cells along the x, y and
+    /// z axes, use this to benchmark different resolutions
+    ///
+    /// # Panics
+    /// if a hittable in the list does not have a bounding box, or a resolution is 0
+    pub fn from_with_resolution(
+        list: &HittableList,
+        time0: f64,
+        time1: f64,
+        resolution: [usize; 3],
+    ) -> UniformGrid {
+        assert!(
+            resolution.iter().all(|&r| r > 0),
+            "a grid needs at least one cell along each axis"
+        );
+        let objects = list.as_slice().to_vec();
+        let bbox = UniformGrid::bounds(list, time0, time1)
+            .unwrap_or_else(|| Aabb::new(Point3::default(), Point3::default()));
+        let extent = bbox.max() - bbox.min();
+        let cell_size = Vec3::new(
+            extent.x() / resolution[0] as f64,
+            extent.y() / resolution[1] as f64,
+            extent.z() / resolution[2] as f64,
+        );
+        let mut grid = UniformGrid {
+            objects: vec![],
+            bbox,
+            resolution,
+            cell_size,
+            cell_starts: vec![],
+            object_indices: vec![],
+            surface_epsilon: list.surface_epsilon(),
+        };
+
+        // list the index of each hittable in every cell its bounding box overlaps
+        let cell_count = resolution.iter().product::<usize>();
+        let mut cells: Vec<Vec<u32>> = vec![vec![]; cell_count];
+        for (index, object) in objects.iter().enumerate() {
+            let object_box = object
+                .bounding_box(time0, time1)
+                .expect("a hittable did not have a bounding box during grid construction");
+            let min = grid.cell_of(&object_box.min());
+            let max = grid.cell_of(&object_box.max());
+            for z in min[2]..=max[2] {
+                for y in min[1]..=max[1] {
+                    for x in min[0]..=max[0] {
+                        cells[grid.cell_index([x, y, z])].push(index as u32);
+                    }
+                }
+            }
+        }
+        grid.cell_starts = Vec::with_capacity(cell_count + 1);
+        grid.cell_starts.push(0);
+        for cell in cells {
+            grid.object_indices.extend(cell);
+            grid.cell_starts.push(grid.object_indices.len() as u32);
+        }
+        grid.objects = objects;
+        grid
+    }
+
+    /// Returns the estimated number of bytes allocated by a grid built by `from` over
+    /// `object_count` hittables: the start of every cell, plus the reference to every hittable
+    /// and its index in each cell it overlaps. Hittables are assumed to overlap a single cell,
+    /// so a grid of hittables that span many cells needs more
+    pub fn estimated_size(object_count: usize) -> usize {
+        let cell_count =
+            ((CELLS_PER_OBJECT * object_count as f64) as usize).clamp(1, MAX_RESOLUTION.pow(3));
+        let cell_bytes = (cell_count + 1) * size_of::<u32>();
+        let object_bytes = object_count * (size_of::<Arc<dyn Hittable>>() + size_of::<u32>());
+        cell_bytes + object_bytes
+    }
+
+    /// Returns the number of cells along the x, y and z axes of this grid
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    /// Returns the bounding box of the hittables in `list`, padded along the axes where it
+    /// would be flat, or `None` if the list is empty
+    fn bounds(list: &HittableList, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = list.bounding_box(time0, time1)?;
+        let (mut min, mut max) = (bbox.min(), bbox.max());
+        for a in 0..3 {
+            if max[a] - min[a] < FLAT_PADDING {
+                min[a] -= FLAT_PADDING;
+                max[a] += FLAT_PADDING;
+            }
+        }
+        Some(Aabb::new(min, max))
+    }
+
+    /// Returns the `[x, y, z]` coordinates of the cell containing the point `p`, points outside
+    /// of the grid are moved to the nearest cell
+    fn cell_of(&self, p: &Point3) -> [usize; 3] {
+        let coordinate = |a: usize| {
+            let cell = ((p[a] - self.bbox.min()[a]) / self.cell_size[a]).floor();
+            (cell.max(0.0) as usize).min(self.resolution[a] - 1)
+        };
+        [coordinate(0), coordinate(1), coordinate(2)]
+    }
+
+    /// Returns the index of the cell with the coordinates `cell` in `cell_starts`
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+
+    /// Returns the indices of the hittables overlapping the cell with coordinates `cell`
+    fn cell_objects(&self, cell: [usize; 3]) -> &[u32] {
+        let index = self.cell_index(cell);
+        &self.object_indices[self.cell_starts[index] as usize..self.cell_starts[index + 1] as usize]
+    }
+
+    /// Walks the cells passed by `ray` between `t_min` and `t_max`, nearest first, using a 3D
+    /// digital differential analyzer. `visit_cell` is called with the coordinates of each cell,
+    /// and the `t` at which the ray leaves it, and stops the walk by returning `true`
+    fn walk_cells<F: FnMut([usize; 3], f64) -> bool>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut visit_cell: F,
+    ) {
+        let (t_enter, t_exit) = match self.bbox.hit(ray, t_min, t_max) {
+            Some(range) => range,
+            None => return,
+        };
+        let entry = ray.at(t_enter);
+        let mut cell = self.cell_of(&entry);
+        let direction = ray.direction();
+        // the direction each axis steps in, the t of the next cell boundary along each axis,
+        // and the distance in t between the boundaries of each axis
+        let mut step = [0isize; 3];
+        let mut t_next = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+        for a in 0..3 {
+            let cell_min = self.bbox.min()[a] + cell[a] as f64 * self.cell_size[a];
+            if direction[a] > 0.0 {
+                step[a] = 1;
+                t_next[a] = t_enter + (cell_min + self.cell_size[a] - entry[a]) / direction[a];
+                t_delta[a] = self.cell_size[a] / direction[a];
+            } else if direction[a] < 0.0 {
+                step[a] = -1;
+                t_next[a] = t_enter + (cell_min - entry[a]) / direction[a];
+                t_delta[a] = -self.cell_size[a] / direction[a];
+            }
+        }
+
+        loop {
+            // the axis whose cell boundary is crossed first
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .expect("there are three axes");
+            if visit_cell(cell, t_next[axis].min(t_exit)) || t_next[axis] > t_exit {
+                return;
+            }
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                return;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl Hittable for UniformGrid {
+    /// Tests the hittables of the cells passed by the `ray`, nearest cell first, and returns the
+    /// closest hit as soon as it lies within the cells visited so far
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // the closest hit so far, and the index of its hittable in the list
+        let mut closest: Option<(HitRecord, u32)> = None;
+        self.walk_cells(ray, t_min, t_max, |cell, t_cell_exit| {
+            for &index in self.cell_objects(cell) {
+                // hits within the surface epsilon of the closest hit may replace it, if their
+                // hittable was added to the list earlier
+                let limit = closest
+                    .as_ref()
+                    .map_or(t_max, |(rec, _)| rec.t + self.surface_epsilon);
+                if let Some(hit_record) = self.objects[index as usize].hit(ray, t_min, limit) {
+                    let replace = match &closest {
+                        Some((rec, closest_index)) => {
+                            hit_record.t < rec.t - self.surface_epsilon || index < *closest_index
+                        }
+                        None => true,
+                    };
+                    if replace {
+                        closest = Some((hit_record, index));
+                    }
+                }
+            }
+            // a hittable in a later cell can not be closer than a hit within this cell
+            closest
+                .as_ref()
+                .is_some_and(|(rec, _)| rec.t <= t_cell_exit)
+        });
+        closest.map(|(rec, _)| rec)
+    }
+
+    /// Returns `true` as soon as a hittable of any cell passed by the ray is hit
+    fn hit_any(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut hit = false;
+        self.walk_cells(ray, t_min, t_max, |cell, _| {
+            hit = self
+                .cell_objects(cell)
+                .iter()
+                .any(|&index| self.objects[index as usize].hit_any(ray, t_min, t_max));
+            hit
+        });
+        hit
+    }
+
+    /// Returns the bounding box of the grid, or `None` if it is empty
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<Aabb> {
+        match self.objects.is_empty() {
+            true => None,
+            false => Some(self.bbox),
+        }
+    }
+
+    /// Returns the combined tessellation of every hittable in this grid
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.objects
+            .iter()
+            .filter_map(|object| object.tessellate(tessellation))
+            .reduce(|mut mesh, other| {
+                mesh.append(other);
+                mesh
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{build_solid_sphere, BvhNode, Hittable, HittableList, UniformGrid};
+    use std::sync::Arc;
+
+    #[test]
+    fn finds_the_same_hits_as_a_bvh_node() {
+        let mut world = HittableList::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                world.add(Arc::new(build_solid_sphere(
+                    Point3::new(i as f64 * 2.5, j as f64 * 2.5, -((i * j) % 5) as f64),
+                    1.0 + (i % 3) as f64 * 0.5,
+                    Color::new(0.5, 0.5, 0.5),
+                )));
+            }
+        }
+        let tree = BvhNode::from(&world, 0.0, 1.0);
+        let grid = UniformGrid::from(&world, 0.0, 1.0);
+        assert!(grid.resolution().iter().all(|&r| r > 1));
+
+        for x in 0..50 {
+            for y in 0..50 {
+                // rays along the grid's diagonal, and rays parallel to its z axis
+                for direction in [Vec3::new(0.3, 0.2, -1.0), Vec3::new(0.0, 0.0, -1.0)] {
+                    let ray = Ray::new(
+                        Point3::new(x as f64 * 0.5 - 2.0, y as f64 * 0.5 - 2.0, 20.0),
+                        direction,
+                        0.0,
+                    );
+                    let tree_hit = tree.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p);
+                    assert_eq!(
+                        grid.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.p),
+                        tree_hit
+                    );
+                    assert_eq!(grid.hit_any(&ray, 0.001, f64::INFINITY), tree_hit.is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn an_empty_grid_is_never_hit() {
+        let list = HittableList::new();
+        let grid = UniformGrid::from(&list, 0.0, 1.0);
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(grid.hit(&ray, 0.001, f64::INFINITY).is_none());
+        assert!(grid.bounding_box(0.0, 1.0).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::common::{Point3, Ray, Vec3};
+use crate::hittable::{Aabb, HitRecord, Hittable, Mesh};
+use std::sync::Arc;
+
+/// Visibility is a "wrapper" struct that controls which kinds of rays can see the hittable it
+/// wraps. An object that is invisible to `primary` (camera) rays still casts shadows and
+/// appears in reflections, while an object that is invisible to `secondary` rays only appears
+/// when seen directly by the camera
+#[derive(Debug)]
+pub struct Visibility {
+    ptr: Arc<dyn Hittable>,
+    primary_visible: bool,
+    secondary_visible: bool,
+}
+
+impl Visibility {
+    pub fn from(other: Arc<dyn Hittable>, primary_visible: bool, secondary_visible: bool) -> Self {
+        Self {
+            ptr: other,
+            primary_visible,
+            secondary_visible,
+        }
+    }
+
+    pub fn primary_visible(&self) -> bool {
+        self.primary_visible
+    }
+
+    pub fn secondary_visible(&self) -> bool {
+        self.secondary_visible
+    }
+}
+
+impl Hittable for Visibility {
+    /// hits the wrapped hittable and clears the visibility flags of the `HitRecord` that are
+    /// disabled for this wrapper. The renderer is responsible for skipping invisible hits
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.ptr.hit(r, t_min, t_max).map(|mut hit_rec| {
+            hit_rec.primary_visible &= self.primary_visible;
+            hit_rec.secondary_visible &= self.secondary_visible;
+            hit_rec
+        })
+    }
+
+    /// occlusion tests are performed by secondary rays, so a hittable that is invisible to
+    /// secondary rays never occludes anything
+    fn hit_any(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.secondary_visible && self.ptr.hit_any(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<Aabb> {
+        self.ptr.bounding_box(t0, t1)
+    }
+
+    fn tessellate(&self, tessellation: u32) -> Option<Mesh> {
+        self.ptr.tessellate(tessellation)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.ptr.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.ptr.random(origin)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::common::{Ray, Vec3};
+use crate::common::{Ray, RngContext, Vec3};
 use crate::hittable::{Aabb, HitRecord, Hittable};
 use crate::material::{Isotropic, Material};
 use crate::texture::Texture;
@@ -44,7 +44,7 @@ impl Hittable for ConstantMedium {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         // used temporarily enable debugging
         const ENABLE_DEBUG: bool = false;
-        let debugging: bool = ENABLE_DEBUG && rand::thread_rng().gen::<f64>() < 0.00001;
+        let debugging: bool = ENABLE_DEBUG && RngContext::current().gen::<f64>() < 0.00001;
 
         let mut rec1 = self.boundary.hit(r, f64::NEG_INFINITY, f64::INFINITY)?;
 
@@ -72,7 +72,7 @@ impl Hittable for ConstantMedium {
 
         let ray_length = r.direction().length();
         let distance_inside_boudary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * rand::thread_rng().gen::<f64>().ln();
+        let hit_distance = self.neg_inv_density * RngContext::current().gen::<f64>().ln();
 
         if hit_distance > distance_inside_boudary {
             None
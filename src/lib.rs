@@ -1,6 +1,9 @@
+pub mod animation;
 pub mod common;
 pub mod hittable;
+pub mod light;
 pub mod material;
+pub mod pdf;
 pub mod renderer;
 pub mod scene;
 pub mod texture;
@@ -8,4 +11,4 @@ pub mod util;
 
 extern crate num_cpus;
 
-extern crate threadpool;
+extern crate rayon;
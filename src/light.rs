@@ -0,0 +1,29 @@
+//! Lights that are not part of the world, such as point lights and directional lights. They can
+//! not be hit by rays, instead the renderer casts a *shadow ray* from every diffuse surface it
+//! hits towards each light, and adds the light's contribution if nothing blocks the shadow ray
+pub mod point_light;
+pub use point_light::*;
+
+pub mod directional_light;
+pub use directional_light::*;
+
+use crate::common::{Color, Point3, Vec3};
+use std::fmt::Debug;
+
+/// The light that arrives at a point from a `Light`, before it is checked for occlusion.
+/// `direction` is the unit vector from the point towards the light
+/// `distance` is the distance from the point to the light, shadow rays are only blocked by
+/// objects closer than this
+/// `radiance` is the light arriving at the point, on a surface facing the light
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f64,
+    pub radiance: Color,
+}
+
+/// A trait for lights that illuminate the world from outside of it
+pub trait Light: Send + Sync + Debug {
+    /// Returns the light arriving at the point `p` from this light
+    fn illuminate(&self, p: &Point3) -> LightSample;
+}
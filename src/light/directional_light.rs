@@ -0,0 +1,94 @@
+use crate::common::{Color, Onb, Point3, RngContext, Vec3};
+use crate::light::{Light, LightSample};
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// An infinitely distant light, such as the sun, whose light travels along `direction`.
+/// `color` is the light arriving at a surface that faces the light, it does not fall off with
+/// distance. `angular_radius` is the angle, in degrees, between the center and the edge of the
+/// light's disc, as seen from the world. A radius of 0 casts hard shadows, while larger radii
+/// sample a different direction within the disc for every shadow ray, which softens the shadows.
+/// The sun has an angular radius of about 0.27 degrees
+#[derive(Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    color: Color,
+    angular_radius: f64,
+    // the basis whose w axis points from the world towards the light
+    uvw: Onb,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: Color, angular_radius: f64) -> Self {
+        let direction = direction.unit_vector();
+        Self {
+            direction,
+            color,
+            angular_radius,
+            uvw: Onb::build_from_w(&-direction),
+        }
+    }
+
+    /// Returns the unit vector along which this light travels
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    /// Returns the color of this light
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Returns the angular radius of this light, in degrees
+    pub fn angular_radius(&self) -> f64 {
+        self.angular_radius
+    }
+}
+
+impl Light for DirectionalLight {
+    fn illuminate(&self, _p: &Point3) -> LightSample {
+        let direction = if self.angular_radius > 0.0 {
+            // a uniformly distributed direction within the cone of the light's disc
+            let mut rng = RngContext::current();
+            let cos_max = self.angular_radius.to_radians().cos();
+            let z = 1.0 - rng.gen::<f64>() * (1.0 - cos_max);
+            let phi = 2.0 * PI * rng.gen::<f64>();
+            let r = (1.0 - z * z).sqrt();
+            self.uvw.local(r * phi.cos(), r * phi.sin(), z)
+        } else {
+            self.uvw.w
+        };
+        LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Vec3};
+    use crate::light::{DirectionalLight, Light};
+
+    #[test]
+    fn shadow_rays_point_back_along_the_light_direction() {
+        let color = Color::new(3.0, 3.0, 2.0);
+        let hard = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), color, 0.0);
+        let soft = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), color, 5.0);
+
+        let sample = hard.illuminate(&Point3::new(10.0, 0.0, -3.0));
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, f64::INFINITY);
+        assert_eq!(sample.radiance, color);
+
+        let cos_max = 5.0_f64.to_radians().cos();
+        let directions: Vec<Vec3> = (0..100)
+            .map(|_| soft.illuminate(&Point3::default()).direction)
+            .collect();
+        assert!(directions
+            .iter()
+            .all(|d| (d.length() - 1.0).abs() < 1e-9 && d.y() >= cos_max - 1e-9));
+        assert!(directions.iter().any(|d| d.y() < 1.0 - 1e-6));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::common::{Color, Point3};
+use crate::light::{Light, LightSample};
+
+/// An infinitely small light at `position`, that emits `intensity` equally in every direction.
+/// The light arriving at a point falls off with the square of its distance to the light
+#[derive(Debug, Copy, Clone)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+
+    /// Returns the position of this light
+    pub fn position(&self) -> Point3 {
+        self.position
+    }
+
+    /// Returns the intensity of this light
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+}
+
+impl Light for PointLight {
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        LightSample {
+            direction: to_light.unit_vector(),
+            distance: distance_squared.sqrt(),
+            radiance: self.intensity / distance_squared,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Vec3};
+    use crate::light::{Light, PointLight};
+
+    #[test]
+    fn light_falls_off_with_the_square_of_the_distance() {
+        let light = PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::new(8.0, 8.0, 8.0));
+
+        let near = light.illuminate(&Point3::new(0.0, 1.0, 0.0));
+        let far = light.illuminate(&Point3::new(0.0, -2.0, 0.0));
+
+        assert_eq!(near.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(near.distance, 1.0);
+        assert_eq!(near.radiance, Color::new(8.0, 8.0, 8.0));
+        assert_eq!(far.distance, 4.0);
+        assert_eq!(far.radiance, Color::new(0.5, 0.5, 0.5));
+    }
+}
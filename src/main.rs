@@ -1,18 +1,42 @@
-use clap::Parser;
-use raytracer::common::Color;
-use raytracer::renderer::{BackgroundColor, Renderer};
-use raytracer::scene::cornell_boxes::{build_cornell_box_with_two_boxes, build_cornell_smoke_box};
+use clap::{Parser, Subcommand};
+use raytracer::common::{
+    ApertureShape, Camera, CameraBuilder, Color, LensModel, Point3, SamplerKind, Vec3,
+};
+use raytracer::hittable::{Hittable, HittableList};
+use raytracer::renderer::{
+    Accelerator, BackgroundColor, Image, IntegratorKind, Renderer, TURNTABLE_FRAME_DELAY_MS,
+};
+use raytracer::scene::cornell_boxes::{
+    build_cornell_box_lights, build_cornell_box_with_two_boxes, build_cornell_smoke_box,
+};
 use raytracer::scene::earth::build_earth_scene;
 use raytracer::scene::final_scene::build_final_scene;
+use raytracer::scene::gltf_import::import_gltf;
+use raytracer::scene::loader::load_scene;
 use raytracer::scene::perlin_spheres::build_perlin_spheres;
 use raytracer::scene::random_spheres::build_random_sphere_scene;
 use raytracer::scene::Scene;
-use raytracer::util::png;
+use raytracer::texture::EnvironmentMap;
+#[cfg(feature = "oidn")]
+use raytracer::util::denoise;
+use raytracer::util::png::BitDepth;
+#[cfg(feature = "preview")]
+use raytracer::util::preview;
+use raytracer::util::tonemap::ToneMap;
+use raytracer::util::{self, hdr, png};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about = "rust raytracer")]
+#[clap(
+    author,
+    version,
+    about = "rust raytracer",
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
     #[clap(
         short,
         long,
@@ -37,8 +61,130 @@ struct Args {
         help = "number of samples to render per pixel. Higher values will increase render times but will produce a 'sharper' image"
     )]
     samples_per_pixel: u32,
-    #[clap(arg_enum, value_parser, help = "the name of the scene to render")]
-    scene: Scene,
+    #[clap(
+        arg_enum,
+        value_parser,
+        required_unless_present = "scene-file",
+        help = "the name of the scene to render"
+    )]
+    scene: Option<Scene>,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "scene",
+        help = "path to a JSON scene description file, or a glTF (.gltf/.glb) file, to render instead of a pre-made scene"
+    )]
+    scene_file: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        help = "render in passes of 16 samples per pixel, and rewrite the image file after every pass"
+    )]
+    progressive: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "write a 16-bit PNG instead of an 8-bit one, to avoid banding in smooth gradients"
+    )]
+    sixteen_bit: bool,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with_all = &["progressive", "sixteen-bit"],
+        help = "write the linear radiance of each pixel to a floating point Radiance HDR (.hdr) file instead of a PNG, so that bright lights are not clipped"
+    )]
+    hdr: bool,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "clamp",
+        help = "tone map operator applied before the image is quantized: clamp, reinhard, aces, reinhard-extended:<white_point> or exposure:<exposure>"
+    )]
+    tone_map: ToneMap,
+    #[clap(
+        long,
+        value_parser,
+        help = "path to an equirectangular (latitude-longitude) image that replaces the background of the scene, lighting it with the environment"
+    )]
+    environment: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "perspective",
+        help = "projection of the camera's lens: perspective, fisheye:<field_of_view> or equirectangular. Equirectangular renders are 360 degree panoramas, and should use an aspect ratio of 2"
+    )]
+    lens: LensModel,
+    #[clap(
+        long,
+        value_parser,
+        help = "number of aperture blades, for polygonal (i.e. hexagonal) bokeh in scenes with a defocus blur. The aperture is round by default"
+    )]
+    aperture_blades: Option<u32>,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "halton",
+        help = "sampler that places the samples within each pixel, and on the lens: halton (low-discrepancy) or random"
+    )]
+    sampler: SamplerKind,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "path",
+        help = "how each sample is computed: path (full path tracing), ao:<max_distance>, a fast ambient occlusion preview of the scene's shapes that ignores its lights and materials, direct[:<shadow_samples>], which only gathers the light that reaches surfaces straight from the lights, averaged over shadow_samples shadow rays towards each emissive light (default 1), or debug:<normals|depth:<far>|uv|bvh:<max_tests>>, which colors each pixel by the normal, distance or texture coordinates of the first surface it sees, or, with the bvh-heatmap feature, by the number of bounding box tests needed to find it"
+    )]
+    integrator: IntegratorKind,
+    #[clap(
+        long,
+        value_parser,
+        help = "acceleration structure the scene is placed in, overriding the accelerator of a scene file: bvh (the default), grid (a uniform grid, for comparing against the BVH on evenly distributed scenes) or kdtree (for architectural scenes of axis aligned walls and boxes)"
+    )]
+    accelerator: Option<Accelerator>,
+    #[clap(
+        long,
+        value_parser,
+        help = "the largest number of megabytes the acceleration structure may need. Scenes that are estimated to need more are rejected before it is built"
+    )]
+    memory_limit_mb: Option<usize>,
+    #[cfg(feature = "preview")]
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "progressive",
+        help = "show the image in a window while it is rendered. Closing the window stops the render early, and writes the tiles that were finished"
+    )]
+    preview: bool,
+    #[cfg(feature = "oidn")]
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "progressive",
+        help = "remove the noise of the rendered image with Intel Open Image Denoise before it is written, guided by the albedo and normals of the scene, so far fewer samples per pixel are needed"
+    )]
+    denoise: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// renders a turntable of a scene, with the camera orbiting the center of the scene's bounding
+    /// box, and writes the frames to numbered PNG files
+    Turntable {
+        #[clap(arg_enum, value_parser, help = "the name of the scene to render")]
+        scene: Scene,
+        #[clap(
+            long,
+            value_parser,
+            default_value_t = 36,
+            help = "number of frames in one orbit of the camera"
+        )]
+        frames: u32,
+        #[clap(
+            long,
+            value_parser,
+            help = "also write the frames to an animated GIF file"
+        )]
+        gif: bool,
+    },
 }
 
 fn main() {
@@ -48,8 +194,60 @@ fn main() {
     // number of worker threads to use for rendering
     let pool_size = num_cpus::get_physical();
 
+    // a scene file describes its own camera, world and background color
+    if let Some(scene_file) = &args.scene_file {
+        let is_gltf = scene_file
+            .extension()
+            .is_some_and(|ext| ext == "gltf" || ext == "glb");
+        let loaded = if is_gltf {
+            import_gltf(scene_file, args.width, args.aspect_ratio)
+        } else {
+            load_scene(scene_file, args.width, args.aspect_ratio)
+        };
+        let scene = match loaded {
+            Ok(scene) => scene,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let renderer = Renderer::new(50, args.samples_per_pixel, scene.background, pool_size)
+            .with_accelerator(scene.accelerator);
+        let name = scene_file
+            .file_stem()
+            .map_or("scene".into(), |stem| stem.to_string_lossy());
+        println!("rendering scene file: {:?}", scene_file);
+        render_to_file(&name, renderer, scene.camera, scene.world, &args);
+        return;
+    }
+    if let Some(Command::Turntable { scene, frames, gif }) = args.command {
+        let (camera, world, renderer) = build_scene(scene, &args, pool_size);
+        println!("rendering a turntable of scene: {:?}", &scene);
+        render_turntable(
+            &format!("{:?}", scene),
+            renderer,
+            camera,
+            world,
+            frames,
+            gif,
+        );
+        return;
+    }
+    let scene = args
+        .scene
+        .expect("clap requires a scene when no scene file is given");
+
+    let (camera, world, renderer) = build_scene(scene, &args, pool_size);
+
+    println!("rendering scene: {:?}", &scene);
+    render_to_file(&format!("{:?}", scene), renderer, camera, world, &args);
+}
+
+/// builds the camera and world of a pre-made `scene`, along with a renderer using the scene's
+/// background color and lights
+fn build_scene(scene: Scene, args: &Args, pool_size: usize) -> (Camera, HittableList, Renderer) {
     // build the camera, world and set the background color for each scene
-    let (camera, world, renderer) = match args.scene {
+    match scene {
         Scene::RandomSpheres => {
             let (c, w) = build_random_sphere_scene(args.width, args.aspect_ratio);
             let renderer = Renderer::new(
@@ -67,7 +265,8 @@ fn main() {
                 args.samples_per_pixel,
                 BackgroundColor::Solid(Color::default()),
                 pool_size,
-            );
+            )
+            .with_lights(build_cornell_box_lights());
             (c, w, renderer)
         }
         Scene::CornellSmokeBoxes => {
@@ -110,19 +309,176 @@ fn main() {
             );
             (c, w, renderer)
         }
-    };
+    }
+}
 
+/// renders the `world` and writes it to a PNG file named after the scene `name`, or to an HDR
+/// file if `args.hdr` is set. A progressive render rewrites the file with the partially
+/// converged image after every pass
+fn render_to_file(
+    name: &str,
+    renderer: Renderer,
+    mut camera: Camera,
+    world: HittableList,
+    args: &Args,
+) {
+    camera.lens_model = args.lens;
+    if let Some(blades) = args.aperture_blades {
+        camera.aperture_shape = ApertureShape::Polygon {
+            blades,
+            rotation: 0.0,
+        };
+    }
     let (width, height) = (camera.image_width, camera.image_height);
-    let file_path = PathBuf::from(format!(
-        "./raytrace_{:?}_{}x{}.png",
-        args.scene, width, height
-    ));
-    println!("rendering scene: {:?}", &args.scene);
+    let mut renderer = renderer
+        .with_tone_map(args.tone_map)
+        .with_sampler(args.sampler)
+        .with_integrator(args.integrator);
+    if let Some(accelerator) = args.accelerator {
+        renderer = renderer.with_accelerator(accelerator);
+    }
+    if let Some(memory_limit_mb) = args.memory_limit_mb {
+        renderer = renderer.with_memory_limit(memory_limit_mb * 1024 * 1024);
+        if let Err(e) = renderer.check_memory_limit(&world) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(environment) = &args.environment {
+        let map = match EnvironmentMap::open(&environment.to_string_lossy()) {
+            Ok(map) => map,
+            Err(e) => {
+                eprintln!(
+                    "could not load the environment map {:?}: {}",
+                    environment, e
+                );
+                std::process::exit(1);
+            }
+        };
+        renderer = renderer.with_background_color(BackgroundColor::Environment(Arc::new(map)));
+    }
+    if args.hdr {
+        let file_path = PathBuf::from(format!("./raytrace_{}_{}x{}.hdr", name, width, height));
+        let image = render_image(name, renderer, camera, world, args);
+        match hdr::write_file(&file_path, width, height, image.pixels()) {
+            Ok(()) => println!("HDR image created at {:?}", file_path),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+    let file_path = PathBuf::from(format!("./raytrace_{}_{}x{}.png", name, width, height));
+    let bit_depth = if args.sixteen_bit {
+        BitDepth::Sixteen
+    } else {
+        BitDepth::Eight
+    };
 
-    let image = renderer.render(camera, world);
     // write the image data to a PNG file
-    match png::write_file(&file_path, width, height, &image) {
+    let written = if args.progressive {
+        let image = renderer.render_progressive(camera, world, |image, samples| {
+            match png::write_file(&file_path, width, height, image, bit_depth) {
+                Ok(()) => println!("{} samples per pixel written to {:?}", samples, file_path),
+                Err(e) => eprintln!("{}", e),
+            }
+            true
+        });
+        png::write_file(&file_path, width, height, &image, bit_depth)
+    } else {
+        render_image(name, renderer, camera, world, args).save_png(&file_path, bit_depth)
+    };
+    match written {
         Ok(()) => println!("test image created at {:?}", file_path),
         Err(e) => eprintln!("{}", e),
     }
 }
+
+/// renders the `world`, in a preview window titled with the scene `name` if `args.preview` is set,
+/// and denoises the image if `args.denoise` is set
+fn render_image(
+    name: &str,
+    renderer: Renderer,
+    camera: Camera,
+    world: HittableList,
+    args: &Args,
+) -> Image {
+    #[cfg(feature = "oidn")]
+    if args.denoise {
+        // the denoiser is guided by the albedo and normal buffers of the render
+        let image = render_or_preview(name, renderer.with_aovs(), camera, world, args);
+        return match denoise::denoise(&image) {
+            Ok(denoised) => denoised,
+            Err(e) => {
+                eprintln!("could not denoise the image: {}", e);
+                image
+            }
+        };
+    }
+    render_or_preview(name, renderer, camera, world, args)
+}
+
+/// renders the `world`, in a preview window titled with the scene `name` if `args.preview` is set
+fn render_or_preview(
+    name: &str,
+    renderer: Renderer,
+    camera: Camera,
+    world: HittableList,
+    args: &Args,
+) -> Image {
+    #[cfg(feature = "preview")]
+    if args.preview {
+        match preview::render_with_preview(renderer.clone(), camera, world.clone(), name) {
+            Ok(image) => return image,
+            Err(e) => eprintln!("could not open a preview window: {}", e),
+        }
+    }
+    #[cfg(not(feature = "preview"))]
+    let _ = (name, args);
+    renderer.render(camera, world)
+}
+
+/// renders `frames` frames of the camera orbiting the center of the `world`'s bounding box, and
+/// writes them to numbered PNG files in a directory named after the scene `name`, and to an
+/// animated GIF if `gif` is set. The orbit keeps the distance and elevation of the scene's
+/// `camera` from the center, and every other camera setting
+fn render_turntable(
+    name: &str,
+    renderer: Renderer,
+    camera: Camera,
+    world: HittableList,
+    frames: u32,
+    gif: bool,
+) {
+    let (width, height) = (camera.image_width, camera.image_height);
+    let center = world
+        .bounding_box(camera.open_time, camera.close_time)
+        .map_or(Point3::default(), |bbox| (bbox.min() + bbox.max()) / 2.0);
+    let offset = camera.look_from - center;
+    let radius = offset.length();
+    let elevation = (offset.y() / radius).asin().to_degrees();
+    let base = CameraBuilder::from(&camera)
+        .up_direction(Vec3::new(0.0, 1.0, 0.0))
+        .focus_distance(radius);
+    let images = renderer.render_turntable(base, world, center, radius, elevation, frames.max(1));
+
+    let dir = PathBuf::from(format!("./raytrace_{}_turntable", name));
+    let written = std::fs::create_dir_all(&dir)
+        .map_err(image::ImageError::from)
+        .and_then(|_| {
+            images.iter().enumerate().try_for_each(|(frame, image)| {
+                let path = dir.join(format!("frame_{:04}.png", frame + 1));
+                png::write_file(path, width, height, image, BitDepth::Eight)
+            })
+        });
+    if let Err(e) = written {
+        eprintln!("{}", e);
+        return;
+    }
+    println!("turntable frames written to {:?}", dir);
+    if gif {
+        let file_path = PathBuf::from(format!("./raytrace_{}_turntable.gif", name));
+        match util::gif::write_file(&file_path, width, height, &images, TURNTABLE_FRAME_DELAY_MS) {
+            Ok(()) => println!("turntable GIF written to {:?}", file_path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
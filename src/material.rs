@@ -13,18 +13,34 @@ pub use diffuse_light::*;
 pub mod isotropic;
 pub use isotropic::*;
 
+pub mod vertex_color;
+pub use vertex_color::*;
+
+pub mod medium_stack;
+pub use medium_stack::*;
+
 use crate::common::{Color, Point3, Ray, Vec3};
 use crate::hittable::HitRecord;
+use crate::pdf::Pdf;
 use std::fmt::Debug;
 use std::ops::Neg;
+use std::sync::Arc;
 
 /// holds the results of how a `Material` scattered an incoming `Ray`.
 /// `attenuation` contains what `Color` was applied by the material to the incoming Ray
 /// `scattered` contains the new `Ray` that was scattered
-#[derive(Default, Debug, Copy, Clone)]
+/// `total_internal_reflection` is true if the ray was reflected because it could not refract
+/// out of a dielectric material
+/// `pdf` is the optional PDF that `scattered` was drawn from. Materials that return a PDF can be
+/// importance sampled, by scattering rays from a mixture of their PDF and the lights of a scene,
+/// see `Material::scattering_pdf`. Specular materials, which scatter in a single direction,
+/// return `None`
+#[derive(Default, Debug, Clone)]
 pub struct ScatterRecord {
     pub attenuation: Color,
     pub scattered: Ray,
+    pub total_internal_reflection: bool,
+    pub pdf: Option<Arc<dyn Pdf>>,
 }
 
 impl ScatterRecord {
@@ -32,6 +48,8 @@ impl ScatterRecord {
         Self {
             attenuation,
             scattered,
+            total_internal_reflection: false,
+            pdf: None,
         }
     }
 }
@@ -42,11 +60,52 @@ pub trait Material: Send + Sync + Debug {
     /// If this material did not scatter `r_in`, `None` is returned
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord>;
 
+    /// Returns `Some(ScatterRecord)` if this material scattered the incoming Ray `r_in`, which
+    /// is travelling through the dielectric `media`. The base implementation of this trait
+    /// ignores the media and calls `scatter`
+    fn scatter_in_medium(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        _media: &MediumStack,
+    ) -> Option<ScatterRecord> {
+        self.scatter(r_in, rec)
+    }
+
+    /// Returns the probability density, with respect to solid angle, that this material
+    /// scatters the incoming ray `r_in` into the direction of `scattered`. Only materials that
+    /// return a `pdf` in their `ScatterRecord` need to implement this. The base implementation
+    /// of this trait returns 0
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Returns the refractive index of this material if it is a dielectric that rays can be
+    /// transmitted into. The base implementation of this trait returns `None`
+    fn refractive_index(&self) -> Option<f64> {
+        None
+    }
+
+    /// Returns `true` if this material scatters light within a volume, like the phase function
+    /// of a participating medium, rather than at a surface. The base implementation of this
+    /// trait returns false
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+
     /// Returns a `Color` emitted by this material. The base implementation of this trait
     /// returns black as the default color
     fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
         Color::default()
     }
+
+    /// Returns `true` if this material scatters light in a single (perfectly specular)
+    /// direction, like a mirror or glass. Denoisers and sampling strategies use this to pass
+    /// through specular hits instead of using their base color. The base implementation of
+    /// this trait returns false
+    fn is_specular(&self) -> bool {
+        false
+    }
 }
 
 /// Returns a *reflected* `Vec3` between `v` and `n`, where `n` is a unit vector
@@ -61,7 +120,7 @@ fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
 fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     let cos_theta = uv.neg().dot(n);
     let r_out_parallel = etai_over_etat * (*uv + cos_theta * *n);
-    let r_out_perp = -1.0 * (1.0 - r_out_parallel.length_squared()).sqrt() * *n;
+    let r_out_perp = -(1.0 - r_out_parallel.length_squared()).sqrt() * *n;
     r_out_parallel + r_out_perp
 }
 
@@ -1,8 +1,8 @@
-use crate::common::{Color, Ray};
+use crate::common::{Color, Ray, RngContext};
 use crate::hittable::HitRecord;
 use crate::material;
-use crate::material::{Material, ScatterRecord};
-use rand::{thread_rng, Rng};
+use crate::material::{Material, MediumStack, ScatterRecord};
+use rand::Rng;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Dielectric {
@@ -17,13 +17,25 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    /// scatter for a Dielectric material that **always** refracts
+    /// scatter for a Dielectric material that **always** refracts, with air outside of it
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        self.scatter_in_medium(r_in, rec, &MediumStack::new())
+    }
+
+    /// scatter for a Dielectric material that is surrounded by the innermost of the `media`
+    /// the ray is travelling through, so that nested dielectrics refract with their relative
+    /// refractive index
+    fn scatter_in_medium(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        media: &MediumStack,
+    ) -> Option<ScatterRecord> {
         let attenuation = Color::new(1.0, 1.0, 1.0);
         let etai_over_etat = if rec.front_face {
-            1.0 / self.ref_idx
+            media.current() / self.ref_idx
         } else {
-            self.ref_idx
+            self.ref_idx / media.outside_of(self.ref_idx)
         };
 
         let unit_direction = r_in.direction().unit_vector();
@@ -31,8 +43,10 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let reflect_prob = material::schlick(cos_theta, etai_over_etat);
 
+        let total_internal_reflection = etai_over_etat * sin_theta > 1.0;
+
         let scattered_ray =
-            if etai_over_etat * sin_theta > 1.0 || thread_rng().gen::<f64>() < reflect_prob {
+            if total_internal_reflection || RngContext::current().gen::<f64>() < reflect_prob {
                 // ray is always reflected OR ray had a chance to reflect
                 let reflected = material::reflect(&unit_direction, &rec.normal);
                 Ray::new(rec.p, reflected, r_in.time())
@@ -41,6 +55,92 @@ impl Material for Dielectric {
                 let refracted = material::refract(&unit_direction, &rec.normal, etai_over_etat);
                 Ray::new(rec.p, refracted, r_in.time())
             };
-        Some(ScatterRecord::new(attenuation, scattered_ray))
+        Some(ScatterRecord {
+            total_internal_reflection,
+            ..ScatterRecord::new(attenuation, scattered_ray)
+        })
+    }
+
+    /// glass always reflects or refracts in a single direction
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn refractive_index(&self) -> Option<f64> {
+        Some(self.ref_idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{HitRecord, Hittable, Sphere};
+    use crate::material::{Dielectric, Material, MediumStack};
+    use std::sync::Arc;
+
+    /// returns the record of a ray hitting the surface of a glass block, at the origin, whose
+    /// outward normal is +y. `direction` is the direction of the ray
+    fn hit_glass(glass: &Arc<Dielectric>, direction: Vec3) -> (Ray, HitRecord) {
+        let ray = Ray::new(Point3::default() - direction, direction, 0.0);
+        let rec = HitRecord::with_face_normal(
+            &ray,
+            Point3::default(),
+            &Vec3::new(0.0, 1.0, 0.0),
+            Arc::clone(glass) as Arc<dyn Material>,
+            1.0,
+            0.0,
+            0.0,
+        );
+        (ray, rec)
+    }
+
+    #[test]
+    fn steep_internal_angle_is_totally_internally_reflected() {
+        let glass = Arc::new(Dielectric::new(1.5));
+        // leaving the glass at 60 degrees from the normal, beyond the critical angle of ~42
+        let (ray, rec) = hit_glass(&glass, Vec3::new(3f64.sqrt() / 2.0, 0.5, 0.0));
+
+        let scatter_rec = glass.scatter(&ray, &rec).unwrap();
+
+        assert!(scatter_rec.total_internal_reflection);
+        assert!(scatter_rec.scattered.direction().y() < 0.0);
+    }
+
+    #[test]
+    fn entering_glass_is_never_totally_internally_reflected() {
+        let glass = Arc::new(Dielectric::new(1.5));
+        let (ray, rec) = hit_glass(&glass, Vec3::new(3f64.sqrt() / 2.0, -0.5, 0.0));
+
+        let scatter_rec = glass.scatter(&ray, &rec).unwrap();
+
+        assert!(!scatter_rec.total_internal_reflection);
+    }
+
+    #[test]
+    fn water_inside_glass_refracts_with_the_relative_index() {
+        let water = Arc::new(Dielectric::new(1.33));
+        let water_sphere = Sphere::new(Point3::default(), 1.0, Arc::clone(&water) as _);
+        // a ray inside the water, that leaves it at the top of the sphere at 60 degrees from
+        // the normal. Beyond the critical angle of water to air (~49), but not of water to glass
+        let direction = Vec3::new(3f64.sqrt() / 2.0, 0.5, 0.0);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0) - 0.5 * direction, direction, 0.0);
+        let rec = water_sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        // the ray entered the surrounding glass sphere, and then the water
+        let mut media = MediumStack::new();
+        media.enter(1.5);
+        media.enter(1.33);
+
+        assert!(water.scatter(&ray, &rec).unwrap().total_internal_reflection);
+        let refracted = (0..1000)
+            .map(|_| water.scatter_in_medium(&ray, &rec, &media).unwrap())
+            .inspect(|scatter_rec| assert!(!scatter_rec.total_internal_reflection))
+            .find(|scatter_rec| scatter_rec.scattered.direction().y() > 0.0)
+            .expect("ray is refracted into the glass")
+            .scattered;
+        let sin_theta = refracted.direction().unit_vector().x();
+        assert!((sin_theta - 1.33 / 1.5 * (3f64.sqrt() / 2.0)).abs() < 1e-9);
+        // the refracted ray is back in the glass
+        let glass_media = media.transmit(&rec, &refracted).unwrap();
+        assert_eq!(glass_media.current(), 1.5);
     }
 }
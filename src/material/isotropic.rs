@@ -25,4 +25,9 @@ impl Material for Isotropic {
 
         Some(ScatterRecord::new(attenuation, scattered))
     }
+
+    /// an isotropic material scatters light inside of a volume
+    fn is_volumetric(&self) -> bool {
+        true
+    }
 }
@@ -1,7 +1,9 @@
 use crate::common::{Ray, Vec3};
 use crate::hittable::HitRecord;
 use crate::material::{Material, ScatterRecord};
+use crate::pdf::CosinePdf;
 use crate::texture::Texture;
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 /// lambertian diffuse material
@@ -20,11 +22,22 @@ impl Lambertian {
 impl Material for Lambertian {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         let scatter_direction = rec.normal + Vec3::random_unit_vector();
-        let attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        let attenuation = self
+            .albedo
+            .value_lod(rec.u, rec.v, &rec.p, rec.uv_footprint);
 
         Some(ScatterRecord {
             scattered: Ray::new(rec.p, scatter_direction, r_in.time()),
             attenuation,
+            total_internal_reflection: false,
+            pdf: Some(Arc::new(CosinePdf::new(&rec.normal))),
         })
     }
+
+    /// Lambertian surfaces scatter light with a density of `cos(theta) / PI`, where theta is
+    /// the angle between the scattered ray and the surface normal
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = rec.normal.dot(&scattered.direction().unit_vector());
+        (cosine / PI).max(0.0)
+    }
 }
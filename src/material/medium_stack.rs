@@ -0,0 +1,94 @@
+use crate::common::Ray;
+use crate::hittable::HitRecord;
+
+/// the refractive index of the medium that surrounds every object, i.e. air
+pub const OUTSIDE_REFRACTIVE_INDEX: f64 = 1.0;
+
+/// A stack of the refractive indices of the (possibly nested) dielectric media that a ray is
+/// currently travelling through, innermost medium last.
+///
+/// Tracking the media lets overlapping dielectrics, like a liquid inside a glass, refract with
+/// the correct *relative* index at their shared boundary, instead of assuming that there is
+/// always air on the other side of a surface
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MediumStack {
+    indices: Vec<f64>,
+}
+
+impl MediumStack {
+    /// Returns a new stack for a ray that is outside of every medium
+    pub fn new() -> Self {
+        MediumStack::default()
+    }
+
+    /// Returns the refractive index of the medium the ray is currently in
+    pub fn current(&self) -> f64 {
+        self.indices
+            .last()
+            .copied()
+            .unwrap_or(OUTSIDE_REFRACTIVE_INDEX)
+    }
+
+    /// Returns the refractive index of the medium the ray would be in after leaving the
+    /// (innermost) medium with the refractive index `ref_idx`
+    pub fn outside_of(&self, ref_idx: f64) -> f64 {
+        match self.indices.iter().rposition(|idx| *idx == ref_idx) {
+            Some(pos) => self.indices[..pos]
+                .last()
+                .copied()
+                .unwrap_or(OUTSIDE_REFRACTIVE_INDEX),
+            None => self.current(),
+        }
+    }
+
+    /// Pushes a medium with the refractive index `ref_idx` onto this stack
+    pub fn enter(&mut self, ref_idx: f64) {
+        self.indices.push(ref_idx);
+    }
+
+    /// Removes the innermost medium with the refractive index `ref_idx` from this stack, if
+    /// the ray was in such a medium
+    pub fn exit(&mut self, ref_idx: f64) {
+        if let Some(pos) = self.indices.iter().rposition(|idx| *idx == ref_idx) {
+            self.indices.remove(pos);
+        }
+    }
+
+    /// Returns the media that `scattered` travels through, if it was transmitted through the
+    /// dielectric surface described by `rec`. Returns `None` if the surface is not a
+    /// dielectric, or if the ray was reflected, in which case the media are unchanged
+    pub fn transmit(&self, rec: &HitRecord, scattered: &Ray) -> Option<MediumStack> {
+        let ref_idx = rec.mat_ptr.refractive_index()?;
+        // the hit record's normal always faces against the incoming ray, so a transmitted ray
+        // continues on the opposite side of it
+        if scattered.direction().dot(&rec.normal) >= 0.0 {
+            return None;
+        }
+        let mut media = self.clone();
+        if rec.front_face {
+            media.enter(ref_idx);
+        } else {
+            media.exit(ref_idx);
+        }
+        Some(media)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::material::MediumStack;
+
+    #[test]
+    fn leaving_an_inner_medium_returns_to_the_outer_one() {
+        let mut media = MediumStack::new();
+        media.enter(1.5);
+        media.enter(1.33);
+
+        assert_eq!(media.current(), 1.33);
+        assert_eq!(media.outside_of(1.33), 1.5);
+
+        media.exit(1.33);
+        assert_eq!(media.current(), 1.5);
+        assert_eq!(media.outside_of(1.5), 1.0);
+    }
+}
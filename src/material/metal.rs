@@ -3,6 +3,9 @@ use crate::hittable::HitRecord;
 use crate::material;
 use crate::material::{Material, ScatterRecord};
 
+/// metals with a fuzz at or below this value are treated as perfect mirrors
+const SPECULAR_FUZZ: f64 = 1e-3;
+
 /// a metal material
 #[derive(Debug, Copy, Clone)]
 pub struct Metal {
@@ -34,4 +37,30 @@ impl Material for Metal {
             None
         }
     }
+
+    /// a metal is specular if it has (almost) no fuzz
+    fn is_specular(&self) -> bool {
+        self.fuzz <= SPECULAR_FUZZ
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::material::{Dielectric, Lambertian, Material, Metal};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn mirror_metal_and_glass_are_specular() {
+        assert!(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0).is_specular());
+        assert!(Dielectric::new(1.5).is_specular());
+    }
+
+    #[test]
+    fn fuzzy_metal_and_lambertian_are_not_specular() {
+        assert!(!Metal::new(Color::new(0.8, 0.8, 0.8), 0.3).is_specular());
+        let lambertian = Lambertian::new(Arc::new(SolidColor::from_rgb(0.5, 0.5, 0.5)));
+        assert!(!lambertian.is_specular());
+    }
 }
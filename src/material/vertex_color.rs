@@ -0,0 +1,45 @@
+use crate::common::{Ray, Vec3};
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::pdf::CosinePdf;
+use crate::texture::Texture;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// a lambertian diffuse material whose albedo is the interpolated vertex color of the hit,
+/// i.e. of a `Triangle` given colors with `with_colors`. Hittables without vertex colors use
+/// the `fallback` texture instead
+#[derive(Debug)]
+pub struct VertexColor {
+    // the albedo of hits that have no vertex color
+    pub fallback: Arc<dyn Texture>,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Arc<dyn Texture>) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Material for VertexColor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let scatter_direction = rec.normal + Vec3::random_unit_vector();
+        let attenuation = rec.vertex_color.unwrap_or_else(|| {
+            self.fallback
+                .value_lod(rec.u, rec.v, &rec.p, rec.uv_footprint)
+        });
+
+        Some(ScatterRecord {
+            scattered: Ray::new(rec.p, scatter_direction, r_in.time()),
+            attenuation,
+            total_internal_reflection: false,
+            pdf: Some(Arc::new(CosinePdf::new(&rec.normal))),
+        })
+    }
+
+    /// scatters light with the same `cos(theta) / PI` density as a `Lambertian`
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = rec.normal.dot(&scattered.direction().unit_vector());
+        (cosine / PI).max(0.0)
+    }
+}
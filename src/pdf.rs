@@ -0,0 +1,30 @@
+//! Probability density functions (PDFs) over directions, used to importance sample the rays
+//! scattered by materials, as described in "Ray Tracing: The Rest of Your Life".
+//!
+//! A material returns the PDF it scatters rays with, such as a `CosinePdf` for a Lambertian
+//! surface, and the renderer mixes it with a `HittablePdf` of the lights in the scene, so that
+//! more of the scattered rays head towards the lights. An `EnvironmentPdf` likewise sends
+//! rays towards the bright regions of an environment map background
+pub mod cosine_pdf;
+pub use cosine_pdf::*;
+
+pub mod environment_pdf;
+pub use environment_pdf::*;
+
+pub mod hittable_pdf;
+pub use hittable_pdf::*;
+
+pub mod mixture_pdf;
+pub use mixture_pdf::*;
+
+use crate::common::Vec3;
+use std::fmt::Debug;
+
+/// A trait for probability density functions over the directions leaving a point
+pub trait Pdf: Send + Sync + Debug {
+    /// Returns the probability density, with respect to solid angle, of generating `direction`
+    fn value(&self, direction: &Vec3) -> f64;
+
+    /// Returns a random direction, distributed according to this PDF
+    fn generate(&self) -> Vec3;
+}
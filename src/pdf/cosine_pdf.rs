@@ -0,0 +1,52 @@
+use crate::common::{Onb, Vec3};
+use crate::pdf::Pdf;
+use std::f64::consts::PI;
+
+/// A PDF that generates directions in the hemisphere around a surface normal, proportional to
+/// the cosine of their angle to the normal. This is the scattering PDF of a Lambertian surface
+#[derive(Debug, Copy, Clone)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    /// Returns a cosine PDF for the hemisphere around `normal`
+    pub fn new(normal: &Vec3) -> Self {
+        Self {
+            uvw: Onb::build_from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    /// Returns `cos(theta) / PI`, or 0 for directions below the surface
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.unit_vector().dot(&self.uvw.w);
+        (cosine / PI).max(0.0)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.local_vec(&Vec3::random_cosine_direction())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Vec3;
+    use crate::pdf::{CosinePdf, Pdf};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn generated_directions_lie_in_the_hemisphere_of_the_normal() {
+        let normal = Vec3::new(1.0, 1.0, 0.0);
+        let pdf = CosinePdf::new(&normal);
+
+        for _ in 0..100 {
+            let direction = pdf.generate();
+            assert!(direction.dot(&normal) >= 0.0);
+            assert!(pdf.value(&direction) >= 0.0);
+        }
+        assert!((pdf.value(&normal) - 1.0 / PI).abs() < 1e-12);
+        assert_eq!(pdf.value(&-normal), 0.0);
+    }
+}
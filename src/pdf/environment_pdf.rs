@@ -0,0 +1,161 @@
+use crate::common::{Point3, RngContext, Vec3};
+use crate::pdf::Pdf;
+use crate::texture::{environment_direction, get_environment_uv, EnvironmentMap, Texture};
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// A PDF that generates directions towards the bright regions of an equirectangular
+/// environment map, such as the sun of an HDRI, in proportion to their luminance.
+///
+/// Each texel is weighted by its luminance times the solid angle it covers, which shrinks
+/// towards the poles of the map. A row of the map is drawn from the cumulative distribution
+/// of the row weights, and then a texel of that row from the cumulative distribution of its
+/// texel weights
+#[derive(Debug)]
+pub struct EnvironmentPdf {
+    width: usize,
+    height: usize,
+    // the cumulative distribution of the rows, starting from the top row of the map
+    row_cdf: Vec<f64>,
+    // the cumulative distribution of the texels within each row, one row after the other
+    texel_cdfs: Vec<f64>,
+    // the probability density of each texel, with respect to the area of the u,v square
+    densities: Vec<f64>,
+}
+
+impl EnvironmentPdf {
+    /// Builds the distribution of the texels of the environment map `image`
+    pub fn new(image: &EnvironmentMap) -> Self {
+        let (width, height) = (
+            image.width().max(1) as usize,
+            image.height().max(1) as usize,
+        );
+        let mut weights: Vec<f64> = Vec::with_capacity(width * height);
+        for j in 0..height {
+            let v = 1.0 - (j as f64 + 0.5) / height as f64;
+            let solid_angle = (PI * v).sin();
+            for i in 0..width {
+                let u = (i as f64 + 0.5) / width as f64;
+                let luminance = image.value(u, v, &Point3::default()).luminance();
+                weights.push(luminance.max(0.0) * solid_angle);
+            }
+        }
+        // a black map is sampled uniformly over the sphere
+        if weights.iter().sum::<f64>() <= 0.0 {
+            for (j, row) in weights.chunks_mut(width).enumerate() {
+                let v = 1.0 - (j as f64 + 0.5) / height as f64;
+                row.fill((PI * v).sin());
+            }
+        }
+        let total: f64 = weights.iter().sum();
+
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut texel_cdfs = Vec::with_capacity(width * height);
+        let mut cumulative_rows = 0.0;
+        for row in weights.chunks(width) {
+            let row_total: f64 = row.iter().sum();
+            cumulative_rows += row_total;
+            row_cdf.push(cumulative_rows / total);
+            let mut cumulative_texels = 0.0;
+            for weight in row {
+                cumulative_texels += weight;
+                texel_cdfs.push(if row_total > 0.0 {
+                    cumulative_texels / row_total
+                } else {
+                    1.0
+                });
+            }
+        }
+        let densities = weights
+            .iter()
+            .map(|weight| weight / total * (width * height) as f64)
+            .collect();
+
+        Self {
+            width,
+            height,
+            row_cdf,
+            texel_cdfs,
+            densities,
+        }
+    }
+
+    /// Returns the index of the first entry of the cumulative distribution `cdf` that is
+    /// greater than the random number `r`
+    fn sample_cdf(cdf: &[f64], r: f64) -> usize {
+        cdf.partition_point(|&c| c <= r).min(cdf.len() - 1)
+    }
+}
+
+impl Pdf for EnvironmentPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let (u, v) = get_environment_uv(&direction.unit_vector());
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = (((1.0 - v) * self.height as f64) as usize).min(self.height - 1);
+        // convert the density over the u,v square to a density over solid angle
+        self.densities[j * self.width + i] / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn generate(&self) -> Vec3 {
+        let mut rng = RngContext::current();
+        let j = EnvironmentPdf::sample_cdf(&self.row_cdf, rng.gen::<f64>());
+        let row = &self.texel_cdfs[j * self.width..(j + 1) * self.width];
+        let i = EnvironmentPdf::sample_cdf(row, rng.gen::<f64>());
+        let u = (i as f64 + rng.gen::<f64>()) / self.width as f64;
+        let v = 1.0 - (j as f64 + rng.gen::<f64>()) / self.height as f64;
+        environment_direction(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Vec3;
+    use crate::pdf::{EnvironmentPdf, Pdf};
+    use crate::texture::{get_environment_uv, EnvironmentMap};
+    use std::f64::consts::PI;
+
+    /// builds a dim, `width` x `height`, map with a single bright texel at column `i`, row `j`
+    fn map_with_a_sun(width: u32, height: u32, i: u32, j: u32) -> EnvironmentMap {
+        let mut data = vec![1; (width * height * 3) as usize];
+        let sun = ((j * width + i) * 3) as usize;
+        data[sun..sun + 3].copy_from_slice(&[255, 255, 255]);
+        EnvironmentMap::from_rgb8(width, height, data)
+    }
+
+    #[test]
+    fn density_integrates_to_one_over_the_sphere() {
+        let pdf = EnvironmentPdf::new(&map_with_a_sun(16, 8, 5, 2));
+
+        let samples = 200_000;
+        let sum: f64 = (0..samples)
+            .map(|_| pdf.value(&Vec3::random_unit_vector()))
+            .sum();
+        let integral = 4.0 * PI * sum / samples as f64;
+
+        assert!((integral - 1.0).abs() < 0.05, "integral was {}", integral);
+    }
+
+    #[test]
+    fn most_directions_are_generated_towards_the_sun() {
+        let pdf = EnvironmentPdf::new(&map_with_a_sun(16, 8, 5, 2));
+
+        let towards_sun = (0..1000)
+            .map(|_| pdf.generate())
+            .filter(|direction| {
+                assert!(pdf.value(direction) > 0.0);
+                let (u, v) = get_environment_uv(direction);
+                ((u * 16.0) as u32, ((1.0 - v) * 8.0) as u32) == (5, 2)
+            })
+            .count();
+
+        assert!(
+            towards_sun > 600,
+            "{} directions towards the sun",
+            towards_sun
+        );
+    }
+}
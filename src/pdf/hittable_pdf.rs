@@ -0,0 +1,29 @@
+use crate::common::{Point3, Vec3};
+use crate::hittable::Hittable;
+use crate::pdf::Pdf;
+use std::sync::Arc;
+
+/// A PDF that generates directions from `origin` towards a hittable, such as a light, using
+/// the hittable's `Hittable::random` and `Hittable::pdf_value` functions
+#[derive(Debug)]
+pub struct HittablePdf {
+    origin: Point3,
+    hittable: Arc<dyn Hittable>,
+}
+
+impl HittablePdf {
+    /// Returns a PDF of the directions from `origin` towards `hittable`
+    pub fn new(hittable: Arc<dyn Hittable>, origin: Point3) -> Self {
+        Self { origin, hittable }
+    }
+}
+
+impl Pdf for HittablePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.hittable.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.hittable.random(&self.origin)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::common::{RngContext, Vec3};
+use crate::pdf::Pdf;
+use rand::Rng;
+use std::sync::Arc;
+
+/// An equal mixture of two PDFs. Each generated direction comes from one of the two PDFs,
+/// chosen at random, so the density of a direction is the average of both densities
+#[derive(Debug)]
+pub struct MixturePdf {
+    pdfs: [Arc<dyn Pdf>; 2],
+}
+
+impl MixturePdf {
+    /// Returns a mixture of `p0` and `p1`
+    pub fn new(p0: Arc<dyn Pdf>, p1: Arc<dyn Pdf>) -> Self {
+        Self { pdfs: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        0.5 * self.pdfs[0].value(direction) + 0.5 * self.pdfs[1].value(direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if RngContext::current().gen::<f64>() < 0.5 {
+            self.pdfs[0].generate()
+        } else {
+            self.pdfs[1].generate()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Vec3};
+    use crate::hittable::{Hittable, Sphere};
+    use crate::material::Lambertian;
+    use crate::pdf::{CosinePdf, HittablePdf, MixturePdf, Pdf};
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn mixture_density_is_the_average_of_both_densities() {
+        let light: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::new(0.0, 5.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from_rgb(
+                1.0, 1.0, 1.0,
+            )))),
+        ));
+        let cosine = Arc::new(CosinePdf::new(&Vec3::new(0.0, 1.0, 0.0)));
+        let towards_light = Arc::new(HittablePdf::new(light, Point3::default()));
+        let mixture = MixturePdf::new(cosine.clone(), towards_light.clone());
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let expected = 0.5 * cosine.value(&up) + 0.5 * towards_light.value(&up);
+        assert!((mixture.value(&up) - expected).abs() < 1e-12);
+        // a direction that misses the light only has the cosine density
+        let side = Vec3::new(1.0, 1.0, 0.0);
+        assert!((mixture.value(&side) - 0.5 * cosine.value(&side)).abs() < 1e-12);
+        assert!(mixture.value(&mixture.generate()) > 0.0);
+    }
+}
@@ -1,22 +1,308 @@
-use rand::Rng;
-use std::sync::mpsc::{channel, Sender};
-use std::sync::Arc;
-use std::time::Instant;
-use threadpool::ThreadPool;
+pub mod accumulator;
+pub use accumulator::*;
+
+pub mod builder;
+pub use builder::*;
+
+pub mod framebuffer;
+pub use framebuffer::*;
+
+pub mod handle;
+pub use handle::*;
+
+pub mod integrator;
+pub use integrator::*;
+
+pub mod sky;
+pub use sky::*;
+
+pub mod tile;
+pub use tile::*;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::common;
-use crate::common::{Camera, Color, Ray};
-use crate::hittable::{BvhNode, Hittable, HittableList};
+use crate::common::{
+    halton, Camera, CameraBuilder, Color, Point3, Ray, RngContext, Sampler, SamplerKind, Vec3,
+};
+use crate::hittable::{
+    FlatBvh, HitRecord, Hittable, HittableList, KdTree, ObjectId, RefitBvh, UniformGrid,
+};
+use crate::light::Light;
+use crate::material::{Lambertian, Material, MediumStack, ScatterRecord};
+use crate::pdf::EnvironmentPdf;
+use crate::texture::{get_environment_uv, EnvironmentMap, SolidColor, Texture};
+use crate::util::gif;
+use crate::util::tonemap::ToneMap;
+
+/// the elevation, in degrees, of the camera orbiting the target of a turntable GIF
+const TURNTABLE_ELEVATION: f64 = 20.0;
+
+/// the number of milliseconds each frame of a turntable GIF is displayed for
+pub const TURNTABLE_FRAME_DELAY_MS: u32 = 100;
 
 /// Indicates what background color should be used by a renderer
-/// Currently only two options are supported:
+/// Currently five options are supported:
 /// `Solid` - a solid color should be used for the background
 /// `LinearInterp(Color1, Color2)` - use linear interpolation to render the background color
 ///  between color1 and color2
-#[derive(Debug, Copy, Clone)]
+/// `Studio(Backdrop, Color1, Color2)` - a "studio" look, where the camera sees a solid backdrop
+///  color, while rays that have bounced off of objects see a linear interpolation between
+///  color1 and color2. This lights objects with an environment while they sit on a plain backdrop
+/// `Sky(PreethamSky)` - a physically based, analytic, daytime sky, positioned by the elevation of
+///  the sun and hazed by the turbidity of the atmosphere
+/// `Environment(Image)` - an equirectangular (latitude-longitude) environment map. Rays that
+///  miss every object sample the image in their direction, so the scene is lit by, and seen
+///  against, a photographed surrounding. Environment maps can not be serialized
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BackgroundColor {
     Solid(Color),
     LinearInterp(Color, Color),
+    Studio(Color, Color, Color),
+    Sky(PreethamSky),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Environment(Arc<EnvironmentMap>),
+}
+
+/// The color space of the final rendered image.
+/// The renderer computes colors using linear sRGB (Rec.709) primaries. Choosing a wide-gamut
+/// color space converts the linear radiance of each pixel to that color space's primaries
+/// **before** gamma correction is applied.
+/// `Srgb` - the default, leaves colors unchanged
+/// `DisplayP3` - Display P3 primaries with a D65 white point
+/// `Rec2020` - ITU-R BT.2020 primaries, used by HDR and wide-gamut video
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+    Rec2020,
+}
+
+impl OutputColorSpace {
+    /// Returns the 3x3 (row major) matrix that converts linear sRGB to this color space
+    fn matrix(&self) -> [[f64; 3]; 3] {
+        match self {
+            OutputColorSpace::Srgb => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            OutputColorSpace::DisplayP3 => [
+                [0.8224621, 0.1775380, 0.0000000],
+                [0.0331941, 0.9668058, 0.0000000],
+                [0.0170827, 0.0723974, 0.9105199],
+            ],
+            OutputColorSpace::Rec2020 => [
+                [0.6274039, 0.3292830, 0.0433131],
+                [0.0690973, 0.9195404, 0.0113623],
+                [0.0163914, 0.0880133, 0.8955953],
+            ],
+        }
+    }
+
+    /// Converts the `linear` sRGB color into this color space
+    pub fn convert(&self, linear: Color) -> Color {
+        if *self == OutputColorSpace::Srgb {
+            return linear;
+        }
+        let m = self.matrix();
+        let c = linear.as_array();
+        Color::new(
+            m[0][0] * c[0] + m[0][1] * c[1] + m[0][2] * c[2],
+            m[1][0] * c[0] + m[1][1] * c[1] + m[1][2] * c[2],
+            m[2][0] * c[0] + m[2][1] * c[1] + m[2][2] * c[2],
+        )
+    }
+}
+
+/// The anti-aliasing strategy used to choose the sample positions within each pixel
+/// `Stochastic` - the default, takes `samples_per_pixel` randomly placed samples per pixel
+/// `Supersample { factor }` - splits each pixel into a regular `factor x factor` grid and
+///  takes one sample at the center of each grid cell, instead of `samples_per_pixel` samples.
+///  The sample positions are the same for every render, so scenes without random scattering
+///  (i.e. lights and backgrounds) render identically every time
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AntiAlias {
+    #[default]
+    Stochastic,
+    Supersample {
+        factor: u32,
+    },
+}
+
+/// The acceleration structure the world is placed in before it is rendered
+/// `Bvh` - the default, a `FlatBvh`, which adapts to any distribution of objects
+/// `Grid` - a `UniformGrid`, which is quicker to build, and can be faster to traverse for
+///  evenly distributed objects
+/// `KdTree` - a `KdTree`, which can be faster to traverse for tightly packed, axis aligned,
+///  objects such as the walls and boxes of architectural scenes
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Accelerator {
+    #[default]
+    Bvh,
+    Grid,
+    KdTree,
+}
+
+impl Accelerator {
+    /// Returns the estimated number of bytes allocated when the hittables of `list` are placed
+    /// in this acceleration structure, see `FlatBvh::estimated_size`,
+    /// `UniformGrid::estimated_size` and `KdTree::estimated_size`
+    pub fn estimated_size(&self, list: &HittableList) -> usize {
+        let object_count = list.len();
+        match self {
+            Accelerator::Bvh => FlatBvh::estimated_size(object_count),
+            Accelerator::Grid => UniformGrid::estimated_size(object_count),
+            Accelerator::KdTree => KdTree::estimated_size(object_count),
+        }
+    }
+}
+
+/// Parses an accelerator from `bvh`, `grid` or `kdtree`
+impl FromStr for Accelerator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bvh" => Ok(Accelerator::Bvh),
+            "grid" => Ok(Accelerator::Grid),
+            "kdtree" => Ok(Accelerator::KdTree),
+            _ => Err(format!(
+                "unknown accelerator '{}', expected bvh, grid or kdtree",
+                s
+            )),
+        }
+    }
+}
+
+/// The error returned when the estimated memory needed to place a world in an acceleration
+/// structure exceeds the memory limit of a `Renderer`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryLimitExceeded {
+    pub accelerator: Accelerator,
+    pub object_count: usize,
+    pub estimated_bytes: usize,
+    pub limit_bytes: usize,
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "building a {:?} accelerator of {} objects needs an estimated {} bytes, which exceeds the limit of {} bytes",
+            self.accelerator, self.object_count, self.estimated_bytes, self.limit_bytes
+        )
+    }
+}
+
+impl Error for MemoryLimitExceeded {}
+
+/// How the depths of a depth pass are expressed
+/// `Raw` - the default, the distance from the camera to the surface, in scene units, for 3D
+///  reconstruction. Pixels that see nothing have an infinite depth
+/// `Normalized { near, far }` - the distance mapped linearly from `[near..far]` to `[0..1]`,
+///  for visualization. Distances outside of the range are clamped, so pixels that see nothing
+///  have a depth of `1`
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DepthMode {
+    #[default]
+    Raw,
+    Normalized {
+        near: f64,
+        far: f64,
+    },
+}
+
+impl DepthMode {
+    /// Returns the `distance` from the camera to a surface, expressed in this mode
+    pub fn map(&self, distance: f64) -> f64 {
+        match *self {
+            DepthMode::Raw => distance,
+            DepthMode::Normalized { near, far } => {
+                ((distance - near) / (far - near)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// the fewest samples taken per pixel by `Renderer::render_reference`, before it estimates a
+/// pixel's error
+const REFERENCE_MIN_SAMPLES: u32 = 16;
+
+/// the number of samples per pixel taken by each pass of `Renderer::render_progressive`
+const PROGRESSIVE_PASS_SAMPLES: u32 = 16;
+
+/// The statistics of a reference render, see `Renderer::render_reference`
+/// `total_samples` is the number of samples taken over every pixel of the image
+/// `elapsed` is how long the render took
+/// `max_standard_error` is the largest (estimated) standard error of any pixel's luminance
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ReferenceStats {
+    pub total_samples: u64,
+    pub elapsed: Duration,
+    pub max_standard_error: f64,
+}
+
+/// A callback invoked with each finished tile of a render, the colors of its pixels, the time
+/// it took to render, and the sampling statistics of its pixels
+type TileCallback<'a> = dyn FnMut(&Tile, &[Color], Duration, &[PixelStats]) + Send + 'a;
+
+/// The sampling statistics of a rendered pixel, the (unbiased) sample `variance` of the
+/// luminance of its samples, and the number of `samples` it took
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct PixelStats {
+    variance: f64,
+    samples: u32,
+}
+
+impl PixelStats {
+    /// Returns the estimated standard error of the mean of the pixel's samples
+    fn standard_error(&self) -> f64 {
+        match self.samples {
+            0 => 0.0,
+            samples => (self.variance / samples as f64).sqrt(),
+        }
+    }
+}
+
+/// The kind of image, or pass, computed by a renderer for each pixel
+/// `Color` - the path traced color of the scene
+/// `Radiance` - the path traced color of the scene, before gamma correction and scaling
+/// `ViewSpaceNormals` - the surface normal seen by the primary ray, in camera space
+/// `Occlusion(light)` - `1` if the primary hit can see the `light` point, else `0`
+/// `MotionVectors` - the screen-space motion of the primary hit during the shutter interval
+/// `Depth` - the distance from the camera to the primary hit
+/// `Albedo` - the color that the primary hit's material applies to the light it scatters
+/// `Irradiance` - the (linear) light arriving at the primary hit, before its material is applied
+/// `Emission` - the light emitted by the primary hit, or the background if nothing was hit
+/// `Normals` - the surface normal seen by the primary ray, in world space
+/// `ObjectId` - the object id of the primary hit, see `ObjectId`
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+enum RenderPass {
+    #[default]
+    Color,
+    Radiance,
+    ViewSpaceNormals,
+    Occlusion(Point3),
+    MotionVectors,
+    Depth,
+    Albedo,
+    Irradiance,
+    Emission,
+    Normals,
+    ObjectId,
 }
 
 /// A Renderer will use ray-tracing to render a scene using a Camera and a list of Hittables.
@@ -30,14 +316,84 @@ pub enum BackgroundColor {
 /// `background_color` sets the default color of the renderer. This color is used as the
 /// default ray color when a ray does not hit something
 /// `num_workers` is the number of **Operating System threads** to spawn for rendering. Ideally
-/// this should be equal to the number of physical cores on your machine
+/// this should be equal to the number of physical cores on your machine. The threads are
+/// spawned once, when the renderer is created, and are reused by every render. Clones of a
+/// renderer share its threads
+/// `ambient` is an optional `(sky, ground)` color pair used to add a cheap, hemispheric fill
+/// light to every surface that scatters light. It is disabled by default
+/// `output_color_space` is the color space of the rendered image, defaults to sRGB
+/// `tone_map` is the operator that maps the radiance of each pixel to the range of a display,
+/// before it is gamma corrected and quantized, defaults to `Clamp`
+/// `anti_alias` is the strategy used to place samples within a pixel, defaults to `Stochastic`
+/// `integrator` is the algorithm that computes the color of each sample, defaults to
+/// `PathTracing`
+/// `sampler` is the kind of sampler that places the samples of `Stochastic` anti-aliasing
+/// within each pixel, and on the lens, defaults to `Halton`
+/// `jitter_pattern` is an optional, fixed, list of sub-pixel offsets that is sampled in every
+/// pixel instead of the anti-aliasing strategy's offsets, for reproducible test baselines
+/// `seed` optionally seeds the random numbers used to render each tile, so that renders
+/// with the same seed are identical. It is disabled by default
+/// `volume_bounce_depth` optionally gives scattering within volumes its own bounce limit,
+/// separate from `ray_bounce_depth`. By default volume scatters use up the same bounce limit
+/// as surface scatters
+/// `target_error` is the standard error that every pixel is sampled down to, instead of taking
+/// a fixed number of samples, it is only set by `render_reference`
+/// `sample_window` is the optional range of sample indices taken in every pixel, instead of
+/// all of them, it is only set by the passes of `render_progressive`
+/// `clay` is an optional material that replaces the material of every non-emissive surface,
+/// it is only set by `render_clay`
+/// `lights` are the optional hittables that are importance sampled, i.e. the lights of a scene.
+/// Materials that scatter rays with a PDF then send half of their rays towards the lights,
+/// which greatly reduces the noise of scenes lit by small lights. It is disabled by default
+/// `portals` are the optional openings, such as `Portal` windows, that the background lights
+/// the scene through. They are importance sampled along with the `lights`. None are set by default
+/// `environment` is the distribution of the luminance of an `Environment` background. It is
+/// built whenever the background is set to an environment map, and materials that scatter
+/// rays with a PDF then send some of their rays towards the bright regions of the map
+/// `direct_lights` are lights outside of the world, such as point lights, that every diffuse
+/// surface casts a shadow ray towards. None are added by default
+/// `tir_tint` is an optional debugging color, that tints the light of rays that were totally
+/// internally reflected inside of dielectrics (glass). It is disabled by default
+/// `watchdog` is the optional number of slowest tiles to report at the end of a render,
+/// for finding the expensive regions of a scene. It is disabled by default
+/// `tile_size` is the width and height, in pixels, of the square tiles the image is split into.
+/// Each tile is rendered as a single job by a worker thread, defaults to 32
+/// `aovs` renders the auxiliary buffers of the scene (albedo, normal, depth and object id)
+/// along with every image returned by `render`. It is disabled by default
+/// `accelerator` is the acceleration structure the world is placed in, defaults to `Bvh`
+/// `memory_limit` is the optional number of bytes the acceleration structure may need. Worlds
+/// that are estimated to need more are rejected before it is built. There is no limit by default
 ///
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Renderer {
     background_color: BackgroundColor,
     ray_bounce_depth: u32,
     samples_per_pixel: u32,
     num_workers: usize,
+    ambient: Option<(Color, Color)>,
+    output_color_space: OutputColorSpace,
+    tone_map: ToneMap,
+    pass: RenderPass,
+    watchdog: Option<usize>,
+    tile_size: u32,
+    tir_tint: Option<Color>,
+    lights: Option<Arc<dyn Hittable>>,
+    portals: Option<Arc<dyn Hittable>>,
+    environment: Option<Arc<EnvironmentPdf>>,
+    direct_lights: Vec<Arc<dyn Light>>,
+    anti_alias: AntiAlias,
+    integrator: Arc<dyn Integrator>,
+    sampler: SamplerKind,
+    accelerator: Accelerator,
+    memory_limit: Option<usize>,
+    jitter_pattern: Option<Vec<(f64, f64)>>,
+    seed: Option<u64>,
+    target_error: Option<f64>,
+    sample_window: Option<Range<u32>>,
+    clay: Option<Arc<dyn Material>>,
+    volume_bounce_depth: Option<u32>,
+    aovs: bool,
+    pool: Arc<ThreadPool>,
 }
 
 impl Renderer {
@@ -51,11 +407,215 @@ impl Renderer {
         Self {
             ray_bounce_depth,
             samples_per_pixel,
+            environment: Renderer::environment_pdf(&background_color),
             background_color,
             num_workers,
+            ambient: None,
+            output_color_space: OutputColorSpace::default(),
+            tone_map: ToneMap::default(),
+            pass: RenderPass::default(),
+            watchdog: None,
+            tile_size: DEFAULT_TILE_SIZE,
+            tir_tint: None,
+            lights: None,
+            portals: None,
+            direct_lights: vec![],
+            anti_alias: AntiAlias::default(),
+            integrator: IntegratorKind::default().integrator(),
+            sampler: SamplerKind::default(),
+            accelerator: Accelerator::default(),
+            memory_limit: None,
+            jitter_pattern: None,
+            seed: None,
+            target_error: None,
+            sample_window: None,
+            clay: None,
+            volume_bounce_depth: None,
+            aovs: false,
+            pool: Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(num_workers.max(1))
+                    .build()
+                    .expect("failed to spawn the render threads"),
+            ),
         }
     }
 
+    /// Enables a hemispheric ambient fill light. Surfaces whose normal points up receive the
+    /// `sky` color, surfaces whose normal points down receive the `ground` color, and
+    /// everything in between receives a blend of the two. The fill light is added on top of
+    /// the path traced lighting, so small colors (i.e. `0.05`) are usually enough to lift
+    /// pitch-black shadows
+    pub fn with_ambient(mut self, sky: Color, ground: Color) -> Self {
+        self.ambient = Some((sky, ground));
+        self
+    }
+
+    /// Replaces the background color this renderer was created with, i.e. with an
+    /// `Environment` map
+    pub fn with_background_color(mut self, background_color: BackgroundColor) -> Self {
+        self.environment = Renderer::environment_pdf(&background_color);
+        self.background_color = background_color;
+        self
+    }
+
+    /// Sets the color space of the rendered image
+    pub fn with_output_color_space(mut self, output_color_space: OutputColorSpace) -> Self {
+        self.output_color_space = output_color_space;
+        self
+    }
+
+    /// Sets the tone map operator, that maps the linear radiance of each pixel into the range
+    /// of a display before it is gamma corrected. The default, `Clamp`, clips every highlight
+    /// to white, while the other operators compress bright emissive surfaces instead
+    pub fn with_tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    /// Enables the render watchdog. Every tile is timed while rendering, and once the render
+    /// is finished the `slowest_count` slowest tiles are reported, along with the coordinates
+    /// of the pixels they contain
+    pub fn with_watchdog(mut self, slowest_count: usize) -> Self {
+        self.watchdog = Some(slowest_count);
+        self
+    }
+
+    /// Sets the width and height, in pixels, of the square tiles that images are split into.
+    /// Smaller tiles balance the work between the worker threads better when a few regions of
+    /// the image are much more expensive than the rest (such as lights or volumes), while
+    /// larger tiles have less overhead
+    ///
+    /// # Panics
+    /// if `tile_size` is zero
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        assert!(tile_size > 0, "tile size must be at least one pixel");
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// Sets the anti-aliasing strategy of this renderer
+    pub fn with_anti_alias(mut self, anti_alias: AntiAlias) -> Self {
+        self.anti_alias = anti_alias;
+        self
+    }
+
+    /// Sets the algorithm used to compute the color of each sample, i.e. `AmbientOcclusion` to
+    /// quickly check the composition of a scene before a long render
+    pub fn with_integrator(mut self, integrator: IntegratorKind) -> Self {
+        self.integrator = integrator.integrator();
+        self
+    }
+
+    /// Sets an integrator that is not one of the `IntegratorKind`s, i.e. one implemented outside
+    /// of this crate
+    pub fn with_custom_integrator(mut self, integrator: Arc<dyn Integrator>) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Sets the kind of sampler that places the pixel and lens offsets of `Stochastic`
+    /// anti-aliasing, i.e. `Random` to compare against plain Monte Carlo sampling
+    pub fn with_sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Sets the acceleration structure the world is placed in, i.e. `Grid` to compare its
+    /// performance against the default BVH
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    /// Limits the memory the acceleration structure may need to `limit_bytes`, as estimated by
+    /// `Accelerator::estimated_size`. This guards against user generated scenes with so many
+    /// objects that building them would exhaust the system's memory. Call
+    /// `check_memory_limit` to find out if a world fits, as rendering one that does not panics
+    pub fn with_memory_limit(mut self, limit_bytes: usize) -> Self {
+        self.memory_limit = Some(limit_bytes);
+        self
+    }
+
+    /// Uses the fixed sub-pixel `offsets` for every pixel, instead of the offsets chosen by the
+    /// anti-aliasing strategy, so that renders are reproducible, i.e. for comparing against a
+    /// golden image. One sample is taken per offset, and each offset should be in the range
+    /// `[0..1)`. The lens offsets are also fixed, so defocus blur is deterministic as well
+    ///
+    /// # Panics
+    /// if `offsets` is empty
+    pub fn with_jitter_pattern(mut self, offsets: Vec<(f64, f64)>) -> Self {
+        assert!(
+            !offsets.is_empty(),
+            "jitter pattern must hold at least one offset"
+        );
+        self.jitter_pattern = Some(offsets);
+        self
+    }
+
+    /// Seeds the random numbers used by this renderer, so that every render of the same scene
+    /// with the same `seed` produces an identical image. Each tile is rendered from its own
+    /// seed, derived from `seed` and its position, so the image does not depend on which worker
+    /// thread renders which tile
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Renders the auxiliary buffers of the scene along with every image returned by `render`,
+    /// such as the albedo and normals expected by denoisers, see `Aovs`. The buffers are
+    /// rendered by separate passes over the image, which trace primary rays only
+    pub fn with_aovs(mut self) -> Self {
+        self.aovs = true;
+        self
+    }
+
+    /// Gives scattering within volumes, such as a `ConstantMedium`, its own limit of
+    /// `volume_bounce_depth` bounces. Volume scatters then no longer use up the
+    /// `ray_bounce_depth` of surface scatters, so that thick fog does not exhaust the bounce
+    /// limit before its light reaches the surfaces around it, and fog and surfaces can be
+    /// tuned independently
+    pub fn with_volume_bounce_depth(mut self, volume_bounce_depth: u32) -> Self {
+        self.volume_bounce_depth = Some(volume_bounce_depth);
+        self
+    }
+
+    /// Enables the total internal reflection debugging mode. Light that was totally internally
+    /// reflected inside a dielectric (glass) is multiplied by `tint` (i.e. red), to show where
+    /// light is trapped inside of glass
+    pub fn with_tir_tint(mut self, tint: Color) -> Self {
+        self.tir_tint = Some(tint);
+        self
+    }
+
+    /// Enables importance sampling of the `lights`, which should also be part of the world
+    /// that is rendered. Materials that scatter rays with a PDF, such as `Lambertian`, then
+    /// scatter half of their rays towards the lights (using their `Hittable::random` and
+    /// `Hittable::pdf_value` functions), and weight each ray by the material's scattering PDF,
+    /// so the image converges to the same result with a lot less noise
+    pub fn with_lights(mut self, lights: Arc<dyn Hittable>) -> Self {
+        self.lights = Some(lights);
+        self
+    }
+
+    /// Enables importance sampling of the `portals`, i.e. a `Portal` or a list of them, the
+    /// openings that the background lights an interior scene through. Materials that scatter
+    /// rays with a PDF then send some of their rays through the openings, where they reach the
+    /// background, instead of wasting them on the walls around the openings. Portals are
+    /// sampled along with the `lights`, if any, and don't need to be part of the world
+    pub fn with_portals(mut self, portals: Arc<dyn Hittable>) -> Self {
+        self.portals = Some(portals);
+        self
+    }
+
+    /// Adds a `light` that is not part of the world, such as a `PointLight` or a
+    /// `DirectionalLight`. Every surface that scatters rays with a PDF casts a shadow ray
+    /// towards the light, and adds its light if the shadow ray is not blocked
+    pub fn with_direct_light(mut self, light: Arc<dyn Light>) -> Self {
+        self.direct_lights.push(light);
+        self
+    }
+
     /// Returns this renderer's bounce depth setting
     pub fn ray_bounce_depth(&self) -> u32 {
         self.ray_bounce_depth
@@ -66,219 +626,2799 @@ impl Renderer {
         self.samples_per_pixel
     }
 
+    /// Returns this renderer's number of worker threads
+    pub fn num_workers(&self) -> usize {
+        self.num_workers
+    }
+
     /// Returns this renderer's background color setting
     pub fn background_color(&self) -> BackgroundColor {
-        self.background_color
+        self.background_color.clone()
     }
 
-    /// Renders an image using the provided `Camera` and `World`.
-    ///
-    /// # Returns
-    /// a Vector of `Color`s representing the final color of each pixel in the image.
-    /// The colors of the image are stored in row major format, starting from top left
-    /// to the bottom right
-    pub fn render(self, camera: Camera, mut world: HittableList) -> Vec<Color> {
-        let now = Instant::now();
-        println!(
-            "rendering a {}x{} image. threads={}  bounce_depth={}  samples_per_pixel={}",
-            &camera.image_width,
-            &camera.image_height,
-            &self.num_workers,
-            &self.ray_bounce_depth,
-            &self.samples_per_pixel
-        );
+    /// Returns this renderer's `(sky, ground)` ambient light setting, if enabled
+    pub fn ambient(&self) -> Option<(Color, Color)> {
+        self.ambient
+    }
 
-        // build a thread pool to render a pixel color per thread
-        let pool = ThreadPool::new(self.num_workers);
+    /// Returns this renderer's output color space setting
+    pub fn output_color_space(&self) -> OutputColorSpace {
+        self.output_color_space
+    }
 
-        // build a BVH
-        let world: Arc<dyn Hittable> = Arc::new(BvhNode::from(&mut world, 0.0, 1.0));
-        let camera = Arc::new(camera);
+    /// Returns this renderer's tone map operator
+    pub fn tone_map(&self) -> ToneMap {
+        self.tone_map
+    }
 
-        let rx = {
-            let (tx, rx) = channel();
+    /// Returns this renderer's anti-aliasing strategy
+    pub fn anti_alias(&self) -> AntiAlias {
+        self.anti_alias
+    }
 
-            // traverse the image from upper left corner to lower right corner and generate pixel
-            // render jobs
-            for row in 0..camera.image_height {
-                let tx = Sender::clone(&tx);
-                let world = Arc::clone(&world);
-                let camera = Arc::clone(&camera);
+    /// Returns the algorithm used to compute the color of each sample
+    pub fn integrator(&self) -> &dyn Integrator {
+        self.integrator.as_ref()
+    }
 
-                pool.execute(move || {
-                    let row_colors = self.render_scanline(row, &*world, &camera);
-                    tx.send((row, row_colors))
-                        .expect("error occurred rendering");
-                });
-            }
-            println!(
-                "submitted {} scanline render jobs with a thread pool size = {}",
-                &camera.image_height, &self.num_workers
-            );
-            rx
-        };
+    /// Returns the kind of sampler used by stochastic anti-aliasing
+    pub fn sampler(&self) -> SamplerKind {
+        self.sampler
+    }
 
-        // allocate a vector to store the pixel colors of the image (in row major format)
-        let mut image: Vec<Color> =
-            vec![Color::default(); (camera.image_width * camera.image_height) as usize];
+    /// Returns the acceleration structure the world is placed in
+    pub fn accelerator(&self) -> Accelerator {
+        self.accelerator
+    }
 
-        // read finished jobs data from the channel and store in image vector
-        for (row, row_colors) in rx.iter() {
-            println!("row {} of {} finished...", &row, &camera.image_height);
-            let ridx = (row * camera.image_width) as usize;
-            let image_slice = &mut image[ridx..(ridx + camera.image_width as usize)];
-            for (i, color) in row_colors.into_iter().enumerate() {
-                image_slice[i] = color;
-            }
+    /// Returns the number of bytes the acceleration structure may need, if limited
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    /// Returns an error if placing the `world` in this renderer's acceleration structure is
+    /// estimated to need more memory than its memory limit
+    pub fn check_memory_limit(&self, world: &HittableList) -> Result<(), MemoryLimitExceeded> {
+        let limit_bytes = match self.memory_limit {
+            Some(limit_bytes) => limit_bytes,
+            None => return Ok(()),
+        };
+        let estimated_bytes = self.accelerator.estimated_size(world);
+        match estimated_bytes > limit_bytes {
+            true => Err(MemoryLimitExceeded {
+                accelerator: self.accelerator,
+                object_count: world.len(),
+                estimated_bytes,
+                limit_bytes,
+            }),
+            false => Ok(()),
         }
-        println!(
-            "done rendering, total elapsed {:.3} secs",
-            now.elapsed().as_secs_f64()
-        );
+    }
 
-        image
+    /// Returns the fixed sub-pixel offsets of this renderer, if a jitter pattern was set
+    pub fn jitter_pattern(&self) -> Option<&[(f64, f64)]> {
+        self.jitter_pattern.as_deref()
     }
 
-    /// Computes the color of a row (scanline) of pixels. `row` is the current row being rendered,
-    /// where row ranges from 0..image_height
-    /// Returns a Vector containing the final pixel colors of the row
-    fn render_scanline<T: Hittable + ?Sized>(
-        &self,
-        row: u32,
-        world: &T,
-        camera: &Camera,
-    ) -> Vec<Color> {
-        let mut rng = rand::thread_rng();
-        let mut colors: Vec<Color> = Vec::with_capacity(camera.image_width as usize);
+    /// Returns the bounce limit of scattering within volumes, if volume scatters have their
+    /// own limit
+    pub fn volume_bounce_depth(&self) -> Option<u32> {
+        self.volume_bounce_depth
+    }
 
-        for col in 0..camera.image_width {
-            let mut pixel_color = Color::default();
+    /// Returns the seed of this renderer, if one was set
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
 
-            for _ in 0..self.samples_per_pixel {
-                // u,v are offsets that randomly choose a point close to the current pixel
-                let u = (col as f64 + rng.gen::<f64>()) / (camera.image_width - 1) as f64;
-                let v = (row as f64 + rng.gen::<f64>()) / (camera.image_height - 1) as f64;
+    /// Returns the lights outside of the world that shadow rays are cast towards
+    pub fn direct_lights(&self) -> &[Arc<dyn Light>] {
+        &self.direct_lights
+    }
 
-                let r: Ray = camera.get_ray(u, v);
+    /// Returns the hittables that are importance sampled as lights, if set
+    pub fn lights(&self) -> Option<&Arc<dyn Hittable>> {
+        self.lights.as_ref()
+    }
 
-                pixel_color += self.ray_color(&r, world, self.ray_bounce_depth);
-            }
-            colors.push(Renderer::multi_sample(&pixel_color, self.samples_per_pixel));
-        }
-        colors
+    /// Returns the portals that are importance sampled along with the lights, if set
+    pub fn portals(&self) -> Option<&Arc<dyn Hittable>> {
+        self.portals.as_ref()
     }
 
-    /// determine if a Ray has hit a `Hittable` object in the `world` and compute the pixel color
-    /// of the Ray, `r`. The Hittable's `Material` is taken into account when performing ray bouncing
-    /// (up to `MAX_RAY_BOUNCE_DEPTH` times) in order to get an accurate color determination. If nothing
-    /// was hit then the `background` color is returned, than a linearly blended "sky" color is returned
-    fn ray_color<T: Hittable + ?Sized>(&self, ray: &Ray, world: &T, depth: u32) -> Color {
-        // exceeded the ray bounce limit, no more light is gathered
-        if depth == 0 {
-            return Color::default();
-        }
+    /// Returns the total internal reflection debugging tint, if enabled
+    pub fn tir_tint(&self) -> Option<Color> {
+        self.tir_tint
+    }
 
-        // if a hittable was hit, determine if its material will scatter the incoming
-        // ray, AND how much light the material emits
-        if let Some(ref rec) = world.hit(ray, 0.001, f64::INFINITY) {
-            let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+    /// Returns the material that replaces the material of every non-emissive surface, if this
+    /// is a clay render, see `render_clay`
+    pub fn clay(&self) -> Option<&Arc<dyn Material>> {
+        self.clay.as_ref()
+    }
 
-            if let Some(scatter_rec) = rec.mat_ptr.scatter(ray, rec) {
-                emitted
-                    + scatter_rec.attenuation
-                        * self.ray_color(&scatter_rec.scattered, world, depth - 1)
-            } else {
-                emitted
-            }
-        } else {
-            // nothing hit, return the background color
-            match self.background_color {
-                BackgroundColor::Solid(color) => color,
-                BackgroundColor::LinearInterp(from, to) => Renderer::linear_blend(ray, &from, &to),
-            }
+    /// Returns the distribution of the luminance of the environment map, if the background is
+    /// an `Environment`, which is importance sampled along with the lights
+    pub fn environment(&self) -> Option<&Arc<EnvironmentPdf>> {
+        self.environment.as_ref()
+    }
+
+    /// Returns the number of slowest tiles reported by the watchdog, if enabled
+    pub fn watchdog(&self) -> Option<usize> {
+        self.watchdog
+    }
+
+    /// Returns the width and height, in pixels, of the tiles rendered by this renderer
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Returns true if auxiliary buffers are rendered along with each image
+    pub fn aovs(&self) -> bool {
+        self.aovs
+    }
+
+    /// Renders an image using the provided `Camera` and `World`.
+    ///
+    /// # Returns
+    /// an `Image` holding the linear radiance of each pixel, and this renderer's tone map.
+    /// The image is converted to displayable colors by `Image::to_rgb8`, or saved with
+    /// `Image::save_png`. If `with_aovs` is enabled, the image also holds the auxiliary
+    /// buffers of the render
+    pub fn render(mut self, camera: Camera, world: HittableList) -> Image {
+        let (width, height) = (camera.image_width, camera.image_height);
+        let tone_map = self.tone_map;
+        let aovs = self
+            .aovs
+            .then(|| self.clone().render_aovs(camera, world.clone()));
+        self.pass = RenderPass::Radiance;
+        let image =
+            Image::new(width, height, self.render_buffer(camera, world)).with_tone_map(tone_map);
+        match aovs {
+            Some(aovs) => image.with_aovs(aovs),
+            None => image,
         }
     }
 
-    // /// determine if a Ray has hit a `Hittable` object in the `world` and compute the pixel color
-    // /// of the Ray, `r`. The Hittable's `Material` is taken into account when performing ray bouncing
-    // /// (up to `MAX_RAY_BOUNCE_DEPTH` times) in order to get an accurate color determination. If nothing
-    // /// was hit then the `background` color is returned, than a linearly blended "sky" color is returned
-    // fn ray_color<T: Hittable + ?Sized>(&self, ray: &Ray, world: &T, mut depth: u32) -> Color {
-    //     // The original book algorithm used a recursive solution, I've converted it to iterative
-    //     // in order to get some speed gains
-    //
-    //     // holds (emittedColor, attenuatedColor) for each ray bounce
-    //     type ColorRec = (Color, Option<Color>);
-    //     // holds the ray color for each object hit
-    //     let mut stack: Vec<ColorRec> = vec![];
-    //     let mut ray = ray.clone();
-    //
-    //     loop {
-    //         if depth == 0 {
-    //             stack.push((Color::default(), None));
-    //             break;
-    //         } else {
-    //             // if a hittable was hit, determine if its material will scatter the incoming
-    //             // ray, AND how much light the material emits
-    //             if let Some(ref rec) = world.hit(&ray, 0.001, f64::INFINITY) {
-    //                 let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
-    //
-    //                 if let Some(scatter_rec) = rec.mat_ptr.scatter(&ray, rec) {
-    //                     stack.push((emitted, Some(scatter_rec.attenuation)));
-    //                     ray = scatter_rec.scattered.to_owned();
-    //                     depth -= 1;
-    //                 } else {
-    //                     stack.push((emitted, None));
-    //                     break;
-    //                 }
-    //             } else {
-    //                 // nothing hit, return the background color
-    //                 match self.background_color {
-    //                     BackgroundColor::Solid(color) => stack.push((color, None)),
-    //                     BackgroundColor::LinearInterp(from, to) => stack.push((Renderer::linear_blend(&ray, &from, &to), None)),
-    //                 }
-    //                 break;
-    //             }
-    //         }
-    //     }
-    //     // now compute the final ray color based on data stored in the stack
-    //     let mut final_color = stack.pop().expect("color stack has at least one color").0;
-    //     for curr in stack.into_iter().rev() {
-    //         if let Some(att) = curr.1 {
-    //             final_color = curr.0 + att * final_color;
-    //         } else {
-    //             final_color = curr.0 + final_color;
-    //         }
-    //     }
-    //     final_color
-    // }
+    /// Starts rendering an image, like `render`, on a background thread and immediately returns
+    /// a `RenderHandle`, which reports the progress of the render and can cancel it. This is
+    /// intended for embedding the renderer in an interactive application, such as a GUI.
+    ///
+    /// `on_tile` is called, on the background thread, with each tile once it is finished, along
+    /// with the linear radiance of its pixels in row major order, i.e. to display the image
+    /// while it is being rendered. The finished `Image` is returned by `RenderHandle::wait`
+    pub fn render_with_progress<F>(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        mut on_tile: F,
+    ) -> RenderHandle
+    where
+        F: FnMut(&Tile, &[Color]) + Send + 'static,
+    {
+        let (width, height) = (camera.image_width, camera.image_height);
+        let pixel_count = (width * height) as usize;
+        let pixels_finished = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
 
-    /// Returns a linearly blended color between `from` and `to`. The input `ray`s
-    /// y coordinate to determine how much of `from` or `to` to apply.
-    fn linear_blend(ray: &Ray, from: &Color, to: &Color) -> Color {
-        let unit_direction = ray.direction().unit_vector();
-        let t = 0.5 * (unit_direction.y() + 1.0);
-        // blue is 0.5, 0.7, 1.0
-        (1.0 - t) * *from + t * *to
+        let (finished, canceled) = (Arc::clone(&pixels_finished), Arc::clone(&cancel));
+        let thread = std::thread::spawn(move || {
+            let tone_map = self.tone_map;
+            let aovs = self
+                .aovs
+                .then(|| self.clone().render_aovs(camera, world.clone()));
+            self.pass = RenderPass::Radiance;
+            let mut pixels = vec![Color::default(); pixel_count];
+            let completed = self.render_pixels(
+                camera,
+                world,
+                None,
+                &mut pixels,
+                Some(&canceled),
+                &mut |tile, colors, _, _| {
+                    on_tile(tile, colors);
+                    finished.fetch_add(tile.pixel_count(), Ordering::SeqCst);
+                },
+            );
+            completed.then(|| {
+                let image = Image::new(width, height, pixels).with_tone_map(tone_map);
+                match aovs {
+                    Some(aovs) => image.with_aovs(aovs),
+                    None => image,
+                }
+            })
+        });
+        RenderHandle::new(thread, pixels_finished, pixel_count, cancel)
     }
 
-    /// Returns a new pixel color using multi-sample color computation
-    fn multi_sample(pixel_color: &Color, samples_per_pixel: u32) -> Color {
-        let mut r = pixel_color.x();
-        let mut g = pixel_color.y();
-        let mut b = pixel_color.z();
+    /// Renders the auxiliary buffers of the scene. The albedo and normals are averaged over the
+    /// samples of each pixel, so that their edges are anti-aliased like the image, while the
+    /// depth and object id are taken from a single ray through the center of each pixel, as
+    /// averaging them would produce depths and ids that do not exist in the scene
+    fn render_aovs(mut self, camera: Camera, mut world: HittableList) -> Aovs {
+        self.pass = RenderPass::Albedo;
+        let albedo = self.clone().render_buffer(camera, world.clone());
+        self.pass = RenderPass::Normals;
+        let normal = self.clone().render_buffer(camera, world.clone());
 
-        // divide the color total by the number of samples and gamma correct for gamma=2.0
-        let scale = 1.0 / samples_per_pixel as f64;
-        r = f64::sqrt(scale * r);
-        g = f64::sqrt(scale * g);
-        b = f64::sqrt(scale * b);
+        self.jitter_pattern = Some(vec![(0.5, 0.5)]);
+        self.pass = RenderPass::Depth;
+        let depth = self.clone().render_buffer(camera, world.clone());
 
-        // compute a translated [0..=255] color value for each color's R,G,B
-        Color::new(
-            256.0 * common::clamp(r, 0.0, 0.999),
-            256.0 * common::clamp(g, 0.0, 0.999),
-            256.0 * common::clamp(b, 0.0, 0.999),
+        // tag every object of the world with its index, objects that already have an id keep it
+        let mut tagged = HittableList::new();
+        tagged.set_surface_epsilon(world.surface_epsilon());
+        for (i, object) in world.objects().iter().enumerate() {
+            tagged.add(Arc::new(ObjectId::from(object.clone(), i as u32 + 1)));
+        }
+        self.pass = RenderPass::ObjectId;
+        let object_id = self.render_buffer(camera, tagged);
+
+        Aovs::new(
+            albedo,
+            normal,
+            depth.into_iter().map(|depth| depth.x()).collect(),
+            object_id.into_iter().map(|id| id.x() as u32).collect(),
         )
     }
+
+    /// Renders the current pass of this renderer into a new buffer, whose colors are stored in
+    /// row major format, starting from the bottom row of the image. The color pass is gamma
+    /// corrected and scaled to `[0..256)`
+    fn render_buffer(self, camera: Camera, world: HittableList) -> Vec<Color> {
+        // allocate a vector to store the pixel colors of the image (in row major format)
+        let mut image: Vec<Color> =
+            vec![Color::default(); (camera.image_width * camera.image_height) as usize];
+        self.render_into(camera, world, &mut image);
+        image
+    }
+
+    /// Renders the linear radiance of each pixel, averaged over this renderer's samples per pixel.
+    /// This is the radiance held by the `Image` returned by `render`, as a bare buffer, so
+    /// that they can be combined with other renders, i.e. by an `Accumulator`
+    pub fn render_radiance(mut self, camera: Camera, world: HittableList) -> Vec<Color> {
+        self.pass = RenderPass::Radiance;
+        self.render_buffer(camera, world)
+    }
+
+    /// Renders an image, gamma corrected and scaled to `[0..256)` like `Image::display_colors`,
+    /// and also returns how long each scanline took to render.
+    /// Images are rendered in tiles, so the render time of each tile is split evenly between
+    /// the rows it covers.
+    ///
+    /// # Returns
+    /// a tuple holding the image and the render time of each scanline, where the scanline
+    /// durations are indexed by the row number of the scanline
+    pub fn render_timed(self, camera: Camera, world: HittableList) -> (Vec<Color>, Vec<Duration>) {
+        let mut image: Vec<Color> =
+            vec![Color::default(); (camera.image_width * camera.image_height) as usize];
+        let mut durations = vec![Duration::ZERO; camera.image_height as usize];
+        self.render_pixels(
+            camera,
+            world,
+            None,
+            &mut image,
+            None,
+            &mut |tile, _, duration, _| {
+                for row in tile.y..tile.y + tile.height {
+                    durations[row as usize] += duration / tile.height;
+                }
+            },
+        );
+        (image, durations)
+    }
+
+    /// Renders an image, gamma corrected and scaled to `[0..256)` like `Image::display_colors`,
+    /// along with the variance of each pixel's samples, to show where the image is still noisy
+    /// and would benefit from more samples. The variance is
+    /// the (unbiased) sample variance of the luminance of the linear radiance of each sample,
+    /// so it is `0` for pixels that see a flat color, or that took a single sample.
+    ///
+    /// # Returns
+    /// a tuple holding the image and the variance of each pixel, both in the same layout
+    pub fn render_with_variance(
+        self,
+        camera: Camera,
+        world: HittableList,
+    ) -> (Vec<Color>, Vec<f64>) {
+        let (width, height) = (camera.image_width as usize, camera.image_height as usize);
+        let mut image: Vec<Color> = vec![Color::default(); width * height];
+        let mut variances = vec![0.0; width * height];
+        self.render_pixels(
+            camera,
+            world,
+            None,
+            &mut image,
+            None,
+            &mut |tile, _, _, tile_stats| {
+                for ((col, row), stats) in tile.pixels().zip(tile_stats) {
+                    variances[row as usize * width + col as usize] = stats.variance;
+                }
+            },
+        );
+        (image, variances)
+    }
+
+    /// Renders the per-pixel motion vectors of the surfaces seen by the camera, for temporal
+    /// denoising and motion blur post-effects. The motion vector of a pixel is the screen-space
+    /// displacement, in pixels, of the surface point it sees, from the camera's shutter open
+    /// time to its shutter close time. `x` increases to the right and `y` increases downwards,
+    /// the same as the coordinates returned by `Camera::project`.
+    ///
+    /// # Returns
+    /// a Vector holding the `(dx, dy)` motion of each pixel, in the same layout as `render`.
+    /// Pixels that see static surfaces, or nothing at all, have no motion. The motion vectors
+    /// are not converted to the renderer's `output_color_space`, which would mix `dx` into `dy`
+    pub fn render_motion_vectors(mut self, camera: Camera, world: HittableList) -> Vec<(f64, f64)> {
+        self.pass = RenderPass::MotionVectors;
+        self.render_buffer(camera, world)
+            .into_iter()
+            .map(|motion| (motion.x(), motion.y()))
+            .collect()
+    }
+
+    /// Renders a quick, low quality, preview of the image that `render` would produce, for
+    /// checking the framing of a scene before committing to a full render. The preview is
+    /// rendered at `scale` (i.e. `0.25`) of the camera's resolution, and the samples per pixel
+    /// are reduced by the same amount as the number of pixels (but at least 1 sample is taken).
+    ///
+    /// # Returns
+    /// a tuple holding the preview image, and its width and height
+    pub fn preview(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        scale: f64,
+    ) -> (Vec<Color>, u32, u32) {
+        // rays are spread over width-1 and height-1 pixels, so keep at least two of each
+        let width = ((camera.image_width as f64 * scale).round() as u32).max(2);
+        let height = ((camera.image_height as f64 * scale).round() as u32).max(2);
+        let samples = (self.samples_per_pixel as f64 * scale * scale).round() as u32;
+        self.samples_per_pixel = samples.max(1);
+
+        let image = self.render_buffer(camera.with_resolution(width, height), world);
+        (image, width, height)
+    }
+
+    /// Renders one frame of `world` for each camera in `cameras`, i.e. to render an animation
+    /// of a camera moving through a static scene
+    ///
+    /// # Returns
+    /// the image of each frame, in the same order as `cameras`
+    pub fn render_animation(self, cameras: Vec<Camera>, world: HittableList) -> Vec<Vec<Color>> {
+        cameras
+            .into_iter()
+            .map(|camera| self.clone().render_buffer(camera, world.clone()))
+            .collect()
+    }
+
+    /// Renders an animation of moving geometry, one frame for each camera in `cameras`, where
+    /// `worlds` holds the scene at each frame. Instead of building a new BVH for every frame, a
+    /// `RefitBvh` is built from the first frame's scene and then refit to each following frame,
+    /// which is much cheaper when the objects only move a little between frames.
+    ///
+    /// Every world must hold the same objects, in the same order, as the first world.
+    ///
+    /// # Returns
+    /// the image of each frame, in the same order as `cameras`
+    ///
+    /// # Panics
+    /// if there is not exactly one world per camera, or if the worlds do not all have the same
+    /// number of objects
+    pub fn render_animated_world(
+        self,
+        cameras: Vec<Camera>,
+        worlds: Vec<HittableList>,
+    ) -> Vec<Vec<Color>> {
+        assert_eq!(
+            cameras.len(),
+            worlds.len(),
+            "there must be one world per camera"
+        );
+        let mut bvh: Option<RefitBvh> = None;
+        cameras
+            .into_iter()
+            .zip(worlds)
+            .map(|(camera, mut world)| {
+                let frame_bvh = match bvh.as_mut() {
+                    Some(frame_bvh) => {
+                        frame_bvh.refit(&mut world, 0.0, 1.0);
+                        frame_bvh
+                    }
+                    None => bvh.insert(RefitBvh::from(&mut world, 0.0, 1.0)),
+                };
+                let world: Arc<dyn Hittable> = Arc::new(frame_bvh.clone());
+                let mut image =
+                    vec![Color::default(); (camera.image_width * camera.image_height) as usize];
+                self.clone().render_world_pixels(
+                    camera,
+                    world,
+                    None,
+                    &mut image,
+                    None,
+                    &mut |_, _, _, _| {},
+                );
+                image
+            })
+            .collect()
+    }
+
+    /// Renders a turntable of `world`, `frames` views of a camera orbiting the `target` point at
+    /// the given `radius` and `elevation` (in degrees). The camera circles the target once, and
+    /// every other camera setting (such as the resolution and field of view) is taken from
+    /// `camera`. The last frame stops one step short of the first, so that the frames loop
+    ///
+    /// # Returns
+    /// the image of each frame, in order, as returned by `render_animation`
+    pub fn render_turntable(
+        self,
+        camera: CameraBuilder,
+        world: HittableList,
+        target: Point3,
+        radius: f64,
+        elevation: f64,
+        frames: u32,
+    ) -> Vec<Vec<Color>> {
+        let mut camera = camera;
+        let cameras: Vec<Camera> = (0..frames)
+            .map(|frame| {
+                let azimuth = 360.0 * frame as f64 / frames as f64;
+                camera.on_orbit(target, radius, azimuth, elevation).build()
+            })
+            .collect();
+        self.render_animation(cameras, world)
+    }
+
+    /// Renders a turntable of `world`, like `render_turntable`, from an elevation of 20 degrees,
+    /// and writes it to an animated GIF file at `path`
+    pub fn render_turntable_gif(
+        self,
+        mut camera: CameraBuilder,
+        world: HittableList,
+        target: Point3,
+        radius: f64,
+        frames: u32,
+        path: impl AsRef<Path>,
+    ) -> image::ImageResult<()> {
+        let (width, height) = {
+            let built = camera.build();
+            (built.image_width, built.image_height)
+        };
+        let images =
+            self.render_turntable(camera, world, target, radius, TURNTABLE_ELEVATION, frames);
+        gif::write_file(path, width, height, &images, TURNTABLE_FRAME_DELAY_MS)
+    }
+
+    /// Renders a stereoscopic pair of images, for VR or 3D displays, from two cameras that are
+    /// offset from `camera` along its horizontal (right) axis by `-eye_separation / 2` and
+    /// `+eye_separation / 2`. Both eyes keep the orientation of `camera`, so they look in
+    /// parallel directions.
+    ///
+    /// # Returns
+    /// a tuple holding the `(left, right)` eye images, each in the same layout as `render`
+    pub fn render_stereo(
+        self,
+        camera: Camera,
+        world: HittableList,
+        eye_separation: f64,
+    ) -> (Vec<Color>, Vec<Color>) {
+        let half_separation = 0.5 * eye_separation * camera.u;
+        let left = self
+            .clone()
+            .render_buffer(camera.translated(-half_separation), world.clone());
+        let right = self.render_buffer(camera.translated(half_separation), world);
+        (left, right)
+    }
+
+    /// Renders a quick preview of the shadows cast by a point light located at `light`.
+    /// A single primary ray is traced per pixel, and a single shadow ray is then traced from
+    /// the surface that was hit towards the light. Materials and emission are ignored.
+    ///
+    /// # Returns
+    /// a Vector holding the visibility of the light for each pixel, in the same layout as
+    /// `render`: `1.0` if the light is visible from the pixel's surface (or if nothing was hit),
+    /// and `0.0` if the surface is shadowed. The visibility is data rather than a color, so it
+    /// is not converted to the renderer's `output_color_space`
+    pub fn render_occlusion(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        light: Point3,
+    ) -> Vec<f64> {
+        self.pass = RenderPass::Occlusion(light);
+        self.samples_per_pixel = 1;
+        self.render_buffer(camera, world)
+            .into_iter()
+            .map(|visibility| visibility.x())
+            .collect()
+    }
+
+    /// Renders the depth of each pixel, the distance from the camera to the surface seen
+    /// through the pixel, i.e. for post-processing with `util::postprocess::depth_of_field`.
+    /// A single sample is taken per pixel, and pixels that see nothing have an infinite depth
+    ///
+    /// # Returns
+    /// the depth of each pixel, in the same layout as `render`
+    pub fn render_depth(self, camera: Camera, world: HittableList) -> Vec<f64> {
+        self.render_depth_as(camera, world, DepthMode::Raw)
+    }
+
+    /// Renders the depth of each pixel, like `render_depth`, expressed in the given depth `mode`
+    ///
+    /// # Returns
+    /// the depth of each pixel, in the same layout as `render`
+    pub fn render_depth_as(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        mode: DepthMode,
+    ) -> Vec<f64> {
+        self.pass = RenderPass::Depth;
+        self.samples_per_pixel = 1;
+        self.anti_alias = AntiAlias::Stochastic;
+        self.jitter_pattern = None;
+        self.render_buffer(camera, world)
+            .into_iter()
+            .map(|depth| mode.map(depth.x()))
+            .collect()
+    }
+
+    /// Renders a "clay" image of the scene, where every surface that does not emit light is
+    /// given the same neutral, diffuse, material of `clay_color`, in order to evaluate the
+    /// lighting and form of a scene without being distracted by its materials. Lights keep
+    /// their own material, so the scene is lit the same way as it is by `render`
+    pub fn render_clay(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        clay_color: Color,
+    ) -> Vec<Color> {
+        let clay = Arc::new(SolidColor::from(clay_color));
+        self.clay = Some(Arc::new(Lambertian::new(clay)));
+        self.render_buffer(camera, world)
+    }
+
+    /// Renders only the light emitted by the surfaces seen by the camera, ignoring all of the
+    /// light they scatter, to isolate the lights of a scene, i.e. for compositing glow effects
+    /// or checking the placement of lights. Pixels that see nothing receive the background
+    /// color, as the background is the light of the sky.
+    ///
+    /// # Returns
+    /// the emission of each pixel, gamma corrected and scaled in the same way as
+    /// `Image::display_colors`
+    pub fn render_emission(mut self, camera: Camera, world: HittableList) -> Vec<Color> {
+        self.pass = RenderPass::Emission;
+        self.render_buffer(camera, world)
+    }
+
+    /// Renders the first-hit albedo and the incident irradiance of each pixel as separate
+    /// buffers, for relighting workflows where the lighting is adjusted in post. The linear
+    /// radiance returned by `render_radiance` is reconstructed, for diffuse surfaces, by
+    /// multiplying the two buffers together.
+    ///
+    /// The albedo of a pixel is the color its material applies to the light it scatters, and
+    /// the irradiance is the light gathered along the first bounce. Pixels that see an emitter,
+    /// or nothing at all, have a white albedo so that their irradiance holds the emitted (or
+    /// background) light. Both buffers are linear, and are **not** color space converted,
+    /// gamma corrected or scaled.
+    ///
+    /// # Returns
+    /// a tuple holding the albedo and irradiance of each pixel, both in the same layout as
+    /// `render`
+    pub fn render_albedo_irradiance(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+    ) -> (Vec<Color>, Vec<Color>) {
+        self.pass = RenderPass::Albedo;
+        let albedo = self.clone().render_buffer(camera, world.clone());
+        self.pass = RenderPass::Irradiance;
+        let irradiance = self.render_buffer(camera, world);
+        (albedo, irradiance)
+    }
+
+    /// Renders the surface normals seen by the camera's primary rays, expressed in camera
+    /// (view) space, as used by deferred relighting pipelines. The camera space x-axis points
+    /// to the right of the image, the y-axis points up, and the z-axis points back towards the
+    /// camera, so a surface facing the camera has a normal of `(0,0,1)`.
+    ///
+    /// # Returns
+    /// a Vector holding the unit normal of each pixel, in the same layout as `render`.
+    /// The normals are **not** gamma corrected or scaled. Pixels that do not hit anything
+    /// have a zero normal
+    pub fn render_normals_view_space(mut self, camera: Camera, world: HittableList) -> Vec<Color> {
+        self.pass = RenderPass::ViewSpaceNormals;
+        self.render_buffer(camera, world)
+    }
+
+    /// Renders an image using the provided `Camera` and `World`, storing the final pixel
+    /// colors into the caller provided `image` buffer (in row major format).
+    ///
+    /// # Panics
+    /// if the length of `image` is not equal to `camera.image_width * camera.image_height`
+    pub fn render_into(self, camera: Camera, world: HittableList, image: &mut [Color]) {
+        self.render_pixels(camera, world, None, image, None, &mut |_, _, _, _| {});
+    }
+
+    /// Renders only the pixels of the image that are enabled in the per-pixel `mask`.
+    /// Pixels whose mask value is `false` are skipped and keep whatever color was previously
+    /// stored in the `image` buffer. This can be used to re-render a fixed region of an
+    /// image, for example to refine only the noisy areas of an earlier render.
+    ///
+    /// `mask` and `image` are both in row major format, one entry per pixel.
+    ///
+    /// # Panics
+    /// if the length of `mask` or `image` is not equal to
+    /// `camera.image_width * camera.image_height`
+    pub fn render_masked(
+        self,
+        camera: Camera,
+        world: HittableList,
+        mask: &[bool],
+        image: &mut [Color],
+    ) {
+        self.render_pixels(camera, world, Some(mask), image, None, &mut |_, _, _, _| {});
+    }
+
+    /// Renders a batch of `jobs`, each consisting of a `Camera` and the world to render with it,
+    /// and returns the rendered images in the same order as the jobs.
+    ///
+    /// This is intended to be driven from an interactive application:
+    /// `on_progress` is called, from the render threads, after every finished tile with the
+    /// overall progress of the entire batch, in the range `0.0..=1.0`.
+    /// Setting `cancel` to `true` (from any thread) aborts the batch. The tiles that have
+    /// not started rendering yet are skipped, and only the images of the jobs that were
+    /// completely rendered before the cancellation are returned.
+    pub fn render_batch_interactive<F: FnMut(f32) + Send>(
+        self,
+        jobs: Vec<(Camera, HittableList)>,
+        cancel: Arc<AtomicBool>,
+        mut on_progress: F,
+    ) -> Vec<Vec<Color>> {
+        let job_count = jobs.len();
+        let mut images: Vec<Vec<Color>> = Vec::with_capacity(job_count);
+
+        for (job, (camera, world)) in jobs.into_iter().enumerate() {
+            if cancel.load(Ordering::SeqCst) {
+                break;
+            }
+            let pixel_count = (camera.image_width * camera.image_height) as usize;
+            let mut image: Vec<Color> = vec![Color::default(); pixel_count];
+            let mut pixels_finished = 0;
+            let completed = self.clone().render_pixels(
+                camera,
+                world,
+                None,
+                &mut image,
+                Some(&cancel),
+                &mut |tile, _, _, _| {
+                    pixels_finished += tile.pixel_count();
+                    let job_progress = pixels_finished as f32 / pixel_count as f32;
+                    on_progress((job as f32 + job_progress) / job_count as f32);
+                },
+            );
+
+            if !completed {
+                break;
+            }
+            images.push(image);
+        }
+
+        images
+    }
+
+    /// Renders a ground truth reference image, for validating faster sampling strategies
+    /// against. Instead of taking a fixed number of samples, every pixel is sampled until the
+    /// estimated standard error of its luminance falls to `target_error` or below, with no
+    /// limit on the number of samples. Every pixel takes at least 16 samples, and the samples
+    /// are placed by the stratified, low-discrepancy, Halton sampler, so the anti-aliasing
+    /// strategy and jitter pattern of this renderer are ignored.
+    ///
+    /// Pixels whose samples vary a lot, such as those that see small, bright, lights, can take
+    /// a very long time to converge to a small `target_error`.
+    ///
+    /// # Returns
+    /// a tuple holding the image, in the same format as `Image::display_colors`, and the
+    /// statistics of the render
+    pub fn render_reference(
+        mut self,
+        camera: Camera,
+        world: HittableList,
+        target_error: f64,
+    ) -> (Vec<Color>, ReferenceStats) {
+        self.target_error = Some(target_error);
+        self.anti_alias = AntiAlias::Stochastic;
+        self.jitter_pattern = None;
+        let now = Instant::now();
+        let mut image: Vec<Color> =
+            vec![Color::default(); (camera.image_width * camera.image_height) as usize];
+        let mut stats = ReferenceStats::default();
+        self.render_pixels(
+            camera,
+            world,
+            None,
+            &mut image,
+            None,
+            &mut |_, _, _, tile_stats| {
+                for pixel in tile_stats {
+                    stats.total_samples += pixel.samples as u64;
+                    stats.max_standard_error = stats.max_standard_error.max(pixel.standard_error());
+                }
+            },
+        );
+        stats.elapsed = now.elapsed();
+        println!(
+            "reference render took {} samples in {:.3} secs, max standard error {}",
+            stats.total_samples,
+            stats.elapsed.as_secs_f64(),
+            stats.max_standard_error
+        );
+        (image, stats)
+    }
+
+    /// Renders an image progressively, in passes of 16 samples per pixel, so that a noisy
+    /// image can be watched while it converges. The passes keep going until the samples per
+    /// pixel of this renderer (or of its jitter pattern or supersampling grid) have all been
+    /// taken.
+    ///
+    /// After every pass `on_pass` is called with the image converged so far, in the same format
+    /// as `Image::display_colors`, and the number of samples per pixel it holds. Returning `false` from
+    /// `on_pass` stops the render early, i.e. once the image already looks good enough.
+    ///
+    /// # Returns
+    /// the image of the last finished pass
+    pub fn render_progressive<F: FnMut(&[Color], u32) -> bool>(
+        mut self,
+        camera: Camera,
+        mut world: HittableList,
+        mut on_pass: F,
+    ) -> Vec<Color> {
+        self.pass = RenderPass::Radiance;
+        let pixel_count = (camera.image_width * camera.image_height) as usize;
+        let total_samples = self.pixel_sample_count();
+        // the acceleration structure is built once, and shared by every pass
+        let world = self.build_accelerator(&mut world);
+
+        // the running sums of the (linear) radiance of every sample taken by each pixel
+        let mut sums = vec![Color::default(); pixel_count];
+        let mut image = vec![Color::default(); pixel_count];
+        let mut samples_taken = 0;
+        while samples_taken < total_samples {
+            let pass_samples = PROGRESSIVE_PASS_SAMPLES.min(total_samples - samples_taken);
+            let mut renderer = self.clone();
+            renderer.sample_window = Some(samples_taken..samples_taken + pass_samples);
+            if let Some(seed) = self.seed {
+                // every pass needs its own random numbers, or it would repeat the first pass
+                let pass = (samples_taken / PROGRESSIVE_PASS_SAMPLES) as u64;
+                renderer.seed = Some(seed ^ (pass + 1).wrapping_mul(0xD1B5_4A32_D192_ED03));
+            }
+
+            let mut frame = vec![Color::default(); pixel_count];
+            renderer.render_world_pixels(
+                camera,
+                Arc::clone(&world),
+                None,
+                &mut frame,
+                None,
+                &mut |_, _, _, _| {},
+            );
+            samples_taken += pass_samples;
+            for ((sum, color), average) in sums.iter_mut().zip(image.iter_mut()).zip(frame) {
+                *sum += average * pass_samples as f64;
+                *color = Renderer::display_color(&self.tone_map, sum, samples_taken);
+            }
+
+            if !on_pass(&image, samples_taken) {
+                break;
+            }
+        }
+        image
+    }
+
+    /// Renders the pixels enabled by the (optional) `mask` into the `image` buffer.
+    /// If `mask` is `None` every pixel of the image is rendered.
+    /// The image is split into square tiles of `tile_size` pixels, which are rendered by the
+    /// worker threads in any order.
+    /// `on_tile` is called, from the render thread that rendered it, with each tile, the colors
+    /// of its pixels, the time it took to render, and the sampling statistics of each of its
+    /// pixels (both in row major order), once it is finished and copied into the image.
+    /// If the (optional) `cancel` flag is set while rendering, the tiles that have not
+    /// started rendering are skipped.
+    ///
+    /// Returns `true` if every tile was rendered, or `false` if the render was canceled
+    fn render_pixels(
+        self,
+        camera: Camera,
+        mut world: HittableList,
+        mask: Option<&[bool]>,
+        image: &mut [Color],
+        cancel: Option<&Arc<AtomicBool>>,
+        on_tile: &mut TileCallback,
+    ) -> bool {
+        let world = self.build_accelerator(&mut world);
+        self.render_world_pixels(camera, world, mask, image, cancel, on_tile)
+    }
+
+    /// Places the `world` in this renderer's acceleration structure, and reports how long it
+    /// took to build
+    ///
+    /// # Panics
+    /// if the acceleration structure is estimated to need more than the renderer's memory limit
+    fn build_accelerator(&self, world: &mut HittableList) -> Arc<dyn Hittable> {
+        if let Err(e) = self.check_memory_limit(world) {
+            panic!("{}", e);
+        }
+        match self.accelerator {
+            Accelerator::Bvh => {
+                // the halves of a large BVH are built in parallel, on this renderer's threads
+                let bvh = self.pool.install(|| FlatBvh::build_auto(world, 0.0, 1.0));
+                let stats = bvh.build_stats();
+                println!(
+                    "built a BVH of {} objects in {:.3} secs. nodes={}  depth={}",
+                    stats.object_count,
+                    stats.elapsed.as_secs_f64(),
+                    stats.node_count,
+                    stats.depth
+                );
+                Arc::new(bvh)
+            }
+            Accelerator::Grid => {
+                let now = Instant::now();
+                let grid = UniformGrid::from(world, 0.0, 1.0);
+                let [x, y, z] = grid.resolution();
+                println!(
+                    "built a grid of {} objects in {:.3} secs. resolution={}x{}x{}",
+                    world.len(),
+                    now.elapsed().as_secs_f64(),
+                    x,
+                    y,
+                    z
+                );
+                Arc::new(grid)
+            }
+            Accelerator::KdTree => {
+                let now = Instant::now();
+                let kd_tree = KdTree::from(world, 0.0, 1.0);
+                println!(
+                    "built a kd-tree of {} objects in {:.3} secs. nodes={}",
+                    world.len(),
+                    now.elapsed().as_secs_f64(),
+                    kd_tree.node_count()
+                );
+                Arc::new(kd_tree)
+            }
+        }
+    }
+
+    /// Renders the pixels of a `world` that has already been placed in an acceleration
+    /// structure (such as a BVH), like `render_pixels`
+    fn render_world_pixels(
+        self,
+        camera: Camera,
+        world: Arc<dyn Hittable>,
+        mask: Option<&[bool]>,
+        image: &mut [Color],
+        cancel: Option<&Arc<AtomicBool>>,
+        on_tile: &mut TileCallback,
+    ) -> bool {
+        let pixel_count = (camera.image_width * camera.image_height) as usize;
+        assert_eq!(
+            image.len(),
+            pixel_count,
+            "image buffer length must equal image_width * image_height"
+        );
+        if let Some(mask) = mask {
+            assert_eq!(
+                mask.len(),
+                pixel_count,
+                "mask length must equal image_width * image_height"
+            );
+        }
+
+        let now = Instant::now();
+        println!(
+            "rendering a {}x{} image. threads={}  bounce_depth={}  samples_per_pixel={}",
+            &camera.image_width,
+            &camera.image_height,
+            &self.num_workers,
+            &self.ray_bounce_depth,
+            &self.samples_per_pixel
+        );
+
+        let width = camera.image_width as usize;
+        let tiles = Tile::split(camera.image_width, camera.image_height, self.tile_size);
+        let tile_count = tiles.len();
+
+        // the tiles are rendered in parallel on this renderer's (reusable) thread pool, whose
+        // idle threads steal tiles from busy ones, so a few slow tiles (i.e. the tiles around a
+        // light) do not hold up the rest of the image. Each worker copies its finished tile
+        // straight into the image, and reports it to `on_tile`
+        println!(
+            "rendering {} tiles with a thread pool size = {}",
+            tile_count, self.num_workers
+        );
+        let completed = AtomicBool::new(true);
+        let tile_durations: Mutex<Vec<(Tile, Duration)>> = Mutex::new(vec![]);
+        let image = Mutex::new(image);
+        let on_tile = Mutex::new(on_tile);
+        self.pool.install(|| {
+            tiles.into_par_iter().for_each(|tile| {
+                // skip rendering if the render was canceled before this tile started
+                if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+                    completed.store(false, Ordering::SeqCst);
+                    return;
+                }
+                let tile_mask: Option<Vec<bool>> = mask.map(|m| {
+                    tile.pixels()
+                        .map(|(col, row)| m[row as usize * width + col as usize])
+                        .collect()
+                });
+                let start = Instant::now();
+                let (tile_colors, tile_stats) =
+                    self.render_tile(&tile, &*world, &camera, tile_mask.as_deref());
+                let duration = start.elapsed();
+
+                {
+                    let mut image = image.lock().expect("a render thread panicked");
+                    for ((col, row), color) in tile.pixels().zip(&tile_colors) {
+                        let idx = row as usize * width + col as usize;
+                        if mask.is_none_or(|m| m[idx]) {
+                            image[idx] = *color;
+                        }
+                    }
+                }
+                let finished = {
+                    let mut tile_durations =
+                        tile_durations.lock().expect("a render thread panicked");
+                    tile_durations.push((tile, duration));
+                    tile_durations.len()
+                };
+                println!("tile {} of {} finished...", finished, tile_count);
+                (on_tile.lock().expect("a render thread panicked"))(
+                    &tile,
+                    &tile_colors,
+                    duration,
+                    &tile_stats,
+                );
+            });
+        });
+        println!(
+            "done rendering, total elapsed {:.3} secs",
+            now.elapsed().as_secs_f64()
+        );
+        if let Some(slowest_count) = self.watchdog {
+            let tile_durations = tile_durations
+                .into_inner()
+                .expect("a render thread panicked");
+            Renderer::report_slowest_tiles(tile_durations, slowest_count);
+        }
+
+        completed.into_inner()
+    }
+
+    /// Prints the `slowest_count` slowest tiles in `tile_durations`, along with the range of
+    /// pixel coordinates in each tile
+    fn report_slowest_tiles(mut tile_durations: Vec<(Tile, Duration)>, slowest_count: usize) {
+        tile_durations.sort_unstable_by_key(|&(_, duration)| std::cmp::Reverse(duration));
+        println!("watchdog: the {} slowest tiles were", slowest_count);
+        for (tile, duration) in tile_durations.into_iter().take(slowest_count) {
+            println!(
+                "  tile pixels ({},{})..({},{}) took {:.3} secs",
+                tile.x,
+                tile.y,
+                tile.x + tile.width - 1,
+                tile.y + tile.height - 1,
+                duration.as_secs_f64()
+            );
+        }
+    }
+
+    /// Computes the colors of the pixels in a `tile` of the image.
+    /// `tile_mask` optionally selects which pixels of the tile are rendered, pixels that are
+    /// masked out are not traced and are returned as black
+    /// Returns a tuple of Vectors containing the final pixel colors of the tile, and the sampling
+    /// statistics of each pixel, both in row major order
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        world: &dyn Hittable,
+        camera: &Camera,
+        tile_mask: Option<&[bool]>,
+    ) -> (Vec<Color>, Vec<PixelStats>) {
+        // the worker's own generator is restored once the tile is done, so that a seeded render
+        // does not leave the pool's threads repeating the same random numbers
+        let _seed_guard = self.seed.map(|seed| {
+            // mix the index of the tile's first pixel into the seed, so that neighboring tiles
+            // get unrelated random numbers
+            let first_pixel = tile.y as u64 * camera.image_width as u64 + tile.x as u64;
+            RngContext::seed_scoped(seed ^ (first_pixel + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        });
+        let mut colors: Vec<Color> = Vec::with_capacity(tile.pixel_count());
+        let mut stats: Vec<PixelStats> = Vec::with_capacity(tile.pixel_count());
+        // the pixel and lens offsets of each sample are drawn from one 4D sampler, so that
+        // defocus blur converges along with the anti-aliasing
+        let mut sampler = self.sampler.sampler();
+
+        for (i, (col, row)) in tile.pixels().enumerate() {
+            let mut pixel_color = Color::default();
+
+            if tile_mask.is_some_and(|m| !m[i]) {
+                colors.push(pixel_color);
+                stats.push(PixelStats::default());
+                continue;
+            }
+
+            // running sums of the luminance, and squared luminance, of the samples
+            let (mut luminance_sum, mut luminance_sq_sum) = (0.0, 0.0);
+            sampler.reseed();
+            let first_sample = self.sample_window.as_ref().map_or(0, |window| window.start);
+            let mut sample_count = 0;
+            while !self.pixel_converged(sample_count, luminance_sum, luminance_sq_sum) {
+                let i = first_sample + sample_count;
+                sample_count += 1;
+                let [pixel_u, pixel_v, lens_u, lens_v] = self.pixel_sample(sampler.as_ref(), i);
+                // u,v are offsets that choose a point close to the current pixel
+                let u = (col as f64 + pixel_u) / (camera.image_width - 1) as f64;
+                let v = (row as f64 + pixel_v) / (camera.image_height - 1) as f64;
+
+                let r: Ray = camera.get_ray_with_lens(u, v, lens_u, lens_v);
+
+                let sample_color = match self.pass {
+                    RenderPass::Color | RenderPass::Radiance => {
+                        self.integrator.ray_color(self, &r, world)
+                    }
+                    RenderPass::ViewSpaceNormals => Renderer::view_space_normal(&r, world, camera),
+                    RenderPass::Occlusion(light) => Renderer::light_visibility(&r, world, &light),
+                    RenderPass::MotionVectors => Renderer::motion_vector(&r, world, camera),
+                    RenderPass::Depth => Renderer::depth(&r, world),
+                    RenderPass::Albedo => Renderer::albedo(&r, world),
+                    RenderPass::Irradiance => self.irradiance(&r, world),
+                    RenderPass::Emission => self.emission(&r, world),
+                    RenderPass::Normals => Renderer::normal(&r, world),
+                    RenderPass::ObjectId => Renderer::object_id(&r, world),
+                };
+                let luminance = sample_color.luminance();
+                luminance_sum += luminance;
+                luminance_sq_sum += luminance * luminance;
+                pixel_color += sample_color;
+            }
+            stats.push(PixelStats {
+                variance: Renderer::sample_variance(luminance_sum, luminance_sq_sum, sample_count),
+                samples: sample_count,
+            });
+            let pixel_color = match self.pass {
+                RenderPass::Color | RenderPass::Emission => {
+                    let pixel_color = self.output_color_space.convert(pixel_color);
+                    Renderer::display_color(&self.tone_map, &pixel_color, sample_count)
+                }
+                RenderPass::Radiance => {
+                    self.output_color_space.convert(pixel_color) / sample_count as f64
+                }
+                // the auxiliary passes hold data, not colors, so they skip the color space
+                RenderPass::Occlusion(_)
+                | RenderPass::MotionVectors
+                | RenderPass::Depth
+                | RenderPass::Albedo
+                | RenderPass::Irradiance
+                | RenderPass::ObjectId => pixel_color / sample_count as f64,
+                // average the normals of every sample
+                RenderPass::ViewSpaceNormals | RenderPass::Normals => match pixel_color.length() {
+                    len if len > 0.0 => pixel_color / len,
+                    _ => pixel_color,
+                },
+            };
+            colors.push(pixel_color);
+        }
+        (colors, stats)
+    }
+
+    /// Returns true once a pixel has taken enough samples, given the number of samples taken
+    /// so far, and the sum of their luminances and squared luminances. A reference render
+    /// samples each pixel until its standard error falls below the target error, a progressive
+    /// pass takes the samples in its window, otherwise a fixed number of samples are taken
+    fn pixel_converged(&self, samples: u32, luminance_sum: f64, luminance_sq_sum: f64) -> bool {
+        match (self.target_error, &self.sample_window) {
+            (Some(target_error), _) => {
+                let stats = PixelStats {
+                    variance: Renderer::sample_variance(luminance_sum, luminance_sq_sum, samples),
+                    samples,
+                };
+                samples >= REFERENCE_MIN_SAMPLES && stats.standard_error() <= target_error
+            }
+            (None, Some(window)) => samples >= window.len() as u32,
+            (None, None) => samples >= self.pixel_sample_count(),
+        }
+    }
+
+    /// Returns the unbiased sample variance of `count` samples, given the `sum` of the samples
+    /// and the sum of their squares, `sq_sum`. Fewer than two samples have no variance
+    fn sample_variance(sum: f64, sq_sum: f64, count: u32) -> f64 {
+        if count < 2 {
+            return 0.0;
+        }
+        let count = count as f64;
+        ((sq_sum - sum * sum / count) / (count - 1.0)).max(0.0)
+    }
+
+    /// Returns the number of samples taken per pixel by this renderer's jitter pattern, or
+    /// anti-aliasing strategy
+    fn pixel_sample_count(&self) -> u32 {
+        if let Some(pattern) = &self.jitter_pattern {
+            return pattern.len() as u32;
+        }
+        match self.anti_alias {
+            AntiAlias::Stochastic => self.samples_per_pixel,
+            AntiAlias::Supersample { factor } => factor.max(1) * factor.max(1),
+        }
+    }
+
+    /// Returns the pixel and lens offsets of the `index`th sample of a pixel. A jitter pattern
+    /// takes precedence over the anti-aliasing strategy, and `sampler` is only used by
+    /// stochastic anti-aliasing
+    fn pixel_sample(&self, sampler: &dyn Sampler, index: u32) -> [f64; 4] {
+        if let Some(pattern) = &self.jitter_pattern {
+            let (pixel_u, pixel_v) = pattern[index as usize];
+            return [pixel_u, pixel_v, halton(index, 5), halton(index, 7)];
+        }
+        match self.anti_alias {
+            AntiAlias::Stochastic => sampler.sample(index),
+            AntiAlias::Supersample { factor } => Renderer::grid_sample(index, factor),
+        }
+    }
+
+    /// Returns the `index`th sample of a regular `factor x factor` supersampling grid, as the
+    /// pixel offset followed by the lens offset. The lens offsets are taken from the Halton
+    /// sequence, without any random offset, so the samples are the same for every pixel
+    fn grid_sample(index: u32, factor: u32) -> [f64; 4] {
+        let factor = factor.max(1);
+        let cell = 1.0 / factor as f64;
+        [
+            ((index % factor) as f64 + 0.5) * cell,
+            ((index / factor) as f64 + 0.5) * cell,
+            halton(index, 5),
+            halton(index, 7),
+        ]
+    }
+
+    /// Returns the color of the background seen by a `ray` that hit nothing. `primary` is true
+    /// for rays cast from the camera, which are the only rays that see the backdrop of a
+    /// `Studio` background
+    pub fn background(&self, ray: &Ray, primary: bool) -> Color {
+        match &self.background_color {
+            BackgroundColor::Solid(color) => *color,
+            BackgroundColor::LinearInterp(from, to) => Renderer::linear_blend(ray, from, to),
+            // only primary (camera) rays see the backdrop
+            BackgroundColor::Studio(backdrop, _, _) if primary => *backdrop,
+            BackgroundColor::Studio(_, from, to) => Renderer::linear_blend(ray, from, to),
+            BackgroundColor::Sky(sky) => sky.value(&ray.direction()),
+            BackgroundColor::Environment(image) => Renderer::environment_color(ray, image),
+        }
+    }
+
+    // /// determine if a Ray has hit a `Hittable` object in the `world` and compute the pixel color
+    // /// of the Ray, `r`. The Hittable's `Material` is taken into account when performing ray bouncing
+    // /// (up to `MAX_RAY_BOUNCE_DEPTH` times) in order to get an accurate color determination. If nothing
+    // /// was hit then the `background` color is returned, than a linearly blended "sky" color is returned
+    // fn ray_color<T: Hittable + ?Sized>(&self, ray: &Ray, world: &T, mut depth: u32) -> Color {
+    //     // The original book algorithm used a recursive solution, I've converted it to iterative
+    //     // in order to get some speed gains
+    //
+    //     // holds (emittedColor, attenuatedColor) for each ray bounce
+    //     type ColorRec = (Color, Option<Color>);
+    //     // holds the ray color for each object hit
+    //     let mut stack: Vec<ColorRec> = vec![];
+    //     let mut ray = ray.clone();
+    //
+    //     loop {
+    //         if depth == 0 {
+    //             stack.push((Color::default(), None));
+    //             break;
+    //         } else {
+    //             // if a hittable was hit, determine if its material will scatter the incoming
+    //             // ray, AND how much light the material emits
+    //             if let Some(ref rec) = world.hit(&ray, 0.001, f64::INFINITY) {
+    //                 let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+    //
+    //                 if let Some(scatter_rec) = rec.mat_ptr.scatter(&ray, rec) {
+    //                     stack.push((emitted, Some(scatter_rec.attenuation)));
+    //                     ray = scatter_rec.scattered.to_owned();
+    //                     depth -= 1;
+    //                 } else {
+    //                     stack.push((emitted, None));
+    //                     break;
+    //                 }
+    //             } else {
+    //                 // nothing hit, return the background color
+    //                 match self.background_color {
+    //                     BackgroundColor::Solid(color) => stack.push((color, None)),
+    //                     BackgroundColor::LinearInterp(from, to) => stack.push((Renderer::linear_blend(&ray, &from, &to), None)),
+    //                 }
+    //                 break;
+    //             }
+    //         }
+    //     }
+    //     // now compute the final ray color based on data stored in the stack
+    //     let mut final_color = stack.pop().expect("color stack has at least one color").0;
+    //     for curr in stack.into_iter().rev() {
+    //         if let Some(att) = curr.1 {
+    //             final_color = curr.0 + att * final_color;
+    //         } else {
+    //             final_color = curr.0 + final_color;
+    //         }
+    //     }
+    //     final_color
+    // }
+
+    /// Returns the closest hit of `ray` that is visible to it, skipping over the hittables that
+    /// are invisible to primary rays (if `primary` is true) or to secondary rays, see
+    /// `Visibility`. Integrators use this instead of `Hittable::hit` to find the surfaces a ray
+    /// sees
+    pub fn visible_hit<T: Hittable + ?Sized>(
+        ray: &Ray,
+        world: &T,
+        primary: bool,
+    ) -> Option<HitRecord> {
+        Renderer::visible_hit_within(ray, world, primary, f64::INFINITY)
+    }
+
+    /// Returns the closest hit of `ray` that is visible to it, and closer than `t_max`, i.e.
+    /// to find the hittables blocking a shadow ray towards a light at `t_max`
+    pub fn visible_hit_within<T: Hittable + ?Sized>(
+        ray: &Ray,
+        world: &T,
+        primary: bool,
+        t_max: f64,
+    ) -> Option<HitRecord> {
+        let mut t_min = 0.001;
+        loop {
+            let rec = world.hit(ray, t_min, t_max)?;
+            if rec.is_visible_to(primary) {
+                return Some(rec);
+            }
+            // continue the ray past the invisible hittable
+            t_min = rec.t + 0.001;
+        }
+    }
+
+    /// Returns the normal of the surface hit by the primary `ray`, rotated from world space into
+    /// the camera's `(u, v, w)` basis, or a zero normal if nothing was hit
+    fn view_space_normal<T: Hittable + ?Sized>(ray: &Ray, world: &T, camera: &Camera) -> Vec3 {
+        match Renderer::visible_hit(ray, world, true) {
+            Some(rec) => {
+                // w points from the camera's look_at point back towards the camera
+                let w = camera.u.cross(camera.v);
+                Vec3::new(
+                    rec.normal.dot(&camera.u),
+                    rec.normal.dot(&camera.v),
+                    rec.normal.dot(&w),
+                )
+            }
+            None => Vec3::default(),
+        }
+    }
+
+    /// Returns the screen-space displacement of the surface hit by the primary `ray`, between
+    /// the camera's shutter open and close times, as the `x,y` components of a `Vec3`
+    fn motion_vector<T: Hittable + ?Sized>(ray: &Ray, world: &T, camera: &Camera) -> Vec3 {
+        let rec = match Renderer::visible_hit(ray, world, true) {
+            Some(rec) if rec.velocity != Vec3::default() => rec,
+            _ => return Vec3::default(),
+        };
+        // the positions of the hit point when the shutter opened and closed
+        let open = rec.p - rec.velocity * (ray.time() - camera.open_time);
+        let close = rec.p + rec.velocity * (camera.close_time - ray.time());
+        match (camera.project(open), camera.project(close)) {
+            (Some((x0, y0)), Some((x1, y1))) => Vec3::new(x1 - x0, y1 - y0, 0.0),
+            _ => Vec3::default(),
+        }
+    }
+
+    /// Returns the distance from the origin of the primary `ray` to the first surface it hits,
+    /// in every component of a `Vec3`, or infinity if the ray hits nothing
+    fn depth<T: Hittable + ?Sized>(ray: &Ray, world: &T) -> Vec3 {
+        let depth = match Renderer::visible_hit(ray, world, true) {
+            Some(rec) => rec.t * ray.direction().length(),
+            None => f64::INFINITY,
+        };
+        Vec3::new(depth, depth, depth)
+    }
+
+    /// Returns the world space normal of the surface hit by the primary `ray`, facing the ray,
+    /// or a zero normal if nothing was hit
+    fn normal<T: Hittable + ?Sized>(ray: &Ray, world: &T) -> Vec3 {
+        Renderer::visible_hit(ray, world, true).map_or(Vec3::default(), |rec| rec.normal)
+    }
+
+    /// Returns the object id of the surface hit by the primary `ray`, in every channel, or `0`
+    /// if nothing was hit
+    fn object_id<T: Hittable + ?Sized>(ray: &Ray, world: &T) -> Color {
+        let id = Renderer::visible_hit(ray, world, true).map_or(0, |rec| rec.object_id);
+        Color::new(id as f64, id as f64, id as f64)
+    }
+
+    /// Returns the attenuation that the material of the surface hit by the primary `ray` applies
+    /// to the light it scatters, or white if the ray hits nothing or an emitter
+    fn albedo<T: Hittable + ?Sized>(ray: &Ray, world: &T) -> Color {
+        Renderer::visible_hit(ray, world, true)
+            .and_then(|rec| rec.mat_ptr.scatter(ray, &rec))
+            .map_or(Color::new(1.0, 1.0, 1.0), |scatter_rec| {
+                scatter_rec.attenuation
+            })
+    }
+
+    /// Returns the light arriving at the surface hit by the primary `ray`, gathered along the
+    /// ray scattered by its material (and the ambient light, if enabled and the surface is
+    /// diffuse). Rays that hit nothing, or an emitter, return the same color as a `PathTracer`
+    fn irradiance<T: Hittable + ?Sized>(&self, ray: &Ray, world: &T) -> Color {
+        let media = MediumStack::new();
+        let rec = Renderer::visible_hit(ray, world, true);
+        match rec.and_then(|rec| Some((rec.mat_ptr.scatter_in_medium(ray, &rec, &media)?, rec))) {
+            Some((scatter_rec, rec)) => {
+                let scattered_media = media.transmit(&rec, &scatter_rec.scattered);
+                let ambient = match scatter_rec.pdf {
+                    Some(_) => self.ambient_light(&rec, &Color::new(1.0, 1.0, 1.0)),
+                    None => Color::default(),
+                };
+                ambient
+                    + PathTracer.ray_color_in_medium(
+                        self,
+                        &scatter_rec.scattered,
+                        world,
+                        self.ray_bounce_depth.saturating_sub(1),
+                        self.volume_bounce_depth,
+                        scattered_media.as_ref().unwrap_or(&media),
+                    )
+            }
+            None => PathTracer.ray_color_at_depth(self, ray, world, self.ray_bounce_depth),
+        }
+    }
+
+    /// Returns the light emitted by the surface hit by the primary `ray`, without any of the
+    /// light it scatters. Rays that hit nothing return the same color as a `PathTracer`
+    fn emission<T: Hittable + ?Sized>(&self, ray: &Ray, world: &T) -> Color {
+        match Renderer::visible_hit(ray, world, true) {
+            Some(rec) => rec.mat_ptr.emitted(rec.u, rec.v, &rec.p),
+            None => PathTracer.ray_color_at_depth(self, ray, world, self.ray_bounce_depth),
+        }
+    }
+
+    /// Returns white if the point `light` is visible from the surface hit by the primary `ray`,
+    /// or black if another hittable lies between them. Rays that hit nothing are treated as lit
+    fn light_visibility<T: Hittable + ?Sized>(ray: &Ray, world: &T, light: &Point3) -> Color {
+        let lit = Color::new(1.0, 1.0, 1.0);
+        match Renderer::visible_hit(ray, world, true) {
+            // the shadow ray's direction spans from the hit point to the light, so the light
+            // is located at t=1
+            Some(rec) => {
+                match world.hit_any(&Ray::new(rec.p, *light - rec.p, ray.time()), 0.001, 1.0) {
+                    true => Color::default(),
+                    false => lit,
+                }
+            }
+            None => lit,
+        }
+    }
+
+    /// Returns the light of the `direct_lights` reflected by the hit `rec`, if its material `mat`
+    /// scattered the ray with a PDF. A shadow ray is cast towards each light, and the light's
+    /// contribution is weighted by the material's scattering PDF in the light's direction,
+    /// which is the cosine weighted BRDF of a diffuse material
+    pub fn direct_light<T: Hittable + ?Sized>(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        scatter_rec: &ScatterRecord,
+        world: &T,
+    ) -> Color {
+        if scatter_rec.pdf.is_none() {
+            return Color::default();
+        }
+        let mut direct = Color::default();
+        for light in self.direct_lights.iter() {
+            let sample = light.illuminate(&rec.p);
+            let shadow_ray = Ray::new(rec.p, sample.direction, ray.time());
+            let scattering_pdf = mat.scattering_pdf(ray, rec, &shadow_ray);
+            if scattering_pdf <= 0.0
+                || Renderer::visible_hit_within(&shadow_ray, world, false, sample.distance)
+                    .is_some()
+            {
+                continue;
+            }
+            direct += scatter_rec.attenuation * scattering_pdf * sample.radiance;
+        }
+        direct
+    }
+
+    /// Returns the hemispheric ambient light reflected by a hit with the given `attenuation`.
+    /// The ambient color is blended between the ground and sky colors using the y component
+    /// of the hit's normal. Returns black if ambient light is disabled
+    pub fn ambient_light(&self, rec: &HitRecord, attenuation: &Color) -> Color {
+        match self.ambient {
+            Some((sky, ground)) => {
+                let t = 0.5 * (rec.normal.y() + 1.0);
+                *attenuation * ((1.0 - t) * ground + t * sky)
+            }
+            None => Color::default(),
+        }
+    }
+
+    /// Returns a linearly blended color between `from` and `to`. The input `ray`s
+    /// y coordinate to determine how much of `from` or `to` to apply.
+    fn linear_blend(ray: &Ray, from: &Color, to: &Color) -> Color {
+        let unit_direction = ray.direction().unit_vector();
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        // blue is 0.5, 0.7, 1.0
+        (1.0 - t) * *from + t * *to
+    }
+
+    /// Returns the distribution of the luminance of the environment map of `background_color`,
+    /// if it is an `Environment` background
+    fn environment_pdf(background_color: &BackgroundColor) -> Option<Arc<EnvironmentPdf>> {
+        match background_color {
+            BackgroundColor::Environment(image) => Some(Arc::new(EnvironmentPdf::new(image))),
+            _ => None,
+        }
+    }
+
+    /// Returns the color of the equirectangular environment map, `image`, in the direction of
+    /// `ray`. The map wraps around the vertical axis, with its top row straight up
+    fn environment_color(ray: &Ray, image: &EnvironmentMap) -> Color {
+        let unit_direction = ray.direction().unit_vector();
+        let (u, v) = get_environment_uv(&unit_direction);
+        image.value(u, v, &unit_direction)
+    }
+
+    /// Returns the final color of a pixel, given the sum of the radiance of its samples. The
+    /// average radiance is tone mapped, then gamma corrected and scaled to `[0..256)`
+    pub(crate) fn display_color(
+        tone_map: &ToneMap,
+        pixel_color: &Color,
+        samples_per_pixel: u32,
+    ) -> Color {
+        match *tone_map {
+            // multi_sample clamps every channel anyway
+            ToneMap::Clamp => Renderer::multi_sample(pixel_color, samples_per_pixel),
+            tone_map => {
+                let mapped = tone_map.map(*pixel_color / samples_per_pixel as f64);
+                Renderer::multi_sample(&mapped, 1)
+            }
+        }
+    }
+
+    /// Returns a new pixel color using multi-sample color computation
+    fn multi_sample(pixel_color: &Color, samples_per_pixel: u32) -> Color {
+        let mut r = pixel_color.x();
+        let mut g = pixel_color.y();
+        let mut b = pixel_color.z();
+
+        // divide the color total by the number of samples and gamma correct for gamma=2.0
+        let scale = 1.0 / samples_per_pixel as f64;
+        r = f64::sqrt(scale * r);
+        g = f64::sqrt(scale * g);
+        b = f64::sqrt(scale * b);
+
+        // compute a translated [0..=255] color value for each color's R,G,B
+        Color::new(
+            256.0 * common::clamp(r, 0.0, 0.999),
+            256.0 * common::clamp(g, 0.0, 0.999),
+            256.0 * common::clamp(b, 0.0, 0.999),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Camera, CameraBuilder, Color, Point3, Ray, Vec3};
+    use crate::hittable::{
+        build_solid_lambertian, build_solid_moving_sphere, build_solid_sphere,
+        build_sphere_diff_light, build_xy_diff_light, build_xz_diff_light, ConstantMedium,
+        Hittable, HittableList, ObjectId, Portal, Sphere, Visibility, XYRect, XZRect,
+    };
+    use crate::light::PointLight;
+    use crate::material::{DiffuseLight, Metal};
+    use crate::renderer::{
+        Accelerator, AntiAlias, BackgroundColor, DepthMode, DirectLighting, Integrator,
+        OutputColorSpace, PathTracer, Renderer, DEFAULT_TILE_SIZE,
+    };
+    use crate::scene::cornell_boxes::{build_cornell_box_lights, build_cornell_box_with_two_boxes};
+    use crate::texture::{EnvironmentMap, SolidColor};
+    use crate::util::tonemap::ToneMap;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    use std::f64::consts::PI;
+    use std::fs::File;
+    use std::mem::size_of;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// builds a small camera looking down the negative z axis
+    fn test_camera(image_width: u32, aspect_ratio: f64) -> Camera {
+        CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .aspect_ratio(aspect_ratio)
+            .image_width(image_width)
+            .focus_distance(1.0)
+            .aperture(0.0)
+            .vertical_field_of_view(90.0)
+            .open_close_time(0.0, 1.0)
+            .build()
+    }
+
+    /// builds a world containing a single sphere that sits behind the test camera
+    fn world_behind_camera() -> HittableList {
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, 10.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        world
+    }
+
+    #[test]
+    fn masked_out_pixels_keep_their_previous_buffer_value() {
+        let camera = test_camera(8, 2.0);
+        let renderer = Renderer::new(
+            5,
+            2,
+            BackgroundColor::Solid(Color::new(0.25, 0.25, 0.25)),
+            2,
+        );
+        let pixel_count = (camera.image_width * camera.image_height) as usize;
+        let previous = Color::new(1.0, 2.0, 3.0);
+        let mut image = vec![previous; pixel_count];
+        let mask: Vec<bool> = (0..pixel_count).map(|i| i % 2 == 0).collect();
+
+        renderer.render_masked(camera, world_behind_camera(), &mask, &mut image);
+
+        for (i, color) in image.iter().enumerate() {
+            if mask[i] {
+                // sqrt(0.25) * 256
+                assert_eq!(*color, Color::new(128.0, 128.0, 128.0));
+            } else {
+                assert_eq!(*color, previous);
+            }
+        }
+    }
+
+    #[test]
+    fn render_into_fills_the_entire_buffer() {
+        let camera = test_camera(8, 2.0);
+        let renderer = Renderer::new(
+            5,
+            2,
+            BackgroundColor::Solid(Color::new(0.25, 0.25, 0.25)),
+            2,
+        );
+        let pixel_count = (camera.image_width * camera.image_height) as usize;
+        let mut image = vec![Color::new(1.0, 2.0, 3.0); pixel_count];
+
+        renderer.render_into(camera, world_behind_camera(), &mut image);
+
+        assert!(image
+            .iter()
+            .all(|color| *color == Color::new(128.0, 128.0, 128.0)));
+    }
+
+    #[test]
+    fn images_do_not_depend_on_the_tile_size() {
+        let gradient = BackgroundColor::LinearInterp(Color::new(1.0, 1.0, 1.0), Color::default());
+        let render = |tile_size: u32| {
+            Renderer::new(5, 1, gradient.clone(), 2)
+                .with_jitter_pattern(vec![(0.5, 0.5)])
+                .with_tile_size(tile_size)
+                .render(test_camera(8, 2.0), world_behind_camera())
+                .display_colors()
+        };
+
+        let image = render(DEFAULT_TILE_SIZE);
+        assert_eq!(render(3), image);
+        assert_eq!(render(1), image);
+        assert!(image.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn progressive_passes_converge_to_the_full_render() {
+        let gradient = BackgroundColor::LinearInterp(Color::new(1.0, 1.0, 1.0), Color::default());
+        let pattern: Vec<(f64, f64)> = (0..40).map(|i| (i as f64 / 40.0, 0.5)).collect();
+        let renderer = Renderer::new(5, 1, gradient, 2).with_jitter_pattern(pattern);
+        let mut passes = vec![];
+
+        let image = renderer.clone().render_progressive(
+            test_camera(8, 2.0),
+            world_behind_camera(),
+            |_, samples| {
+                passes.push(samples);
+                true
+            },
+        );
+        let full = renderer
+            .render(test_camera(8, 2.0), world_behind_camera())
+            .display_colors();
+
+        assert_eq!(passes, vec![16, 32, 40]);
+        assert!(image
+            .iter()
+            .zip(full.iter())
+            .all(|(a, b)| (*a - *b).length() < 1e-9));
+    }
+
+    #[test]
+    fn progressive_render_stops_when_the_callback_returns_false() {
+        let renderer = Renderer::new(
+            5,
+            64,
+            BackgroundColor::Solid(Color::new(0.25, 0.25, 0.25)),
+            2,
+        );
+        let mut passes = 0;
+
+        let image =
+            renderer.render_progressive(test_camera(8, 2.0), world_behind_camera(), |_, _| {
+                passes += 1;
+                false
+            });
+
+        assert_eq!(passes, 1);
+        assert!(image
+            .iter()
+            .all(|color| *color == Color::new(128.0, 128.0, 128.0)));
+    }
+
+    #[test]
+    fn ambient_brightens_shadowed_diffuse_surfaces() {
+        let sphere =
+            build_solid_sphere(Point3::new(0.0, 0.0, -2.0), 1.0, Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let renderer = Renderer::new(2, 1, BackgroundColor::Solid(Color::default()), 1);
+
+        let unlit = PathTracer.ray_color_at_depth(&renderer, &ray, &sphere, 2);
+        let renderer = renderer.with_ambient(Color::new(0.2, 0.2, 0.2), Color::new(0.1, 0.1, 0.1));
+        let lit = PathTracer.ray_color_at_depth(&renderer, &ray, &sphere, 2);
+
+        assert_eq!(unlit, Color::default());
+        // the hit normal is horizontal, so the ambient is an even blend of sky and ground
+        assert!((lit - Color::new(0.075, 0.075, 0.075)).length() < 1e-9);
+    }
+
+    #[test]
+    fn ambient_does_not_change_emissive_surfaces() {
+        let light = DiffuseLight::from(Arc::new(SolidColor::from_rgb(4.0, 4.0, 4.0)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, Arc::new(light));
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let renderer = Renderer::new(2, 1, BackgroundColor::Solid(Color::default()), 1);
+
+        let unlit = PathTracer.ray_color_at_depth(&renderer, &ray, &sphere, 2);
+        let renderer = renderer.with_ambient(Color::new(1.0, 1.0, 1.0), Color::new(1.0, 1.0, 1.0));
+        let lit = PathTracer.ray_color_at_depth(&renderer, &ray, &sphere, 2);
+
+        assert_eq!(unlit, Color::new(4.0, 4.0, 4.0));
+        assert_eq!(lit, unlit);
+    }
+
+    #[test]
+    fn ambient_does_not_change_specular_surfaces() {
+        let mirror = Metal::new(Color::new(0.5, 0.5, 0.5), 0.0);
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, Arc::new(mirror));
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let renderer = Renderer::new(2, 1, BackgroundColor::Solid(Color::default()), 1);
+
+        let renderer = renderer.with_ambient(Color::new(1.0, 1.0, 1.0), Color::new(1.0, 1.0, 1.0));
+        let lit = PathTracer.ray_color_at_depth(&renderer, &ray, &sphere, 2);
+
+        // the mirror reflects the black background straight back, and nothing else
+        assert_eq!(lit, Color::default());
+    }
+
+    #[test]
+    fn tone_mapping_keeps_a_bright_light_below_white() {
+        let light = BackgroundColor::Solid(Color::new(4.0, 4.0, 4.0));
+        let clamped = Renderer::new(5, 1, light.clone(), 2)
+            .render(test_camera(4, 2.0), world_behind_camera())
+            .display_colors();
+        let mapped = Renderer::new(5, 1, light, 2)
+            .with_tone_map(ToneMap::Reinhard)
+            .render(test_camera(4, 2.0), world_behind_camera())
+            .display_colors();
+
+        assert_eq!(clamped[0].x(), 255.744);
+        // reinhard maps 4 to 0.8, before gamma correction
+        assert!((mapped[0].x() - 256.0 * 0.8f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn srgb_output_color_space_is_the_identity() {
+        let color = Color::new(0.3, 1.7, 0.02);
+        assert_eq!(OutputColorSpace::Srgb.convert(color), color);
+        assert_eq!(OutputColorSpace::default(), OutputColorSpace::Srgb);
+    }
+
+    #[test]
+    fn rec2020_converts_pure_red_to_rec2020_primaries() {
+        let red = OutputColorSpace::Rec2020.convert(Color::new(1.0, 0.0, 0.0));
+        assert!((red - Color::new(0.6274039, 0.0690973, 0.0163914)).length() < 1e-7);
+
+        // white is preserved since both color spaces share the D65 white point
+        let white = OutputColorSpace::Rec2020.convert(Color::new(1.0, 1.0, 1.0));
+        assert!((white - Color::new(1.0, 1.0, 1.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn batch_reports_progress_and_renders_every_job() {
+        let renderer = Renderer::new(
+            5,
+            1,
+            BackgroundColor::Solid(Color::new(0.25, 0.25, 0.25)),
+            2,
+        )
+        // two tiles per 8x4 image
+        .with_tile_size(4);
+        let jobs = (0..3)
+            .map(|_| (test_camera(8, 2.0), world_behind_camera()))
+            .collect();
+        let mut progress: Vec<f32> = vec![];
+
+        let images =
+            renderer.render_batch_interactive(jobs, Arc::new(AtomicBool::new(false)), |p| {
+                progress.push(p)
+            });
+
+        assert_eq!(images.len(), 3);
+        assert!(images.iter().all(|image| image.len() == 32));
+        assert_eq!(progress.len(), 6);
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert!((progress[progress.len() - 1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn canceling_a_batch_midway_returns_only_completed_jobs() {
+        let renderer = Renderer::new(
+            5,
+            1,
+            BackgroundColor::Solid(Color::new(0.25, 0.25, 0.25)),
+            1,
+        );
+        let jobs = (0..3)
+            .map(|_| (test_camera(16, 2.0), world_behind_camera()))
+            .collect();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_in_callback = Arc::clone(&cancel);
+
+        // cancel once the second job is half way rendered
+        let images = renderer.render_batch_interactive(jobs, cancel, |p| {
+            if p >= 0.5 {
+                cancel_in_callback.store(true, Ordering::SeqCst);
+            }
+        });
+
+        assert!(images.len() < 3);
+        assert!(images.iter().all(|image| image.len() == 128));
+    }
+
+    #[test]
+    fn a_batch_canceled_before_it_starts_returns_no_images() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1);
+        let jobs = vec![(test_camera(8, 2.0), world_behind_camera())];
+
+        let images =
+            renderer.render_batch_interactive(jobs, Arc::new(AtomicBool::new(true)), |_| {});
+
+        assert!(images.is_empty());
+    }
+
+    #[test]
+    fn render_with_progress_reports_every_tile_and_returns_the_image() {
+        let renderer = Renderer::new(5, 2, BackgroundColor::Solid(Color::new(0.5, 0.7, 1.0)), 2)
+            .with_seed(3)
+            .with_tile_size(4);
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let handle = renderer.clone().render_with_progress(
+            test_camera(16, 2.0),
+            world_behind_camera(),
+            move |tile, colors| {
+                assert_eq!(tile.pixel_count(), colors.len());
+                tx.send(colors.len()).unwrap();
+            },
+        );
+        let image = handle.wait().unwrap();
+
+        assert_eq!(rx.iter().sum::<usize>(), 128);
+        assert_eq!(
+            image,
+            renderer.render(test_camera(16, 2.0), world_behind_camera())
+        );
+    }
+
+    #[test]
+    fn a_canceled_render_returns_no_image() {
+        let renderer =
+            Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 1).with_tile_size(8);
+
+        // the first finished tile holds up the only render thread until the render is canceled
+        let (finished_tx, finished_rx) = std::sync::mpsc::channel();
+        let (canceled_tx, canceled_rx) = std::sync::mpsc::channel::<()>();
+        let handle = renderer.render_with_progress(
+            test_camera(64, 2.0),
+            world_behind_camera(),
+            move |_, _| {
+                if finished_tx.send(()).is_ok() {
+                    canceled_rx.recv().unwrap();
+                }
+            },
+        );
+        finished_rx.recv().unwrap();
+        handle.cancel();
+        drop(finished_rx);
+        canceled_tx.send(()).unwrap();
+
+        assert!(handle.progress() < 1.0);
+        assert!(handle.wait().is_none());
+    }
+
+    #[test]
+    fn distant_sphere_light_gives_less_irradiance_than_a_near_one() {
+        let emit = Color::new(4.0, 4.0, 4.0);
+        // a ray that hits the ground, which has an albedo of 0.5, at the origin
+        let ray = Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, -1.0, -5.0), 0.0);
+        let reflected_light = |height: f64| {
+            let light: Arc<dyn Hittable> = Arc::new(build_sphere_diff_light(
+                emit,
+                Point3::new(0.0, height, 0.0),
+                1.0,
+            ));
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+            world.add(Arc::clone(&light));
+            let renderer =
+                Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1).with_lights(light);
+            DirectLighting::new(500)
+                .ray_color(&renderer, &ray, &world)
+                .x()
+        };
+
+        let (near, far) = (reflected_light(5.0), reflected_light(20.0));
+
+        // a sphere of radius 1 at distance d, directly overhead, has an irradiance of
+        // pi * L / d^2, of which a diffuse surface reflects albedo / pi, so moving it 4 times
+        // further away gives 1/16 of the light
+        let expected_near = 0.5 * 4.0 / 25.0;
+        assert!((near - expected_near).abs() < 0.05 * expected_near);
+        assert!(far < near);
+        assert!((near / far - 16.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn portals_reduce_the_noise_of_light_entering_through_a_window() {
+        // a gray floor under a gray ceiling with a 1x1 window above the origin, lit by a white sky
+        let mut world = HittableList::new();
+        let gray = || Arc::new(build_solid_lambertian(0.5, 0.5, 0.5));
+        // both are so large that hardly any light reaches the floor from around the ceiling
+        let far = 5000.0;
+        world.add(Arc::new(XZRect::from(-far, far, -far, far, 0.0, gray())));
+        for (x0, x1, z0, z1) in [
+            (-far, -0.5, -far, far),
+            (0.5, far, -far, far),
+            (-0.5, 0.5, -far, -0.5),
+            (-0.5, 0.5, 0.5, far),
+        ] {
+            world.add(Arc::new(XZRect::from(x0, x1, z0, z1, 3.0, gray())));
+        }
+        let window = Portal::new(
+            Point3::new(-0.5, 3.0, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let ray = Ray::new(Point3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0), 0.0);
+        // in 2 bounces, only the light arriving straight through the window reaches the floor
+        let stats = |renderer: Renderer| {
+            let samples: Vec<f64> = (0..4000)
+                .map(|_| {
+                    PathTracer
+                        .ray_color_at_depth(&renderer, &ray, &world, 2)
+                        .x()
+                })
+                .collect();
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let variance =
+                samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+            (mean, variance)
+        };
+        let sky = BackgroundColor::Solid(Color::new(1.0, 1.0, 1.0));
+
+        let (mean, variance) = stats(Renderer::new(2, 1, sky.clone(), 1));
+        let (portal_mean, portal_variance) =
+            stats(Renderer::new(2, 1, sky, 1).with_portals(Arc::new(window)));
+
+        // the window gives the origin an irradiance of about 0.107, of which the floor reflects
+        // albedo / pi
+        let expected = 0.5 * 0.107 / PI;
+        assert!((portal_mean - expected).abs() < 0.1 * expected);
+        assert!((mean - expected).abs() < 0.3 * expected);
+        assert!(portal_variance < variance / 10.0);
+    }
+
+    #[test]
+    fn surface_facing_the_camera_has_a_view_space_normal_of_positive_z() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 1);
+        // a camera looking along the world x axis, at a large sphere that fills the view
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(1.0, 0.0, 0.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(9)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(20.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(110.0, 0.0, 0.0),
+            100.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+
+        let normals = renderer.render_normals_view_space(camera, world);
+
+        // the center pixel sees the point of the sphere closest to the camera
+        let center = normals[4 * 9 + 4];
+        assert!((center - Vec3::new(0.0, 0.0, 1.0)).length() < 0.01);
+        assert!(normals.iter().all(|n| (n.length() - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn occlusion_is_one_under_an_unobstructed_light_and_zero_behind_an_occluder() {
+        let renderer = Renderer::new(5, 16, BackgroundColor::Solid(Color::default()), 1);
+        // look straight down at the ground, from high above
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 30.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, 0.0))
+            .up_direction(Vec3::new(0.0, 0.0, -1.0))
+            .image_width(21)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(40.0)
+            .focus_distance(30.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        // an occluder sitting on the ground, between the light and the right side of the image
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(5.0, 2.0, 0.0),
+            2.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        let light = Point3::new(-12.0, 2.0, 0.0);
+
+        let occlusion = renderer.render_occlusion(camera, world, light);
+
+        // the center of the image sees the ground directly below the camera, which is lit
+        assert_eq!(occlusion[10 * 21 + 10], 1.0);
+        // the ground at x=9, behind the occluder, is in its shadow
+        let col = (10.0 + 10.0 * 9.0 / (30.0 * 20f64.to_radians().tan())) as usize;
+        assert_eq!(occlusion[10 * 21 + col], 0.0);
+    }
+
+    #[test]
+    fn object_invisible_to_the_camera_still_appears_in_reflections() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 1);
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(5)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(10.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let mut world = HittableList::new();
+        // a white light in front of the camera that can only be seen by secondary rays. It is
+        // large enough for the reflection of every sample of the middle pixel, which spans up
+        // to 3.5 degrees from the view direction, to pass through it
+        let light =
+            build_sphere_diff_light(Color::new(1.0, 1.0, 1.0), Point3::new(0.0, 0.0, -3.0), 1.5);
+        world.add(Arc::new(Visibility::from(Arc::new(light), false, true)));
+        // a mirror behind the light, that reflects half of the light back to the camera
+        let mirror = Metal::new(Color::new(0.5, 0.5, 0.5), 0.0);
+        world.add(Arc::new(XYRect::from(
+            -5.0,
+            5.0,
+            -5.0,
+            5.0,
+            -10.0,
+            Arc::new(mirror),
+        )));
+
+        let image = renderer.render(camera, world).display_colors();
+
+        // the light is only seen in the mirror, at half of its brightness, instead of being
+        // seen directly at its full brightness of 255.744
+        let expected = 0.5f64.sqrt() * 256.0;
+        assert!((image[2 * 5 + 2].x() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn view_space_normals_and_occlusion_skip_objects_invisible_to_the_camera() {
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .focus_distance(1.0)
+            .build();
+        let sphere =
+            build_solid_sphere(Point3::new(0.0, 0.0, -3.0), 1.0, Color::new(0.5, 0.5, 0.5));
+        let world = Visibility::from(Arc::new(sphere), false, true);
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        // the ray passes through the sphere, so it sees nothing, and is lit by a light behind
+        // the sphere
+        assert_eq!(
+            Renderer::view_space_normal(&ray, &world, &camera),
+            Vec3::default()
+        );
+        let light = Point3::new(0.0, 0.0, -10.0);
+        assert_eq!(
+            Renderer::light_visibility(&ray, &world, &light),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn stereo_images_differ_unless_the_eyes_are_not_separated() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 1);
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(5)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(90.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        // a light whose edges are aligned with pixel boundaries, so every pixel is either
+        // entirely lit or entirely dark
+        let mut world = HittableList::new();
+        let white = Color::new(1.0, 1.0, 1.0);
+        world.add(Arc::new(build_xy_diff_light(
+            white, -1.0, 1.0, -2.0, 2.0, -2.0,
+        )));
+
+        let (left, right) = renderer.clone().render_stereo(camera, world.clone(), 0.0);
+        assert_eq!(left, right);
+
+        let (left, right) = renderer.render_stereo(camera, world, 2.0);
+        // the left eye sees the light on the right side of the image, and vice versa
+        assert_eq!(left[2 * 5 + 1], Color::default());
+        assert_eq!(right[2 * 5 + 1].x(), 255.744);
+        assert_eq!(left[2 * 5 + 3].x(), 255.744);
+        assert_eq!(right[2 * 5 + 3], Color::default());
+    }
+
+    #[test]
+    fn render_timed_returns_one_duration_per_scanline() {
+        let renderer =
+            Renderer::new(5, 2, BackgroundColor::Solid(Color::default()), 2).with_watchdog(3);
+
+        let (image, durations) = renderer.render_timed(test_camera(8, 2.0), world_behind_camera());
+
+        assert_eq!(image.len(), 32);
+        assert_eq!(durations.len(), 4);
+        assert!(durations.iter().all(|d| d.as_secs_f64() >= 0.0));
+        assert!(durations.iter().any(|d| !d.is_zero()));
+    }
+
+    #[test]
+    fn studio_background_shows_the_backdrop_to_primary_rays_only() {
+        let backdrop = Color::new(1.0, 1.0, 1.0);
+        let (from, to) = (Color::new(0.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+        let renderer = Renderer::new(5, 1, BackgroundColor::Studio(backdrop, from, to), 1);
+        let world = world_behind_camera();
+        let up = Ray::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0), 0.0);
+
+        let primary =
+            PathTracer.ray_color_at_depth(&renderer, &up, &world, renderer.ray_bounce_depth());
+        let bounced =
+            PathTracer.ray_color_at_depth(&renderer, &up, &world, renderer.ray_bounce_depth() - 1);
+
+        assert_eq!(primary, backdrop);
+        assert_eq!(bounced, to);
+    }
+
+    #[test]
+    fn environment_background_samples_the_map_in_the_ray_direction() {
+        // a 4x2 map with a white sky and four differently colored columns along the horizon
+        let mut data = vec![255; 4 * 3];
+        data.extend([0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        let map = Arc::new(EnvironmentMap::from_rgb8(4, 2, data));
+        let renderer = Renderer::new(5, 1, BackgroundColor::Environment(map), 1);
+        let world = HittableList::new();
+        let color_towards = |x: f64, y: f64, z: f64| {
+            let ray = Ray::new(Point3::default(), Vec3::new(x, y, z), 0.0);
+            PathTracer.ray_color_at_depth(&renderer, &ray, &world, renderer.ray_bounce_depth())
+        };
+
+        assert_eq!(color_towards(0.0, 1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        // looking down -z, the columns to the right of the image are to the right of the view
+        assert_eq!(color_towards(0.0, 0.0, -1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(color_towards(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(color_towards(0.0, 0.0, 1.0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn objects_invisible_to_the_camera_have_no_motion_vectors() {
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(10)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(90.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let moving = build_solid_moving_sphere(
+            Color::new(0.5, 0.5, 0.5),
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::new(0.5, 0.0, -5.0),
+            0.0,
+            1.0,
+            1.0,
+        );
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.5);
+
+        assert!(Renderer::motion_vector(&ray, &moving, &camera).x() > 0.0);
+        let hidden = Visibility::from(Arc::new(moving), false, true);
+        assert_eq!(
+            Renderer::motion_vector(&ray, &hidden, &camera),
+            Vec3::default()
+        );
+    }
+
+    #[test]
+    fn only_moving_objects_have_motion_vectors() {
+        let renderer = Renderer::new(5, 2, BackgroundColor::Solid(Color::default()), 1);
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.0, 0.0, 0.0))
+            .look_at(Point3::new(0.0, 0.0, -1.0))
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(10)
+            .aspect_ratio(2.0)
+            .vertical_field_of_view(90.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let mut world = HittableList::new();
+        // a static sphere on the left, and a sphere moving to the right on the right, both are
+        // large enough to cover their pixel for every sub-pixel offset
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(-6.5, 2.5, -5.0),
+            3.2,
+            gray,
+        )));
+        world.add(Arc::new(build_solid_moving_sphere(
+            gray,
+            Point3::new(6.5, 2.5, -5.0),
+            Point3::new(7.0, 2.5, -5.0),
+            0.0,
+            1.0,
+            3.2,
+        )));
+
+        let motion = renderer
+            .clone()
+            .with_seed(3)
+            .render_motion_vectors(camera, world.clone());
+
+        // a pixel of the middle row on the left, and its mirror image on the right
+        let (static_dx, static_dy) = motion[2 * 10 + 1];
+        let (moving_dx, moving_dy) = motion[2 * 10 + 7];
+        assert_eq!((static_dx, static_dy), (0.0, 0.0));
+        assert!(moving_dx > 0.0);
+        assert!(moving_dy.abs() < 1e-9);
+        // motion vectors are not colors, so the output color space leaves them unchanged
+        let wide_gamut = renderer
+            .with_output_color_space(OutputColorSpace::Rec2020)
+            .with_seed(3)
+            .render_motion_vectors(camera, world);
+        assert_eq!(wide_gamut, motion);
+    }
+
+    #[test]
+    fn half_scale_preview_has_half_the_width_and_height() {
+        let renderer = Renderer::new(5, 8, BackgroundColor::Solid(Color::default()), 1);
+        let camera = test_camera(21, 2.0);
+
+        let (image, width, height) = renderer.preview(camera, world_behind_camera(), 0.5);
+
+        // 21x10 is halved and rounded to 11x5
+        assert_eq!((width, height), (11, 5));
+        assert_eq!(image.len(), 55);
+    }
+
+    #[test]
+    fn supersampling_renders_identical_images_every_time() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 2)
+            .with_anti_alias(AntiAlias::Supersample { factor: 4 });
+        // the edges of a light sphere cross many pixels
+        let world = || {
+            let mut world = HittableList::new();
+            let white = Color::new(1.0, 1.0, 1.0);
+            world.add(Arc::new(build_sphere_diff_light(
+                white,
+                Point3::new(0.0, 0.0, -2.0),
+                1.0,
+            )));
+            world
+        };
+
+        let first = renderer
+            .clone()
+            .render(test_camera(16, 1.0), world())
+            .display_colors();
+        let second = renderer
+            .render(test_camera(16, 1.0), world())
+            .display_colors();
+
+        assert_eq!(first, second);
+        // some pixels are only partially covered by the light
+        assert!(first.iter().any(|c| c.x() > 0.0 && c.x() < 255.0));
+    }
+
+    #[test]
+    fn renders_with_the_same_jitter_pattern_are_identical() {
+        let offsets = vec![(0.1, 0.2), (0.7, 0.4), (0.3, 0.9), (0.8, 0.6)];
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 2)
+            .with_jitter_pattern(offsets.clone());
+        let world = || {
+            let mut world = HittableList::new();
+            let white = Color::new(1.0, 1.0, 1.0);
+            world.add(Arc::new(build_sphere_diff_light(
+                white,
+                Point3::new(0.0, 0.0, -2.0),
+                1.0,
+            )));
+            world
+        };
+
+        let first = renderer.clone().render(test_camera(16, 1.0), world());
+        let second = renderer.render(test_camera(16, 1.0), world());
+
+        assert_eq!(first, second);
+        assert_eq!(
+            Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 2)
+                .with_jitter_pattern(offsets)
+                .jitter_pattern()
+                .map(|p| p.len()),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn depth_is_the_distance_to_the_surface_or_infinite_on_a_miss() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 2);
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+
+        let depth = renderer.render_depth(test_camera(17, 1.0), world);
+
+        // the center pixel sees the front of the sphere, 2 units away, and the corners see
+        // nothing. The samples of the center pixel are up to 10 degrees off the view direction,
+        // where the sphere is up to 2.1 units away
+        assert!((depth[8 * 17 + 8] - 2.0).abs() < 0.1);
+        assert_eq!(depth[0], f64::INFINITY);
+        assert_eq!(depth[17 * 17 - 1], f64::INFINITY);
+    }
+
+    #[test]
+    fn aovs_describe_the_first_hit_of_each_pixel() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 2).with_aovs();
+        let mut world = HittableList::new();
+        // an object outside of the view, so that the visible sphere is the second object
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, 10.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Color::new(0.8, 0.3, 0.3),
+        )));
+
+        let image = renderer.render(test_camera(9, 1.0), world);
+        let aovs = image.aovs().unwrap();
+
+        // the center pixel sees the front of the sphere, and the corners see nothing
+        let center = 4 * 9 + 4;
+        assert_eq!(aovs.albedo()[center], Color::new(0.8, 0.3, 0.3));
+        assert!((aovs.normal()[center].length() - 1.0).abs() < 1e-9);
+        assert!(aovs.normal()[center].z() > 0.8);
+        assert!((aovs.depth()[center] - 2.0).abs() < 0.2);
+        assert_eq!(aovs.object_id()[center], 2);
+        assert_eq!(aovs.albedo()[0], Color::new(1.0, 1.0, 1.0));
+        assert_eq!(aovs.normal()[0], Vec3::default());
+        assert_eq!(aovs.depth()[0], f64::INFINITY);
+        assert_eq!(aovs.object_id()[0], 0);
+    }
+
+    #[test]
+    fn tagged_objects_keep_their_object_id() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 2).with_aovs();
+        let mut world = HittableList::new();
+        world.add(Arc::new(ObjectId::from(
+            Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -3.0),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )),
+            42,
+        )));
+
+        let image = renderer.clone().render(test_camera(9, 1.0), world.clone());
+        let plain = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 2)
+            .render(test_camera(9, 1.0), world);
+
+        assert_eq!(image.aovs().unwrap().object_id()[4 * 9 + 4], 42);
+        assert!(plain.aovs().is_none());
+    }
+
+    #[test]
+    fn coincident_rects_always_show_the_one_added_first() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 2);
+        let camera = CameraBuilder::new()
+            .look_from(Point3::new(0.3, 1.0, 0.2))
+            .look_at(Point3::new(0.3, 0.0, 0.2))
+            .up_direction(Vec3::new(0.0, 0.0, -1.0))
+            .image_width(16)
+            .aspect_ratio(1.0)
+            .vertical_field_of_view(60.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0)
+            .build();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_xz_diff_light(
+            red, -5.0, 5.0, -5.0, 5.0, 0.0,
+        )));
+        world.add(Arc::new(build_xz_diff_light(
+            green, -5.0, 5.0, -5.0, 5.0, 0.0,
+        )));
+
+        for _ in 0..4 {
+            let image = renderer
+                .clone()
+                .render(camera, world.clone())
+                .display_colors();
+            assert!(image.iter().all(|c| c.x() > 255.0 && c.y() == 0.0));
+        }
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_xz_diff_light(
+            green, -5.0, 5.0, -5.0, 5.0, 0.0,
+        )));
+        world.add(Arc::new(build_xz_diff_light(
+            red, -5.0, 5.0, -5.0, 5.0, 0.0,
+        )));
+        let image = renderer.render(camera, world).display_colors();
+        assert!(image.iter().all(|c| c.x() == 0.0 && c.y() > 255.0));
+    }
+
+    #[test]
+    fn turntable_gif_has_one_frame_per_view() {
+        let renderer = Renderer::new(2, 1, BackgroundColor::Solid(Color::default()), 2);
+        let camera = CameraBuilder::new()
+            .up_direction(Vec3::new(0.0, 1.0, 0.0))
+            .image_width(8)
+            .aspect_ratio(2.0)
+            .vertical_field_of_view(40.0)
+            .focus_distance(1.0)
+            .open_close_time(0.0, 1.0);
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_sphere_diff_light(
+            Color::new(1.0, 1.0, 1.0),
+            Point3::default(),
+            1.0,
+        )));
+        let path = std::env::temp_dir().join("raytracer_turntable.gif");
+
+        renderer
+            .render_turntable_gif(camera, world, Point3::default(), 5.0, 3, &path)
+            .unwrap();
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frame_count = decoder.into_frames().count();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(frame_count, 3);
+    }
+
+    #[test]
+    fn flat_background_has_no_variance_but_a_light_edge_does() {
+        let renderer = Renderer::new(5, 16, BackgroundColor::Solid(Color::default()), 2);
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_sphere_diff_light(
+            Color::new(1.0, 1.0, 1.0),
+            Point3::new(0.0, 0.0, -2.0),
+            1.0,
+        )));
+
+        let (image, variances) = renderer.render_with_variance(test_camera(16, 1.0), world);
+
+        assert_eq!(variances.len(), image.len());
+        // the corners only see the background, while the edge of the light is partially
+        // covered by some pixels
+        assert_eq!(variances[0], 0.0);
+        assert!(variances.iter().any(|v| *v > 0.01));
+    }
+
+    #[test]
+    fn renders_with_the_same_seed_are_identical() {
+        let renderer = Renderer::new(
+            5,
+            4,
+            BackgroundColor::LinearInterp(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0)),
+            3,
+        )
+        .with_seed(42);
+        let world = || {
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -2.0),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+            world
+        };
+
+        let first = renderer.clone().render(test_camera(16, 1.0), world());
+        let second = renderer.render(test_camera(16, 1.0), world());
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn albedo_times_irradiance_reconstructs_the_diffuse_radiance() {
+        let renderer = Renderer::new(
+            5,
+            1,
+            BackgroundColor::LinearInterp(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0)),
+            2,
+        )
+        .with_seed(7);
+        let world = || {
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -2.0),
+                1.0,
+                Color::new(0.8, 0.3, 0.3),
+            )));
+            world
+        };
+
+        let radiance = renderer
+            .clone()
+            .render_radiance(test_camera(16, 1.0), world());
+        let (albedo, irradiance) = renderer.render_albedo_irradiance(test_camera(16, 1.0), world());
+
+        for ((radiance, albedo), irradiance) in radiance.iter().zip(&albedo).zip(&irradiance) {
+            assert!((*radiance - *albedo * *irradiance).length() < 1e-6);
+        }
+        // the sphere's color is in the albedo, not the lighting
+        assert!(albedo.contains(&Color::new(0.8, 0.3, 0.3)));
+    }
+
+    #[test]
+    fn clay_render_is_clay_colored_except_for_the_lights() {
+        let renderer = Renderer::new(5, 8, BackgroundColor::Solid(Color::new(0.6, 0.6, 0.6)), 2);
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(-1.2, 0.0, -2.5),
+            1.0,
+            Color::new(0.9, 0.1, 0.1),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(1.2, 0.0, -2.5),
+            1.0,
+            Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)),
+        )));
+        world.add(Arc::new(build_sphere_diff_light(
+            Color::new(4.0, 4.0, 4.0),
+            Point3::new(0.0, 1.5, -2.0),
+            0.5,
+        )));
+
+        let image = renderer.render_clay(test_camera(16, 1.0), world, Color::new(0.5, 0.5, 0.5));
+
+        // the gray clay, lit by a white light and background, is gray everywhere
+        for color in image.iter() {
+            assert!((color.x() - color.y()).abs() < 1e-9 && (color.y() - color.z()).abs() < 1e-9);
+        }
+        // and the light is still emitting
+        assert!(image.iter().any(|color| color.x() > 255.0));
+    }
+
+    #[test]
+    fn volume_bounce_depth_brightens_thick_fog_but_not_surfaces() {
+        let renderer =
+            Renderer::new(4, 16, BackgroundColor::Solid(Color::new(1.0, 1.0, 1.0)), 2).with_seed(3);
+        let fog = || {
+            let mut world = HittableList::new();
+            let boundary = Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -2.0),
+                1.5,
+                Color::default(),
+            ));
+            world.add(Arc::new(ConstantMedium::from(
+                boundary,
+                10.0,
+                Arc::new(SolidColor::from_rgb(1.0, 1.0, 1.0)),
+            )));
+            world
+        };
+        let surfaces = || {
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -3.0),
+                2.0,
+                Color::new(0.7, 0.7, 0.7),
+            )));
+            world
+        };
+        let brightness =
+            |image: &[Color]| image.iter().map(|c| c.x()).sum::<f64>() / image.len() as f64;
+
+        let shared_fog = renderer.clone().render_radiance(test_camera(8, 1.0), fog());
+        let volume_fog = renderer
+            .clone()
+            .with_volume_bounce_depth(64)
+            .render_radiance(test_camera(8, 1.0), fog());
+        assert!(brightness(&volume_fog) > brightness(&shared_fog) + 0.2);
+
+        let shared_surfaces = renderer
+            .clone()
+            .render_radiance(test_camera(8, 1.0), surfaces());
+        let volume_surfaces = renderer
+            .with_volume_bounce_depth(64)
+            .render_radiance(test_camera(8, 1.0), surfaces());
+        assert_eq!(shared_surfaces, volume_surfaces);
+    }
+
+    #[test]
+    fn rendering_twice_reuses_the_thread_pool() {
+        let renderer =
+            Renderer::new(5, 4, BackgroundColor::Solid(Color::new(0.5, 0.7, 1.0)), 2).with_seed(11);
+        let world = || {
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, 0.0, -2.0),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+            world
+        };
+
+        let first = renderer.clone();
+        let first_image = first.clone().render(test_camera(16, 1.0), world());
+        let second_image = renderer.clone().render(test_camera(16, 1.0), world());
+
+        assert!(Arc::ptr_eq(&first.pool, &renderer.pool));
+        assert_eq!(renderer.pool.current_num_threads(), 2);
+        assert_eq!(first_image, second_image);
+        // the corner sees the background, and the center sees the sphere
+        assert_ne!(first_image.pixel(0, 15), first_image.pixel(8, 7));
+    }
+
+    #[test]
+    fn normalized_depth_maps_near_to_zero_and_far_to_one() {
+        let mode = DepthMode::Normalized {
+            near: 2.0,
+            far: 6.0,
+        };
+
+        assert_eq!(mode.map(2.0), 0.0);
+        assert_eq!(mode.map(4.0), 0.5);
+        assert_eq!(mode.map(6.0), 1.0);
+        assert_eq!(mode.map(f64::INFINITY), 1.0);
+        assert_eq!(DepthMode::Raw.map(4.0), 4.0);
+    }
+
+    #[test]
+    fn importance_sampling_the_light_reduces_noise_without_changing_the_image() {
+        let render = |renderer: Renderer| {
+            let (camera, world) = build_cornell_box_with_two_boxes(16, 1.0);
+            let (_, variances) = renderer.clone().render_with_variance(camera, world);
+            let (camera, world) = build_cornell_box_with_two_boxes(16, 1.0);
+            let radiance = renderer.render_radiance(camera, world);
+            let mean = radiance.iter().map(|c| c.luminance()).sum::<f64>() / radiance.len() as f64;
+            (mean, variances.iter().sum::<f64>())
+        };
+        let renderer =
+            Renderer::new(10, 64, BackgroundColor::Solid(Color::default()), 2).with_seed(7);
+
+        let (mean, variance) = render(renderer.clone());
+        let (sampled_mean, sampled_variance) =
+            render(renderer.with_lights(build_cornell_box_lights()));
+
+        assert!((sampled_mean - mean).abs() < 0.1 * mean);
+        assert!(sampled_variance < 0.8 * variance);
+    }
+
+    #[test]
+    fn importance_sampling_the_environment_reduces_noise_without_changing_the_image() {
+        // a dim 16x8 map, with a bright sun just above the horizon, behind the camera
+        let mut data = vec![1; 16 * 8 * 3];
+        let sun = (3 * 16 + 12) * 3;
+        data[sun..sun + 3].copy_from_slice(&[255, 255, 255]);
+        let map = Arc::new(EnvironmentMap::from_rgb8(16, 8, data));
+        let render = |renderer: Renderer| {
+            // a wall, facing the camera and the sun, fills the view
+            let world = || {
+                let mut world = HittableList::new();
+                world.add(Arc::new(build_solid_sphere(
+                    Point3::new(0.0, 0.0, -1002.0),
+                    1000.0,
+                    Color::new(0.5, 0.5, 0.5),
+                )));
+                world
+            };
+            let (_, variances) = renderer
+                .clone()
+                .render_with_variance(test_camera(8, 2.0), world());
+            let radiance = renderer.render_radiance(test_camera(8, 2.0), world());
+            let mean = radiance.iter().map(|c| c.luminance()).sum::<f64>() / radiance.len() as f64;
+            (mean, variances.iter().sum::<f64>())
+        };
+        let renderer = Renderer::new(5, 64, BackgroundColor::Environment(map), 2).with_seed(7);
+        let mut unsampled = renderer.clone();
+        unsampled.environment = None;
+
+        let (_, variance) = render(unsampled);
+        let (sampled_mean, sampled_variance) = render(renderer);
+
+        // the wall reflects albedo / PI times the irradiance of the sun and the dim sky, which
+        // integrates to about 0.1545
+        let expected = 0.5 / PI * 0.1545;
+        assert!((sampled_mean - expected).abs() < 0.05 * expected);
+        assert!(sampled_variance < 0.5 * variance);
+    }
+
+    #[test]
+    fn point_lights_light_the_surfaces_they_are_not_shadowed_from() {
+        let ground = || {
+            let mut world = HittableList::new();
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+            world
+        };
+        let light = PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::new(4.0, 4.0, 4.0));
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1)
+            .with_direct_light(Arc::new(light));
+        let down = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+
+        // with a bounce depth of 1 only the direct light of the first hit is gathered, which is
+        // albedo / PI, times the light's intensity over the squared distance
+        let lit = PathTracer.ray_color_at_depth(&renderer, &down, &ground(), 1);
+        assert!((lit.x() - 0.5 / PI).abs() < 1e-9);
+
+        let mut shadowed = ground();
+        shadowed.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 1.5, 0.0),
+            0.2,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        let shadow = PathTracer.ray_color_at_depth(&renderer, &down, &shadowed, 1);
+        assert_eq!(shadow, Color::default());
+    }
+
+    #[test]
+    fn emission_pass_of_the_cornell_box_only_shows_the_light() {
+        let renderer = Renderer::new(5, 4, BackgroundColor::Solid(Color::default()), 2);
+        let (camera, world) = build_cornell_box_with_two_boxes(24, 1.0);
+
+        let image = renderer.render_emission(camera, world);
+
+        // the ceiling light is at the top of the image, and the walls and boxes below it
+        // do not emit any light
+        let width = 24;
+        let top_center = image[(width - 5) * width + width / 2 - 1];
+        assert!(top_center.x() > 250.0);
+        let center = image[(width / 2) * width + width / 2];
+        assert_eq!(center, Color::default());
+        let dark = image.iter().filter(|c| **c == Color::default()).count();
+        assert!(dark > image.len() * 9 / 10);
+    }
+
+    #[test]
+    fn reference_render_of_a_flat_scene_converges_quickly() {
+        let renderer = Renderer::new(5, 500, BackgroundColor::Solid(Color::new(0.5, 0.5, 0.5)), 2);
+
+        let (image, stats) =
+            renderer.render_reference(test_camera(8, 1.0), world_behind_camera(), 0.001);
+
+        // every sample sees the same color, so each pixel stops at the minimum sample count
+        assert_eq!(stats.total_samples, 16 * image.len() as u64);
+        assert_eq!(stats.max_standard_error, 0.0);
+        assert_eq!(
+            image[0],
+            Color::new(
+                0.5f64.sqrt() * 256.0,
+                0.5f64.sqrt() * 256.0,
+                0.5f64.sqrt() * 256.0
+            )
+        );
+    }
+
+    #[test]
+    fn building_within_the_memory_limit_succeeds_and_a_tiny_limit_fails() {
+        let mut world = HittableList::new();
+        for i in 0..100 {
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(i as f64, 0.0, 0.0),
+                0.5,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+        }
+
+        for accelerator in [Accelerator::Bvh, Accelerator::Grid, Accelerator::KdTree] {
+            let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1)
+                .with_accelerator(accelerator);
+            assert!(renderer.check_memory_limit(&world).is_ok());
+            let renderer = renderer.with_memory_limit(1024 * 1024);
+            assert!(renderer.check_memory_limit(&world).is_ok());
+            // the estimate counts the 100 spheres, not only the nodes holding them
+            assert!(accelerator.estimated_size(&world) > 100 * size_of::<Arc<dyn Hittable>>());
+
+            let err = renderer
+                .with_memory_limit(1024)
+                .check_memory_limit(&world)
+                .unwrap_err();
+            assert_eq!(err.accelerator, accelerator);
+            assert_eq!(err.object_count, 100);
+            assert_eq!(err.estimated_bytes, accelerator.estimated_size(&world));
+            assert!(err.estimated_bytes > 1024);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the limit of 1024 bytes")]
+    fn rendering_a_world_over_the_memory_limit_panics() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1)
+            .with_memory_limit(1024);
+        let camera = CameraBuilder::new()
+            .image_width(4)
+            .aspect_ratio(1.0)
+            .build();
+        let mut world = HittableList::new();
+        for i in 0..100 {
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(i as f64, 0.0, -5.0),
+                0.5,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+        }
+
+        renderer.render(camera, world);
+    }
 }
@@ -0,0 +1,94 @@
+use crate::common::Color;
+use crate::renderer::Renderer;
+
+/// Accumulates successive frames of the same view into a running average, as used by an
+/// interactive viewport that renders a single sample per pixel every frame and progressively
+/// refines the image while the camera stays still.
+///
+/// Frames must hold the *linear* radiance of each pixel, such as the frames returned by
+/// `Renderer::render_radiance`. The accumulator is bounded to a fixed number of pixels, every
+/// frame added to it must have exactly that many pixels
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    sums: Vec<Color>,
+    frame_count: u32,
+}
+
+impl Accumulator {
+    /// Returns a new, empty, accumulator for frames of `pixel_count` pixels
+    pub fn new(pixel_count: usize) -> Self {
+        Self {
+            sums: vec![Color::default(); pixel_count],
+            frame_count: 0,
+        }
+    }
+
+    /// Adds the linear radiance of `frame` to the running sums of this accumulator
+    ///
+    /// # Panics
+    /// if the length of `frame` is not equal to the pixel count of this accumulator
+    pub fn add_frame(&mut self, frame: &[Color]) {
+        assert_eq!(
+            frame.len(),
+            self.sums.len(),
+            "frame length must equal the accumulator's pixel count"
+        );
+        for (sum, color) in self.sums.iter_mut().zip(frame.iter()) {
+            *sum += *color;
+        }
+        self.frame_count += 1;
+    }
+
+    /// Clears the accumulated frames, i.e. when the camera or scene has changed
+    pub fn reset(&mut self) {
+        self.sums.fill(Color::default());
+        self.frame_count = 0;
+    }
+
+    /// Returns the number of frames accumulated since this accumulator was created or reset
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Returns the average of the accumulated frames, gamma corrected and scaled to `[0..256)`
+    /// in the same way as the colors returned by `Image::display_colors`.
+    /// If no frames were accumulated, every pixel is black
+    pub fn resolved(&self) -> Vec<Color> {
+        let frame_count = self.frame_count.max(1);
+        self.sums
+            .iter()
+            .map(|sum| Renderer::multi_sample(sum, frame_count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::renderer::Accumulator;
+
+    #[test]
+    fn adding_the_same_frame_twice_resolves_to_that_frame() {
+        let frame = vec![Color::new(0.25, 0.25, 0.25), Color::new(0.0, 0.5, 1.0)];
+        let mut accumulator = Accumulator::new(2);
+
+        accumulator.add_frame(&frame);
+        accumulator.add_frame(&frame);
+        let resolved = accumulator.resolved();
+
+        assert_eq!(accumulator.frame_count(), 2);
+        assert_eq!(resolved[0], Color::new(128.0, 128.0, 128.0));
+        assert!((resolved[1] - Color::new(0.0, 0.5f64.sqrt() * 256.0, 255.744)).length() < 1e-9);
+    }
+
+    #[test]
+    fn reset_clears_the_accumulated_frames() {
+        let mut accumulator = Accumulator::new(1);
+        accumulator.add_frame(&[Color::new(1.0, 1.0, 1.0)]);
+
+        accumulator.reset();
+
+        assert_eq!(accumulator.frame_count(), 0);
+        assert_eq!(accumulator.resolved()[0], Color::default());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::common::{Color, SamplerKind};
+use crate::renderer::{Accelerator, AntiAlias, BackgroundColor, Renderer, DEFAULT_TILE_SIZE};
+
+/// A builder struct for constructing a `Renderer`.
+///
+/// New users can start from one of the presets, `preview`, `balanced` or `final_quality`, which
+/// each set a coherent group of defaults, and then override individual settings before calling
+/// `build()`. Every preset uses a black background and one worker thread per physical cpu core
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RendererBuilder {
+    ray_bounce_depth: u32,
+    samples_per_pixel: u32,
+    background_color: BackgroundColor,
+    num_workers: usize,
+    anti_alias: AntiAlias,
+    #[cfg_attr(feature = "serde", serde(default))]
+    sampler: SamplerKind,
+    tile_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    accelerator: Accelerator,
+}
+
+impl RendererBuilder {
+    /// Returns a new builder with the `balanced` preset
+    pub fn new() -> Self {
+        RendererBuilder::balanced()
+    }
+
+    /// A fast, noisy, preset for checking the framing and lighting of a scene
+    pub fn preview() -> Self {
+        Self {
+            ray_bounce_depth: 8,
+            samples_per_pixel: 16,
+            background_color: BackgroundColor::Solid(Color::default()),
+            num_workers: num_cpus::get_physical(),
+            anti_alias: AntiAlias::Stochastic,
+            sampler: SamplerKind::Halton,
+            tile_size: DEFAULT_TILE_SIZE,
+            accelerator: Accelerator::default(),
+        }
+    }
+
+    /// A preset that gives a reasonably clean image in a reasonable amount of time
+    pub fn balanced() -> Self {
+        Self {
+            ray_bounce_depth: 50,
+            samples_per_pixel: 200,
+            ..RendererBuilder::preview()
+        }
+    }
+
+    /// A slow preset, for final renders of a scene
+    pub fn final_quality() -> Self {
+        Self {
+            ray_bounce_depth: 100,
+            samples_per_pixel: 2000,
+            ..RendererBuilder::preview()
+        }
+    }
+
+    ///////////////////////////////////////////////////////////
+    //              Builder Functions BEGIN HERE
+
+    /// Sets the maximum number of times a ray can bounce
+    pub fn ray_bounce_depth(&mut self, ray_bounce_depth: u32) -> Self {
+        self.ray_bounce_depth = ray_bounce_depth;
+        self.clone()
+    }
+
+    /// Sets the number of samples taken per pixel
+    pub fn samples_per_pixel(&mut self, samples_per_pixel: u32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self.clone()
+    }
+
+    /// Sets the background color of the scene
+    pub fn background_color(&mut self, background_color: BackgroundColor) -> Self {
+        self.background_color = background_color;
+        self.clone()
+    }
+
+    /// Sets the number of worker threads used to render
+    pub fn num_workers(&mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers;
+        self.clone()
+    }
+
+    /// Sets the anti-aliasing strategy
+    pub fn anti_alias(&mut self, anti_alias: AntiAlias) -> Self {
+        self.anti_alias = anti_alias;
+        self.clone()
+    }
+
+    /// Sets the kind of sampler used by stochastic anti-aliasing
+    pub fn sampler(&mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self.clone()
+    }
+
+    /// Sets the width and height, in pixels, of the tiles the image is split into
+    pub fn tile_size(&mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size;
+        self.clone()
+    }
+
+    /// Sets the acceleration structure the scene is placed in, i.e. `KdTree` for scenes of
+    /// axis aligned walls and boxes
+    pub fn accelerator(&mut self, accelerator: Accelerator) -> Self {
+        self.accelerator = accelerator;
+        self.clone()
+    }
+
+    /// Builds a new `Renderer` from this builder's settings
+    pub fn build(&self) -> Renderer {
+        Renderer::new(
+            self.ray_bounce_depth,
+            self.samples_per_pixel,
+            self.background_color.clone(),
+            self.num_workers.max(1),
+        )
+        .with_anti_alias(self.anti_alias)
+        .with_sampler(self.sampler)
+        .with_tile_size(self.tile_size)
+        .with_accelerator(self.accelerator)
+    }
+}
+
+impl Default for RendererBuilder {
+    fn default() -> Self {
+        RendererBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::RendererBuilder;
+
+    #[test]
+    fn presets_increase_in_quality() {
+        let presets = [
+            RendererBuilder::preview().build(),
+            RendererBuilder::balanced().build(),
+            RendererBuilder::final_quality().build(),
+        ];
+
+        for pair in presets.windows(2) {
+            assert!(pair[0].samples_per_pixel() < pair[1].samples_per_pixel());
+            assert!(pair[0].ray_bounce_depth() < pair[1].ray_bounce_depth());
+        }
+        assert!(presets
+            .iter()
+            .all(|r| r.samples_per_pixel() > 0 && r.num_workers() > 0));
+    }
+
+    #[test]
+    fn settings_override_the_preset() {
+        let renderer = RendererBuilder::preview().samples_per_pixel(3).build();
+
+        assert_eq!(renderer.samples_per_pixel(), 3);
+        assert_eq!(
+            renderer.ray_bounce_depth(),
+            RendererBuilder::preview().build().ray_bounce_depth()
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::common::Color;
+use crate::renderer::Renderer;
+use crate::util::png::BitDepth;
+use crate::util::tonemap::ToneMap;
+use crate::util::{png, ppm};
+use std::io;
+use std::path::Path;
+
+/// A rendered image, as returned by `Renderer::render`.
+///
+/// An image holds the *linear* radiance of each pixel, averaged over all of its samples, along
+/// with the `ToneMap` that the renderer was configured with. The radiance is only tone mapped,
+/// gamma corrected and quantized when the image is converted for display, by `display_colors`,
+/// `to_rgb8`, `save_png` or `save_ppm`.
+///
+/// Like every other render of this crate, the pixels are stored in row major order starting
+/// from the **bottom** row of the image. The `x, y` coordinates taken by `pixel` start from the
+/// top left corner of the image instead, as in the saved image files.
+///
+/// Images rendered by a renderer with `Renderer::with_aovs` also hold the auxiliary buffers of
+/// the render, see `Aovs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    tone_map: ToneMap,
+    aovs: Option<Aovs>,
+}
+
+/// The auxiliary buffers (arbitrary output variables) of a render, which describe the surface
+/// first hit in each pixel, i.e. to guide an external denoiser or for compositing. Every buffer
+/// is stored in the same layout as the pixels of an `Image`, starting from the bottom row.
+///
+/// `albedo` is the color that the material of the first hit applies to the light it scatters,
+/// averaged over the samples of each pixel, or white where nothing, or an emitter, was hit
+/// `normal` is the (unit) world space normal of the first hit, facing the camera, averaged over
+/// the samples of each pixel, or zero where nothing was hit
+/// `depth` is the distance from the camera to the first hit at the center of each pixel,
+/// or infinity where nothing was hit
+/// `object_id` is the id of the object first hit at the center of each pixel, or `0` where
+/// nothing was hit. Objects wrapped in an `ObjectId` keep their id, every other object is given
+/// its (1-based) index in the world's list of objects
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    albedo: Vec<Color>,
+    normal: Vec<Color>,
+    depth: Vec<f64>,
+    object_id: Vec<u32>,
+}
+
+impl Aovs {
+    /// Returns new auxiliary buffers, which must all hold the same number of pixels
+    pub fn new(
+        albedo: Vec<Color>,
+        normal: Vec<Color>,
+        depth: Vec<f64>,
+        object_id: Vec<u32>,
+    ) -> Self {
+        assert!(
+            albedo.len() == normal.len()
+                && albedo.len() == depth.len()
+                && albedo.len() == object_id.len(),
+            "auxiliary buffers must have the same length"
+        );
+        Self {
+            albedo,
+            normal,
+            depth,
+            object_id,
+        }
+    }
+
+    /// Returns the albedo of every pixel
+    pub fn albedo(&self) -> &[Color] {
+        &self.albedo
+    }
+
+    /// Returns the world space normal of every pixel
+    pub fn normal(&self) -> &[Color] {
+        &self.normal
+    }
+
+    /// Returns the depth of every pixel
+    pub fn depth(&self) -> &[f64] {
+        &self.depth
+    }
+
+    /// Returns the object id of every pixel
+    pub fn object_id(&self) -> &[u32] {
+        &self.object_id
+    }
+}
+
+impl Image {
+    /// Returns a new image of `width` x `height` pixels, holding the linear radiance `pixels`,
+    /// stored starting from the bottom row of the image
+    ///
+    /// # Panics
+    /// if the length of `pixels` is not equal to `width * height`
+    pub fn new(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            (width * height) as usize,
+            "image length must equal width * height"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+            tone_map: ToneMap::default(),
+            aovs: None,
+        }
+    }
+
+    /// Sets the tone map that is applied when this image is converted for display
+    pub fn with_tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    /// Attaches the auxiliary buffers `aovs`, rendered along with this image
+    ///
+    /// # Panics
+    /// if the buffers do not hold the same number of pixels as this image
+    pub fn with_aovs(mut self, aovs: Aovs) -> Self {
+        assert_eq!(
+            aovs.albedo.len(),
+            self.pixels.len(),
+            "auxiliary buffers must have the same size as the image"
+        );
+        self.aovs = Some(aovs);
+        self
+    }
+
+    /// Returns the auxiliary buffers rendered along with this image, if any
+    pub fn aovs(&self) -> Option<&Aovs> {
+        self.aovs.as_ref()
+    }
+
+    /// Returns the width of this image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of this image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the tone map applied when this image is converted for display
+    pub fn tone_map(&self) -> ToneMap {
+        self.tone_map
+    }
+
+    /// Returns the linear radiance of every pixel, starting from the bottom row of the image
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Consumes this image and returns the linear radiance of every pixel, starting from the
+    /// bottom row of the image
+    pub fn into_pixels(self) -> Vec<Color> {
+        self.pixels
+    }
+
+    /// Returns the linear radiance of the pixel in column `x` and row `y`, where `(0, 0)` is
+    /// the top left corner of the image
+    ///
+    /// # Panics
+    /// if `x` or `y` are outside of the image
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel is outside the image"
+        );
+        self.pixels[((self.height - 1 - y) * self.width + x) as usize]
+    }
+
+    /// Returns the display color of every pixel, tone mapped, gamma corrected and scaled to
+    /// `[0..256)`, starting from the bottom row of the image. These are the colors expected by
+    /// the writers of `util::png` and `util::ppm`
+    pub fn display_colors(&self) -> Vec<Color> {
+        self.pixels
+            .iter()
+            .map(|c| Renderer::display_color(&self.tone_map, c, 1))
+            .collect()
+    }
+
+    /// Returns the 8-bit R,G,B channels of every pixel, converted for display, starting from
+    /// the top left corner of the image, as expected by most image libraries
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let display = self.display_colors();
+        (0..self.height)
+            .rev()
+            .flat_map(|row| {
+                let start = (row * self.width) as usize;
+                display[start..start + self.width as usize].to_vec()
+            })
+            .flat_map(|c| c.as_array().map(|channel| channel as u8))
+            .collect()
+    }
+
+    /// Writes this image, converted for display, to a PNG file at `file_path`, using
+    /// `bit_depth` bits per color channel
+    pub fn save_png(
+        &self,
+        file_path: impl AsRef<Path>,
+        bit_depth: BitDepth,
+    ) -> image::ImageResult<()> {
+        png::write_file(
+            file_path,
+            self.width,
+            self.height,
+            &self.display_colors(),
+            bit_depth,
+        )
+    }
+
+    /// Writes this image, converted for display, to a PPM file at `file_path`
+    pub fn save_ppm(&self, file_path: &str) -> io::Result<()> {
+        ppm::write_file(file_path, self.width, self.height, &self.display_colors())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::renderer::Image;
+    use crate::util::tonemap::ToneMap;
+
+    #[test]
+    fn pixels_are_addressed_from_the_top_left_corner() {
+        // the bottom row is stored first
+        let image = Image::new(
+            2,
+            2,
+            vec![
+                Color::new(0.25, 0.25, 0.25),
+                Color::new(0.0, 0.0, 0.0),
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+            ],
+        );
+
+        assert_eq!(image.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(image.pixel(0, 1), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(
+            image.to_rgb8(),
+            vec![255, 0, 0, 0, 255, 0, 128, 128, 128, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn the_tone_map_is_applied_for_display() {
+        let bright = vec![Color::new(3.0, 3.0, 3.0)];
+        let clamped = Image::new(1, 1, bright.clone());
+        let mapped = Image::new(1, 1, bright).with_tone_map(ToneMap::Reinhard);
+
+        assert_eq!(clamped.to_rgb8(), vec![255, 255, 255]);
+        // reinhard maps 3 to 0.75, which is gamma corrected to sqrt(0.75)
+        assert_eq!(mapped.to_rgb8()[0], (0.75f64.sqrt() * 256.0) as u8);
+        assert_eq!(mapped.pixel(0, 0), Color::new(3.0, 3.0, 3.0));
+    }
+}
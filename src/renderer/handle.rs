@@ -0,0 +1,64 @@
+use crate::renderer::Image;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// A handle to a render running in the background, as returned by
+/// `Renderer::render_with_progress`. The handle can be polled for the progress of the render,
+/// and used to cancel it, from any thread, i.e. from the event loop of a GUI.
+///
+/// Dropping the handle does **not** cancel the render, it keeps running until it is finished
+#[derive(Debug)]
+pub struct RenderHandle {
+    thread: JoinHandle<Option<Image>>,
+    pixels_finished: Arc<AtomicUsize>,
+    pixel_count: usize,
+    cancel: Arc<AtomicBool>,
+}
+
+impl RenderHandle {
+    /// Returns a handle to the render running on `thread`, which counts its finished pixels in
+    /// `pixels_finished`, out of `pixel_count`, and stops when `cancel` is set
+    pub(crate) fn new(
+        thread: JoinHandle<Option<Image>>,
+        pixels_finished: Arc<AtomicUsize>,
+        pixel_count: usize,
+        cancel: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            thread,
+            pixels_finished,
+            pixel_count,
+            cancel,
+        }
+    }
+
+    /// Returns the fraction of the image's pixels that have been rendered, in the range
+    /// `0.0..=1.0`
+    pub fn progress(&self) -> f32 {
+        match self.pixel_count {
+            0 => 1.0,
+            count => self.pixels_finished.load(Ordering::SeqCst) as f32 / count as f32,
+        }
+    }
+
+    /// Cancels the render. The tiles that have not started rendering yet are skipped, so the
+    /// render stops once the tiles in progress are finished
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true once the render has stopped, because it is finished or was canceled
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Blocks until the render stops, and returns the rendered image, or `None` if the render
+    /// was canceled before every tile was rendered
+    ///
+    /// # Panics
+    /// if the render panicked
+    pub fn wait(self) -> Option<Image> {
+        self.thread.join().expect("the render thread panicked")
+    }
+}
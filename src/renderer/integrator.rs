@@ -0,0 +1,742 @@
+use crate::common::{Color, Ray};
+use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::material::{Material, MediumStack, ScatterRecord};
+use crate::pdf::{CosinePdf, HittablePdf, MixturePdf, Pdf};
+use crate::renderer::Renderer;
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// An algorithm that computes the color of each sample of a `Color` or `Radiance` render pass.
+/// The `renderer` gives access to the scene wide settings of the render, so an integrator only
+/// has to decide how rays are followed: `Renderer::visible_hit` finds the surface a ray sees,
+/// `background` the color of rays that hit nothing, `direct_light` and `ambient_light` the
+/// light of the renderer's lights that are not part of the world, `lights` the emitters that
+/// can be importance sampled, `clay` the material of a clay render, and `ray_bounce_depth`
+/// the bounce limit
+pub trait Integrator: Send + Sync + Debug {
+    /// Returns the light arriving at the camera along the camera `ray`, from the `world`
+    fn ray_color(&self, renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color;
+}
+
+/// The kind of `Integrator` a renderer uses
+/// `PathTracing` - the default, a `PathTracer`
+/// `AmbientOcclusion { max_distance }` - an `AmbientOcclusion` preview of the scene's shapes
+/// `DirectLighting { shadow_samples }` - a `DirectLighting` integrator, that skips all
+/// indirect light
+/// `Debug(mode)` - a `DebugIntegrator`, that shows a property of the surfaces seen by the camera
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntegratorKind {
+    #[default]
+    PathTracing,
+    AmbientOcclusion {
+        max_distance: f64,
+    },
+    DirectLighting {
+        shadow_samples: u32,
+    },
+    Debug(DebugMode),
+}
+
+impl IntegratorKind {
+    /// Returns a new integrator of this kind
+    pub fn integrator(&self) -> Arc<dyn Integrator> {
+        match *self {
+            IntegratorKind::PathTracing => Arc::new(PathTracer),
+            IntegratorKind::AmbientOcclusion { max_distance } => {
+                Arc::new(AmbientOcclusion::new(max_distance))
+            }
+            IntegratorKind::DirectLighting { shadow_samples } => {
+                Arc::new(DirectLighting::new(shadow_samples))
+            }
+            IntegratorKind::Debug(mode) => Arc::new(DebugIntegrator::new(mode)),
+        }
+    }
+}
+
+/// Parses an integrator kind from `path`, `ao:<max_distance>`, `direct`, `direct:<shadow_samples>`
+/// or `debug:<mode>`
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            ("path", None) => Ok(IntegratorKind::PathTracing),
+            ("ao", Some(arg)) => arg
+                .parse::<f64>()
+                .map(|max_distance| IntegratorKind::AmbientOcclusion { max_distance })
+                .map_err(|e| format!("invalid ambient occlusion distance '{}': {}", arg, e)),
+            ("direct", None) => Ok(IntegratorKind::DirectLighting { shadow_samples: 1 }),
+            ("direct", Some(arg)) => match arg.parse::<u32>() {
+                Ok(shadow_samples) if shadow_samples > 0 => {
+                    Ok(IntegratorKind::DirectLighting { shadow_samples })
+                }
+                _ => Err(format!(
+                    "invalid shadow sample count '{}', expected a positive integer",
+                    arg
+                )),
+            },
+            ("debug", Some(arg)) => arg.parse::<DebugMode>().map(IntegratorKind::Debug),
+            _ => Err(format!(
+                "unknown integrator '{}', expected path, ao:<max_distance>, direct[:<shadow_samples>] or debug:<mode>",
+                s
+            )),
+        }
+    }
+}
+
+/// The full path tracer, follows each ray as it bounces around the scene, up to the renderer's
+/// ray bounce depth, gathering the light of the emitters and the background it reaches.
+/// Diffuse surfaces also gather the renderer's ambient light and the light of its direct
+/// lights, and send some of their scattered rays towards its `lights`, `portals` and
+/// environment map, which greatly reduces the noise of scenes lit by small lights
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PathTracer;
+
+impl PathTracer {
+    /// determine if a Ray has hit a `Hittable` object in the `world` and compute the pixel color
+    /// of the Ray, `r`. The Hittable's `Material` is taken into account when performing ray bouncing
+    /// (up to `depth` times) in order to get an accurate color determination. If nothing
+    /// was hit then the `background` color of the `renderer` is returned
+    pub fn ray_color_at_depth<T: Hittable + ?Sized>(
+        &self,
+        renderer: &Renderer,
+        ray: &Ray,
+        world: &T,
+        depth: u32,
+    ) -> Color {
+        self.ray_color_in_medium(
+            renderer,
+            ray,
+            world,
+            depth,
+            renderer.volume_bounce_depth(),
+            &MediumStack::new(),
+        )
+    }
+
+    /// computes the color of `ray` like `ray_color_at_depth`, for a ray that is travelling
+    /// through the (nested) dielectric `media`. The media are updated whenever the ray is
+    /// transmitted into, or out of, a dielectric.
+    /// `volume_depth` is the remaining bounce limit of volume scatters, if they have their own
+    /// limit, otherwise volume scatters use up `depth` like surface scatters
+    pub fn ray_color_in_medium<T: Hittable + ?Sized>(
+        &self,
+        renderer: &Renderer,
+        ray: &Ray,
+        world: &T,
+        depth: u32,
+        volume_depth: Option<u32>,
+        media: &MediumStack,
+    ) -> Color {
+        // exceeded the ray bounce limit, no more light is gathered
+        if depth == 0 || volume_depth == Some(0) {
+            return Color::default();
+        }
+
+        // if a hittable was hit, determine if its material will scatter the incoming
+        // ray, AND how much light the material emits
+        let primary =
+            depth == renderer.ray_bounce_depth() && volume_depth == renderer.volume_bounce_depth();
+        if let Some(ref rec) = Renderer::visible_hit(ray, world, primary) {
+            let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+            // a clay render replaces the material of every surface that does not emit light
+            let mat_ptr = match renderer.clay() {
+                Some(clay) if emitted == Color::default() => clay,
+                _ => &rec.mat_ptr,
+            };
+
+            if let Some(mut scatter_rec) = mat_ptr.scatter_in_medium(ray, rec, media) {
+                if let Some(tint) = renderer
+                    .tir_tint()
+                    .filter(|_| scatter_rec.total_internal_reflection)
+                {
+                    scatter_rec.attenuation = scatter_rec.attenuation * tint;
+                }
+                // only diffuse surfaces, which scatter with a PDF, pick up the ambient light
+                let ambient = match scatter_rec.pdf {
+                    Some(_) => renderer.ambient_light(rec, &scatter_rec.attenuation),
+                    None => Color::default(),
+                };
+                let direct = renderer.direct_light(ray, rec, mat_ptr.as_ref(), &scatter_rec, world);
+                let weight = PathTracer::importance_sample(
+                    renderer,
+                    ray,
+                    rec,
+                    mat_ptr.as_ref(),
+                    &mut scatter_rec,
+                );
+                if weight <= 0.0 {
+                    return emitted + ambient + direct;
+                }
+                let scattered_media = media.transmit(rec, &scatter_rec.scattered);
+                // volume scatters use up their own bounce limit, if they have one
+                let (depth, volume_depth) = match volume_depth {
+                    Some(volume_depth) if mat_ptr.is_volumetric() => {
+                        (depth, Some(volume_depth - 1))
+                    }
+                    _ => (depth - 1, volume_depth),
+                };
+                emitted
+                    + ambient
+                    + direct
+                    + weight
+                        * scatter_rec.attenuation
+                        * self.ray_color_in_medium(
+                            renderer,
+                            &scatter_rec.scattered,
+                            world,
+                            depth,
+                            volume_depth,
+                            scattered_media.as_ref().unwrap_or(media),
+                        )
+            } else {
+                emitted
+            }
+        } else {
+            // nothing hit, return the background color
+            renderer.background(ray, primary)
+        }
+    }
+
+    /// Importance samples the lights and the environment map of the `renderer`, if it has any
+    /// and the material `mat` scattered the ray with a PDF. The scattered ray of `scatter_rec`
+    /// is replaced by a ray drawn from an equal mixture of the material's PDF and the PDF of
+    /// the lights, or of the environment map, or of an equal mixture of both.
+    /// Returns the weight of the scattered ray, the material's scattering PDF divided by the
+    /// PDF the ray was drawn from, which is 1 if the scattered ray was not replaced
+    fn importance_sample(
+        renderer: &Renderer,
+        ray: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        scatter_rec: &mut ScatterRecord,
+    ) -> f64 {
+        let pdf = match &scatter_rec.pdf {
+            Some(pdf) => pdf,
+            None => return 1.0,
+        };
+        let hittable_pdf = |hittable: &Arc<dyn Hittable>| -> Arc<dyn Pdf> {
+            Arc::new(HittablePdf::new(Arc::clone(hittable), rec.p))
+        };
+        // the lights and the portals are sampled as one set of emitters
+        let emitters_pdf = match (renderer.lights(), renderer.portals()) {
+            (Some(lights), Some(portals)) => Some(Arc::new(MixturePdf::new(
+                hittable_pdf(lights),
+                hittable_pdf(portals),
+            )) as Arc<dyn Pdf>),
+            (lights, portals) => lights.or(portals).map(hittable_pdf),
+        };
+        let light_pdf: Arc<dyn Pdf> = match (emitters_pdf, renderer.environment()) {
+            (Some(emitters), Some(environment)) => Arc::new(MixturePdf::new(
+                emitters,
+                Arc::clone(environment) as Arc<dyn Pdf>,
+            )),
+            (Some(emitters), None) => emitters,
+            (None, Some(environment)) => Arc::clone(environment) as Arc<dyn Pdf>,
+            (None, None) => return 1.0,
+        };
+        let mixture = MixturePdf::new(light_pdf, Arc::clone(pdf));
+        let scattered = Ray::new(rec.p, mixture.generate(), ray.time());
+        let pdf_value = mixture.value(&scattered.direction());
+        let weight = if pdf_value > 0.0 {
+            mat.scattering_pdf(ray, rec, &scattered) / pdf_value
+        } else {
+            0.0
+        };
+        scatter_rec.scattered = scattered;
+        weight
+    }
+}
+
+impl Integrator for PathTracer {
+    fn ray_color(&self, renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color {
+        self.ray_color_at_depth(renderer, ray, world, renderer.ray_bounce_depth())
+    }
+}
+
+/// A fast preview of the shapes of a scene, that ignores all lights and materials. Each sample
+/// casts one cosine distributed ray from the surface seen by the camera, which is white if it
+/// escapes further than `max_distance`, or black if it hits another surface first. Rays that
+/// hit nothing are white
+#[derive(Debug, Copy, Clone)]
+pub struct AmbientOcclusion {
+    max_distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(max_distance: f64) -> Self {
+        Self { max_distance }
+    }
+
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn ray_color(&self, _renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color {
+        let open = Color::new(1.0, 1.0, 1.0);
+        match Renderer::visible_hit(ray, world, true) {
+            Some(rec) => {
+                let direction = CosinePdf::new(&rec.normal).generate().unit_vector();
+                let occlusion_ray = Ray::new(rec.p, direction, ray.time());
+                match world.hit_any(&occlusion_ray, 0.001, self.max_distance) {
+                    true => Color::default(),
+                    false => open,
+                }
+            }
+            None => open,
+        }
+    }
+}
+
+/// Gathers only the light that reaches a diffuse surface straight from a light source: the
+/// renderer's direct lights, its emissive `lights`, and its ambient light.
+/// The emissive `lights` are sampled by `shadow_samples` shadow rays cast towards random points
+/// on them from each diffuse hit. The light of the shadow rays is averaged, so more shadow rays
+/// give smoother soft shadows without taking more samples per pixel. Only this integrator casts
+/// them, the `PathTracer` samples the lights with its scattered rays instead.
+/// Specular surfaces (mirrors and glass) are followed until a diffuse surface is reached, but
+/// light that bounces off another diffuse surface first is skipped, as is the environment map.
+/// Much faster, and noise free, compared to path tracing, for checking the placement of lights
+#[derive(Debug, Copy, Clone)]
+pub struct DirectLighting {
+    shadow_samples: u32,
+}
+
+impl Default for DirectLighting {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl DirectLighting {
+    /// Returns a new integrator that casts `shadow_samples` shadow rays towards the emissive
+    /// lights from each diffuse hit
+    ///
+    /// # Panics
+    /// if `shadow_samples` is 0
+    pub fn new(shadow_samples: u32) -> Self {
+        assert!(shadow_samples > 0, "at least one shadow ray is needed");
+        Self { shadow_samples }
+    }
+
+    pub fn shadow_samples(&self) -> u32 {
+        self.shadow_samples
+    }
+
+    /// Returns the direct light arriving along `ray`, which has `depth` specular bounces left
+    fn ray_color_at_depth(
+        &self,
+        renderer: &Renderer,
+        ray: &Ray,
+        world: &dyn Hittable,
+        depth: u32,
+    ) -> Color {
+        if depth == 0 {
+            return Color::default();
+        }
+        let primary = depth == renderer.ray_bounce_depth();
+        let rec = match Renderer::visible_hit(ray, world, primary) {
+            Some(rec) => rec,
+            None => return renderer.background(ray, primary),
+        };
+        let emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+        // a clay render replaces the material of every surface that does not emit light
+        let mat_ptr = match renderer.clay() {
+            Some(clay) if emitted == Color::default() => clay,
+            _ => &rec.mat_ptr,
+        };
+        let scatter_rec = match mat_ptr.scatter(ray, &rec) {
+            Some(scatter_rec) => scatter_rec,
+            None => return emitted,
+        };
+        if scatter_rec.pdf.is_none() {
+            // a specular surface only reflects the light arriving along its scattered ray
+            return emitted
+                + scatter_rec.attenuation
+                    * self.ray_color_at_depth(renderer, &scatter_rec.scattered, world, depth - 1);
+        }
+        emitted
+            + renderer.ambient_light(&rec, &scatter_rec.attenuation)
+            + renderer.direct_light(ray, &rec, mat_ptr.as_ref(), &scatter_rec, world)
+            + self.area_light(
+                renderer,
+                ray,
+                &rec,
+                mat_ptr.as_ref(),
+                &scatter_rec.attenuation,
+                world,
+            )
+    }
+
+    /// Returns the light of the renderer's emissive `lights` reflected by the hit `rec`,
+    /// averaged over `shadow_samples` shadow rays cast towards random points on the lights
+    fn area_light(
+        &self,
+        renderer: &Renderer,
+        ray: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        attenuation: &Color,
+        world: &dyn Hittable,
+    ) -> Color {
+        let lights = match renderer.lights() {
+            Some(lights) => lights,
+            None => return Color::default(),
+        };
+        let light_pdf = HittablePdf::new(Arc::clone(lights), rec.p);
+        let shadow_samples = self.shadow_samples;
+        let mut light = Color::default();
+        for _ in 0..shadow_samples {
+            let shadow_ray = Ray::new(rec.p, light_pdf.generate(), ray.time());
+            let pdf = light_pdf.value(&shadow_ray.direction());
+            let scattering_pdf = mat.scattering_pdf(ray, rec, &shadow_ray);
+            if pdf <= 0.0 || scattering_pdf <= 0.0 {
+                continue;
+            }
+            // the first surface along the shadow ray is the light, unless the light is occluded
+            if let Some(light_rec) = Renderer::visible_hit(&shadow_ray, world, false) {
+                light += scattering_pdf / pdf
+                    * light_rec
+                        .mat_ptr
+                        .emitted(light_rec.u, light_rec.v, &light_rec.p);
+            }
+        }
+        *attenuation * light / shadow_samples as f64
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn ray_color(&self, renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color {
+        self.ray_color_at_depth(renderer, ray, world, renderer.ray_bounce_depth())
+    }
+}
+
+/// The surface property shown by a `DebugIntegrator`
+/// `Normals` - the outward normal of the surface, with its `x`, `y` and `z` components mapped
+///  from `[-1..1]` to red, green and blue. The outward normal follows `HitRecord::front_face`,
+///  so a face flipped by `FlipFace` shows the opposite color of its unflipped neighbours
+/// `Depth { far }` - the distance from the camera to the surface, white right at the camera
+///  fading linearly to black at `far`
+/// `Uv` - the texture coordinates of the surface, `u` in red and `v` in green
+/// `BvhHeatmap { max_tests }` - the number of `Aabb::hit` tests performed to find the surface,
+///  as a heatmap going from blue, for no tests, over green to red, for `max_tests` or more.
+///  Hot spots show where the bounding volume hierarchy splits the scene poorly. Unlike the
+///  other modes, rays that hit nothing are colored too. The tests are only counted with the
+///  `bvh-heatmap` feature
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebugMode {
+    Normals,
+    Depth { far: f64 },
+    Uv,
+    BvhHeatmap { max_tests: u32 },
+}
+
+/// Parses a debug mode from `normals`, `depth:<far>`, `uv` or `bvh:<max_tests>`
+impl FromStr for DebugMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            ("normals", None) => Ok(DebugMode::Normals),
+            ("depth", Some(arg)) => match arg.parse::<f64>() {
+                Ok(far) if far > 0.0 => Ok(DebugMode::Depth { far }),
+                _ => Err(format!(
+                    "invalid far distance '{}', expected a positive number",
+                    arg
+                )),
+            },
+            ("uv", None) => Ok(DebugMode::Uv),
+            ("bvh", Some(_)) if cfg!(not(feature = "bvh-heatmap")) => Err(
+                "the bvh debug mode needs the bvh-heatmap feature, which counts the bounding \
+                 box tests"
+                    .to_string(),
+            ),
+            ("bvh", Some(arg)) => match arg.parse::<u32>() {
+                Ok(max_tests) if max_tests > 0 => Ok(DebugMode::BvhHeatmap { max_tests }),
+                _ => Err(format!(
+                    "invalid maximum test count '{}', expected a positive integer",
+                    arg
+                )),
+            },
+            _ => Err(format!(
+                "unknown debug mode '{}', expected normals, depth:<far>, uv or bvh:<max_tests>",
+                s
+            )),
+        }
+    }
+}
+
+/// Colors each sample by a property of the first surface seen by the camera, ignoring all
+/// lights and materials, to diagnose the geometry of a scene. Rays that hit nothing are black
+#[derive(Debug, Copy, Clone)]
+pub struct DebugIntegrator {
+    mode: DebugMode,
+}
+
+impl DebugIntegrator {
+    pub fn new(mode: DebugMode) -> Self {
+        Self { mode }
+    }
+
+    pub fn mode(&self) -> DebugMode {
+        self.mode
+    }
+
+    /// Maps `heat`, in the range `[0..1]`, from blue over green to red
+    fn heat_color(heat: f64) -> Color {
+        let heat = heat.clamp(0.0, 1.0);
+        Color::new(
+            (2.0 * heat - 1.0).max(0.0),
+            1.0 - (2.0 * heat - 1.0).abs(),
+            (1.0 - 2.0 * heat).max(0.0),
+        )
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn ray_color(&self, _renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color {
+        if let DebugMode::BvhHeatmap { max_tests } = self.mode {
+            let tests_before = Aabb::hit_tests();
+            Renderer::visible_hit(ray, world, true);
+            let tests = Aabb::hit_tests() - tests_before;
+            return DebugIntegrator::heat_color(tests as f64 / max_tests as f64);
+        }
+        let rec = match Renderer::visible_hit(ray, world, true) {
+            Some(rec) => rec,
+            None => return Color::default(),
+        };
+        match self.mode {
+            DebugMode::Normals => {
+                let outward = if rec.front_face {
+                    rec.normal
+                } else {
+                    -rec.normal
+                };
+                0.5 * (outward.unit_vector() + Color::new(1.0, 1.0, 1.0))
+            }
+            DebugMode::Depth { far } => {
+                let distance = rec.t * ray.direction().length();
+                let brightness = (1.0 - distance / far).max(0.0);
+                Color::new(brightness, brightness, brightness)
+            }
+            DebugMode::Uv => Color::new(rec.u, rec.v, 0.0),
+            DebugMode::BvhHeatmap { .. } => unreachable!("the heatmap is computed above"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3, Ray, Vec3};
+    use crate::hittable::{build_solid_sphere, FlipFace, Hittable, HittableList, Sphere};
+    use crate::light::PointLight;
+    use crate::material::DiffuseLight;
+    use crate::renderer::{
+        AmbientOcclusion, BackgroundColor, DebugIntegrator, DebugMode, DirectLighting, Integrator,
+        IntegratorKind, PathTracer, Renderer,
+    };
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    /// a large ground sphere with a unit sphere resting on it, at the origin
+    fn build_world() -> HittableList {
+        let mut world = HittableList::new();
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        world.add(Arc::new(build_solid_sphere(
+            Point3::new(0.0, 1.0, 0.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        world
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_the_ground_beside_an_object() {
+        let world = build_world();
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1);
+        let integrator = AmbientOcclusion::new(2.0);
+        let occlusion = |x: f64| -> f64 {
+            let ray = Ray::new(Point3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+            (0..1000)
+                .map(|_| integrator.ray_color(&renderer, &ray, &world).x())
+                .sum::<f64>()
+                / 1000.0
+        };
+
+        // the ground right beside the sphere is partly covered by it, the ground far away is not
+        assert!(occlusion(1.1) < 0.8);
+        assert_eq!(occlusion(10.0), 1.0);
+        assert_eq!(
+            "ao:2.5".parse::<IntegratorKind>(),
+            Ok(IntegratorKind::AmbientOcclusion { max_distance: 2.5 })
+        );
+        assert!("ao".parse::<IntegratorKind>().is_err());
+    }
+
+    #[test]
+    fn direct_lighting_only_sees_unshadowed_lights() {
+        let world = build_world();
+        let light = Arc::new(PointLight::new(
+            Point3::new(0.0, 10.0, 0.0),
+            Color::new(100.0, 100.0, 100.0),
+        ));
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1)
+            .with_direct_light(light);
+        // rays that hit the ground at x, from the side of the sphere
+        let color_at = |x: f64| {
+            let ray = Ray::new(Point3::new(x, 0.5, 5.0), Vec3::new(0.0, -0.5, -5.0), 0.0);
+            DirectLighting::default().ray_color(&renderer, &ray, &world)
+        };
+
+        // the ground away from the sphere is lit, the ground beside its base is in its shadow
+        assert!(color_at(3.0).x() > 0.0);
+        assert_eq!(color_at(0.5), Color::default());
+        assert_eq!(
+            "direct".parse::<IntegratorKind>(),
+            Ok(IntegratorKind::DirectLighting { shadow_samples: 1 })
+        );
+        assert_eq!(
+            "direct:16".parse::<IntegratorKind>(),
+            Ok(IntegratorKind::DirectLighting { shadow_samples: 16 })
+        );
+        assert!("direct:0".parse::<IntegratorKind>().is_err());
+    }
+
+    #[test]
+    fn more_shadow_samples_reduce_the_noise_of_a_penumbra() {
+        let mut world = build_world();
+        let light: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::new(0.0, 4.0, 0.0),
+            1.0,
+            Arc::new(DiffuseLight::from(Arc::new(SolidColor::from_rgb(
+                4.0, 4.0, 4.0,
+            )))),
+        ));
+        world.add(Arc::clone(&light));
+        // a ray that hits the ground in the penumbra of the unit sphere, where the area light
+        // is partly hidden by the sphere
+        let ray = Ray::new(Point3::new(1.3, 5.0, 5.0), Vec3::new(0.0, -5.0, -5.0), 0.0);
+        let variance = |shadow_samples: u32| {
+            let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1)
+                .with_lights(Arc::clone(&light));
+            let integrator = DirectLighting::new(shadow_samples);
+            let samples: Vec<f64> = (0..500)
+                .map(|_| integrator.ray_color(&renderer, &ray, &world).x())
+                .collect();
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            assert!(mean > 0.0);
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64
+        };
+
+        // averaging 16 shadow rays divides the variance by about 16
+        assert!(variance(16) < variance(1) / 4.0);
+    }
+
+    #[test]
+    fn custom_integrators_can_be_built_on_the_public_renderer_api() {
+        /// only gathers the light emitted by the surfaces that camera rays see
+        #[derive(Debug)]
+        struct EmissionOnly;
+
+        impl Integrator for EmissionOnly {
+            fn ray_color(&self, renderer: &Renderer, ray: &Ray, world: &dyn Hittable) -> Color {
+                match Renderer::visible_hit(ray, world, true) {
+                    Some(rec) => rec.mat_ptr.emitted(rec.u, rec.v, &rec.p),
+                    None => renderer.background(ray, true),
+                }
+            }
+        }
+
+        let renderer = Renderer::new(1, 1, BackgroundColor::Solid(Color::new(0.1, 0.2, 0.3)), 1);
+        let light: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Arc::new(DiffuseLight::from(Arc::new(SolidColor::from_rgb(
+                4.0, 4.0, 4.0,
+            )))),
+        ));
+        let hit = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let miss = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+        // with a single bounce, the path tracer gathers nothing but emission and background
+        for ray in [hit, miss] {
+            assert_eq!(
+                EmissionOnly.ray_color(&renderer, &ray, &*light),
+                PathTracer.ray_color(&renderer, &ray, &*light)
+            );
+        }
+        assert_eq!(
+            EmissionOnly.ray_color(&renderer, &hit, &*light),
+            Color::new(4.0, 4.0, 4.0)
+        );
+    }
+
+    #[test]
+    fn debug_normals_show_flipped_faces() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1);
+        let sphere: Arc<dyn Hittable> = Arc::new(build_solid_sphere(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5),
+        ));
+        let flipped = FlipFace::from(Arc::clone(&sphere));
+        let integrator = DebugIntegrator::new(DebugMode::Normals);
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        // the front of the sphere faces the camera, along +z
+        let front = integrator.ray_color(&renderer, &ray, &*sphere);
+        assert!((front - Color::new(0.5, 0.5, 1.0)).length() < 1e-9);
+        let back = integrator.ray_color(&renderer, &ray, &flipped);
+        assert!((back - Color::new(0.5, 0.5, 0.0)).length() < 1e-9);
+
+        let depth = DebugIntegrator::new(DebugMode::Depth { far: 4.0 });
+        assert!((depth.ray_color(&renderer, &ray, &*sphere).x() - 0.5).abs() < 1e-9);
+        assert_eq!(
+            "debug:depth:4".parse::<IntegratorKind>(),
+            Ok(IntegratorKind::Debug(DebugMode::Depth { far: 4.0 }))
+        );
+        assert!("debug:depth:0".parse::<IntegratorKind>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "bvh-heatmap")]
+    fn bvh_heatmap_counts_bounding_box_tests() {
+        let renderer = Renderer::new(5, 1, BackgroundColor::Solid(Color::default()), 1);
+        let mut world = HittableList::new();
+        for i in 0..16 {
+            world.add(Arc::new(build_solid_sphere(
+                Point3::new(i as f64 * 3.0, 0.0, -3.0),
+                1.0,
+                Color::new(0.5, 0.5, 0.5),
+            )));
+        }
+        let bvh = crate::hittable::BvhNode::from(&world, 0.0, 1.0);
+        let heatmap = |max_tests: u32| {
+            let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            DebugIntegrator::new(DebugMode::BvhHeatmap { max_tests })
+                .ray_color(&renderer, &ray, &bvh)
+        };
+
+        // finding the first sphere tests at least the boxes on the path from the root to it
+        assert_eq!(heatmap(1), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(heatmap(1000).x(), 0.0);
+        assert!(heatmap(1000).z() > 0.9);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::common::{Color, Vec3};
+use std::f64::consts::PI;
+
+/// scales the luminance of the sky model, in kcd/m², to the radiance range of the renderer.
+/// A clear sky, with the sun half way up, has a zenith radiance of about `0.5`
+const SKY_LUMINANCE_SCALE: f64 = 0.05;
+
+/// The Perez coefficients `A` to `E` of the luminance `Y` and of the chromaticities `x` and
+/// `y`, as linear functions `a*T + b` of the turbidity `T`
+const PEREZ_Y: [(f64, f64); 5] = [
+    (0.1787, -1.4630),
+    (-0.3554, 0.4275),
+    (-0.0227, 5.3251),
+    (0.1206, -2.5771),
+    (-0.0670, 0.3703),
+];
+const PEREZ_X: [(f64, f64); 5] = [
+    (-0.0193, -0.2592),
+    (-0.0665, 0.0008),
+    (-0.0004, 0.2125),
+    (-0.0641, -0.8989),
+    (-0.0033, 0.0452),
+];
+const PEREZ_CHROMA_Y: [(f64, f64); 5] = [
+    (-0.0167, -0.2608),
+    (-0.0950, 0.0092),
+    (-0.0079, 0.2102),
+    (-0.0441, -1.6537),
+    (-0.0109, 0.0529),
+];
+
+/// The Preetham analytic model of a clear daytime sky, from "A Practical Analytic Model for
+/// Daylight" (Preetham, Shirley and Smits, 1999).
+///
+/// The sky is positioned by the `sun_elevation`, in degrees above the horizon, and the
+/// `sun_azimuth`, in degrees from the negative z axis towards the positive x axis. `turbidity`
+/// is the haziness of the atmosphere, from about `2` for a very clear sky to `10` for a hazy
+/// one. Only the sky is modelled, the sun itself should be added as a `DirectionalLight`
+/// travelling along the negated `sun_direction`. Rays below the horizon see the sky mirrored
+/// in the horizon
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    turbidity: f64,
+    // the Perez coefficients of Y, x and y
+    perez: [[f64; 5]; 3],
+    // the Y, x and y values at the zenith, divided by the Perez function at the zenith
+    zenith: [f64; 3],
+}
+
+impl PreethamSky {
+    /// Returns a sky lit by a sun at `sun_elevation` and `sun_azimuth`, in degrees. The
+    /// elevation is clamped to `[0..90]` and the turbidity to `[1.7..10]`, where the model is
+    /// valid
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        let elevation = sun_elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = sun_azimuth.to_radians();
+        let t = turbidity.clamp(1.7, 10.0);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        // the angle between the zenith and the sun
+        let theta_s = PI / 2.0 - elevation;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic =
+            |c: [f64; 4]| c[0] * theta_s.powi(3) + c[1] * theta_s.powi(2) + c[2] * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let coefficients = |table: [(f64, f64); 5]| table.map(|(a, b)| a * t + b);
+        let perez = [
+            coefficients(PEREZ_Y),
+            coefficients(PEREZ_X),
+            coefficients(PEREZ_CHROMA_Y),
+        ];
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let mut normalized = [0.0; 3];
+        for (i, value) in normalized.iter_mut().enumerate() {
+            *value = zenith[i] / PreethamSky::perez_function(&perez[i], 0.0, theta_s);
+        }
+
+        Self {
+            sun_direction,
+            turbidity: t,
+            perez,
+            zenith: normalized,
+        }
+    }
+
+    /// Returns the unit vector pointing from the world towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    /// Returns the turbidity of this sky
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    /// Returns the linear sRGB radiance of the sky in the given `direction`
+    pub fn value(&self, direction: &Vec3) -> Color {
+        // rays below the horizon see the sky mirrored in the horizon
+        let direction = Vec3::new(direction.x(), direction.y().abs(), direction.z()).unit_vector();
+        let cos_theta = direction.y().max(0.001);
+        let theta = cos_theta.acos();
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y] = [0, 1, 2]
+            .map(|i| self.zenith[i] * PreethamSky::perez_function(&self.perez[i], theta, gamma));
+        let luminance = luminance * SKY_LUMINANCE_SCALE;
+
+        // xyY to XYZ, and then to linear sRGB
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        Color::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+        )
+    }
+
+    /// The Perez sky distribution function, of the angle `theta` between a direction and the
+    /// zenith, and the angle `gamma` between the direction and the sun
+    fn perez_function(c: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+        (1.0 + c[0] * (c[1] / theta.cos()).exp())
+            * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Vec3;
+    use crate::renderer::PreethamSky;
+
+    #[test]
+    fn clear_sky_is_blue_and_brightest_around_the_sun() {
+        let sky = PreethamSky::new(45.0, 0.0, 2.5);
+        let sun = sky.sun_direction();
+        assert!((sun - Vec3::new(0.0, 1.0, -1.0).unit_vector()).length() < 1e-9);
+
+        let zenith = sky.value(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.z() > zenith.x());
+        assert!(zenith.luminance() > 0.1 && zenith.luminance() < 2.0);
+
+        // at the same height, the sky towards the sun is brighter than away from it
+        let towards = sky.value(&Vec3::new(0.0, 0.5, -1.0));
+        let away = sky.value(&Vec3::new(0.0, 0.5, 1.0));
+        assert!(towards.luminance() > away.luminance());
+        assert_eq!(
+            sky.value(&Vec3::new(1.0, -0.5, 0.0)),
+            sky.value(&Vec3::new(1.0, 0.5, 0.0))
+        );
+    }
+
+    #[test]
+    fn hazy_skies_are_less_saturated() {
+        let saturation = |turbidity: f64| {
+            let c = PreethamSky::new(30.0, 0.0, turbidity).value(&Vec3::new(0.0, 1.0, 0.0));
+            c.z() / c.x()
+        };
+
+        assert!(saturation(2.0) > saturation(8.0));
+    }
+}
@@ -0,0 +1,95 @@
+/// the default width and height, in pixels, of the tiles an image is split into for rendering
+pub const DEFAULT_TILE_SIZE: u32 = 32;
+
+/// A rectangular region of an image, that is rendered as a single job by a `Renderer`.
+/// `x` and `y` are the column and row of the tile's first pixel, and `width` and `height` are
+/// the number of pixels it covers in each direction
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Splits an image of `image_width` by `image_height` pixels into square tiles of
+    /// `tile_size` pixels, ordered row by row. The tiles along the last column and row of the
+    /// image are clipped to the image, so they may be smaller than `tile_size`
+    ///
+    /// # Panics
+    /// if `tile_size` is zero
+    pub fn split(image_width: u32, image_height: u32, tile_size: u32) -> Vec<Tile> {
+        assert!(tile_size > 0, "tile size must be at least one pixel");
+        (0..image_height)
+            .step_by(tile_size as usize)
+            .flat_map(|y| {
+                (0..image_width)
+                    .step_by(tile_size as usize)
+                    .map(move |x| Tile {
+                        x,
+                        y,
+                        width: tile_size.min(image_width - x),
+                        height: tile_size.min(image_height - y),
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the number of pixels in this tile
+    pub fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns the `(column, row)` image coordinates of the pixels in this tile, in row major
+    /// order
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let tile = *self;
+        (tile.y..tile.y + tile.height)
+            .flat_map(move |row| (tile.x..tile.x + tile.width).map(move |col| (col, row)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::Tile;
+
+    #[test]
+    fn tiles_cover_every_pixel_exactly_once() {
+        let (width, height) = (70, 45);
+        let tiles = Tile::split(width, height, 32);
+
+        assert_eq!(tiles.len(), 6);
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in &tiles {
+            for (col, row) in tile.pixels() {
+                covered[(row * width + col) as usize] += 1;
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn edge_tiles_are_clipped_to_the_image() {
+        let tiles = Tile::split(40, 10, 32);
+
+        assert_eq!(
+            tiles,
+            vec![
+                Tile {
+                    x: 0,
+                    y: 0,
+                    width: 32,
+                    height: 10
+                },
+                Tile {
+                    x: 32,
+                    y: 0,
+                    width: 8,
+                    height: 10
+                },
+            ]
+        );
+        assert_eq!(tiles[1].pixel_count(), 80);
+    }
+}
@@ -4,9 +4,15 @@ pub mod checkered_spheres;
 pub mod cornell_boxes;
 pub mod earth;
 pub mod final_scene;
+pub mod gltf_import;
+pub mod graph;
+pub mod loader;
 pub mod perlin_spheres;
 pub mod random_spheres;
+pub mod registry;
+pub mod room;
 
+use crate::scene::graph::SceneGraph;
 use clap::ValueEnum;
 
 /// `Scene` lists the available pre-made, default scenes that can be rendered
@@ -33,4 +39,23 @@ impl Scene {
             _ => None,
         }
     }
+
+    /// Returns the `SceneGraph` of this pre-made scene, if it is built from one. Currently only
+    /// the random sphere scene is, as the other scenes use hittables and textures that a scene
+    /// file cannot describe
+    pub fn scene_graph(&self) -> Option<SceneGraph> {
+        match self {
+            Scene::RandomSpheres => Some(random_spheres::build_random_sphere_scene_graph()),
+            _ => None,
+        }
+    }
+
+    /// Saves the `SceneGraph` of this scene to a scene file at `path`, see `scene_graph`
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), loader::SceneFileError> {
+        let graph = self.scene_graph().ok_or_else(|| {
+            loader::SceneFileError::Invalid(format!("the {:?} scene cannot be saved", self))
+        })?;
+        graph.save(path)
+    }
 }
@@ -1,9 +1,9 @@
-use crate::common::{Camera, CameraBuilder, Color, Point3, Vec3};
+use crate::common::{Camera, CameraBuilder, Color, Point3, RngContext, Vec3};
 use crate::hittable::primitive::builder::build_solid_moving_sphere;
 use crate::hittable::{
     build_constant_medium, build_dielectric_sphere, build_earth_sphere, build_metal_sphere,
     build_perlin_sphere, build_solid_lambertian, build_solid_sphere, build_xz_diff_light, BoxInst,
-    BvhNode, Hittable, HittableList, RotateY, Translate,
+    FlatBvh, Hittable, HittableList, Transform,
 };
 use crate::material::Material;
 use rand::Rng;
@@ -28,7 +28,7 @@ pub fn build_final_scene(image_width: u32, aspect_ratio: f64) -> (Camera, Hittab
     let mut boxes1 = HittableList::new();
     let ground_mat: Arc<dyn Material> = Arc::new(build_solid_lambertian(0.48, 0.83, 0.53));
     let boxes_per_side = 20;
-    let mut rng = rand::thread_rng();
+    let mut rng = RngContext::current();
     for i in 0..boxes_per_side {
         for j in 0..boxes_per_side {
             let w = 100.0;
@@ -52,7 +52,7 @@ pub fn build_final_scene(image_width: u32, aspect_ratio: f64) -> (Camera, Hittab
     let mut objects = HittableList::new();
 
     // add the ground boxes into a BVH and then add that to the list of objects
-    objects.add(Arc::new(BvhNode::from(&mut boxes1, 0., 1.)));
+    objects.add(Arc::new(FlatBvh::from(&mut boxes1, 0., 1.)));
 
     // build a light source
     let light = build_xz_diff_light(Color::new(7., 7., 7.), 123., 423., 147., 412., 554.);
@@ -120,12 +120,12 @@ pub fn build_final_scene(image_width: u32, aspect_ratio: f64) -> (Camera, Hittab
     }
 
     // add the box of spheres to a BVH and then rotate and translate the entire box of spheres
-    let sphere_box = BvhNode::from(&mut box_of_sphere, 0.0, 1.0);
-    let rotated_spheres: Arc<dyn Hittable> = Arc::new(RotateY::from(Arc::new(sphere_box), 15.0));
-    let translated_spheres: Arc<dyn Hittable> = Arc::new(Translate::from(
-        Arc::clone(&rotated_spheres),
-        Vec3::new(-100., 270., 395.),
-    ));
+    let sphere_box = FlatBvh::from(&mut box_of_sphere, 0.0, 1.0);
+    let translated_spheres = Transform {
+        rotate_y: 15.0,
+        offset: Vec3::new(-100., 270., 395.),
+    }
+    .apply(Arc::new(sphere_box));
     objects.add(translated_spheres);
 
     (camera, objects)
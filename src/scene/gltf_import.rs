@@ -0,0 +1,344 @@
+//! Imports the geometry, node transforms, cameras and materials of glTF 2.0 (`.gltf` and
+//! `.glb`) files, so that the large amount of freely available glTF content can be rendered.
+//!
+//! Every triangle primitive of the file's default scene becomes a `TriangleMesh`, with the
+//! transforms of its node hierarchy applied to its vertices and normals. The first
+//! perspective camera found in the scene is used to view it. Files without a camera are
+//! viewed from the front (+z) of their bounding box.
+//!
+//! glTF describes materials with the metallic-roughness model, which is mapped to the closest
+//! available `Material`:
+//! - emissive materials become a `DiffuseLight` of the emissive color
+//! - blended (`BLEND`) materials that are partially transparent become a glass `Dielectric`
+//! - materials that are mostly metallic become a `Metal` of the base color, with a fuzz equal
+//!   to their roughness
+//! - every other material is a `Lambertian` of the base color texture, or of the base color
+//!   when the material has no texture
+use crate::common::{Camera, CameraBuilder, Color, Mat4, Point3, Vec3};
+use crate::hittable::{Hittable, HittableList, Mesh, Triangle, TriangleMesh};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::renderer::{Accelerator, BackgroundColor};
+use crate::scene::loader::{SceneFile, SceneFileError};
+use crate::texture::{ImageTexture, SolidColor, Texture};
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use gltf::Document;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// the refractive index given to the glass of transparent glTF materials
+const GLASS_REFRACTIVE_INDEX: f64 = 1.5;
+
+/// Imports the default scene of the glTF file at `path`. The camera renders images that are
+/// `image_width` pixels wide, with the given `aspect_ratio`, which replaces the aspect ratio
+/// of the file's camera
+pub fn import_gltf(
+    path: impl AsRef<Path>,
+    image_width: u32,
+    aspect_ratio: f64,
+) -> Result<SceneFile, SceneFileError> {
+    let (document, buffers, images) = gltf::import(path)?;
+    build_scene(&document, &buffers, &images, image_width, aspect_ratio)
+}
+
+/// Imports the default scene of the glTF (or binary glTF) file contents in `bytes`, see
+/// `import_gltf`. Any buffers and images must be embedded in the file
+pub fn parse_gltf(
+    bytes: &[u8],
+    image_width: u32,
+    aspect_ratio: f64,
+) -> Result<SceneFile, SceneFileError> {
+    let (document, buffers, images) = gltf::import_slice(bytes)?;
+    build_scene(&document, &buffers, &images, image_width, aspect_ratio)
+}
+
+/// the state shared while walking the node hierarchy of a glTF scene
+struct Importer<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    // the converted materials, by glTF material index. `None` is the glTF default material
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    world: HittableList,
+    camera: Option<(Mat4, f64)>,
+}
+
+fn build_scene(
+    document: &Document,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
+    image_width: u32,
+    aspect_ratio: f64,
+) -> Result<SceneFile, SceneFileError> {
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| SceneFileError::Invalid("the glTF file has no scenes".to_string()))?;
+
+    let mut importer = Importer {
+        buffers,
+        images,
+        materials: HashMap::new(),
+        world: HittableList::new(),
+        camera: None,
+    };
+    for node in scene.nodes() {
+        importer.add_node(&node, &Mat4::identity());
+    }
+    if importer.world.objects().is_empty() {
+        return Err(SceneFileError::Invalid(
+            "the glTF scene has no triangle geometry".to_string(),
+        ));
+    }
+
+    let camera = match importer.camera {
+        Some((transform, yfov)) => {
+            let look_from = transform.transform_point(&Point3::new(0.0, 0.0, 0.0));
+            // glTF cameras look down their local -z axis, with +y up
+            let forward = transform.transform_vector(&Vec3::new(0.0, 0.0, -1.0));
+            CameraBuilder::new()
+                .look_from(look_from)
+                .look_at(look_from + forward)
+                .up_direction(transform.transform_vector(&Vec3::new(0.0, 1.0, 0.0)))
+                .vertical_field_of_view(yfov.to_degrees())
+                .focus_distance(forward.length())
+                .aspect_ratio(aspect_ratio)
+                .image_width(image_width)
+                .open_close_time(0.0, 1.0)
+                .build()
+        }
+        None => framing_camera(&mut importer.world, image_width, aspect_ratio),
+    };
+
+    Ok(SceneFile {
+        camera,
+        world: importer.world,
+        background: BackgroundColor::LinearInterp(
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.5, 0.7, 1.0),
+        ),
+        accelerator: Accelerator::default(),
+    })
+}
+
+/// Returns a camera that looks at the center of the `world`'s bounding box, from far enough
+/// in front of it (along +z) to see all of it
+fn framing_camera(world: &mut HittableList, image_width: u32, aspect_ratio: f64) -> Camera {
+    let (center, radius) = match world.bounding_box(0.0, 1.0) {
+        Some(bbox) => (
+            (bbox.min() + bbox.max()) / 2.0,
+            (bbox.max() - bbox.min()).length() / 2.0,
+        ),
+        None => (Point3::default(), 1.0),
+    };
+    let vfov: f64 = 40.0;
+    let distance = radius / (vfov.to_radians() / 2.0).sin();
+    CameraBuilder::new()
+        .look_from(center + Vec3::new(0.0, 0.0, distance))
+        .look_at(center)
+        .up_direction(Vec3::new(0.0, 1.0, 0.0))
+        .vertical_field_of_view(vfov)
+        .focus_distance(distance)
+        .aspect_ratio(aspect_ratio)
+        .image_width(image_width)
+        .open_close_time(0.0, 1.0)
+        .build()
+}
+
+impl<'a> Importer<'a> {
+    /// Adds the mesh and camera of `node`, and of all of its children, whose parent node has
+    /// the world transform `parent`
+    fn add_node(&mut self, node: &gltf::Node, parent: &Mat4) {
+        // glTF matrices are stored in column major order
+        let local = Mat4::from_rows(
+            node.transform()
+                .matrix()
+                .map(|column| column.map(|value| value as f64)),
+        )
+        .transpose();
+        let transform = *parent * local;
+
+        if let (None, Some(camera)) = (&self.camera, node.camera()) {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                self.camera = Some((transform, perspective.yfov() as f64));
+            }
+        }
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(triangles) = self.triangle_mesh(&primitive, &transform) {
+                    self.world.add(Arc::new(triangles));
+                }
+            }
+        }
+        for child in node.children() {
+            self.add_node(&child, &transform);
+        }
+    }
+
+    /// Returns the triangles of `primitive`, transformed into world space by `transform`, or
+    /// `None` if the primitive does not contain triangles
+    fn triangle_mesh(
+        &mut self,
+        primitive: &gltf::Primitive,
+        transform: &Mat4,
+    ) -> Option<TriangleMesh> {
+        if primitive.mode() != Mode::Triangles {
+            return None;
+        }
+        let reader =
+            primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data[..]));
+        let positions: Vec<Point3> = reader
+            .read_positions()?
+            .map(|p| transform.transform_point(&to_vec3(p)))
+            .collect();
+        // the inverse transpose keeps normals perpendicular to the surface under non-uniform
+        // scales. A singular transform flattens the mesh, whose normals are then left as they are
+        let normal_matrix = transform
+            .inverse()
+            .map_or(Mat4::identity(), |inverse| inverse.transpose());
+        let normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|n| normal_matrix.transform_vector(&to_vec3(n)))
+                .collect()
+        });
+        let material = primitive.material();
+        let tex_coord_set = material
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .map_or(0, |info| info.tex_coord());
+        // glTF texture coordinates start at the top left of an image, while the v coordinate
+        // of an `ImageTexture` starts at the bottom
+        let uvs: Option<Vec<(f64, f64)>> = reader.read_tex_coords(tex_coord_set).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                .collect()
+        });
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mat = self.material(&material);
+        let mut mesh = Mesh::new();
+        let mut triangles = Vec::new();
+        for face in indices.chunks_exact(3) {
+            let [a, b, c] = [face[0], face[1], face[2]];
+            if a.max(b).max(c) >= positions.len() {
+                continue;
+            }
+            let mut triangle =
+                Triangle::new(positions[a], positions[b], positions[c], Arc::clone(&mat));
+            if let Some(normals) = normals.as_ref().filter(|n| n.len() == positions.len()) {
+                triangle = triangle.with_normals([normals[a], normals[b], normals[c]]);
+            }
+            if let Some(uvs) = uvs.as_ref().filter(|uvs| uvs.len() == positions.len()) {
+                triangle = triangle.with_uvs([uvs[a], uvs[b], uvs[c]]);
+            }
+            triangles.push(triangle);
+            mesh.faces.push([a, b, c]);
+        }
+        if triangles.is_empty() {
+            return None;
+        }
+        mesh.vertices = positions;
+        Some(TriangleMesh::from_triangles(triangles, mesh))
+    }
+
+    /// Returns the `Material` that best matches the glTF `material`, converting it the first
+    /// time it is used
+    fn material(&mut self, material: &gltf::Material) -> Arc<dyn Material> {
+        if let Some(converted) = self.materials.get(&material.index()) {
+            return Arc::clone(converted);
+        }
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor().map(|c| c as f64);
+        let base_color = Color::new(r, g, b);
+        let emissive = to_vec3(material.emissive_factor());
+
+        let converted: Arc<dyn Material> = if emissive.x().max(emissive.y()).max(emissive.z()) > 0.0
+        {
+            Arc::new(DiffuseLight::from(Arc::new(SolidColor::from(emissive))))
+        } else if material.alpha_mode() == AlphaMode::Blend && alpha < 1.0 {
+            Arc::new(Dielectric::new(GLASS_REFRACTIVE_INDEX))
+        } else if pbr.metallic_factor() >= 0.5 {
+            Arc::new(Metal::new(base_color, pbr.roughness_factor() as f64))
+        } else {
+            let texture: Arc<dyn Texture> = pbr
+                .base_color_texture()
+                .and_then(|info| self.images.get(info.texture().source().index()))
+                .and_then(image_texture)
+                .unwrap_or_else(|| Arc::new(SolidColor::from(base_color)));
+            Arc::new(Lambertian::new(texture))
+        };
+        self.materials
+            .insert(material.index(), Arc::clone(&converted));
+        converted
+    }
+}
+
+/// Returns an `ImageTexture` of the 8-bit RGB(A) `image`, or `None` if it has another format
+fn image_texture(image: &gltf::image::Data) -> Option<Arc<dyn Texture>> {
+    let rgb = match image.format {
+        Format::R8G8B8 => image.pixels.clone(),
+        Format::R8G8B8A8 => image
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+            .collect(),
+        _ => return None,
+    };
+    Some(Arc::new(ImageTexture::from_rgb8(
+        image.width,
+        image.height,
+        rgb,
+    )))
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::Hittable;
+    use crate::scene::gltf_import::parse_gltf;
+
+    /// a glTF file with a single metallic triangle, raised by its node, and a camera at z=5
+    const TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [ { "nodes": [0, 1] } ],
+        "nodes": [
+            { "mesh": 0, "translation": [0, 1, 0] },
+            { "camera": 0, "translation": [0, 0, 5] }
+        ],
+        "cameras": [ { "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } } ],
+        "materials": [ { "pbrMetallicRoughness": { "metallicFactor": 1.0, "roughnessFactor": 0.2 } } ],
+        "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 }, "material": 0 } ] } ],
+        "accessors": [ { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                         "min": [0, 0, 0], "max": [1, 1, 0] } ],
+        "bufferViews": [ { "buffer": 0, "byteLength": 36 } ],
+        "buffers": [ { "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA" } ]
+    }"#;
+
+    #[test]
+    fn imports_transformed_triangles_and_the_camera() {
+        let mut scene = parse_gltf(TRIANGLE_GLTF.as_bytes(), 40, 2.0).unwrap();
+
+        assert_eq!(scene.camera.image_width, 40);
+        assert_eq!(scene.camera.image_height, 20);
+        assert_eq!(scene.camera.look_from, Point3::new(0.0, 0.0, 5.0));
+        assert_eq!(scene.world.objects().len(), 1);
+
+        let ray = Ray::new(Point3::new(0.25, 1.25, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.25, 1.25, 0.0));
+        // the triangle was moved up by its node, so it no longer covers its original position
+        let ray = Ray::new(Point3::new(0.25, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,526 @@
+//! A `SceneGraph` is a plain data description of a scene: its camera settings, background,
+//! and named textures and materials, along with the objects that use them. Unlike a built
+//! `HittableList`, a scene graph can be inspected, edited and, with the `serde` feature
+//! enabled, saved to a file in the scene file format read by `loader`, so that a generated
+//! scene can be tweaked on disk and re-rendered later
+use crate::common::{CameraBuilder, Color, Mat4, Point3, Vec3};
+use crate::hittable::{
+    AffineTransform, BoxInst, ConstantMedium, FlipFace, Hittable, HittableList, MovingSphere,
+    Sphere, XYRect, XZRect, YZRect,
+};
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::renderer::{Accelerator, BackgroundColor, PreethamSky};
+use crate::scene::loader::{SceneFile, SceneFileError};
+use crate::scene::registry::Registry;
+use crate::texture::{
+    CheckerTexture, EnvironmentMap, ImageTexture, NoiseTexture, SolidColor, Texture,
+};
+use std::sync::Arc;
+
+/// A description of a scene, that is built into a renderable `SceneFile` by `build`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneGraph {
+    pub camera: CameraSettings,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub background: Option<BackgroundSettings>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub accelerator: Option<Accelerator>,
+    // textures and materials may only refer to the textures defined before them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub textures: Vec<NamedTexture>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub materials: Vec<NamedMaterial>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub objects: Vec<ObjectNode>,
+}
+
+/// The settings of a scene's camera. A `focus_distance` of `None` focuses on `look_at`
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraSettings {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub up: Vec3,
+    pub vertical_fov: f64,
+    pub aperture: f64,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub focus_distance: Option<f64>,
+    pub open_time: f64,
+    pub close_time: f64,
+}
+
+impl CameraSettings {
+    /// Returns the settings of a camera at `look_from` that looks at `look_at`, with the
+    /// default settings of a scene file: a 40 degree field of view, no defocus blur, focused
+    /// on `look_at`, and a shutter that is open from time 0 to 1
+    pub fn new(look_from: Point3, look_at: Point3) -> Self {
+        Self {
+            look_from,
+            look_at,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_fov: 40.0,
+            aperture: 0.0,
+            focus_distance: None,
+            open_time: 0.0,
+            close_time: 1.0,
+        }
+    }
+}
+
+/// The background of a scene, either a `Solid` color, a `Gradient` that blends from the
+/// `bottom` color to the `top` color, an equirectangular `Environment` map loaded from the
+/// image at `environment`, or a physically based daytime `Sky`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum BackgroundSettings {
+    Solid(Color),
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    Environment {
+        environment: String,
+    },
+    Sky {
+        sun_elevation: f64,
+        #[cfg_attr(feature = "serde", serde(default))]
+        sun_azimuth: f64,
+        #[cfg_attr(feature = "serde", serde(default = "default_turbidity"))]
+        turbidity: f64,
+    },
+}
+
+/// the turbidity of a `Sky` background that does not give one, a clear day
+#[cfg(feature = "serde")]
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+/// A reference to a texture, either by the `Named` of a texture of the scene, or as a solid
+/// `Color`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum TextureRef {
+    Named(String),
+    Color(Color),
+}
+
+/// A texture of a scene, registered under `name`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamedTexture {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub texture: TextureNode,
+}
+
+/// The kinds of textures that a scene can describe
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum TextureNode {
+    Solid { color: Color },
+    Checker { even: TextureRef, odd: TextureRef },
+    Noise { scale: f64 },
+    Image { file: String },
+}
+
+/// A material of a scene, registered under `name`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamedMaterial {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub material: MaterialNode,
+}
+
+/// The kinds of materials that a scene can describe
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum MaterialNode {
+    Lambertian { texture: TextureRef },
+    DiffuseLight { texture: TextureRef },
+    Isotropic { texture: TextureRef },
+    Metal { color: Color, fuzz: f64 },
+    Dielectric { refractive_index: f64 },
+}
+
+/// The material of an object, either the `Named` material of the scene, or a material that is
+/// defined `Inline`, and used only by that object
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum MaterialRef {
+    Named(String),
+    Inline(MaterialNode),
+}
+
+/// An object of a scene, a `shape` along with the transforms applied to it. The face of the
+/// shape is flipped first, then it is scaled by the factor of each axis in `scale`, rotated by
+/// `rotate_x`, `rotate_y` and `rotate_z` degrees about the x, y and z axes, in that order, and
+/// finally moved by `translate`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectNode {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub shape: Shape,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub flip_face: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub scale: Option<Vec3>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rotate_x: Option<f64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rotate_y: Option<f64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rotate_z: Option<f64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub translate: Option<Vec3>,
+}
+
+impl From<Shape> for ObjectNode {
+    /// Returns an object of `shape` without any transforms
+    fn from(shape: Shape) -> Self {
+        Self {
+            shape,
+            flip_face: false,
+            scale: None,
+            rotate_x: None,
+            rotate_y: None,
+            rotate_z: None,
+            translate: None,
+        }
+    }
+}
+
+/// The kinds of shapes that a scene can describe. The rectangles are given by the `[min, max]`
+/// ranges of their two axes, and their position `k` along the third axis. The `boundary` of a
+/// `Volume` is the object that contains it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum Shape {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        material: MaterialRef,
+    },
+    MovingSphere {
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: MaterialRef,
+    },
+    Box {
+        min: Point3,
+        max: Point3,
+        material: MaterialRef,
+    },
+    XyRect {
+        x: [f64; 2],
+        y: [f64; 2],
+        k: f64,
+        material: MaterialRef,
+    },
+    XzRect {
+        x: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: MaterialRef,
+    },
+    YzRect {
+        y: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: MaterialRef,
+    },
+    Volume {
+        boundary: Box<ObjectNode>,
+        density: f64,
+        texture: TextureRef,
+    },
+}
+
+impl SceneGraph {
+    /// Returns a new scene graph, without any objects, viewed by a `camera` with the given
+    /// settings
+    pub fn new(camera: CameraSettings) -> Self {
+        Self {
+            camera,
+            background: None,
+            accelerator: None,
+            textures: vec![],
+            materials: vec![],
+            objects: vec![],
+        }
+    }
+
+    /// Builds the camera, world, background and accelerator described by this scene graph. The camera
+    /// renders images that are `image_width` pixels wide, with the given `aspect_ratio`.
+    /// An error is returned if a texture or material refers to a name that was not defined
+    pub fn build(&self, image_width: u32, aspect_ratio: f64) -> Result<SceneFile, SceneFileError> {
+        let settings = &self.camera;
+        let camera = CameraBuilder::new()
+            .look_from(settings.look_from)
+            .look_at(settings.look_at)
+            .up_direction(settings.up)
+            .aspect_ratio(aspect_ratio)
+            .image_width(image_width)
+            .vertical_field_of_view(settings.vertical_fov)
+            .aperture(settings.aperture)
+            .focus_distance(
+                settings
+                    .focus_distance
+                    .unwrap_or_else(|| (settings.look_from - settings.look_at).length()),
+            )
+            .open_close_time(settings.open_time, settings.close_time)
+            .build();
+        let background = match &self.background {
+            None => BackgroundColor::Solid(Color::default()),
+            Some(BackgroundSettings::Solid(color)) => BackgroundColor::Solid(*color),
+            Some(BackgroundSettings::Gradient { bottom, top }) => {
+                BackgroundColor::LinearInterp(*bottom, *top)
+            }
+            Some(BackgroundSettings::Sky {
+                sun_elevation,
+                sun_azimuth,
+                turbidity,
+            }) => BackgroundColor::Sky(PreethamSky::new(*sun_elevation, *sun_azimuth, *turbidity)),
+            Some(BackgroundSettings::Environment { environment }) => BackgroundColor::Environment(
+                Arc::new(EnvironmentMap::open(environment).map_err(|e| {
+                    SceneFileError::Invalid(format!(
+                        "could not load the environment map '{}': {}",
+                        environment, e
+                    ))
+                })?),
+            ),
+        };
+
+        let mut registry = Registry::new();
+        for NamedTexture { name, texture } in self.textures.iter() {
+            let built = texture.build(&registry)?;
+            registry.add_texture(name.as_str(), built);
+        }
+        for NamedMaterial { name, material } in self.materials.iter() {
+            let built = material.build(&registry)?;
+            registry.add_material(name.as_str(), built);
+        }
+        let mut world = HittableList::new();
+        for object in self.objects.iter() {
+            world.add(object.build(&registry)?);
+        }
+
+        Ok(SceneFile {
+            camera,
+            world,
+            background,
+            accelerator: self.accelerator.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl SceneGraph {
+    /// Saves this scene graph to the file at `path`, as a scene file that can be read by
+    /// `loader::load_scene`
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), SceneFileError> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+impl TextureRef {
+    fn build(&self, registry: &Registry) -> Result<Arc<dyn Texture>, SceneFileError> {
+        match self {
+            TextureRef::Named(name) => registry
+                .texture(name)
+                .ok_or_else(|| SceneFileError::Invalid(format!("unknown texture '{}'", name))),
+            TextureRef::Color(color) => Ok(Arc::new(SolidColor::from(*color))),
+        }
+    }
+}
+
+impl TextureNode {
+    fn build(&self, registry: &Registry) -> Result<Arc<dyn Texture>, SceneFileError> {
+        let texture: Arc<dyn Texture> = match self {
+            TextureNode::Solid { color } => Arc::new(SolidColor::from(*color)),
+            TextureNode::Checker { even, odd } => Arc::new(CheckerTexture::from(
+                even.build(registry)?,
+                odd.build(registry)?,
+            )),
+            TextureNode::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+            TextureNode::Image { file } => Arc::new(ImageTexture::open(file).map_err(|e| {
+                SceneFileError::Invalid(format!("could not load the image '{}': {}", file, e))
+            })?),
+        };
+        Ok(texture)
+    }
+}
+
+impl MaterialNode {
+    fn build(&self, registry: &Registry) -> Result<Arc<dyn Material>, SceneFileError> {
+        let material: Arc<dyn Material> = match self {
+            MaterialNode::Lambertian { texture } => {
+                Arc::new(Lambertian::new(texture.build(registry)?))
+            }
+            MaterialNode::DiffuseLight { texture } => {
+                Arc::new(DiffuseLight::from(texture.build(registry)?))
+            }
+            MaterialNode::Isotropic { texture } => {
+                Arc::new(Isotropic::from(texture.build(registry)?))
+            }
+            MaterialNode::Metal { color, fuzz } => Arc::new(Metal::new(*color, *fuzz)),
+            MaterialNode::Dielectric { refractive_index } => {
+                Arc::new(Dielectric::new(*refractive_index))
+            }
+        };
+        Ok(material)
+    }
+}
+
+impl MaterialRef {
+    fn build(&self, registry: &Registry) -> Result<Arc<dyn Material>, SceneFileError> {
+        match self {
+            MaterialRef::Named(name) => registry
+                .material(name)
+                .ok_or_else(|| SceneFileError::Invalid(format!("unknown material '{}'", name))),
+            MaterialRef::Inline(material) => material.build(registry),
+        }
+    }
+}
+
+impl ObjectNode {
+    fn build(&self, registry: &Registry) -> Result<Arc<dyn Hittable>, SceneFileError> {
+        let mut object: Arc<dyn Hittable> = match &self.shape {
+            Shape::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(*center, *radius, material.build(registry)?)),
+            Shape::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Arc::new(MovingSphere::new(
+                *center0,
+                *center1,
+                *time0,
+                *time1,
+                *radius,
+                material.build(registry)?,
+            )),
+            Shape::Box { min, max, material } => {
+                Arc::new(BoxInst::from(*min, *max, material.build(registry)?))
+            }
+            Shape::XyRect { x, y, k, material } => Arc::new(XYRect::from(
+                x[0],
+                x[1],
+                y[0],
+                y[1],
+                *k,
+                material.build(registry)?,
+            )),
+            Shape::XzRect { x, z, k, material } => Arc::new(XZRect::from(
+                x[0],
+                x[1],
+                z[0],
+                z[1],
+                *k,
+                material.build(registry)?,
+            )),
+            Shape::YzRect { y, z, k, material } => Arc::new(YZRect::from(
+                y[0],
+                y[1],
+                z[0],
+                z[1],
+                *k,
+                material.build(registry)?,
+            )),
+            Shape::Volume {
+                boundary,
+                density,
+                texture,
+            } => Arc::new(ConstantMedium::from(
+                boundary.build(registry)?,
+                *density,
+                texture.build(registry)?,
+            )),
+        };
+
+        if self.flip_face {
+            object = Arc::new(FlipFace::from(object));
+        }
+        if let Some(matrix) = self.matrix() {
+            object = Arc::new(AffineTransform::from(object, matrix));
+        }
+        Ok(object)
+    }
+
+    /// Returns the matrix that scales, rotates and translates this object, or `None` if the
+    /// object has none of those transforms
+    fn matrix(&self) -> Option<Mat4> {
+        let transforms = [
+            self.scale.map(Mat4::scaling),
+            self.rotate_x.map(Mat4::rotation_x),
+            self.rotate_y.map(Mat4::rotation_y),
+            self.rotate_z.map(Mat4::rotation_z),
+            self.translate.map(Mat4::translation),
+        ];
+        transforms
+            .into_iter()
+            .flatten()
+            .reduce(|matrix, transform| transform * matrix)
+    }
+}
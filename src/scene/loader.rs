@@ -0,0 +1,630 @@
+//! Loads a scene from a JSON scene description file, so that custom scenes can be rendered
+//! without recompiling the crate.
+//!
+//! A scene file is a JSON object with the following keys. Colors, points and vectors are
+//! arrays of three numbers:
+//!
+//! ```json
+//! {
+//!   "camera": { "look_from": [13, 2, 3], "look_at": [0, 0, 0], "vertical_fov": 20 },
+//!   "background": [0.7, 0.8, 1.0],
+//!   "accelerator": "bvh",
+//!   "textures": [
+//!     { "name": "checks", "type": "checker", "even": [0.2, 0.3, 0.1], "odd": [0.9, 0.9, 0.9] }
+//!   ],
+//!   "materials": [
+//!     { "name": "ground", "type": "lambertian", "texture": "checks" },
+//!     { "name": "glass", "type": "dielectric", "refractive_index": 1.5 }
+//!   ],
+//!   "objects": [
+//!     { "type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground" },
+//!     { "type": "box", "min": [0, 0, 0], "max": [1, 1, 1], "material": "glass",
+//!       "rotate_y": 15, "translate": [2, 0, 0] }
+//!   ]
+//! }
+//! ```
+//!
+//! `camera` - `look_from` and `look_at` are required. `up` defaults to `[0, 1, 0]`,
+//! `vertical_fov` to `40`, `aperture` to `0`, `focus_distance` to the distance between
+//! `look_from` and `look_at`, and `open_time` and `close_time` to `0` and `1`
+//!
+//! `background` - optional, either a solid color, an object with a `"top"` and `"bottom"`
+//! color that are blended vertically, an object with the path of an equirectangular
+//! `"environment"` map image, or a daytime sky with a `"sun_elevation"` and optionally a
+//! `"sun_azimuth"` (defaults to `0`) and `"turbidity"` (defaults to `3`). Defaults to black
+//!
+//! `accelerator` - optional, the acceleration structure the scene is placed in, `bvh`, `grid`
+//! or `kdtree`, see `renderer::Accelerator`. Defaults to `bvh`
+//!
+//! `textures` - a list of named textures, of type `solid` (`color`), `checker` (`even`,
+//! `odd`), `noise` (`scale`) or `image` (`file`). The colors of a checker texture can also be
+//! the name of a texture defined earlier in the list
+//!
+//! `materials` - a list of named materials, of type `lambertian`, `diffuse_light` or
+//! `isotropic`, which take a `texture` name or a `color`, `metal` (`color`, `fuzz`) or
+//! `dielectric` (`refractive_index`)
+//!
+//! `objects` - a list of hittables, of type `sphere` (`center`, `radius`), `moving_sphere`
+//! (`center0`, `center1`, `radius`, and optionally `time0` and `time1`), `box` (`min`,
+//! `max`), `xy_rect` (`x`, `y`, `k`), `xz_rect` (`x`, `z`, `k`), `yz_rect` (`y`, `z`, `k`),
+//! where each rect range is a `[min, max]` pair, or `volume` (`boundary`, `density`, and a
+//! `texture` or `color`), where the boundary is another object. The `material` of an object is
+//! either the name of a material, or a material object defined in place. Every object can
+//! also have a `flip_face` flag, a `scale`, either one factor or a factor for each axis,
+//! `rotate_x`, `rotate_y` and `rotate_z` angles, in degrees, and a `translate` displacement,
+//! which are applied in that order
+//!
+//! A scene file is parsed into a `SceneGraph`, which is then built into the scene
+use crate::common::{Camera, Vec3};
+use crate::hittable::HittableList;
+use crate::renderer::{Accelerator, BackgroundColor};
+use crate::scene::graph::{
+    BackgroundSettings, CameraSettings, MaterialNode, MaterialRef, NamedMaterial, NamedTexture,
+    ObjectNode, SceneGraph, Shape, TextureNode, TextureRef,
+};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+
+/// The error returned when a scene file cannot be loaded
+/// `Io` - the file could not be read
+/// `Json` - the file is not valid JSON
+/// `Gltf` - the glTF file could not be imported, see `gltf_import`
+/// `Invalid` - the file does not describe a valid scene, i.e. a required key is missing
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Gltf(gltf::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(e) => write!(f, "could not read the scene file: {}", e),
+            SceneFileError::Json(e) => write!(f, "the scene file is not valid JSON: {}", e),
+            SceneFileError::Gltf(e) => write!(f, "could not import the glTF file: {}", e),
+            SceneFileError::Invalid(msg) => write!(f, "invalid scene file: {}", msg),
+        }
+    }
+}
+
+impl Error for SceneFileError {}
+
+impl From<std::io::Error> for SceneFileError {
+    fn from(e: std::io::Error) -> Self {
+        SceneFileError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SceneFileError {
+    fn from(e: serde_json::Error) -> Self {
+        SceneFileError::Json(e)
+    }
+}
+
+impl From<gltf::Error> for SceneFileError {
+    fn from(e: gltf::Error) -> Self {
+        SceneFileError::Gltf(e)
+    }
+}
+
+/// A scene loaded from a scene file, ready to be rendered
+#[derive(Debug)]
+pub struct SceneFile {
+    pub camera: Camera,
+    pub world: HittableList,
+    pub background: BackgroundColor,
+    pub accelerator: Accelerator,
+}
+
+/// Loads the scene described by the JSON file at `path`. The camera renders images that are
+/// `image_width` pixels wide, with the given `aspect_ratio`
+pub fn load_scene(
+    path: impl AsRef<Path>,
+    image_width: u32,
+    aspect_ratio: f64,
+) -> Result<SceneFile, SceneFileError> {
+    load_scene_graph(path)?.build(image_width, aspect_ratio)
+}
+
+/// Builds the scene described by the `json` string, see `load_scene`
+pub fn parse_scene(
+    json: &str,
+    image_width: u32,
+    aspect_ratio: f64,
+) -> Result<SceneFile, SceneFileError> {
+    parse_scene_graph(json)?.build(image_width, aspect_ratio)
+}
+
+/// Loads the JSON file at `path` as a `SceneGraph`, without building it
+pub fn load_scene_graph(path: impl AsRef<Path>) -> Result<SceneGraph, SceneFileError> {
+    let json = std::fs::read_to_string(path)?;
+    parse_scene_graph(&json)
+}
+
+/// Parses the `json` string as a `SceneGraph`, without building it
+pub fn parse_scene_graph(json: &str) -> Result<SceneGraph, SceneFileError> {
+    let root: Value = serde_json::from_str(json)?;
+    let root = as_object(&root, "the scene")?;
+
+    let mut graph = SceneGraph::new(parse_camera(as_object(
+        required(root, "camera")?,
+        "camera",
+    )?)?);
+    graph.background = match root.get("background") {
+        None => None,
+        Some(Value::Object(env)) if env.contains_key("environment") => {
+            Some(BackgroundSettings::Environment {
+                environment: string(&env["environment"], "environment")?.to_string(),
+            })
+        }
+        Some(Value::Object(sky)) if sky.contains_key("sun_elevation") => {
+            Some(BackgroundSettings::Sky {
+                sun_elevation: number(&sky["sun_elevation"], "sun_elevation")?,
+                sun_azimuth: optional_number(sky, "sun_azimuth", 0.0)?,
+                turbidity: optional_number(sky, "turbidity", 3.0)?,
+            })
+        }
+        Some(Value::Object(gradient)) => Some(BackgroundSettings::Gradient {
+            bottom: vec3(required(gradient, "bottom")?, "bottom")?,
+            top: vec3(required(gradient, "top")?, "top")?,
+        }),
+        Some(color) => Some(BackgroundSettings::Solid(vec3(color, "background")?)),
+    };
+    graph.accelerator = match root.get("accelerator") {
+        None => None,
+        Some(accelerator) => Some(
+            string(accelerator, "accelerator")?
+                .parse::<Accelerator>()
+                .map_err(invalid)?,
+        ),
+    };
+    for texture in list(root, "textures")? {
+        let texture = as_object(texture, "texture")?;
+        graph.textures.push(NamedTexture {
+            name: string(required(texture, "name")?, "name")?.to_string(),
+            texture: parse_texture(texture)?,
+        });
+    }
+    for material in list(root, "materials")? {
+        let material = as_object(material, "material")?;
+        graph.materials.push(NamedMaterial {
+            name: string(required(material, "name")?, "name")?.to_string(),
+            material: parse_material(material)?,
+        });
+    }
+    for object in list(root, "objects")? {
+        graph
+            .objects
+            .push(parse_object(as_object(object, "object")?)?);
+    }
+    Ok(graph)
+}
+
+/// Parses the camera `settings` of a scene file
+fn parse_camera(settings: &Map<String, Value>) -> Result<CameraSettings, SceneFileError> {
+    let mut camera = CameraSettings::new(
+        vec3(required(settings, "look_from")?, "look_from")?,
+        vec3(required(settings, "look_at")?, "look_at")?,
+    );
+    if let Some(up) = settings.get("up") {
+        camera.up = vec3(up, "up")?;
+    }
+    camera.vertical_fov = optional_number(settings, "vertical_fov", camera.vertical_fov)?;
+    camera.aperture = optional_number(settings, "aperture", camera.aperture)?;
+    if let Some(distance) = settings.get("focus_distance") {
+        camera.focus_distance = Some(number(distance, "focus_distance")?);
+    }
+    camera.open_time = optional_number(settings, "open_time", camera.open_time)?;
+    camera.close_time = optional_number(settings, "close_time", camera.close_time)?;
+    Ok(camera)
+}
+
+/// Parses the scene file `description` of a texture
+fn parse_texture(description: &Map<String, Value>) -> Result<TextureNode, SceneFileError> {
+    let texture = match kind(description)? {
+        "solid" => TextureNode::Solid {
+            color: vec3(required(description, "color")?, "color")?,
+        },
+        "checker" => TextureNode::Checker {
+            even: texture_ref(required(description, "even")?)?,
+            odd: texture_ref(required(description, "odd")?)?,
+        },
+        "noise" => TextureNode::Noise {
+            scale: number(required(description, "scale")?, "scale")?,
+        },
+        "image" => TextureNode::Image {
+            file: string(required(description, "file")?, "file")?.to_string(),
+        },
+        other => return Err(invalid(format!("unknown texture type '{}'", other))),
+    };
+    Ok(texture)
+}
+
+/// Parses the scene file `description` of a material
+fn parse_material(description: &Map<String, Value>) -> Result<MaterialNode, SceneFileError> {
+    let material = match kind(description)? {
+        "lambertian" => MaterialNode::Lambertian {
+            texture: material_texture(description)?,
+        },
+        "diffuse_light" => MaterialNode::DiffuseLight {
+            texture: material_texture(description)?,
+        },
+        "isotropic" => MaterialNode::Isotropic {
+            texture: material_texture(description)?,
+        },
+        "metal" => MaterialNode::Metal {
+            color: vec3(required(description, "color")?, "color")?,
+            fuzz: optional_number(description, "fuzz", 0.0)?,
+        },
+        "dielectric" => MaterialNode::Dielectric {
+            refractive_index: number(
+                required(description, "refractive_index")?,
+                "refractive_index",
+            )?,
+        },
+        other => return Err(invalid(format!("unknown material type '{}'", other))),
+    };
+    Ok(material)
+}
+
+/// Parses the scene file `description` of an object, along with its transforms
+fn parse_object(description: &Map<String, Value>) -> Result<ObjectNode, SceneFileError> {
+    let material = || -> Result<MaterialRef, SceneFileError> {
+        match required(description, "material")? {
+            Value::String(name) => Ok(MaterialRef::Named(name.clone())),
+            Value::Object(inline) => Ok(MaterialRef::Inline(parse_material(inline)?)),
+            _ => Err(invalid("material must be a name or a material object")),
+        }
+    };
+    let point = |key: &str| vec3(required(description, key)?, key);
+    let scalar = |key: &str| number(required(description, key)?, key);
+
+    let shape = match kind(description)? {
+        "sphere" => Shape::Sphere {
+            center: point("center")?,
+            radius: scalar("radius")?,
+            material: material()?,
+        },
+        "moving_sphere" => Shape::MovingSphere {
+            center0: point("center0")?,
+            center1: point("center1")?,
+            time0: optional_number(description, "time0", 0.0)?,
+            time1: optional_number(description, "time1", 1.0)?,
+            radius: scalar("radius")?,
+            material: material()?,
+        },
+        "box" => Shape::Box {
+            min: point("min")?,
+            max: point("max")?,
+            material: material()?,
+        },
+        "xy_rect" => Shape::XyRect {
+            x: range(description, "x")?,
+            y: range(description, "y")?,
+            k: scalar("k")?,
+            material: material()?,
+        },
+        "xz_rect" => Shape::XzRect {
+            x: range(description, "x")?,
+            z: range(description, "z")?,
+            k: scalar("k")?,
+            material: material()?,
+        },
+        "yz_rect" => Shape::YzRect {
+            y: range(description, "y")?,
+            z: range(description, "z")?,
+            k: scalar("k")?,
+            material: material()?,
+        },
+        "volume" => Shape::Volume {
+            boundary: Box::new(parse_object(as_object(
+                required(description, "boundary")?,
+                "boundary",
+            )?)?),
+            density: scalar("density")?,
+            texture: material_texture(description)?,
+        },
+        other => return Err(invalid(format!("unknown object type '{}'", other))),
+    };
+
+    let mut object = ObjectNode::from(shape);
+    if let Some(flip) = description.get("flip_face") {
+        object.flip_face = flip
+            .as_bool()
+            .ok_or_else(|| invalid("flip_face must be true or false"))?;
+    }
+    if let Some(scale) = description.get("scale") {
+        object.scale = Some(match scale.as_f64() {
+            Some(factor) => Vec3::new(factor, factor, factor),
+            None => vec3(scale, "scale")?,
+        });
+    }
+    if let Some(angle) = description.get("rotate_x") {
+        object.rotate_x = Some(number(angle, "rotate_x")?);
+    }
+    if let Some(angle) = description.get("rotate_y") {
+        object.rotate_y = Some(number(angle, "rotate_y")?);
+    }
+    if let Some(angle) = description.get("rotate_z") {
+        object.rotate_z = Some(number(angle, "rotate_z")?);
+    }
+    if let Some(offset) = description.get("translate") {
+        object.translate = Some(vec3(offset, "translate")?);
+    }
+    Ok(object)
+}
+
+/// Returns the texture of a material or volume `description`, given either by the name of a
+/// texture in its `texture` key, or as a solid `color`
+fn material_texture(description: &Map<String, Value>) -> Result<TextureRef, SceneFileError> {
+    match (description.get("texture"), description.get("color")) {
+        (Some(texture), _) => texture_ref(texture),
+        (None, Some(color)) => texture_ref(color),
+        (None, None) => Err(invalid("expected a 'texture' or a 'color'")),
+    }
+}
+
+/// Returns a reference to the texture named by `value`, or to a solid color if `value` is a
+/// color
+fn texture_ref(value: &Value) -> Result<TextureRef, SceneFileError> {
+    match value {
+        Value::String(name) => Ok(TextureRef::Named(name.clone())),
+        color => Ok(TextureRef::Color(vec3(color, "color")?)),
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> SceneFileError {
+    SceneFileError::Invalid(msg.into())
+}
+
+/// Returns the value of `key` in `object`, or an error if it is missing
+fn required<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a Value, SceneFileError> {
+    object
+        .get(key)
+        .ok_or_else(|| invalid(format!("missing '{}'", key)))
+}
+
+/// Returns the `type` of an object description
+fn kind(description: &Map<String, Value>) -> Result<&str, SceneFileError> {
+    string(required(description, "type")?, "type")
+}
+
+/// Returns the array at `key` of `object`, or an empty slice if the key is missing
+fn list<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a [Value], SceneFileError> {
+    match object.get(key) {
+        Some(value) => value
+            .as_array()
+            .map(|values| values.as_slice())
+            .ok_or_else(|| invalid(format!("'{}' must be a list", key))),
+        None => Ok(&[]),
+    }
+}
+
+fn as_object<'a>(value: &'a Value, what: &str) -> Result<&'a Map<String, Value>, SceneFileError> {
+    value
+        .as_object()
+        .ok_or_else(|| invalid(format!("{} must be an object", what)))
+}
+
+fn string<'a>(value: &'a Value, key: &str) -> Result<&'a str, SceneFileError> {
+    value
+        .as_str()
+        .ok_or_else(|| invalid(format!("'{}' must be a string", key)))
+}
+
+fn number(value: &Value, key: &str) -> Result<f64, SceneFileError> {
+    value
+        .as_f64()
+        .ok_or_else(|| invalid(format!("'{}' must be a number", key)))
+}
+
+/// Returns the number at `key` of `object`, or `default` if the key is missing
+fn optional_number(
+    object: &Map<String, Value>,
+    key: &str,
+    default: f64,
+) -> Result<f64, SceneFileError> {
+    object.get(key).map_or(Ok(default), |v| number(v, key))
+}
+
+/// Returns the `[min, max]` pair at `key` of `object`
+fn range(object: &Map<String, Value>, key: &str) -> Result<[f64; 2], SceneFileError> {
+    match required(object, key)?.as_array().map(|a| a.as_slice()) {
+        Some([min, max]) => Ok([number(min, key)?, number(max, key)?]),
+        _ => Err(invalid(format!("'{}' must be a [min, max] pair", key))),
+    }
+}
+
+/// Returns the array of three numbers in `value` as a `Vec3`
+fn vec3(value: &Value, key: &str) -> Result<Vec3, SceneFileError> {
+    match value.as_array().map(|a| a.as_slice()) {
+        Some([x, y, z]) => Ok(Vec3::new(number(x, key)?, number(y, key)?, number(z, key)?)),
+        _ => Err(invalid(format!(
+            "'{}' must be an array of three numbers",
+            key
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::Hittable;
+    use crate::renderer::Accelerator;
+    use crate::scene::graph::BackgroundSettings;
+    use crate::scene::loader::{parse_scene, parse_scene_graph, SceneFileError};
+
+    #[test]
+    fn parses_a_scene_with_named_and_inline_materials() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0], "vertical_fov": 30 },
+            "background": { "bottom": [1, 1, 1], "top": [0.5, 0.7, 1.0] },
+            "textures": [
+                { "name": "white", "type": "solid", "color": [0.9, 0.9, 0.9] },
+                { "name": "checks", "type": "checker", "even": "white", "odd": [0.1, 0.1, 0.1] }
+            ],
+            "materials": [
+                { "name": "floor", "type": "lambertian", "texture": "checks" }
+            ],
+            "objects": [
+                { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "floor" },
+                { "type": "box", "min": [0, 0, 0], "max": [1, 1, 1], "translate": [0, 3, 0],
+                  "material": { "type": "metal", "color": [0.8, 0.8, 0.8], "fuzz": 0.1 } },
+                { "type": "volume", "density": 0.1, "color": [1, 1, 1],
+                  "boundary": { "type": "sphere", "center": [0, -3, 0], "radius": 1,
+                                "material": { "type": "dielectric", "refractive_index": 1.5 } } }
+            ]
+        }"#;
+        let mut scene = parse_scene(json, 40, 2.0).unwrap();
+
+        assert_eq!(scene.camera.image_width, 40);
+        assert_eq!(scene.camera.image_height, 20);
+        assert_eq!(scene.world.objects().len(), 3);
+        let ray = Ray::new(Point3::new(0.5, 3.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(rec.p, Point3::new(0.5, 3.5, 1.0));
+    }
+
+    #[test]
+    fn objects_are_scaled_before_they_are_rotated() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "objects": [
+                { "type": "box", "min": [0, 0, 0], "max": [1, 1, 1], "scale": 2, "rotate_z": 90,
+                  "material": { "type": "lambertian", "color": [0.5, 0.5, 0.5] } }
+            ]
+        }"#;
+        let scene = parse_scene(json, 40, 2.0).unwrap();
+
+        // the box is scaled to [0, 2] on each axis, and then turned onto the negative x-axis
+        let ray = Ray::new(Point3::new(-1.0, 5.0, 1.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point3::new(-1.0, 2.0, 1.0)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_material_names_are_reported() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "objects": [ { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "gold" } ]
+        }"#;
+        match parse_scene(json, 40, 2.0) {
+            Err(SceneFileError::Invalid(msg)) => assert!(msg.contains("gold")),
+            other => panic!(
+                "expected an invalid scene error, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+
+    #[test]
+    fn missing_image_textures_are_reported() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "textures": [ { "name": "photo", "type": "image", "file": "./no-such-image.png" } ]
+        }"#;
+        match parse_scene(json, 40, 2.0) {
+            Err(SceneFileError::Invalid(msg)) => assert!(msg.contains("no-such-image.png")),
+            other => panic!(
+                "expected an invalid scene error, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+
+    #[test]
+    fn missing_environment_maps_are_reported() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "background": { "environment": "./no-such-sky.hdr" }
+        }"#;
+        match parse_scene(json, 40, 2.0) {
+            Err(SceneFileError::Invalid(msg)) => assert!(msg.contains("no-such-sky.hdr")),
+            other => panic!(
+                "expected an invalid scene error, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+
+    #[test]
+    fn parses_the_accelerator() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "accelerator": "kdtree"
+        }"#;
+        let scene = parse_scene(json, 40, 2.0).unwrap();
+        assert_eq!(scene.accelerator, Accelerator::KdTree);
+
+        let json = r#"{ "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] } }"#;
+        assert_eq!(
+            parse_scene(json, 40, 2.0).unwrap().accelerator,
+            Accelerator::Bvh
+        );
+
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "accelerator": "octree"
+        }"#;
+        assert!(matches!(
+            parse_scene_graph(json),
+            Err(SceneFileError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn parses_an_environment_map_background() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "background": { "environment": "./sky.hdr" }
+        }"#;
+        let graph = parse_scene_graph(json).unwrap();
+
+        assert_eq!(
+            graph.background,
+            Some(BackgroundSettings::Environment {
+                environment: "./sky.hdr".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parses_a_sky_background_with_default_azimuth_and_turbidity() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+            "background": { "sun_elevation": 30 }
+        }"#;
+        let graph = parse_scene_graph(json).unwrap();
+
+        assert_eq!(
+            graph.background,
+            Some(BackgroundSettings::Sky {
+                sun_elevation: 30.0,
+                sun_azimuth: 0.0,
+                turbidity: 3.0,
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialized_sky_backgrounds_default_to_a_turbidity_of_three() {
+        let background: BackgroundSettings =
+            serde_json::from_str(r#"{ "sun_elevation": 30 }"#).unwrap();
+
+        assert_eq!(
+            background,
+            BackgroundSettings::Sky {
+                sun_elevation: 30.0,
+                sun_azimuth: 0.0,
+                turbidity: 3.0,
+            }
+        );
+    }
+}
@@ -1,51 +1,88 @@
-use crate::common::{Camera, CameraBuilder, Color, Point3, Vec3};
-use crate::hittable::builder::{
-    build_checker_sphere, build_dielectric_sphere, build_metal_sphere, build_perlin_sphere,
+use crate::common::{Camera, Color, Point3, RngContext, Vec3};
+use crate::hittable::HittableList;
+use crate::scene::graph::{
+    BackgroundSettings, CameraSettings, MaterialNode, MaterialRef, NamedMaterial, NamedTexture,
+    ObjectNode, SceneGraph, Shape, TextureNode, TextureRef,
 };
-use crate::hittable::{HittableList, MovingSphere, Sphere};
-use crate::material::{Dielectric, Lambertian, Material, Metal};
-use crate::texture::{SolidColor, Texture};
 use rand::Rng;
-use std::sync::Arc;
 
 /// builds and returns the Camera and HittableList for the random sphere scene.
 /// This scene contains 484 small spheres randomly positioned around
 /// 3 bigger spheres. These are then positioned on top of an enormous sphere with a checkerboard
 /// texture, which acts as the ground plane
 pub fn build_random_sphere_scene(image_width: u32, aspect_ratio: f64) -> (Camera, HittableList) {
+    let scene = build_random_sphere_scene_graph()
+        .build(image_width, aspect_ratio)
+        .expect("the random sphere scene only refers to the textures and materials it defines");
+    (scene.camera, scene.world)
+}
+
+/// builds the `SceneGraph` of the random sphere scene, see `build_random_sphere_scene`. Every
+/// call generates a new random arrangement of spheres, which can be saved to a scene file with
+/// the `serde` feature and then rendered again later
+pub fn build_random_sphere_scene_graph() -> SceneGraph {
     // build the camera
-    let camera = CameraBuilder::new()
-        .look_from(Point3::new(13.0, 2.0, 3.0))
-        .look_at(Point3::new(0.0, 0.0, 0.0))
-        .up_direction(Vec3::new(0.0, 1.0, 0.0))
-        .aspect_ratio(aspect_ratio)
-        .image_width(image_width)
-        .focus_distance(10.0)
-        .aperture(0.0)
-        .vertical_field_of_view(20.0)
-        .open_close_time(0.0, 1.0)
-        .build();
+    let mut camera = CameraSettings::new(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0));
+    camera.focus_distance = Some(10.0);
+    camera.vertical_fov = 20.0;
+
+    let mut graph = SceneGraph::new(camera);
+    graph.background = Some(BackgroundSettings::Gradient {
+        bottom: Color::new(1., 1., 1.),
+        top: Color::new(0.5, 0.5, 1.0),
+    });
 
     // generate a world with spheres in random locations
-    let world = generate_random_spheres();
+    generate_random_spheres(&mut graph);
 
-    (camera, world)
+    graph
 }
 
-/// performs the actual generation of the spheres in the scene
-fn generate_random_spheres() -> HittableList {
-    let mut rng = rand::thread_rng();
+/// returns a sphere of the given `material` that is not transformed
+fn sphere(center: Point3, radius: f64, material: MaterialRef) -> ObjectNode {
+    ObjectNode::from(Shape::Sphere {
+        center,
+        radius,
+        material,
+    })
+}
 
-    let mut world = HittableList::new();
+/// returns an inline, solid color, lambertian material
+fn solid_lambertian(color: Color) -> MaterialRef {
+    MaterialRef::Inline(MaterialNode::Lambertian {
+        texture: TextureRef::Color(color),
+    })
+}
+
+/// performs the actual generation of the spheres in the scene
+fn generate_random_spheres(graph: &mut SceneGraph) {
+    let mut rng = RngContext::current();
 
     // a big, checkered sphere that will act at the ground
-    let ground_sphere = build_checker_sphere(
+    graph.textures.push(NamedTexture {
+        name: "ground".to_string(),
+        texture: TextureNode::Checker {
+            even: TextureRef::Color(Color::new(0.1, 0.2, 0.1)),
+            odd: TextureRef::Color(Color::new(0.8, 0.8, 0.8)),
+        },
+    });
+    graph.materials.push(NamedMaterial {
+        name: "ground".to_string(),
+        material: MaterialNode::Lambertian {
+            texture: TextureRef::Named("ground".to_string()),
+        },
+    });
+    graph.materials.push(NamedMaterial {
+        name: "glass".to_string(),
+        material: MaterialNode::Dielectric {
+            refractive_index: 1.5,
+        },
+    });
+    graph.objects.push(sphere(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
-        Color::new(0.1, 0.2, 0.1),
-        Color::new(0.8, 0.8, 0.8),
-    );
-    world.add(Arc::new(ground_sphere));
+        MaterialRef::Named("ground".to_string()),
+    ));
 
     // generate 484 spheres with random materials and colors, all of radius 0.2
     for a in -11..11 {
@@ -61,66 +98,100 @@ fn generate_random_spheres() -> HittableList {
                 let prob = rng.gen::<f64>();
                 if prob < 0.1 {
                     // create movingSpheres with Lambertian material
-                    let albedo: Arc<dyn Texture> =
-                        Arc::new(SolidColor::from(Color::random() * Color::random()));
+                    let albedo = Color::random() * Color::random();
                     let center2 = center + Vec3::new(0., rng.gen::<f64>(), 0.);
-                    world.add(Arc::new(MovingSphere::new(
-                        center,
-                        center2,
-                        0.0,
-                        1.0,
-                        0.2,
-                        Arc::new(Lambertian::new(Arc::clone(&albedo))),
-                    )));
-                    // } else if prob < 0.4 {
-                    //     // create a marble textured sphere
-                    //     let marble_tex: Arc<dyn Texture> = Arc::new(Noise::new(Perlin::new(), 5.0));
-                    //     let center = center + Vec3::new(0., rng.gen::<f64>(), 0.);
-                    //     let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::clone(&marble_tex)));
-                    //     let sphere = Sphere::new(center, 0.2, mat);
-                    //     world.add(Arc::new(sphere));
+                    graph.objects.push(ObjectNode::from(Shape::MovingSphere {
+                        center0: center,
+                        center1: center2,
+                        time0: 0.0,
+                        time1: 1.0,
+                        radius: 0.2,
+                        material: solid_lambertian(albedo),
+                    }));
                 } else if prob < 0.7 {
                     // create a solid color, Lambertian sphere
-                    let solid_tex: Arc<dyn Texture> =
-                        Arc::new(SolidColor::from(Color::random() * Color::random()));
+                    let albedo = Color::random() * Color::random();
                     let center = center + Vec3::new(0., rng.gen::<f64>(), 0.);
-                    let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::clone(&solid_tex)));
-                    let sphere = Sphere::new(center, 0.2, mat);
-                    world.add(Arc::new(sphere));
+                    graph
+                        .objects
+                        .push(sphere(center, 0.2, solid_lambertian(albedo)));
                 } else if prob < 0.95 {
                     let albedo = Color::random_range(0.5, 1.0);
                     let fuzz = rng.gen_range(0.0..0.5);
-                    world.add(Arc::new(Sphere::new(
+                    graph.objects.push(sphere(
                         center,
                         0.2,
-                        Arc::new(Metal::new(albedo, fuzz)),
-                    )));
+                        MaterialRef::Inline(MaterialNode::Metal {
+                            color: albedo,
+                            fuzz,
+                        }),
+                    ));
                 } else {
-                    world.add(Arc::new(Sphere::new(
+                    graph.objects.push(sphere(
                         center,
                         0.2,
-                        Arc::new(Dielectric::new(1.5)),
-                    )));
+                        MaterialRef::Named("glass".to_string()),
+                    ));
                 }
             }
         }
     }
 
     // add a single, larger glass sphere
-    let glass_shere = build_dielectric_sphere(Point3::new(0.0, 1.0, 0.0), 1.0, 1.5);
-    world.add(Arc::new(glass_shere));
+    graph.objects.push(sphere(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        MaterialRef::Named("glass".to_string()),
+    ));
 
     // add a sphere with perlin noise
-    let perlin_sphere = build_perlin_sphere(Point3::new(-4., 1., 0.), 1.0, 0.9);
-    //let tex2: Arc<dyn Texture> = Arc::new(SolidColor::from_rgb(1.0, 0.1, 0.1));
-    //let mat2: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::clone(&tex2)));
-    //let sphere2: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(-4., 1., 0.), 1.0, Arc::clone(&mat2)));
-    world.add(Arc::new(perlin_sphere));
+    graph.textures.push(NamedTexture {
+        name: "perlin".to_string(),
+        texture: TextureNode::Noise { scale: 0.9 },
+    });
+    graph.objects.push(sphere(
+        Point3::new(-4., 1., 0.),
+        1.0,
+        MaterialRef::Inline(MaterialNode::Lambertian {
+            texture: TextureRef::Named("perlin".to_string()),
+        }),
+    ));
 
     // add a single metal sphere (tan color)
-    let metal_sphere =
-        build_metal_sphere(Point3::new(4., 1., 0.), 1.0, Color::new(0.7, 0.6, 0.5), 0.0);
-    world.add(Arc::new(metal_sphere));
+    graph.objects.push(sphere(
+        Point3::new(4., 1., 0.),
+        1.0,
+        MaterialRef::Inline(MaterialNode::Metal {
+            color: Color::new(0.7, 0.6, 0.5),
+            fuzz: 0.0,
+        }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::random_spheres::build_random_sphere_scene_graph;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_random_sphere_scene_loads_back_to_the_same_graph() {
+        use crate::scene::loader::load_scene_graph;
+
+        let graph = build_random_sphere_scene_graph();
+        let path = std::env::temp_dir().join("raytracer_random_spheres_scene.json");
+        graph.save(&path).unwrap();
+        let loaded = load_scene_graph(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
 
-    world
+        assert_eq!(loaded, graph);
+    }
+
+    #[test]
+    fn random_sphere_scene_graph_builds() {
+        let graph = build_random_sphere_scene_graph();
+        let mut scene = graph.build(40, 2.0).unwrap();
+
+        assert_eq!(scene.world.objects().len(), graph.objects.len());
+        assert_eq!(scene.camera.image_width, 40);
+    }
 }
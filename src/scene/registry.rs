@@ -0,0 +1,91 @@
+use crate::material::Material;
+use crate::texture::Texture;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A Registry maps names to the materials and textures of a scene, so that a material
+/// (i.e. `"red_glass"`) can be defined once and shared, as a single `Arc`, by every object that
+/// references it by name. Materials and textures have separate namespaces
+#[derive(Debug, Default, Clone)]
+pub struct Registry {
+    materials: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Registers `material` under `name`, returning the material previously registered under
+    /// that name, if any
+    pub fn add_material(
+        &mut self,
+        name: impl Into<String>,
+        material: Arc<dyn Material>,
+    ) -> Option<Arc<dyn Material>> {
+        self.materials.insert(name.into(), material)
+    }
+
+    /// Registers `texture` under `name`, returning the texture previously registered under
+    /// that name, if any
+    pub fn add_texture(
+        &mut self,
+        name: impl Into<String>,
+        texture: Arc<dyn Texture>,
+    ) -> Option<Arc<dyn Texture>> {
+        self.textures.insert(name.into(), texture)
+    }
+
+    /// Returns a shared reference to the material registered under `name`
+    pub fn material(&self, name: &str) -> Option<Arc<dyn Material>> {
+        self.materials.get(name).map(Arc::clone)
+    }
+
+    /// Returns a shared reference to the texture registered under `name`
+    pub fn texture(&self, name: &str) -> Option<Arc<dyn Texture>> {
+        self.textures.get(name).map(Arc::clone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3};
+    use crate::hittable::{HittableList, Sphere};
+    use crate::material::{Dielectric, Lambertian, Material};
+    use crate::scene::registry::Registry;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    #[test]
+    fn objects_referencing_the_same_name_share_one_material() {
+        let mut registry = Registry::new();
+        registry.add_texture("red", Arc::new(SolidColor::from(Color::new(1.0, 0.0, 0.0))));
+        registry.add_material(
+            "red_lambertian",
+            Arc::new(Lambertian::new(registry.texture("red").unwrap())),
+        );
+        registry.add_material("red_glass", Arc::new(Dielectric::new(1.5)));
+
+        let first: Arc<dyn Material> = registry.material("red_glass").unwrap();
+        let second: Arc<dyn Material> = registry.material("red_glass").unwrap();
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(-1.0, 0.0, 0.0),
+            0.5,
+            Arc::clone(&first),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(1.0, 0.0, 0.0),
+            0.5,
+            Arc::clone(&second),
+        )));
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &registry.material("red_lambertian").unwrap()
+        ));
+        assert!(registry.material("blue_glass").is_none());
+    }
+}
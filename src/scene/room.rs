@@ -0,0 +1,220 @@
+use crate::common::{Color, Vec3};
+use crate::hittable::{FlipFace, Hittable, HittableList, XYRect, XZRect, YZRect};
+use crate::material::{DiffuseLight, Lambertian, Material};
+use crate::texture::SolidColor;
+use std::sync::Arc;
+
+/// A builder struct for constructing a "Cornell box" style room.
+///
+/// The room spans from the origin to its `size`, and every wall is oriented so that its front
+/// face points into the room. The left and right walls are named as seen by a camera that looks
+/// into the room along the positive z-axis, so the left wall lies at `x = size.x()`.
+/// A rectangular light hangs just below the ceiling and faces down. It is centered on the
+/// ceiling, unless it is moved with `light_center`.
+/// The front wall, at `z = 0`, is left open by default so that a camera can look inside
+#[derive(Debug, Copy, Clone)]
+pub struct RoomBuilder {
+    size: Vec3,
+    left_color: Color,
+    right_color: Color,
+    wall_color: Color,
+    light_color: Color,
+    light_size: (f64, f64),
+    light_center: Option<(f64, f64)>,
+    front_wall: bool,
+}
+
+impl RoomBuilder {
+    /// Returns a builder for the classic 555 unit Cornell box, with a green left wall, a red
+    /// right wall, and white floor, ceiling and back walls
+    pub fn new() -> Self {
+        Self {
+            size: Vec3::new(555.0, 555.0, 555.0),
+            left_color: Color::new(0.12, 0.45, 0.15),
+            right_color: Color::new(0.65, 0.05, 0.05),
+            wall_color: Color::new(0.73, 0.73, 0.73),
+            light_color: Color::new(16.0, 16.0, 16.0),
+            light_size: (190.0, 165.0),
+            light_center: None,
+            front_wall: false,
+        }
+    }
+
+    ///////////////////////////////////////////////////////////
+    //              Builder Functions BEGIN HERE
+
+    /// Sets the width (x), height (y) and depth (z) of the room
+    pub fn size(&mut self, size: Vec3) -> Self {
+        self.size = size;
+        *self
+    }
+
+    /// Sets the colors of the left and right walls
+    pub fn side_wall_colors(&mut self, left: Color, right: Color) -> Self {
+        self.left_color = left;
+        self.right_color = right;
+        *self
+    }
+
+    /// Sets the color of the floor, ceiling, back wall and (optional) front wall
+    pub fn wall_color(&mut self, color: Color) -> Self {
+        self.wall_color = color;
+        *self
+    }
+
+    /// Sets the emitted color of the ceiling light, and its size along the x and z axes
+    pub fn light(&mut self, color: Color, width: f64, depth: f64) -> Self {
+        self.light_color = color;
+        self.light_size = (width, depth);
+        *self
+    }
+
+    /// Moves the center of the ceiling light to `x` and `z`
+    pub fn light_center(&mut self, x: f64, z: f64) -> Self {
+        self.light_center = Some((x, z));
+        *self
+    }
+
+    /// Closes the front of the room with a wall, i.e. for rendering from inside of the room
+    pub fn front_wall(&mut self, front_wall: bool) -> Self {
+        self.front_wall = front_wall;
+        *self
+    }
+
+    /// Returns a list containing the left and right walls of the room, its light, and then its
+    /// ceiling, floor, back wall and (optional) front wall. Objects added to a list first win
+    /// ties between coplanar surfaces, see `HittableList`
+    pub fn build(&self) -> HittableList {
+        let (x, y, z) = (self.size.x(), self.size.y(), self.size.z());
+        let lambertian = |color: Color| -> Arc<dyn Material> {
+            Arc::new(Lambertian::new(Arc::new(SolidColor::from(color))))
+        };
+        let left_mat = lambertian(self.left_color);
+        let right_mat = lambertian(self.right_color);
+        let wall_mat = lambertian(self.wall_color);
+        let flipped = |hittable: Arc<dyn Hittable>| -> Arc<dyn Hittable> {
+            Arc::new(FlipFace::from(hittable))
+        };
+
+        let mut room = HittableList::new();
+        // rectangles face the positive direction of their axis, so the walls at the far end of
+        // each axis are flipped to face into the room
+        room.add(flipped(Arc::new(YZRect::from(0., y, 0., z, x, left_mat))));
+        room.add(Arc::new(YZRect::from(0., y, 0., z, 0., right_mat)));
+        room.add(self.build_light());
+        room.add(flipped(Arc::new(XZRect::from(
+            0.,
+            x,
+            0.,
+            z,
+            y,
+            Arc::clone(&wall_mat),
+        ))));
+        room.add(Arc::new(XZRect::from(
+            0.,
+            x,
+            0.,
+            z,
+            0.,
+            Arc::clone(&wall_mat),
+        )));
+        room.add(flipped(Arc::new(XYRect::from(
+            0.,
+            x,
+            0.,
+            y,
+            z,
+            Arc::clone(&wall_mat),
+        ))));
+        if self.front_wall {
+            room.add(Arc::new(XYRect::from(0., x, 0., y, 0., wall_mat)));
+        }
+        room
+    }
+
+    /// Returns the ceiling light of the room, the same light that `build` adds to the room, i.e.
+    /// for importance sampling it with `Renderer::with_lights`
+    pub fn build_light(&self) -> Arc<dyn Hittable> {
+        let (x, y, z) = (self.size.x(), self.size.y(), self.size.z());
+        let (width, depth) = self.light_size;
+        let (center_x, center_z) = self.light_center.unwrap_or((x / 2.0, z / 2.0));
+        let (x0, z0) = (center_x - width / 2.0, center_z - depth / 2.0);
+        let light_mat: Arc<dyn Material> = Arc::new(DiffuseLight::from(Arc::new(
+            SolidColor::from(self.light_color),
+        )));
+        Arc::new(FlipFace::from(Arc::new(XZRect::from(
+            x0,
+            x0 + width,
+            z0,
+            z0 + depth,
+            y - 1.0,
+            light_mat,
+        ))))
+    }
+}
+
+impl Default for RoomBuilder {
+    fn default() -> Self {
+        RoomBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, HittableList};
+    use crate::scene::room::RoomBuilder;
+
+    #[test]
+    fn closed_room_has_six_inward_facing_walls_and_a_light() {
+        let mut room = RoomBuilder::new()
+            .size(Vec3::new(10.0, 8.0, 12.0))
+            .light(Vec3::new(4.0, 4.0, 4.0), 2.0, 2.0)
+            .front_wall(true)
+            .build();
+        let center = Point3::new(5.0, 4.0, 6.0);
+        let hit = |room: &HittableList, direction: Vec3| {
+            room.hit(&Ray::new(center, direction, 0.0), 0.001, f64::INFINITY)
+                .expect("every ray from inside of a closed room hits something")
+        };
+
+        // rays from the center towards each wall, the ceiling ray misses the light
+        let directions = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.3, 1.0, 0.4),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for direction in directions {
+            let rec = hit(&room, direction);
+            assert!(
+                rec.front_face,
+                "the wall hit towards {} faces outward",
+                direction
+            );
+        }
+        // the light hangs just below the center of the ceiling, facing down
+        let light = hit(&room, Vec3::new(0.0, 1.0, 0.0));
+
+        assert!(light.front_face);
+        assert!((light.p.y() - 7.0).abs() < 1e-9);
+        assert_eq!(room.objects().len(), 7);
+    }
+
+    #[test]
+    fn the_light_can_be_moved_away_from_the_center_of_the_ceiling() {
+        let light = RoomBuilder::new()
+            .light_center(278.0, 219.5)
+            .build_light()
+            .bounding_box(0.0, 1.0)
+            .unwrap();
+
+        // the light of the original cornell box scene
+        assert!((light.min().x() - 183.0).abs() < 1e-3);
+        assert!((light.max().x() - 373.0).abs() < 1e-3);
+        assert!((light.min().z() - 137.0).abs() < 1e-3);
+        assert!((light.max().z() - 302.0).abs() < 1e-3);
+    }
+}
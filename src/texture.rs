@@ -7,12 +7,18 @@ pub use checker_texture::*;
 pub mod image_texture;
 pub use image_texture::*;
 
+pub mod environment_map;
+pub use environment_map::*;
+
 pub mod perlin;
 pub use perlin::*;
 
 pub mod noise_texture;
 pub use noise_texture::*;
 
+pub mod uv_debug_texture;
+pub use uv_debug_texture::*;
+
 use crate::common::{Color, Point3, Vec3};
 use std::f64::consts::PI;
 
@@ -27,6 +33,28 @@ pub fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
     (u, v)
 }
 
+/// Computes the `u,v` coordinates of an equirectangular environment map, seen from inside of
+/// the sphere it wraps, in the direction of the unit vector `direction`. The map wraps around
+/// the vertical axis, with `v = 1` straight up
+pub fn get_environment_uv(direction: &Vec3) -> (f64, f64) {
+    let (u, v) = get_sphere_uv(direction);
+    // sphere coordinates are seen from outside of the sphere, flip u so that the map is not
+    // mirrored when seen from inside of it
+    (1.0 - u, v)
+}
+
+/// Returns the unit vector pointing towards the `u,v` coordinates of an equirectangular
+/// environment map. This is the inverse of `get_environment_uv`
+pub fn environment_direction(u: f64, v: f64) -> Vec3 {
+    let phi = 2.0 * PI * u - PI;
+    let latitude = PI * v - PI / 2.0;
+    Vec3::new(
+        latitude.cos() * phi.cos(),
+        latitude.sin(),
+        latitude.cos() * phi.sin(),
+    )
+}
+
 /// A trait for Hittables that have a texture. A texture in graphics is usually a function that makes
 /// the colors on a surface procedural. This procedure can be synthesis code, or it could be an
 /// image lookup, or a combination of both.
@@ -34,4 +62,11 @@ pub fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
 pub trait Texture: Send + Sync + std::fmt::Debug {
     /// Returns the color of a texture at the given `u,v` coordinate and point `p`
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// Returns the color of a texture at the given `u,v` coordinate and point `p`, filtered
+    /// over the area covered by a ray, which is `uv_footprint` wide in `u,v` units.
+    /// The default implementation ignores the footprint and calls `value`
+    fn value_lod(&self, u: f64, v: f64, p: &Point3, _uv_footprint: f64) -> Color {
+        self.value(u, v, p)
+    }
 }
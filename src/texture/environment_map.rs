@@ -0,0 +1,124 @@
+use crate::common::{clamp, Color, Point3};
+use crate::texture::Texture;
+use image::codecs::hdr::HdrDecoder;
+use image::{GenericImageView, ImageFormat};
+use std::fs::File;
+use std::io::BufReader;
+
+/// An equirectangular (latitude-longitude) environment map, such as an HDRI, that is used as
+/// the background of a scene, see `BackgroundColor::Environment`.
+///
+/// Unlike an `ImageTexture`, the texels are stored as linear floating point colors, so the
+/// bright regions of a high dynamic range image, like the sun, keep their full radiance
+/// instead of being clamped to 1
+#[derive(Debug, Default)]
+pub struct EnvironmentMap {
+    // the red, green and blue values of each texel, in row major order from the top left
+    data: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
+impl EnvironmentMap {
+    /// Loads the image at `file_name`, i.e. a Radiance `.hdr` file, as an environment map, or
+    /// returns an error if the file can't be read or is not an image. Radiance files are read
+    /// with their full floating point range, other images are converted to floats in `[0, 1]`
+    pub fn open(file_name: &str) -> Result<Self, image::ImageError> {
+        if ImageFormat::from_path(file_name)? == ImageFormat::Hdr {
+            // the generic image loader converts Radiance files to 8-bits per channel
+            let decoder = HdrDecoder::new(BufReader::new(File::open(file_name)?))?;
+            let metadata = decoder.metadata();
+            let pixels = decoder.read_image_hdr()?;
+            return Ok(Self {
+                data: pixels.iter().flat_map(|pixel| pixel.0).collect(),
+                width: metadata.width,
+                height: metadata.height,
+            });
+        }
+        let img = image::open(file_name)?;
+        let (width, height) = img.dimensions();
+        Ok(Self {
+            data: img.into_rgb32f().into_raw(),
+            width,
+            height,
+        })
+    }
+
+    /// Builds an environment map from raw 8-bit RGB pixel `data`, stored in row major order
+    /// starting from the top left of the image. Each byte is scaled to `[0, 1]`
+    ///
+    /// # Panics
+    /// if the length of `data` is not equal to `width * height * 3`
+    pub fn from_rgb8(width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            (width * height * 3) as usize,
+            "image data length must equal width * height * 3"
+        );
+        Self {
+            data: data.iter().map(|byte| *byte as f32 / 255.0).collect(),
+            width,
+            height,
+        }
+    }
+
+    /// Returns the width of this map, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of this map, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Texture for EnvironmentMap {
+    /// Returns the color of the texel at the `u,v` coordinates of the map
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // if no map data, return solid cyan as a debugging aid
+        if self.data.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        // clamp texture coordinates to [0,1] x [1,0], flipping v to image coordinates
+        let u = clamp(u, 0.0, 1.0);
+        let v = 1.0 - clamp(v, 0.0, 1.0);
+        let i = ((u * self.width as f64) as usize).min(self.width as usize - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height as usize - 1);
+
+        let idx = (j * self.width as usize + i) * 3;
+        let pixel = &self.data[idx..idx + 3];
+        Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3};
+    use crate::texture::{EnvironmentMap, Texture};
+    use crate::util::hdr;
+
+    #[test]
+    fn hdr_maps_keep_radiance_above_one() {
+        let path = std::env::temp_dir().join(format!(
+            "raytracer_environment_map_{}.hdr",
+            std::process::id()
+        ));
+        let pixels = [Color::new(0.25, 0.5, 1.0), Color::new(40.0, 20.0, 10.0)];
+        hdr::write_file(&path, 2, 1, &pixels).unwrap();
+
+        let map = EnvironmentMap::open(&path.to_string_lossy()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((map.width(), map.height()), (2, 1));
+        let sun = map.value(0.75, 0.5, &Point3::default());
+        assert!((sun - Color::new(40.0, 20.0, 10.0)).length() < 0.5);
+        let sky = map.value(0.25, 0.5, &Point3::default());
+        assert!((sky - Color::new(0.25, 0.5, 1.0)).length() < 0.02);
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        assert!(EnvironmentMap::open("./no-such-environment.hdr").is_err());
+    }
+}
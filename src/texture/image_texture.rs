@@ -5,65 +5,170 @@ use image::{DynamicImage, GenericImageView};
 const BYTES_PER_PIXEL: u32 = 3;
 const COLOR_SCALE: f64 = 1.0 / 255.0;
 
+/// A single, downsampled, level of an image texture's mipmap chain
+#[derive(Debug, Default)]
+struct MipLevel {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
 /// Enables in image to be texture mapped onto a Hittable
 /// To test this, assign it to a sphere, and then temporarily cripple the ray_color() function
 /// in `Renderer` to just return attenuation
+///
+/// When the texture is constructed, a chain of *mipmaps* is precomputed by repeatedly halving
+/// the image's resolution. Lookups made through `value_lod` read from the mipmap level whose
+/// texels best match the ray's footprint, which avoids aliasing on distant surfaces
 #[derive(Debug, Default)]
 pub struct ImageTexture {
     data: Vec<u8>,
     width: u32,
     height: u32,
     bytes_per_scanline: u32,
+    // the downsampled levels of this texture, starting at half of the full resolution
+    mips: Vec<MipLevel>,
 }
 
 impl ImageTexture {
     pub fn from(file_name: &str) -> Self {
-        let img: DynamicImage = image::open(file_name)
-            .unwrap_or_else(|_| panic!("could not load image at {}", file_name));
+        ImageTexture::open(file_name)
+            .unwrap_or_else(|_| panic!("could not load image at {}", file_name))
+    }
+
+    /// Loads the image at `file_name` as a texture, or returns an error if the file can't be
+    /// read or is not an image
+    pub fn open(file_name: &str) -> Result<Self, image::ImageError> {
+        let img: DynamicImage = image::open(file_name)?;
         let (width, height) = img.dimensions();
-        let data: Vec<u8> = img.into_rgb8().into_vec();
-        let bytes_per_scanline = width * BYTES_PER_PIXEL;
+        Ok(ImageTexture::from_rgb8(
+            width,
+            height,
+            img.into_rgb8().into_vec(),
+        ))
+    }
+
+    /// Builds an image texture from raw 8-bit RGB pixel `data`, stored in row major order
+    /// starting from the top left of the image
+    ///
+    /// # Panics
+    /// if the length of `data` is not equal to `width * height * 3`
+    pub fn from_rgb8(width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            (width * height * BYTES_PER_PIXEL) as usize,
+            "image data length must equal width * height * 3"
+        );
+        let mut mips: Vec<MipLevel> = vec![];
+        let (mut prev_data, mut prev_width, mut prev_height) = (&data, width, height);
+        while prev_width > 1 || prev_height > 1 {
+            let mip = ImageTexture::downsample(prev_data, prev_width, prev_height);
+            mips.push(mip);
+            let last = mips.last().unwrap();
+            (prev_data, prev_width, prev_height) = (&last.data, last.width, last.height);
+        }
 
         Self {
+            bytes_per_scanline: width * BYTES_PER_PIXEL,
             data,
             width,
             height,
-            bytes_per_scanline,
+            mips,
         }
     }
-}
 
-impl Texture for ImageTexture {
-    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
-        // if no texture data, return solid cyan as a debugging aid
-        if self.data.is_empty() {
-            return Color::new(0.0, 1.0, 1.0);
+    /// Returns the width of this texture's full resolution image, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of this texture's full resolution image, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of mipmap levels of this texture, including the full resolution image
+    pub fn mip_level_count(&self) -> usize {
+        self.mips.len() + 1
+    }
+
+    /// Returns the mipmap level that is sampled for a ray footprint that is `uv_footprint` wide,
+    /// in `u,v` units. Level 0 is the full resolution image, and each following level has half
+    /// of the resolution of the previous level
+    pub fn mip_level(&self, uv_footprint: f64) -> usize {
+        let texels = uv_footprint * self.width.max(self.height) as f64;
+        if texels <= 1.0 {
+            return 0;
         }
+        (texels.log2() as usize).min(self.mips.len())
+    }
+
+    /// Returns a new mipmap level with half of the resolution of the given image `data`, where
+    /// each pixel is the average of (up to) four pixels of `data`
+    fn downsample(data: &[u8], width: u32, height: u32) -> MipLevel {
+        let mip_width = (width / 2).max(1);
+        let mip_height = (height / 2).max(1);
+        let mut mip_data: Vec<u8> = Vec::with_capacity((mip_width * mip_height * 3) as usize);
+
+        for j in 0..mip_height {
+            for i in 0..mip_width {
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for y in (2 * j)..(2 * j + 2).min(height) {
+                    for x in (2 * i)..(2 * i + 2).min(width) {
+                        let idx = ((y * width + x) * BYTES_PER_PIXEL) as usize;
+                        for (c, total) in sum.iter_mut().enumerate() {
+                            *total += data[idx + c] as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                mip_data.extend(sum.iter().map(|total| (total / count) as u8));
+            }
+        }
+
+        MipLevel {
+            data: mip_data,
+            width: mip_width,
+            height: mip_height,
+        }
+    }
+
+    /// Returns the color of the texel at the `u,v` coordinates of an image of size `width` x
+    /// `height`, stored in `data` with `bytes_per_scanline` bytes per row
+    fn texel(
+        data: &[u8],
+        width: u32,
+        height: u32,
+        bytes_per_scanline: u32,
+        u: f64,
+        v: f64,
+    ) -> Color {
         // clamp texture coordinates to [0,1] x [1,0]
         let u = clamp(u, 0.0, 1.0);
         let v = 1.0 - clamp(v, 0.0, 1.0); //flip v to image coordinates
 
         let i = {
-            let i = (u * self.width as f64) as usize;
+            let i = (u * width as f64) as usize;
             // Clamp integer mapping, since actual coordinates should be less than 1.0
-            if i >= self.width as usize {
-                self.width as usize - 1
+            if i >= width as usize {
+                width as usize - 1
             } else {
                 i
             }
         };
         let j = {
-            let j = (v * self.height as f64) as usize;
+            let j = (v * height as f64) as usize;
             // Clamp integer mapping, since actual coordinates should be less than 1.0
-            if j >= self.height as usize {
-                self.height as usize - 1
+            if j >= height as usize {
+                height as usize - 1
             } else {
                 j
             }
         };
 
-        let idx = j * self.bytes_per_scanline as usize + i * BYTES_PER_PIXEL as usize;
-        let pixel = &self.data[idx..idx + 3];
+        let idx = j * bytes_per_scanline as usize + i * BYTES_PER_PIXEL as usize;
+        let pixel = &data[idx..idx + 3];
 
         Color::new(
             COLOR_SCALE * pixel[0] as f64,
@@ -73,9 +178,57 @@ impl Texture for ImageTexture {
     }
 }
 
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // if no texture data, return solid cyan as a debugging aid
+        if self.data.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        ImageTexture::texel(
+            &self.data,
+            self.width,
+            self.height,
+            self.bytes_per_scanline,
+            u,
+            v,
+        )
+    }
+
+    /// Returns the color of the texture read from the mipmap level matching `uv_footprint`
+    fn value_lod(&self, u: f64, v: f64, p: &Point3, uv_footprint: f64) -> Color {
+        match self.mip_level(uv_footprint) {
+            0 => self.value(u, v, p),
+            level => {
+                let mip = &self.mips[level - 1];
+                let bytes_per_scanline = mip.width * BYTES_PER_PIXEL;
+                ImageTexture::texel(&mip.data, mip.width, mip.height, bytes_per_scanline, u, v)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::texture::ImageTexture;
+    use crate::common::{Point3, Ray, Vec3};
+    use crate::hittable::{Hittable, Sphere, Translate};
+    use crate::material::Lambertian;
+    use crate::texture::{ImageTexture, Texture};
+    use std::sync::Arc;
+
+    /// builds a `size` x `size` black and white checker board texture with 1 pixel squares
+    fn checker_board(size: u32) -> ImageTexture {
+        let data = (0..size * size)
+            .flat_map(|idx| {
+                let c = if (idx % size + idx / size).is_multiple_of(2) {
+                    255
+                } else {
+                    0
+                };
+                [c, c, c]
+            })
+            .collect();
+        ImageTexture::from_rgb8(size, size, data)
+    }
 
     #[test]
     fn load_an_image() {
@@ -94,4 +247,68 @@ mod tests {
         assert_eq!(image_tex.bytes_per_scanline, 0);
         assert!(image_tex.data.is_empty());
     }
+
+    #[test]
+    fn mip_chain_halves_down_to_a_single_pixel() {
+        let tex = checker_board(16);
+
+        // 16, 8, 4, 2 and 1 pixel wide levels
+        assert_eq!(tex.mip_level_count(), 5);
+        let p = Point3::default();
+        assert_eq!(tex.value_lod(0.5, 0.5, &p, 0.0).x(), 1.0);
+        // the coarsest level averages the black and white squares to gray
+        let gray = tex.value_lod(0.5, 0.5, &p, 1.0);
+        assert!((gray.x() - 127.0 / 255.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn distant_lookup_reads_from_a_coarser_mip_level_than_a_near_lookup() {
+        let tex = Arc::new(checker_board(256));
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Arc::clone(&tex) as Arc<dyn Texture>)),
+        );
+        let spread = 0.001;
+        let near_ray = Ray::new(Point3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+            .with_spread(spread);
+        let far_ray = Ray::new(Point3::new(0.0, 0.0, 300.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+            .with_spread(spread);
+
+        let near = sphere.hit(&near_ray, 0.001, f64::INFINITY).unwrap();
+        let far = sphere.hit(&far_ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!(far.uv_footprint > near.uv_footprint);
+        assert_eq!(tex.mip_level(near.uv_footprint), 0);
+        assert!(tex.mip_level(far.uv_footprint) > tex.mip_level(near.uv_footprint));
+    }
+
+    #[test]
+    fn lookup_through_a_translate_reads_from_the_same_mip_level() {
+        let tex = Arc::new(checker_board(256));
+        let sphere = |center: Point3| {
+            Sphere::new(
+                center,
+                1.0,
+                Arc::new(Lambertian::new(Arc::clone(&tex) as Arc<dyn Texture>)),
+            )
+        };
+        let moved = Translate::from(
+            Arc::new(sphere(Point3::default())),
+            Vec3::new(0.0, 0.0, -10.0),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 100.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+            .with_spread(0.001);
+
+        let direct = sphere(Point3::new(0.0, 0.0, -10.0))
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        let translated = moved.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!(tex.mip_level(direct.uv_footprint) > 0);
+        assert_eq!(
+            tex.mip_level(translated.uv_footprint),
+            tex.mip_level(direct.uv_footprint)
+        );
+    }
 }
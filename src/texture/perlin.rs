@@ -1,4 +1,4 @@
-use crate::common::{Point3, Vec3};
+use crate::common::{Point3, RngContext, Vec3};
 use rand::seq::SliceRandom;
 use std::fmt::Formatter;
 
@@ -90,7 +90,7 @@ impl Perlin {
     /// fills the input array with integers in the range 0..POINT_COUNT and then
     /// "shuffles" the array
     fn generate_perm(arr: &mut [i32; POINT_COUNT]) {
-        let mut rng = rand::thread_rng();
+        let mut rng = RngContext::current();
 
         for (i, item) in arr.iter_mut().enumerate() {
             *item = i as i32;
@@ -0,0 +1,37 @@
+use crate::common::{Color, Point3};
+use crate::texture::Texture;
+
+/// A debugging Texture that colors a surface by its texture coordinates, where the red channel
+/// is `u` and the green channel is `v`. Apply it to a primitive to see its UV layout, i.e. to
+/// tell whether a texture that looks wrong is caused by the primitive's `u,v` coordinates or
+/// by the texture itself
+#[derive(Debug, Default, Copy, Clone)]
+pub struct UvDebugTexture;
+
+impl UvDebugTexture {
+    pub fn new() -> Self {
+        UvDebugTexture
+    }
+}
+
+impl Texture for UvDebugTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        Color::new(u, v, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Color, Point3};
+    use crate::texture::{Texture, UvDebugTexture};
+
+    #[test]
+    fn colors_are_the_texture_coordinates() {
+        let texture = UvDebugTexture::new();
+
+        assert_eq!(
+            texture.value(0.5, 0.25, &Point3::default()),
+            Color::new(0.5, 0.25, 0.0)
+        );
+    }
+}
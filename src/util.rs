@@ -1,3 +1,14 @@
+pub mod blend;
 pub mod command;
+#[cfg(feature = "oidn")]
+pub mod denoise;
+pub mod gif;
+pub mod grade;
+pub mod hdr;
 pub mod png;
+pub mod postprocess;
 pub mod ppm;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod spatial_hash;
+pub mod tonemap;
@@ -0,0 +1,78 @@
+use crate::common::Color;
+
+/// Returns the per-pixel mean of `images`, which must all hold the same number of pixels.
+/// The images should hold *linear* radiance, such as those returned by
+/// `Renderer::render_radiance`, so that independent estimates of the same view (i.e. from
+/// different seeds or render nodes) can be combined. Returns an empty image if `images` is empty
+///
+/// # Panics
+/// if the images are not all the same length
+pub fn average(images: &[&[Color]]) -> Vec<Color> {
+    let weights = vec![1.0; images.len()];
+    weighted_average(images, &weights)
+}
+
+/// Returns the per-pixel weighted mean of `images`, where `weights[i]` is the weight of
+/// `images[i]`, i.e. the number of samples per pixel each image was rendered with.
+/// Returns an empty image if `images` is empty, or black pixels if the weights sum to zero
+///
+/// # Panics
+/// if the images are not all the same length, or if there is not exactly one weight per image
+pub fn weighted_average(images: &[&[Color]], weights: &[f64]) -> Vec<Color> {
+    assert_eq!(
+        images.len(),
+        weights.len(),
+        "there must be one weight per image"
+    );
+    let Some(first) = images.first() else {
+        return Vec::new();
+    };
+    assert!(
+        images.iter().all(|image| image.len() == first.len()),
+        "images must all have the same number of pixels"
+    );
+
+    let total_weight: f64 = weights.iter().sum();
+    let mut blended = vec![Color::default(); first.len()];
+    if total_weight == 0.0 {
+        return blended;
+    }
+    for (image, weight) in images.iter().zip(weights.iter()) {
+        for (sum, color) in blended.iter_mut().zip(image.iter()) {
+            *sum += *color * *weight;
+        }
+    }
+    for color in blended.iter_mut() {
+        *color /= total_weight;
+    }
+    blended
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::blend::{average, weighted_average};
+
+    #[test]
+    fn average_is_the_per_pixel_mean() {
+        let a = [Color::new(0.0, 1.0, 2.0), Color::new(1.0, 1.0, 1.0)];
+        let b = [Color::new(2.0, 3.0, 4.0), Color::new(0.0, 0.0, 0.0)];
+
+        let blended = average(&[&a, &b]);
+
+        assert_eq!(
+            blended,
+            vec![Color::new(1.0, 2.0, 3.0), Color::new(0.5, 0.5, 0.5)]
+        );
+    }
+
+    #[test]
+    fn weighted_average_respects_the_weights() {
+        let a = [Color::new(1.0, 1.0, 1.0)];
+        let b = [Color::new(5.0, 5.0, 5.0)];
+
+        let blended = weighted_average(&[&a, &b], &[3.0, 1.0]);
+
+        assert_eq!(blended, vec![Color::new(2.0, 2.0, 2.0)]);
+    }
+}
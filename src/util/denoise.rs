@@ -0,0 +1,160 @@
+use crate::common::Color;
+use crate::renderer::Image;
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+
+// the parts of the C API of Intel Open Image Denoise (OIDN) 2 that are used by `denoise`. The
+// library is linked from the system, so that offline builds need no extra crates
+type OIDNDevice = *mut c_void;
+type OIDNFilter = *mut c_void;
+
+/// `OIDN_DEVICE_TYPE_DEFAULT`, the fastest device available
+const OIDN_DEVICE_TYPE_DEFAULT: i32 = 0;
+
+/// `OIDN_FORMAT_FLOAT3`, three 32-bit floats per pixel
+const OIDN_FORMAT_FLOAT3: i32 = 3;
+
+/// `OIDN_ERROR_NONE`
+const OIDN_ERROR_NONE: i32 = 0;
+
+#[link(name = "OpenImageDenoise")]
+extern "C" {
+    fn oidnNewDevice(device_type: i32) -> OIDNDevice;
+    fn oidnCommitDevice(device: OIDNDevice);
+    fn oidnGetDeviceError(device: OIDNDevice, out_message: *mut *const c_char) -> i32;
+    fn oidnReleaseDevice(device: OIDNDevice);
+    fn oidnNewFilter(device: OIDNDevice, filter_type: *const c_char) -> OIDNFilter;
+    fn oidnSetSharedFilterImage(
+        filter: OIDNFilter,
+        name: *const c_char,
+        ptr: *mut c_void,
+        format: i32,
+        width: usize,
+        height: usize,
+        byte_offset: usize,
+        pixel_byte_stride: usize,
+        row_byte_stride: usize,
+    );
+    fn oidnSetFilterBool(filter: OIDNFilter, name: *const c_char, value: bool);
+    fn oidnCommitFilter(filter: OIDNFilter);
+    fn oidnExecuteFilter(filter: OIDNFilter);
+    fn oidnReleaseFilter(filter: OIDNFilter);
+}
+
+/// Returns a copy of the `image` with the noise of its (linear) radiance removed by the "RT"
+/// filter of Intel Open Image Denoise, which is trained on path traced images.
+/// If the image holds auxiliary buffers, see `Renderer::with_aovs`, the filter is guided by
+/// their albedo and normals, which keeps the edges and textures of the surfaces sharp. Low
+/// sample renders then need far fewer samples per pixel to look clean.
+///
+/// The pixels are passed to OIDN as HDR colors, so bright lights are not clipped, and the
+/// tone map and auxiliary buffers of the image are kept
+///
+/// Returns the error message of OIDN if the image could not be denoised
+pub fn denoise(image: &Image) -> Result<Image, String> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut color = to_f32(image.pixels());
+    let mut output = vec![0.0f32; color.len()];
+    let mut guides = image
+        .aovs()
+        .map(|aovs| (to_f32(aovs.albedo()), to_f32(aovs.normal())));
+
+    // SAFETY: the buffers passed to the filter hold `width * height` pixels of three floats
+    // each, and outlive the filter, which is released before they are dropped
+    unsafe {
+        let device = oidnNewDevice(OIDN_DEVICE_TYPE_DEFAULT);
+        if device.is_null() {
+            return Err("could not create an Open Image Denoise device".to_string());
+        }
+        oidnCommitDevice(device);
+        let filter = oidnNewFilter(device, c"RT".as_ptr());
+        let set_image = |name: &CStr, buffer: &mut [f32]| {
+            oidnSetSharedFilterImage(
+                filter,
+                name.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_void,
+                OIDN_FORMAT_FLOAT3,
+                width,
+                height,
+                0,
+                0,
+                0,
+            )
+        };
+        set_image(c"color", &mut color);
+        set_image(c"output", &mut output);
+        if let Some((albedo, normal)) = guides.as_mut() {
+            set_image(c"albedo", albedo);
+            set_image(c"normal", normal);
+        }
+        oidnSetFilterBool(filter, c"hdr".as_ptr(), true);
+        oidnCommitFilter(filter);
+        oidnExecuteFilter(filter);
+
+        let mut message: *const c_char = ptr::null();
+        let error = oidnGetDeviceError(device, &mut message);
+        let result = match error {
+            OIDN_ERROR_NONE => Ok(()),
+            _ if message.is_null() => Err(format!("Open Image Denoise error {}", error)),
+            _ => Err(CStr::from_ptr(message).to_string_lossy().into_owned()),
+        };
+        oidnReleaseFilter(filter);
+        oidnReleaseDevice(device);
+        result?;
+    }
+
+    let pixels = output
+        .chunks_exact(3)
+        .map(|c| Color::new(c[0] as f64, c[1] as f64, c[2] as f64))
+        .collect();
+    let denoised =
+        Image::new(image.width(), image.height(), pixels).with_tone_map(image.tone_map());
+    Ok(match image.aovs() {
+        Some(aovs) => denoised.with_aovs(aovs.clone()),
+        None => denoised,
+    })
+}
+
+/// Returns the channels of the `colors`, as the 32-bit floats expected by OIDN
+fn to_f32(colors: &[Color]) -> Vec<f32> {
+    colors
+        .iter()
+        .flat_map(|c| c.as_array().map(|channel| channel as f32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::renderer::Image;
+    use crate::util::denoise::denoise;
+    use crate::util::tonemap::ToneMap;
+
+    #[test]
+    fn denoising_smooths_a_noisy_flat_image() {
+        // a flat gray image, where every other pixel is a bright or dark sample
+        let pixels: Vec<Color> = (0..64 * 64)
+            .map(|i| match (i % 64 + i / 64) % 2 {
+                0 => Color::new(0.8, 0.8, 0.8),
+                _ => Color::new(0.2, 0.2, 0.2),
+            })
+            .collect();
+        let image = Image::new(64, 64, pixels).with_tone_map(ToneMap::Reinhard);
+        let variance = |image: &Image| {
+            let n = image.pixels().len() as f64;
+            let mean = image.pixels().iter().map(|c| c.x()).sum::<f64>() / n;
+            image
+                .pixels()
+                .iter()
+                .map(|c| (c.x() - mean).powi(2))
+                .sum::<f64>()
+                / n
+        };
+
+        let denoised = denoise(&image).unwrap();
+
+        assert_eq!(denoised.tone_map(), ToneMap::Reinhard);
+        assert_eq!(denoised.pixels().len(), image.pixels().len());
+        assert!(variance(&denoised) < variance(&image) / 4.0);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::common::Color;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, Rgba, RgbaImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// writes the `frames` of an animation into a looping, animated, GIF file
+/// `file_path` is the path to the image file that will be written to
+/// `width` the width of each frame in pixels
+/// `height` the height of each frame in pixels
+/// `frames` the image data of each frame, as returned by `Image::display_colors`
+/// `frame_delay_ms` the number of milliseconds each frame is displayed for
+pub fn write_file(
+    file_path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    frames: &[Vec<Color>],
+    frame_delay_ms: u32,
+) -> image::ImageResult<()> {
+    let file = BufWriter::new(File::create(file_path)?);
+    let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    for image in frames.iter() {
+        // the first row of the image data is the bottom row of the frame
+        let buffer = RgbaImage::from_fn(width, height, |c, r| {
+            let [red, green, blue] = image[((height - 1 - r) * width + c) as usize].as_array();
+            Rgba([red as u8, green as u8, blue as u8, u8::MAX])
+        });
+        let delay = Delay::from_numer_denom_ms(frame_delay_ms, 1);
+        encoder.encode_frame(Frame::from_parts(buffer, 0, 0, delay))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use crate::common::Color;
+
+/// the value of each color channel of a white pixel, in the colors returned by
+/// `Image::display_colors`
+const WHITE: f64 = 256.0;
+
+/// the value of each normalized color channel that contrast is scaled around
+const MID_GRAY: f64 = 0.5;
+
+/// Applies a simple color grade to an `image` of final (gamma corrected) colors, as returned by
+/// `Image::display_colors`, without re-rendering it. Each color channel is normalized to `[0..1]`
+/// before grading, and the graded channels are clamped back into the image's range.
+///
+/// `brightness` is added to every channel, `0.0` leaves the image unchanged
+/// `contrast` scales every channel around mid-gray, `1.0` leaves the image unchanged
+/// `saturation` interpolates each pixel from its luminance (`0.0`, grayscale) to its color
+/// (`1.0`, unchanged), values above `1.0` boost the saturation
+pub fn grade(image: &mut [Color], brightness: f64, contrast: f64, saturation: f64) {
+    for pixel in image.iter_mut() {
+        let color = *pixel / WHITE;
+        let color = color + Color::new(brightness, brightness, brightness);
+        let mid_gray = Color::new(MID_GRAY, MID_GRAY, MID_GRAY);
+        let color = mid_gray + (color - mid_gray) * contrast;
+        let luminance = color.luminance();
+        let gray = Color::new(luminance, luminance, luminance);
+        let mut color = gray + (color - gray) * saturation;
+
+        color.clamped(0.0, 0.999);
+        *pixel = color * WHITE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::grade::grade;
+
+    fn test_image() -> Vec<Color> {
+        vec![
+            Color::new(200.0, 30.0, 90.0),
+            Color::new(10.0, 128.0, 250.0),
+            Color::new(64.0, 64.0, 64.0),
+        ]
+    }
+
+    #[test]
+    fn zero_saturation_produces_a_grayscale_image() {
+        let mut image = test_image();
+
+        grade(&mut image, 0.0, 1.0, 0.0);
+
+        for pixel in image.iter() {
+            assert!((pixel.x() - pixel.y()).abs() < 1e-9);
+            assert!((pixel.y() - pixel.z()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn neutral_grade_leaves_the_image_unchanged() {
+        let mut image = test_image();
+
+        grade(&mut image, 0.0, 1.0, 1.0);
+
+        for (graded, original) in image.iter().zip(test_image().iter()) {
+            assert!((*graded - *original).length() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::common::Color;
+use image::codecs::hdr::HdrEncoder;
+use image::Rgb;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// writes the `image` data into a Radiance HDR (.hdr) file, which stores a floating point
+/// value per color channel, so that bright light sources are not clipped and can be exposed
+/// or tone mapped later
+/// `file_path` is the path to the image file that will be written to
+/// `width` the width of the image in pixels
+/// `height` the height of the image in pixels
+/// `image` the *linear* radiance of each pixel, as returned by `Renderer::render_radiance`
+pub fn write_file(
+    file_path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    image: &[Color],
+) -> image::ImageResult<()> {
+    assert_eq!(
+        image.len(),
+        (width * height) as usize,
+        "image length must equal width * height"
+    );
+    // the renderer stores the bottom row of the image first, HDR files start from the top row
+    let pixels: Vec<Rgb<f32>> = (0..height)
+        .flat_map(|r| {
+            (0..width).map(move |c| {
+                let color = image[((height - 1 - r) * width + c) as usize];
+                Rgb([color.x() as f32, color.y() as f32, color.z() as f32])
+            })
+        })
+        .collect();
+    let writer = BufWriter::new(File::create(file_path)?);
+    HdrEncoder::new(writer).encode(&pixels, width as usize, height as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::hdr::write_file;
+    use image::codecs::hdr::HdrDecoder;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn bright_values_are_not_clipped() {
+        // a bright light on the bottom row, and a dim wall on the top row
+        let image = vec![
+            Color::new(16.0, 16.0, 16.0),
+            Color::new(4.0, 2.0, 1.0),
+            Color::new(0.25, 0.5, 0.125),
+            Color::new(0.0, 0.0, 0.0),
+        ];
+        let path = std::env::temp_dir().join("raytracer_bright_light.hdr");
+
+        write_file(&path, 2, 2, &image).unwrap();
+        let decoder = HdrDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let decoded = decoder.read_image_hdr().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // the top row is written first
+        assert_eq!(decoded[2].0, [16.0, 16.0, 16.0]);
+        assert_eq!(decoded[3].0, [4.0, 2.0, 1.0]);
+        assert_eq!(decoded[0].0, [0.25, 0.5, 0.125]);
+    }
+}
@@ -1,23 +1,102 @@
 use crate::common::Color;
-use image::ColorType;
+use image::{ColorType, ImageBuffer, Rgb};
 use std::path::Path;
 
+/// The number of bits used to store each color channel of a PNG pixel.
+/// `Eight` - the default, 256 levels per channel
+/// `Sixteen` - 65536 levels per channel, which avoids the banding of smooth gradients
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+/// writes the `image` data into a PNG file
+/// `file_path` is the path to the image file that will be written to
+/// `width` the width of the image in pixels
+/// `height` the height of the image in pixels
+/// `image` the image data, with each color channel in the range `[0..256)`, as returned by
+/// `Image::display_colors`
+/// `bit_depth` the number of bits per color channel written to the file
 pub fn write_file(
     file_path: impl AsRef<Path>,
     width: u32,
     height: u32,
     image: &[Color],
+    bit_depth: BitDepth,
 ) -> image::ImageResult<()> {
-    // extract the R,G,B color data from each Color struct in the image slice,
-    // save it as a new slice of 8-bit R,G,B color values
-    let mut rgbs: Vec<u8> = Vec::with_capacity((width * height * 3) as usize);
-    for r in 0..height {
-        for c in 0..width {
-            let idx = ((height - 1 - r) * width + c) as usize;
-            let color = image[idx];
-            rgbs.append(&mut color.as_array().map(|c| c as u8).to_vec());
+    match bit_depth {
+        BitDepth::Eight => {
+            // extract the R,G,B color data from each Color struct in the image slice,
+            // save it as a new slice of 8-bit R,G,B color values
+            let rgbs: Vec<u8> = flipped_channels(width, height, image)
+                .map(|c| c as u8)
+                .collect();
+            image::save_buffer(file_path, &rgbs, width, height, ColorType::Rgb8)
+        }
+        BitDepth::Sixteen => {
+            // rescale each channel from [0..256) to [0..65536)
+            let rgbs: Vec<u16> = flipped_channels(width, height, image)
+                .map(|c| (c * 256.0).clamp(0.0, u16::MAX as f64) as u16)
+                .collect();
+            let buffer: ImageBuffer<Rgb<u16>, Vec<u16>> =
+                ImageBuffer::from_raw(width, height, rgbs)
+                    .expect("image length must equal width * height");
+            buffer.save(file_path)
         }
     }
+}
+
+/// Returns the R,G,B channels of every pixel in `image`, starting from the top row, since the
+/// renderer stores the bottom row of the image first
+fn flipped_channels(width: u32, height: u32, image: &[Color]) -> impl Iterator<Item = f64> + '_ {
+    (0..height).flat_map(move |r| {
+        (0..width).flat_map(move |c| {
+            let idx = ((height - 1 - r) * width + c) as usize;
+            image[idx].as_array()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::png::{write_file, BitDepth};
+    use std::collections::HashSet;
 
-    image::save_buffer(file_path, &rgbs, width, height, ColorType::Rgb8)
+    #[test]
+    fn sixteen_bit_gradient_has_more_distinct_values_than_eight_bit() {
+        // a smooth horizontal gradient spanning only a few 8-bit levels
+        let width = 256;
+        let image: Vec<Color> = (0..width)
+            .map(|i| {
+                let c = 100.0 + 4.0 * i as f64 / width as f64;
+                Color::new(c, c, c)
+            })
+            .collect();
+        let dir = std::env::temp_dir();
+        let path8 = dir.join("raytracer_gradient_8bit.png");
+        let path16 = dir.join("raytracer_gradient_16bit.png");
+
+        write_file(&path8, width, 1, &image, BitDepth::Eight).unwrap();
+        write_file(&path16, width, 1, &image, BitDepth::Sixteen).unwrap();
+        let eight: HashSet<u16> = image::open(&path8)
+            .unwrap()
+            .to_rgb16()
+            .pixels()
+            .map(|p| p[0])
+            .collect();
+        let sixteen: HashSet<u16> = image::open(&path16)
+            .unwrap()
+            .to_rgb16()
+            .pixels()
+            .map(|p| p[0])
+            .collect();
+        let _ = std::fs::remove_file(path8);
+        let _ = std::fs::remove_file(path16);
+
+        assert_eq!(eight.len(), 4);
+        assert!(sixteen.len() > eight.len());
+    }
 }
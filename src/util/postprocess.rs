@@ -0,0 +1,101 @@
+use crate::common::Color;
+
+/// the largest blur radius, in pixels, applied by `depth_of_field`, which bounds the cost of
+/// blurring pixels that are very far from the focus plane (or see nothing at all)
+pub const MAX_BLUR_RADIUS: usize = 16;
+
+/// Applies a cheap, "fake", depth of field effect to an `image` that was rendered without
+/// lens sampling, by box blurring each pixel by an amount proportional to its distance from
+/// the focus plane. This is much faster than rendering with a camera aperture, and is intended
+/// for previews.
+///
+/// `depth` holds the depth of each pixel, as returned by `Renderer::render_depth`
+/// `width` is the width of the image in pixels
+/// `focus_distance` is the distance from the camera to the plane that is in perfect focus
+/// `strength` is the blur radius, in pixels, per unit of distance from the focus plane. The
+/// radius is rounded to whole pixels and limited to `MAX_BLUR_RADIUS`
+///
+/// # Panics
+/// if `image` and `depth` are not the same length, or their length is not a multiple of `width`
+pub fn depth_of_field(
+    image: &[Color],
+    depth: &[f64],
+    width: u32,
+    focus_distance: f64,
+    strength: f64,
+) -> Vec<Color> {
+    assert_eq!(
+        image.len(),
+        depth.len(),
+        "image and depth must have the same number of pixels"
+    );
+    let width = width as usize;
+    assert!(
+        width > 0 && image.len().is_multiple_of(width),
+        "image length must be a multiple of its width"
+    );
+    let height = image.len() / width;
+
+    let mut blurred = Vec::with_capacity(image.len());
+    for row in 0..height {
+        for col in 0..width {
+            let idx = row * width + col;
+            let radius = (strength * (depth[idx] - focus_distance).abs()).round();
+            let radius = if radius.is_nan() {
+                0
+            } else {
+                (radius as usize).min(MAX_BLUR_RADIUS)
+            };
+            blurred.push(box_average(image, width, height, row, col, radius));
+        }
+    }
+    blurred
+}
+
+/// Returns the average color of the square of pixels centered on `(row, col)` that extends
+/// `radius` pixels in every direction, clipped to the edges of the image
+fn box_average(
+    image: &[Color],
+    width: usize,
+    height: usize,
+    row: usize,
+    col: usize,
+    radius: usize,
+) -> Color {
+    let rows = row.saturating_sub(radius)..=(row + radius).min(height - 1);
+    let cols = col.saturating_sub(radius)..=(col + radius).min(width - 1);
+    let mut sum = Color::default();
+    let mut count = 0;
+    for r in rows {
+        for c in cols.clone() {
+            sum += image[r * width + c];
+            count += 1;
+        }
+    }
+    sum / count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::postprocess::depth_of_field;
+
+    #[test]
+    fn only_pixels_away_from_the_focus_plane_are_blurred() {
+        // a 3x3 checkerboard, where the top row is far from the focus plane
+        let (black, white) = (Color::default(), Color::new(1.0, 1.0, 1.0));
+        let image: Vec<Color> = (0..9)
+            .map(|i| if i % 2 == 0 { white } else { black })
+            .collect();
+        let mut depth = vec![2.0; 9];
+        depth[..3].fill(10.0);
+
+        let blurred = depth_of_field(&image, &depth, 3, 2.0, 1.0);
+
+        assert_eq!(blurred[3..], image[3..]);
+        assert!(blurred[..3]
+            .iter()
+            .zip(image[..3].iter())
+            .all(|(b, i)| b != i));
+    }
+}
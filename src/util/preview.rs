@@ -0,0 +1,135 @@
+use crate::common::{Camera, Color};
+use crate::hittable::HittableList;
+use crate::renderer::{Image, Renderer, Tile};
+use crate::util::tonemap::ToneMap;
+use minifb::{Key, Window, WindowOptions};
+use std::sync::mpsc::channel;
+
+/// Renders the `world` seen by the `camera`, while showing the image in a window titled `title`.
+/// Each tile is drawn in the window as soon as it is finished, so the render can be watched as
+/// it converges.
+///
+/// Closing the window, or pressing `Escape`, stops the render early. The tiles that were
+/// finished by then are kept, and every other pixel is black.
+///
+/// # Returns
+/// the rendered image, which is only partially rendered if the render was stopped early, or an
+/// error if the window could not be opened
+pub fn render_with_preview(
+    renderer: Renderer,
+    camera: Camera,
+    world: HittableList,
+    title: &str,
+) -> minifb::Result<Image> {
+    let (width, height) = (camera.image_width, camera.image_height);
+    let tone_map = renderer.tone_map();
+    let mut window = Window::new(
+        title,
+        width as usize,
+        height as usize,
+        WindowOptions::default(),
+    )?;
+    window.set_target_fps(30);
+
+    // the linear radiance of the finished tiles, and their display colors in the window
+    let mut pixels = vec![Color::default(); (width * height) as usize];
+    let mut buffer = vec![0u32; pixels.len()];
+
+    let (tx, rx) = channel();
+    let handle = renderer.render_with_progress(camera, world, move |tile, colors| {
+        // the window may be closed before the render stops
+        let _ = tx.send((*tile, colors.to_vec()));
+    });
+    while !handle.is_finished() {
+        for (tile, colors) in rx.try_iter() {
+            blit_tile(
+                &mut pixels,
+                &mut buffer,
+                width,
+                height,
+                &tile,
+                &colors,
+                &tone_map,
+            );
+        }
+        if !window.is_open() || window.is_key_down(Key::Escape) {
+            handle.cancel();
+            break;
+        }
+        window.update_with_buffer(&buffer, width as usize, height as usize)?;
+    }
+
+    match handle.wait() {
+        Some(image) => Ok(image),
+        None => {
+            // keep the tiles that were in progress when the render was stopped
+            for (tile, colors) in rx.try_iter() {
+                blit_tile(
+                    &mut pixels,
+                    &mut buffer,
+                    width,
+                    height,
+                    &tile,
+                    &colors,
+                    &tone_map,
+                );
+            }
+            Ok(Image::new(width, height, pixels).with_tone_map(tone_map))
+        }
+    }
+}
+
+/// Copies the linear radiance `colors` of a finished `tile` into `pixels`, which are stored
+/// starting from the bottom row of the image, and their display colors into the `buffer` of the
+/// window, which starts from the top row, as `0RGB` values
+fn blit_tile(
+    pixels: &mut [Color],
+    buffer: &mut [u32],
+    width: u32,
+    height: u32,
+    tile: &Tile,
+    colors: &[Color],
+    tone_map: &ToneMap,
+) {
+    for ((col, row), color) in tile.pixels().zip(colors) {
+        pixels[(row * width + col) as usize] = *color;
+        let [r, g, b] = Renderer::display_color(tone_map, color, 1).as_array();
+        buffer[((height - 1 - row) * width + col) as usize] =
+            ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::renderer::Tile;
+    use crate::util::preview::blit_tile;
+    use crate::util::tonemap::ToneMap;
+
+    #[test]
+    fn tiles_are_drawn_upside_down_in_the_window() {
+        let (width, height) = (2, 2);
+        let mut pixels = vec![Color::default(); 4];
+        let mut buffer = vec![0u32; 4];
+        // the bottom row of the image
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 1,
+        };
+
+        blit_tile(
+            &mut pixels,
+            &mut buffer,
+            width,
+            height,
+            &tile,
+            &[Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)],
+            &ToneMap::Clamp,
+        );
+
+        assert_eq!(pixels[0], Color::new(1.0, 0.0, 0.0));
+        assert_eq!(buffer, vec![0, 0, 0x00ff_0000, 0x0000_00ff]);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::common::Point3;
+use std::collections::HashMap;
+
+/// A uniform grid that buckets 3D points, each with an associated value, by hashing the grid
+/// cell they lie in. It accelerates the nearest-neighbor radius queries used by techniques like
+/// photon mapping, where many points are gathered around a query location.
+///
+/// Queries are fastest when the `cell_size` is close to the typical query radius, as a query
+/// only visits the cells overlapped by its radius
+#[derive(Debug, Clone)]
+pub struct SpatialHash<T> {
+    cell_size: f64,
+    cells: HashMap<(i64, i64, i64), Vec<(Point3, T)>>,
+    len: usize,
+}
+
+impl<T> SpatialHash<T> {
+    /// Returns a new, empty, spatial hash with grid cells of width `cell_size`
+    ///
+    /// # Panics
+    /// if `cell_size` is not greater than zero
+    pub fn new(cell_size: f64) -> Self {
+        assert!(cell_size > 0.0, "cell_size must be greater than zero");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the width of the grid cells
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Returns the number of points in this spatial hash
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if this spatial hash holds no points
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `point`, along with its `value`, into this spatial hash
+    pub fn insert(&mut self, point: Point3, value: T) {
+        self.cells
+            .entry(self.cell(&point))
+            .or_default()
+            .push((point, value));
+        self.len += 1;
+    }
+
+    /// Removes every point from this spatial hash
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.len = 0;
+    }
+
+    /// Returns the points, and their values, that lie within `radius` of `center`
+    /// (inclusive). The points are returned in no particular order. A negative or NaN radius
+    /// finds no points, and an infinite radius finds every point
+    pub fn query_radius(&self, center: &Point3, radius: f64) -> Vec<(Point3, &T)> {
+        let mut found = Vec::new();
+        if radius.is_nan() || radius < 0.0 {
+            return found;
+        }
+        let radius_squared = radius * radius;
+        let (min_x, min_y, min_z) = self.cell(&(*center - Point3::new(radius, radius, radius)));
+        let (max_x, max_y, max_z) = self.cell(&(*center + Point3::new(radius, radius, radius)));
+
+        // a radius much larger than the cells overlaps more cells than there are occupied
+        // cells (or, if infinite, overlaps every cell), so the occupied cells are searched
+        let overlapped_cells = [(min_x, max_x), (min_y, max_y), (min_z, max_z)]
+            .iter()
+            .map(|&(min, max)| (max as i128 - min as i128 + 1) as u128)
+            .fold(1u128, |cells, extent| cells.saturating_mul(extent));
+        if overlapped_cells > self.cells.len() as u128 {
+            for ((x, y, z), points) in self.cells.iter() {
+                if (min_x..=max_x).contains(x)
+                    && (min_y..=max_y).contains(y)
+                    && (min_z..=max_z).contains(z)
+                {
+                    found.extend(Self::within(points, *center, radius_squared));
+                }
+            }
+            return found;
+        }
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                for z in min_z..=max_z {
+                    let Some(points) = self.cells.get(&(x, y, z)) else {
+                        continue;
+                    };
+                    found.extend(Self::within(points, *center, radius_squared));
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns the `points` of a cell, and their values, that lie within the square root of
+    /// `radius_squared` of `center`
+    fn within(
+        points: &[(Point3, T)],
+        center: Point3,
+        radius_squared: f64,
+    ) -> impl Iterator<Item = (Point3, &T)> {
+        points
+            .iter()
+            .filter(move |(p, _)| (*p - center).length_squared() <= radius_squared)
+            .map(|(p, value)| (*p, value))
+    }
+
+    /// Returns the integer coordinates of the grid cell that contains `point`
+    fn cell(&self, point: &Point3) -> (i64, i64, i64) {
+        (
+            (point.x() / self.cell_size).floor() as i64,
+            (point.y() / self.cell_size).floor() as i64,
+            (point.z() / self.cell_size).floor() as i64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Point3;
+    use crate::util::spatial_hash::SpatialHash;
+    use rand::Rng;
+
+    #[test]
+    fn radius_query_returns_exactly_the_points_within_the_radius() {
+        let mut rng = rand::thread_rng();
+        let points: Vec<Point3> = (0..500)
+            .map(|_| {
+                Point3::new(
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                )
+            })
+            .collect();
+        let mut hash = SpatialHash::new(0.7);
+        for (i, p) in points.iter().enumerate() {
+            hash.insert(*p, i);
+        }
+        let center = Point3::new(0.5, -1.0, 2.0);
+        let radius = 1.6;
+
+        let mut found: Vec<usize> = hash
+            .query_radius(&center, radius)
+            .into_iter()
+            .map(|(_, i)| *i)
+            .collect();
+        found.sort_unstable();
+        let expected: Vec<usize> = (0..points.len())
+            .filter(|i| (points[*i] - center).length() <= radius)
+            .collect();
+
+        assert_eq!(hash.len(), 500);
+        assert!(!expected.is_empty());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn huge_and_infinite_radius_queries_return_every_point() {
+        let mut hash = SpatialHash::new(0.5);
+        for i in 0..10 {
+            hash.insert(Point3::new(i as f64, -(i as f64), 100.0 * i as f64), i);
+        }
+        let center = Point3::default();
+
+        assert_eq!(hash.query_radius(&center, f64::INFINITY).len(), 10);
+        assert_eq!(hash.query_radius(&center, 1e12).len(), 10);
+        assert!(hash.query_radius(&center, f64::NAN).is_empty());
+        assert!(hash.query_radius(&center, -1.0).is_empty());
+    }
+}
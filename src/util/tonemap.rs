@@ -0,0 +1,142 @@
+use crate::common::Color;
+use std::str::FromStr;
+
+/// The operators used to map the unbounded, linear, radiance of a render, such as the colors
+/// returned by `Renderer::render_radiance`, into the `[0..1]` range of a display.
+///
+/// `Clamp` - clamps every channel to `[0..1]`, so all highlights brighter than white clip
+/// `Reinhard` - the Reinhard operator `c/(1+c)`, which compresses every highlight but never
+/// reaches white
+/// `ReinhardExtended` - the extended Reinhard operator `c*(1 + c/white^2)/(1+c)`, where
+/// `white_point` is the radiance that maps to display white. Raising the white point rolls the
+/// highlights off more gently, preserving detail in very bright areas such as light sources
+/// `AcesFilmic` - Krzysztof Narkowicz's fit of the ACES filmic curve, which gives a slight
+/// contrast boost to the mid tones and a film-like roll off of the highlights
+/// `Exposure` - the exponential operator `1 - e^(-exposure*c)`, which behaves like exposing
+/// film for `exposure`. Higher exposures brighten the image while still never clipping
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToneMap {
+    #[default]
+    Clamp,
+    Reinhard,
+    ReinhardExtended {
+        white_point: f64,
+    },
+    AcesFilmic,
+    Exposure {
+        exposure: f64,
+    },
+}
+
+impl ToneMap {
+    /// Returns the display value, in the range `[0..1]`, of the linear radiance `color`.
+    /// Each channel is mapped independently
+    pub fn map(&self, color: Color) -> Color {
+        let map_channel = |c: f64| -> f64 {
+            let c = c.max(0.0);
+            let mapped = match self {
+                ToneMap::Clamp => c,
+                ToneMap::Reinhard => c / (1.0 + c),
+                ToneMap::ReinhardExtended { white_point } => {
+                    c * (1.0 + c / (white_point * white_point)) / (1.0 + c)
+                }
+                ToneMap::AcesFilmic => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+                ToneMap::Exposure { exposure } => 1.0 - (-exposure * c).exp(),
+            };
+            mapped.min(1.0)
+        };
+        Color::new(
+            map_channel(color.x()),
+            map_channel(color.y()),
+            map_channel(color.z()),
+        )
+    }
+}
+
+/// Parses a tone map operator from its name: `clamp`, `reinhard`, `aces`,
+/// `reinhard-extended:<white_point>` or `exposure:<exposure>`
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once(':') {
+            Some((name, value)) => {
+                let value = value
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid tone map parameter '{}': {}", value, e))?;
+                (name, Some(value))
+            }
+            None => (s, None),
+        };
+        match (name, value) {
+            ("clamp", None) => Ok(ToneMap::Clamp),
+            ("reinhard", None) => Ok(ToneMap::Reinhard),
+            ("aces", None) => Ok(ToneMap::AcesFilmic),
+            ("reinhard-extended", Some(white_point)) => {
+                Ok(ToneMap::ReinhardExtended { white_point })
+            }
+            ("exposure", Some(exposure)) => Ok(ToneMap::Exposure { exposure }),
+            _ => Err(format!(
+                "unknown tone map '{}', expected clamp, reinhard, aces, \
+                 reinhard-extended:<white_point> or exposure:<exposure>",
+                s
+            )),
+        }
+    }
+}
+
+/// Tone maps an `image` of linear radiance, in place, using the `tone_map` operator. The
+/// mapped colors are still linear, and must be gamma corrected for display
+pub fn tone_map(image: &mut [Color], tone_map: ToneMap) {
+    for pixel in image.iter_mut() {
+        *pixel = tone_map.map(*pixel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Color;
+    use crate::util::tonemap::ToneMap;
+
+    #[test]
+    fn higher_white_point_keeps_bright_radiance_further_below_white() {
+        let bright = Color::new(10.0, 10.0, 10.0);
+        let low = ToneMap::ReinhardExtended { white_point: 12.0 }.map(bright);
+        let high = ToneMap::ReinhardExtended { white_point: 50.0 }.map(bright);
+
+        assert!(high.x() < low.x());
+        assert!(low.x() < 1.0);
+        // radiance at the white point maps to white
+        let white = ToneMap::ReinhardExtended { white_point: 50.0 }.map(Color::new(50.0, 0.0, 0.0));
+        assert!((white.x() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn aces_and_exposure_compress_highlights_without_clipping() {
+        let operators = [ToneMap::AcesFilmic, ToneMap::Exposure { exposure: 1.0 }];
+        for operator in operators {
+            let mid = operator.map(Color::new(0.5, 0.5, 0.5)).x();
+            let bright = operator.map(Color::new(4.0, 4.0, 4.0)).x();
+            let brighter = operator.map(Color::new(8.0, 8.0, 8.0)).x();
+
+            assert!(mid < bright && bright < brighter && brighter <= 1.0);
+            assert_eq!(operator.map(Color::default()), Color::default());
+        }
+    }
+
+    #[test]
+    fn tone_maps_are_parsed_from_their_names() {
+        assert_eq!("aces".parse(), Ok(ToneMap::AcesFilmic));
+        assert_eq!(
+            "exposure:1.5".parse(),
+            Ok(ToneMap::Exposure { exposure: 1.5 })
+        );
+        assert_eq!(
+            "reinhard-extended:10".parse(),
+            Ok(ToneMap::ReinhardExtended { white_point: 10.0 })
+        );
+        assert!("reinhard-extended".parse::<ToneMap>().is_err());
+        assert!("filmic".parse::<ToneMap>().is_err());
+    }
+}